@@ -0,0 +1,216 @@
+//! Format-agnostic representation of the configuration file. Every supported format is parsed
+//! into a `ConfigValue`, so that rulesets are read the same way regardless of the format.
+
+use serde_json;
+use serde_yaml;
+use toml;
+
+/// Formats of the configuration file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+/// A value of the configuration file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigValue {
+    String(String),
+    List(Vec<ConfigValue>),
+    /// Entries of a mapping, in the order they appear in the file.
+    Map(Vec<(ConfigValue, ConfigValue)>),
+    /// Any other scalar, such as a number or a boolean, in textual form.
+    Other(String),
+}
+
+impl ConfigFormat {
+    pub fn from_str(format: &str) -> Self {
+        match format {
+            "toml" => ConfigFormat::Toml,
+            "json" => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+
+    /// Detects the format from the extension of the path, defaulting to YAML.
+    pub fn from_path(path: &str) -> Self {
+        if path.ends_with(".toml") {
+            ConfigFormat::Toml
+        } else if path.ends_with(".json") {
+            ConfigFormat::Json
+        } else {
+            ConfigFormat::Yaml
+        }
+    }
+
+    /// Parses the configuration, returning None if it is malformed.
+    pub fn parse(self, conf: &str) -> Option<ConfigValue> {
+        match self {
+            ConfigFormat::Yaml => serde_yaml::from_str::<serde_yaml::Value>(conf)
+                .ok()
+                .map(ConfigValue::from),
+            ConfigFormat::Toml => toml::from_str::<toml::Value>(conf)
+                .ok()
+                .map(ConfigValue::from),
+            ConfigFormat::Json => serde_json::from_str::<serde_json::Value>(conf)
+                .ok()
+                .map(ConfigValue::from),
+        }
+    }
+}
+
+impl ConfigValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ConfigValue::String(string) => Some(string.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a boolean scalar.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ConfigValue::Other(other) => other.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a non-negative integer scalar which fits in 32 bits.
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            ConfigValue::Other(other) => other.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, ConfigValue::String(_))
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self, ConfigValue::Map(_))
+    }
+
+    /// Returns the value of the given key if this is a mapping containing it.
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        match self {
+            ConfigValue::Map(entries) => entries
+                .iter()
+                .find(|(name, _)| name.as_str() == Some(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_yaml::Value> for ConfigValue {
+    fn from(value: serde_yaml::Value) -> Self {
+        use serde_yaml::Value;
+        match value {
+            Value::String(string) => ConfigValue::String(string),
+            Value::Sequence(values) => {
+                ConfigValue::List(values.into_iter().map(ConfigValue::from).collect())
+            }
+            Value::Mapping(entries) => ConfigValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (ConfigValue::from(key), ConfigValue::from(value)))
+                    .collect(),
+            ),
+            Value::Null => ConfigValue::Other("null".to_string()),
+            Value::Bool(boolean) => ConfigValue::Other(boolean.to_string()),
+            Value::Number(number) => ConfigValue::Other(number.to_string()),
+        }
+    }
+}
+
+impl From<toml::Value> for ConfigValue {
+    fn from(value: toml::Value) -> Self {
+        use toml::Value;
+        match value {
+            Value::String(string) => ConfigValue::String(string),
+            Value::Array(values) => {
+                ConfigValue::List(values.into_iter().map(ConfigValue::from).collect())
+            }
+            Value::Table(entries) => ConfigValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (ConfigValue::String(key), ConfigValue::from(value)))
+                    .collect(),
+            ),
+            other => ConfigValue::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Value> for ConfigValue {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value;
+        match value {
+            Value::String(string) => ConfigValue::String(string),
+            Value::Array(values) => {
+                ConfigValue::List(values.into_iter().map(ConfigValue::from).collect())
+            }
+            Value::Object(entries) => ConfigValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (ConfigValue::String(key), ConfigValue::from(value)))
+                    .collect(),
+            ),
+            other => ConfigValue::Other(other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_parse_alike() {
+        let yaml = "
+ewasm:
+  file: \"contract.wasm\"
+  verifyexports:
+    preset: \"ewasm\"
+";
+        let toml = "
+[ewasm]
+file = \"contract.wasm\"
+verifyexports = { preset = \"ewasm\" }
+";
+        let json = r#"{"ewasm": {"file": "contract.wasm", "verifyexports": {"preset": "ewasm"}}}"#;
+
+        let expected = ConfigFormat::Yaml.parse(yaml).unwrap();
+        assert_eq!(ConfigFormat::Toml.parse(toml).unwrap(), expected);
+        assert_eq!(ConfigFormat::Json.parse(json).unwrap(), expected);
+        assert_eq!(
+            expected
+                .get("ewasm")
+                .and_then(|ruleset| ruleset.get("file")),
+            Some(&ConfigValue::String("contract.wasm".to_string()))
+        );
+    }
+
+    #[test]
+    fn map_preserves_order() {
+        let json = r#"{"b": "1", "a": "2", "c": "3"}"#;
+        let keys: Vec<String> = match ConfigFormat::Json.parse(json).unwrap() {
+            ConfigValue::Map(entries) => entries
+                .iter()
+                .filter_map(|(key, _)| key.as_str().map(|key| key.to_string()))
+                .collect(),
+            _ => vec![],
+        };
+        assert_eq!(keys, vec!["b", "a", "c"]);
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(ConfigFormat::from_path("chisel.toml"), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::from_path("chisel.json"), ConfigFormat::Json);
+        assert_eq!(ConfigFormat::from_path("chisel.yml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("chisel.conf"), ConfigFormat::Yaml);
+    }
+}
@@ -0,0 +1,335 @@
+//! Rendering of a module in a form of the WebAssembly text format. Only the structure of the
+//! module is shown: every section with its entries and the signature of each function, but not
+//! the instructions of the function bodies.
+
+use std::fmt::Write;
+
+use parity_wasm::elements::{
+    External, FunctionType, InitExpr, Instruction, Internal, Module, ResizableLimits, Section,
+    TableElementType, Type,
+};
+
+/// Renders the module, one line per entry of each section in the order the sections appear.
+pub fn disassemble(module: &Module) -> String {
+    let mut text = String::from("(module\n");
+    // Functions are indexed after the imported ones, and likewise for the other index spaces.
+    let mut next_func = 0;
+    let mut next_table = 0;
+    let mut next_memory = 0;
+    let mut next_global = 0;
+
+    for section in module.sections() {
+        match section {
+            Section::Type(section) => {
+                for (index, Type::Function(sig)) in section.types().iter().enumerate() {
+                    line(
+                        &mut text,
+                        format!("(type (;{};) (func{}))", index, signature(sig)),
+                    );
+                }
+            }
+            Section::Import(section) => {
+                for entry in section.entries() {
+                    let desc = match entry.external() {
+                        External::Function(type_ref) => {
+                            next_func += 1;
+                            format!(
+                                "(func (;{};) (type {}){})",
+                                next_func - 1,
+                                type_ref,
+                                type_signature(module, *type_ref)
+                            )
+                        }
+                        External::Table(table) => {
+                            next_table += 1;
+                            format!(
+                                "(table (;{};) {} {})",
+                                next_table - 1,
+                                limits(table.limits()),
+                                elem_type(table.elem_type())
+                            )
+                        }
+                        External::Memory(memory) => {
+                            next_memory += 1;
+                            format!(
+                                "(memory (;{};) {})",
+                                next_memory - 1,
+                                limits(memory.limits())
+                            )
+                        }
+                        External::Global(global) => {
+                            next_global += 1;
+                            format!(
+                                "(global (;{};) {})",
+                                next_global - 1,
+                                global_type(global.content_type(), global.is_mutable())
+                            )
+                        }
+                    };
+                    line(
+                        &mut text,
+                        format!(
+                            "(import \"{}\" \"{}\" {})",
+                            entry.module(),
+                            entry.field(),
+                            desc
+                        ),
+                    );
+                }
+            }
+            Section::Function(section) => {
+                let bodies = module.code_section().map_or(&[][..], |code| code.bodies());
+                for (index, func) in section.entries().iter().enumerate() {
+                    let mut desc = format!(
+                        "(func (;{};) (type {}){}",
+                        next_func + index,
+                        func.type_ref(),
+                        type_signature(module, func.type_ref())
+                    );
+                    if let Some(body) = bodies.get(index) {
+                        for local in body.locals() {
+                            for _ in 0..local.count() {
+                                write!(desc, " (local {})", local.value_type()).unwrap();
+                            }
+                        }
+                        let count = body.code().elements().len();
+                        let plural = if count == 1 { "" } else { "s" };
+                        write!(desc, ") ;; {} instruction{}", count, plural).unwrap();
+                    } else {
+                        desc.push(')');
+                    }
+                    line(&mut text, desc);
+                }
+            }
+            Section::Table(section) => {
+                for (index, table) in section.entries().iter().enumerate() {
+                    line(
+                        &mut text,
+                        format!(
+                            "(table (;{};) {} {})",
+                            next_table + index,
+                            limits(table.limits()),
+                            elem_type(table.elem_type())
+                        ),
+                    );
+                }
+            }
+            Section::Memory(section) => {
+                for (index, memory) in section.entries().iter().enumerate() {
+                    line(
+                        &mut text,
+                        format!(
+                            "(memory (;{};) {})",
+                            next_memory + index,
+                            limits(memory.limits())
+                        ),
+                    );
+                }
+            }
+            Section::Global(section) => {
+                for (index, global) in section.entries().iter().enumerate() {
+                    let ty = global.global_type();
+                    line(
+                        &mut text,
+                        format!(
+                            "(global (;{};) {} {})",
+                            next_global + index,
+                            global_type(ty.content_type(), ty.is_mutable()),
+                            init_expr(global.init_expr())
+                        ),
+                    );
+                }
+            }
+            Section::Export(section) => {
+                for entry in section.entries() {
+                    let desc = match entry.internal() {
+                        Internal::Function(index) => format!("func {}", index),
+                        Internal::Table(index) => format!("table {}", index),
+                        Internal::Memory(index) => format!("memory {}", index),
+                        Internal::Global(index) => format!("global {}", index),
+                    };
+                    line(
+                        &mut text,
+                        format!("(export \"{}\" ({}))", entry.field(), desc),
+                    );
+                }
+            }
+            Section::Start(index) => line(&mut text, format!("(start {})", index)),
+            Section::Element(section) => {
+                for segment in section.entries() {
+                    let members: Vec<String> =
+                        segment.members().iter().map(|m| m.to_string()).collect();
+                    line(
+                        &mut text,
+                        format!(
+                            "(elem {} {})",
+                            segment
+                                .offset()
+                                .as_ref()
+                                .map_or_else(String::new, init_expr),
+                            members.join(" ")
+                        ),
+                    );
+                }
+            }
+            // Function bodies are summarized with their signatures above.
+            Section::Code(_) => {}
+            Section::Data(section) => {
+                for segment in section.entries() {
+                    line(
+                        &mut text,
+                        format!(
+                            "(data {}) ;; {} bytes",
+                            segment
+                                .offset()
+                                .as_ref()
+                                .map_or_else(String::new, init_expr),
+                            segment.value().len()
+                        ),
+                    );
+                }
+            }
+            Section::Custom(section) => line(
+                &mut text,
+                format!(
+                    ";; custom section \"{}\" ({} bytes)",
+                    section.name(),
+                    section.payload().len()
+                ),
+            ),
+            Section::Name(_) => line(&mut text, ";; custom section \"name\"".to_string()),
+            Section::Reloc(section) => line(
+                &mut text,
+                format!(";; custom section \"{}\"", section.name()),
+            ),
+            Section::Unparsed { id, payload } => line(
+                &mut text,
+                format!(";; unknown section {} ({} bytes)", id, payload.len()),
+            ),
+        }
+    }
+    text.push_str(")\n");
+    text
+}
+
+fn line(text: &mut String, line: String) {
+    text.push_str("  ");
+    text.push_str(&line);
+    text.push('\n');
+}
+
+/// Formats the parameters and result of a function, e.g. ` (param i32 i32) (result i64)`.
+fn signature(sig: &FunctionType) -> String {
+    let mut text = String::new();
+    if !sig.params().is_empty() {
+        let params: Vec<String> = sig.params().iter().map(|param| param.to_string()).collect();
+        write!(text, " (param {})", params.join(" ")).unwrap();
+    }
+    if let Some(result) = sig.return_type() {
+        write!(text, " (result {})", result).unwrap();
+    }
+    text
+}
+
+/// Formats the signature of the type at the given index, or nothing if there is no such type.
+fn type_signature(module: &Module, type_ref: u32) -> String {
+    module
+        .type_section()
+        .and_then(|section| section.types().get(type_ref as usize))
+        .map_or_else(String::new, |Type::Function(sig)| signature(sig))
+}
+
+fn limits(limits: &ResizableLimits) -> String {
+    match limits.maximum() {
+        Some(maximum) => format!("{} {}", limits.initial(), maximum),
+        None => limits.initial().to_string(),
+    }
+}
+
+fn elem_type(elem_type: TableElementType) -> &'static str {
+    match elem_type {
+        TableElementType::AnyFunc => "anyfunc",
+    }
+}
+
+fn global_type(content_type: parity_wasm::elements::ValueType, is_mutable: bool) -> String {
+    if is_mutable {
+        format!("(mut {})", content_type)
+    } else {
+        content_type.to_string()
+    }
+}
+
+/// Formats a constant expression, e.g. `(i32.const 0)`, leaving out its final `end`.
+fn init_expr(expr: &InitExpr) -> String {
+    let instructions: Vec<String> = expr
+        .code()
+        .iter()
+        .filter(|instruction| **instruction != Instruction::End)
+        .map(|instruction| format!("({})", instruction))
+        .collect();
+    instructions.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use parity_wasm::elements::ValueType;
+
+    #[test]
+    fn structure_rendered() {
+        let module = builder::module()
+            .import()
+            .module("ethereum")
+            .field("finish")
+            .external()
+            .func(0)
+            .build()
+            .function()
+            .signature()
+            .with_params(vec![ValueType::I32, ValueType::I32])
+            .build()
+            .body()
+            .build()
+            .build()
+            .memory()
+            .with_min(1)
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(1)
+            .build()
+            .build();
+        assert_eq!(
+            disassemble(&module),
+            "(module
+  (type (;0;) (func (param i32 i32)))
+  (import \"ethereum\" \"finish\" (func (;0;) (type 0) (param i32 i32)))
+  (func (;1;) (type 0) (param i32 i32)) ;; 1 instruction
+  (memory (;0;) 1)
+  (export \"main\" (func 1))
+)
+"
+        );
+    }
+
+    #[test]
+    fn data_and_custom_sections_summarized() {
+        let mut module = builder::module()
+            .memory()
+            .with_min(1)
+            .with_data(16, vec![1, 2, 3])
+            .build()
+            .build();
+        module.set_custom_section("producers", vec![0; 4]);
+        let text = disassemble(&module);
+        assert!(
+            text.contains("(data (i32.const 16)) ;; 3 bytes"),
+            "{}",
+            text
+        );
+        assert!(text.contains(";; custom section \"producers\" (4 bytes)"));
+    }
+}
@@ -0,0 +1,367 @@
+//! Errors reported by the chisel command line tool. Errors of the library are wrapped rather
+//! than repeated, see `Create`.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use libchisel::error::ChiselError as LibraryError;
+use libchisel::presets::Preset;
+
+#[derive(Debug)]
+pub enum ChiselError {
+    NoSubcommand,
+    /// No configuration file was given, and none was found in the current directory or its
+    /// parents.
+    ConfigNotFound,
+    /// The configuration file could not be read.
+    OpenConfig(io::Error),
+    /// The named configuration file already exists and would be overwritten.
+    ConfigExists(String),
+    /// The configuration file could not be written.
+    WriteConfig(io::Error),
+    /// The configuration file is not valid in its format.
+    ConfigParse,
+    /// The configuration file does not have the structure of a list of rulesets.
+    ConfigInvalid,
+    /// The configuration file parsed, but has no ruleset mapping a name to its options.
+    NoRuleset,
+    /// The selected ruleset does not exist in the configuration, alongside the rulesets which do.
+    RulesetNotFound(String, Vec<String>),
+    /// The named ruleset is not a map of options.
+    RulesetTypeMismatch(String),
+    /// The named ruleset does not give the binary to chisel.
+    MissingFile(String),
+    /// Several errors were found in the configuration, each of which is reported.
+    ConfigErrors(Vec<ChiselError>),
+    /// The 'file' field of the ruleset is neither a string nor a list of strings.
+    FileTypeMismatch,
+    /// The named environment variable, referenced by the 'file' field, is not set.
+    UndefinedVariable(String),
+    /// The named glob pattern is malformed.
+    InvalidGlob(String),
+    /// The named glob pattern does not match any files.
+    GlobNoMatch(String),
+    /// The named module configuration is not a map of options.
+    ModuleTypeMismatch(String),
+    /// The named module has a preset which is not a string.
+    PresetTypeMismatch(String),
+    /// The named module does not exist, alongside the closest valid name if there is one.
+    UnknownModule(String, Option<String>),
+    /// The named module does not support the preset, named second, alongside the presets it
+    /// accepts.
+    InvalidPreset(String, String, Vec<Preset>),
+    /// The named module has a preset, although it does not take one.
+    PresetNotTaken(String),
+    /// The named module has an option, named second, whose value is malformed.
+    InvalidOption(String, String),
+    /// The named module does not accept an option, named second, alongside the closest accepted
+    /// option if there is one. Only reported with `--strict-config`.
+    UnknownOption(String, String, Option<String>),
+    /// The named module requires an option, named second, which is not configured.
+    MissingOption(String, String),
+    /// The named host interface file of verifyimports could not be read.
+    OpenInterface(String, io::Error),
+    /// The named host interface file of verifyimports is not valid JSON listing imports.
+    InvalidInterface(String),
+    /// The named module is a translator, which has no rules to explain.
+    ExplainTranslator(String),
+    /// The named module failed to run, with the reason given by the library.
+    ModuleFailed(String, String),
+    /// The wasm binary could not be read.
+    OpenBinary(io::Error),
+    /// The wasm binary could not be deserialized, with the reason and where it went wrong.
+    Deserialize(String),
+    /// The module could not be created from the input, with the error given by the library,
+    /// such as the location of a parse error in WebAssembly text.
+    Create(LibraryError),
+    /// The input is not in the named format it was read in, for the given reason.
+    InvalidInput(&'static str, &'static str),
+    /// The resulting wasm binary could not be written.
+    WriteBinary(io::Error),
+    /// The output or diagnostics of the subcommand could not be written.
+    WriteOutput(io::Error),
+    /// The `--report` option, given in full, is not a path followed by a known format.
+    InvalidReport(String),
+    /// The named report file could not be written.
+    WriteReport(String, io::Error),
+    InvalidJobs,
+    MultipleFiles,
+    /// An output file was given with a backend other than parity, which may not deserialize the
+    /// module.
+    BackendOutput,
+    OutputRequired,
+    ConflictingVerbosity,
+}
+
+impl ChiselError {
+    /// Combines the errors found in the configuration, returning the error alone if there is only
+    /// one. Errors which were already combined are flattened into the others.
+    pub fn from_config_errors(errors: Vec<ChiselError>) -> Self {
+        let mut errors: Vec<ChiselError> = errors
+            .into_iter()
+            .flat_map(|err| match err {
+                ChiselError::ConfigErrors(errors) => errors,
+                err => vec![err],
+            })
+            .collect();
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            ChiselError::ConfigErrors(errors)
+        }
+    }
+}
+
+impl fmt::Display for ChiselError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChiselError::NoSubcommand => write!(f, "No subcommand provided."),
+            ChiselError::OpenConfig(err) => {
+                write!(f, "Failed to open configuration file: {}", err)
+            }
+            ChiselError::ConfigNotFound => write!(
+                f,
+                "Could not find chisel.yml in the current directory or any of its parents."
+            ),
+            ChiselError::ConfigExists(path) => write!(
+                f,
+                "{} already exists. Use --force to overwrite it.",
+                path
+            ),
+            ChiselError::WriteConfig(err) => {
+                write!(f, "Failed to write configuration file: {}", err)
+            }
+            ChiselError::ConfigParse => write!(f, "Failed to parse configuration file."),
+            ChiselError::ConfigInvalid => write!(f, "Config is invalid."),
+            ChiselError::NoRuleset => write!(
+                f,
+                "Config does not contain any ruleset. A ruleset is a name mapping to the 'file' to chisel and its modules."
+            ),
+            ChiselError::RulesetNotFound(name, available) => write!(
+                f,
+                "Config does not contain the ruleset '{}'. Available rulesets: {}",
+                name,
+                available.join(", ")
+            ),
+            ChiselError::RulesetTypeMismatch(name) => write!(
+                f,
+                "Config ruleset '{}' does not point to a key-value map of its 'file' and modules.",
+                name
+            ),
+            ChiselError::MissingFile(name) => write!(
+                f,
+                "Config ruleset '{}' is missing the 'file' path to chisel.",
+                name
+            ),
+            ChiselError::ConfigErrors(errors) => {
+                write!(f, "The configuration has {} errors:", errors.len())?;
+                for err in errors {
+                    write!(f, "\n\t{}", err)?;
+                }
+                Ok(())
+            }
+            ChiselError::FileTypeMismatch => write!(
+                f,
+                "Config 'file' field is not a string or a list of strings."
+            ),
+            ChiselError::UndefinedVariable(name) => write!(
+                f,
+                "Config 'file' field references the environment variable '{}', which is not set.",
+                name
+            ),
+            ChiselError::InvalidGlob(pattern) => {
+                write!(f, "Config 'file' glob pattern '{}' is invalid.", pattern)
+            }
+            ChiselError::GlobNoMatch(pattern) => write!(
+                f,
+                "Config 'file' glob pattern '{}' does not match any files.",
+                pattern
+            ),
+            ChiselError::ModuleTypeMismatch(name) => write!(
+                f,
+                "The configuration of module '{}' does not point to a key-value map. Perhaps an option field is missing?",
+                name
+            ),
+            ChiselError::PresetTypeMismatch(name) => write!(
+                f,
+                "The configuration of module '{}' has a 'preset' field that is not a string.",
+                name
+            ),
+            ChiselError::UnknownModule(name, Some(suggestion)) => write!(
+                f,
+                "Unknown module '{}'. Did you mean '{}'?",
+                name, suggestion
+            ),
+            ChiselError::UnknownModule(name, None) => write!(f, "Unknown module '{}'.", name),
+            ChiselError::InvalidPreset(name, preset, accepted) => {
+                let accepted: Vec<&str> = accepted.iter().map(|preset| preset.name()).collect();
+                write!(
+                    f,
+                    "Module '{}' does not support the preset '{}'. Accepted presets: {}.",
+                    name,
+                    preset,
+                    accepted.join(", ")
+                )
+            }
+            ChiselError::PresetNotTaken(name) => write!(
+                f,
+                "The configuration of module '{}' has a 'preset' field, but the module does not take a preset.",
+                name
+            ),
+            ChiselError::InvalidOption(name, option) => write!(
+                f,
+                "The configuration of module '{}' has an invalid '{}' option.",
+                name, option
+            ),
+            ChiselError::UnknownOption(name, option, Some(suggestion)) => write!(
+                f,
+                "The configuration of module '{}' has an unknown '{}' option. Did you mean '{}'?",
+                name, option, suggestion
+            ),
+            ChiselError::UnknownOption(name, option, None) => write!(
+                f,
+                "The configuration of module '{}' has an unknown '{}' option.",
+                name, option
+            ),
+            ChiselError::MissingOption(name, option) => write!(
+                f,
+                "The configuration of module '{}' is missing the required '{}' option.",
+                name, option
+            ),
+            ChiselError::OpenInterface(path, err) => {
+                write!(f, "Failed to open host interface file '{}': {}", path, err)
+            }
+            ChiselError::InvalidInterface(path) => write!(
+                f,
+                "Host interface file '{}' is not a JSON list of imports, each a 'module::field' name or a map of its 'name', 'kind', 'params' and 'result'.",
+                path
+            ),
+            ChiselError::ExplainTranslator(name) => write!(
+                f,
+                "Module '{}' is a translator, which applies no checks to explain.",
+                name
+            ),
+            ChiselError::ModuleFailed(name, reason) => write!(f, "{}: {}", name, reason),
+            ChiselError::OpenBinary(err) => write!(f, "Failed to open wasm binary: {}", err),
+            ChiselError::Deserialize(reason) => {
+                write!(f, "Failed to deserialize the wasm binary: {}", reason)
+            }
+            ChiselError::Create(err) => write!(f, "{}", err),
+            ChiselError::InvalidInput(format, reason) => write!(
+                f,
+                "Failed to read the input as {}, as {}. Set its format with --input-format.",
+                format, reason
+            ),
+            ChiselError::WriteBinary(err) => write!(f, "Failed to write wasm binary: {}", err),
+            ChiselError::WriteOutput(err) => write!(f, "Failed to write output: {}", err),
+            ChiselError::InvalidReport(report) => write!(
+                f,
+                "Invalid report '{}', expected FILE:FORMAT with a format of text, json, junit or sarif.",
+                report
+            ),
+            ChiselError::WriteReport(path, err) => {
+                write!(f, "Failed to write report '{}': {}", path, err)
+            }
+            ChiselError::InvalidJobs => {
+                write!(f, "The number of jobs must be a positive integer.")
+            }
+            ChiselError::MultipleFiles => write!(
+                f,
+                "An output file cannot be used with multiple input files."
+            ),
+            ChiselError::BackendOutput => write!(
+                f,
+                "An output file cannot be used with the fast or stream backends, which may not deserialize the module."
+            ),
+            ChiselError::OutputRequired => {
+                write!(f, "An output file is required when reading from stdin or a .wat file.")
+            }
+            ChiselError::ConflictingVerbosity => write!(
+                f,
+                "The --quiet and --verbose flags are mutually exclusive."
+            ),
+        }
+    }
+}
+
+/// Writing to the output of a subcommand is the only fallible IO not mapped to its own error.
+impl From<io::Error> for ChiselError {
+    fn from(err: io::Error) -> Self {
+        ChiselError::WriteOutput(err)
+    }
+}
+
+impl Error for ChiselError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChiselError::OpenConfig(err)
+            | ChiselError::WriteConfig(err)
+            | ChiselError::OpenBinary(err)
+            | ChiselError::WriteBinary(err)
+            | ChiselError::WriteOutput(err)
+            | ChiselError::OpenInterface(_, err)
+            | ChiselError::WriteReport(_, err) => Some(err),
+            ChiselError::Create(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_with_context() {
+        assert_eq!(
+            ChiselError::PresetTypeMismatch("verifyexports".to_string()).to_string(),
+            "The configuration of module 'verifyexports' has a 'preset' field that is not a string."
+        );
+        assert_eq!(
+            ChiselError::InvalidPreset(
+                "trimexports".to_string(),
+                "wasm".to_string(),
+                vec![Preset::Ewasm, Preset::PWasm]
+            )
+            .to_string(),
+            "Module 'trimexports' does not support the preset 'wasm'. Accepted presets: ewasm, pwasm."
+        );
+    }
+
+    #[test]
+    fn config_errors_name_ruleset() {
+        assert_eq!(
+            ChiselError::MissingFile("ewasm".to_string()).to_string(),
+            "Config ruleset 'ewasm' is missing the 'file' path to chisel."
+        );
+        assert!(ChiselError::NoRuleset.to_string().contains("any ruleset"));
+    }
+
+    #[test]
+    fn config_errors_listed() {
+        let err = ChiselError::from_config_errors(vec![
+            ChiselError::MissingFile("ewasm".to_string()),
+            ChiselError::InvalidPreset(
+                "trimexports".to_string(),
+                "wasm".to_string(),
+                vec![Preset::Ewasm],
+            ),
+        ]);
+        assert_eq!(
+            err.to_string(),
+            "The configuration has 2 errors:\n\
+             \tConfig ruleset 'ewasm' is missing the 'file' path to chisel.\n\
+             \tModule 'trimexports' does not support the preset 'wasm'. Accepted presets: ewasm."
+        );
+        let err = ChiselError::from_config_errors(vec![ChiselError::NoRuleset]);
+        assert!(matches!(err, ChiselError::NoRuleset));
+    }
+
+    #[test]
+    fn io_errors_have_source() {
+        let err = ChiselError::OpenBinary(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert!(err.source().is_some());
+        assert!(ChiselError::Deserialize(String::new()).source().is_none());
+    }
+}
@@ -0,0 +1,1215 @@
+//! Running the modules of a ruleset on its binaries, with each of the backends reading them.
+
+use std::collections::HashMap;
+use std::fs::{read, remove_file, rename, write, File};
+use std::io::{self, BufReader, Read, Write};
+use std::panic;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use libchisel::{
+    diff::SectionDiff,
+    frombinary::*,
+    fromwat::*,
+    pipeline::{Chisel, Stage, StageOutcome},
+    stream, ModuleCreator,
+};
+
+use error::ChiselError;
+use parity_wasm::elements::{serialize, Module};
+use registry::{module_presets, ModuleKind, DEFAULT_PRESET};
+use report::{ModuleResult, Verbosity};
+use ruleset::{ChiselContext, ModuleContext, STDIN_PATH};
+use summary::summarize;
+
+/// First bytes of every wasm binary.
+static WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Outcome of running a ruleset against a single wasm binary.
+pub struct FileResults {
+    pub file: String,
+    /// The resulting module alongside the results, or an error message if the binary could not
+    /// be loaded. The module is None if the fast backend never needed to deserialize it, and is
+    /// shared with the cache if no translator changed it.
+    pub outcome: Result<(Option<Arc<Module>>, Vec<ModuleResult>), ChiselError>,
+}
+
+/// Modules deserialized by `chisel run`, by path, so that a binary checked by several rulesets
+/// is only read and deserialized once.
+#[derive(Default)]
+pub struct ModuleCache {
+    modules: Mutex<HashMap<String, Arc<Module>>>,
+}
+
+/// Outcome of running the `translate` subcommand on a ruleset.
+pub struct TranslateResults {
+    /// The transformed module, present only if every module succeeded.
+    pub module: Option<Module>,
+    /// Results of the modules, in the order of the ruleset.
+    pub results: Vec<ModuleResult>,
+    /// Changes made by each translator, only collected for dry runs.
+    pub diffs: Vec<ModuleDiff>,
+    /// Changes made by each translator to the sections of the module, only collected with
+    /// `--diff`.
+    pub section_diffs: Vec<Result<SectionDiff, String>>,
+}
+
+impl TranslateResults {
+    /// Returns the results of the translators, in the order of the ruleset.
+    pub fn translation_results(&self) -> impl Iterator<Item = &ModuleResult> {
+        self.results
+            .iter()
+            .filter(|result| result.kind == ModuleKind::Translator)
+    }
+
+    /// Returns true if any translator changed the module.
+    pub fn modified(&self) -> bool {
+        self.translation_results()
+            .any(|result| matches!(result.outcome, Ok(true)))
+    }
+}
+
+/// Changes made to a module by a translator, as reported by a dry run.
+pub struct ModuleDiff {
+    size_before: usize,
+    size_after: usize,
+    pub removed_exports: Vec<String>,
+    pub added_exports: Vec<String>,
+    pub removed_imports: Vec<String>,
+    pub added_imports: Vec<String>,
+}
+
+/// Backends on which the `run` subcommand reads the binary.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Backend {
+    /// Deserializes the whole module with parity-wasm before running any chisel module.
+    Parity,
+    /// Lets validators which support it read the binary with wasmparser. The module is only
+    /// deserialized once a chisel module needs it.
+    Fast,
+    /// Reads only the interface of the module from the file as a stream, if every chisel module
+    /// is a validator which needs no more than that, and otherwise falls back to `Parity`.
+    Stream,
+}
+
+/// Formats in which a module is read.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputFormat {
+    /// The WebAssembly binary format.
+    Wasm,
+    /// The WebAssembly text format.
+    Wat,
+}
+
+impl ModuleDiff {
+    fn between(before: &Module, after: &Module) -> Self {
+        let (exports_before, exports_after) = (export_names(before), export_names(after));
+        let (imports_before, imports_after) = (import_names(before), import_names(after));
+        ModuleDiff {
+            size_before: module_size(before),
+            size_after: module_size(after),
+            removed_exports: difference(&exports_before, &exports_after),
+            added_exports: difference(&exports_after, &exports_before),
+            removed_imports: difference(&imports_before, &imports_after),
+            added_imports: difference(&imports_after, &imports_before),
+        }
+    }
+
+    /// Human-readable summary of the changes, e.g. "would remove 3 exports".
+    pub fn summary(&self) -> String {
+        let changes: Vec<String> = [
+            ("remove", &self.removed_exports, "export"),
+            ("add", &self.added_exports, "export"),
+            ("remove", &self.removed_imports, "import"),
+            ("add", &self.added_imports, "import"),
+        ]
+        .iter()
+        .filter(|(_, names, _)| !names.is_empty())
+        .map(|(action, names, kind)| {
+            let plural = if names.len() == 1 { "" } else { "s" };
+            format!("{} {} {}{}", action, names.len(), kind, plural)
+        })
+        .collect();
+
+        if !changes.is_empty() {
+            format!(
+                "would {} ({} -> {} bytes)",
+                changes.join(", "),
+                self.size_before,
+                self.size_after
+            )
+        } else if self.size_before != self.size_after {
+            format!(
+                "would change the size from {} to {} bytes",
+                self.size_before, self.size_after
+            )
+        } else {
+            "would not change the module".to_string()
+        }
+    }
+}
+
+impl InputFormat {
+    pub fn from_str(format: &str) -> Self {
+        match format {
+            "wat" => InputFormat::Wat,
+            _ => InputFormat::Wasm,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            InputFormat::Wasm => "wasm",
+            InputFormat::Wat => "wat",
+        }
+    }
+}
+
+impl Backend {
+    pub fn from_str(backend: &str) -> Self {
+        match backend {
+            "fast" => Backend::Fast,
+            "stream" => Backend::Stream,
+            _ => Backend::Parity,
+        }
+    }
+}
+
+/// Returns whether the module at the path is in the WebAssembly text format rather than a binary,
+/// as given by `format` or otherwise by the extension of the path.
+pub fn is_text(path: &str, format: Option<InputFormat>) -> bool {
+    match format {
+        Some(format) => format == InputFormat::Wat,
+        None => Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension == "wat"),
+    }
+}
+
+/// Runs a single chisel module. Validators only inspect the module, while translators may
+/// replace it with the result of their transformation. Progress is written to `log` in verbose
+/// mode.
+fn execute_module(
+    context: &ModuleContext,
+    stage: &Stage,
+    module: &mut Module,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<ModuleResult, ChiselError> {
+    execute_stage(context, stage, verbosity, log, |_| stage.run(module))
+}
+
+/// Runs a single chisel module as `execute_module` does, by calling `run`, which is timed and
+/// given `log` to write its own progress to.
+fn execute_stage<F>(
+    context: &ModuleContext,
+    stage: &Stage,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+    run: F,
+) -> Result<ModuleResult, ChiselError>
+where
+    F: FnOnce(&mut dyn Write) -> Result<StageOutcome, String>,
+{
+    let (conf_name, conf_preset) = context.fields();
+    let preset = if module_presets(conf_name).is_empty() {
+        None
+    } else {
+        Some(conf_preset.unwrap_or(DEFAULT_PRESET).name().to_string())
+    };
+
+    let start = if verbosity == Verbosity::Verbose {
+        match preset {
+            Some(ref preset) => writeln!(log, "Running {} with preset {}", conf_name, preset)?,
+            None => writeln!(log, "Running {}", conf_name)?,
+        }
+        Some(Instant::now())
+    } else {
+        None
+    };
+
+    let (outcome, reasons) = match run(log) {
+        Ok(StageOutcome::Validated(result)) => (Ok(result.passed()), result.reasons().to_vec()),
+        Ok(StageOutcome::Translated(modified)) => (Ok(modified), vec![]),
+        Err(err) => (
+            Err(ChiselError::ModuleFailed(conf_name.clone(), err)),
+            vec![],
+        ),
+    };
+
+    Ok(ModuleResult {
+        module_name: conf_name.clone(),
+        kind: if stage.is_translator() {
+            ModuleKind::Translator
+        } else {
+            ModuleKind::Validator
+        },
+        preset,
+        outcome,
+        reasons,
+        elapsed: start.map(|start| start.elapsed()),
+        severity: context.severity,
+    })
+}
+
+/// Reads the binary from the given path, or from stdin if the path is `-`.
+fn read_binary(path: &str) -> Result<Vec<u8>, ChiselError> {
+    if path == STDIN_PATH {
+        let mut buffer = Vec::new();
+        match io::stdin().read_to_end(&mut buffer) {
+            Ok(len) if len > 0 => Ok(buffer),
+            Ok(_) => Err(ChiselError::OpenBinary(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stdin is empty",
+            ))),
+            Err(err) => Err(ChiselError::OpenBinary(err)),
+        }
+    } else {
+        read(path).map_err(ChiselError::OpenBinary)
+    }
+}
+
+/// Loads the module from the given path, converting it to a binary first if it is a `.wat` file
+/// or `format` is text.
+pub fn load_module(
+    path: &str,
+    format: Option<InputFormat>,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<Module, ChiselError> {
+    let module = parse_module(path, format, &read_input(path, verbosity, log)?)?;
+    print_module_summary(path, &module, verbosity, log)?;
+    Ok(module)
+}
+
+/// Reads the file at the given path, reporting its size to `log` in verbose mode.
+pub fn read_input(
+    path: &str,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<Vec<u8>, ChiselError> {
+    let buffer = read_binary(path)?;
+    if verbosity == Verbosity::Verbose {
+        writeln!(log, "Read {} bytes from {}", buffer.len(), path)?;
+    }
+    Ok(buffer)
+}
+
+/// Creates the module from the contents of the file at the given path, which are read as text if
+/// it is a `.wat` file or `format` is text, and as a binary otherwise. This is the first phase of a
+/// run, before any validator or translator of the ruleset.
+fn parse_module(
+    path: &str,
+    format: Option<InputFormat>,
+    buffer: &[u8],
+) -> Result<Module, ChiselError> {
+    let is_binary = buffer.starts_with(WASM_MAGIC);
+    if is_text(path, format) {
+        if is_binary {
+            return Err(ChiselError::InvalidInput(
+                InputFormat::Wat.name(),
+                "it is a wasm binary",
+            ));
+        }
+        let source = std::str::from_utf8(buffer).map_err(|_| {
+            ChiselError::InvalidInput(InputFormat::Wat.name(), "it is not valid UTF-8")
+        })?;
+        return FromWat::with_path(source, Path::new(path))
+            .create()
+            .map_err(ChiselError::Create);
+    }
+    if !is_binary {
+        return Err(ChiselError::InvalidInput(
+            InputFormat::Wasm.name(),
+            "it does not start with the wasm magic number",
+        ));
+    }
+    FromBinary::new(buffer)
+        .create()
+        .map_err(ChiselError::Create)
+}
+
+fn module_size(module: &Module) -> usize {
+    serialize(module.clone()).map_or(0, |buffer| buffer.len())
+}
+
+pub fn export_names(module: &Module) -> Vec<String> {
+    module.export_section().map_or(vec![], |section| {
+        section
+            .entries()
+            .iter()
+            .map(|entry| entry.field().to_string())
+            .collect()
+    })
+}
+
+pub fn import_names(module: &Module) -> Vec<String> {
+    module.import_section().map_or(vec![], |section| {
+        section
+            .entries()
+            .iter()
+            .map(|entry| format!("{}.{}", entry.module(), entry.field()))
+            .collect()
+    })
+}
+
+/// Returns the names present in the first list but not in the second.
+fn difference(names: &[String], other: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| !other.contains(name))
+        .cloned()
+        .collect()
+}
+
+/// Prints an overview of the structure of the module read from the path to `log`, in verbose
+/// mode.
+fn print_module_summary(
+    path: &str,
+    module: &Module,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<(), ChiselError> {
+    if verbosity == Verbosity::Verbose {
+        write!(log, "Summary of {}:\n{}", path, summarize(module))?;
+    }
+    Ok(())
+}
+
+/// Prints the size of the module as it would be serialized to `log`, in verbose mode.
+fn print_module_size(
+    module: &Module,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<(), ChiselError> {
+    if verbosity == Verbosity::Verbose {
+        if let Ok(buffer) = serialize(module.clone()) {
+            writeln!(log, "Resulting module is {} bytes", buffer.len())?;
+        }
+    }
+    Ok(())
+}
+
+impl ModuleCache {
+    /// Returns the module at the given path, loading it on first use. A binary which fails to
+    /// load is not cached, and fails again for the next ruleset.
+    fn load(
+        &self,
+        path: &str,
+        format: Option<InputFormat>,
+        verbosity: Verbosity,
+        log: &mut dyn Write,
+    ) -> Result<Arc<Module>, ChiselError> {
+        if let Some(module) = self.lock().get(path) {
+            return Ok(module.clone());
+        }
+        // Loading happens without the lock, so that other files are not held up by this one.
+        let module = Arc::new(load_module(path, format, verbosity, log)?);
+        self.lock().insert(path.to_string(), module.clone());
+        Ok(module)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Module>>> {
+        self.modules
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Collects the results of the modules as they run, stopping after the first one which fails if
+/// `fail_fast` is set. The modules run lazily, so that those after it are skipped altogether.
+/// Fails if the progress of a module could not be written.
+fn collect_results<I: Iterator<Item = Result<ModuleResult, ChiselError>>>(
+    results: I,
+    fail_fast: bool,
+) -> Result<Vec<ModuleResult>, ChiselError> {
+    let mut collected = Vec::new();
+    for result in results {
+        let result = result?;
+        let failed = result.failed();
+        collected.push(result);
+        if failed && fail_fast {
+            break;
+        }
+    }
+    Ok(collected)
+}
+
+/// Runs every module of the ruleset in order on a single binary, returning the resulting module
+/// alongside the results. Validators run on the cached module directly, which is only copied if
+/// the ruleset has a translator. With `fail_fast`, the modules after the first failing one are
+/// not run. Progress is written to `log` in verbose mode.
+fn chisel_execute_file(
+    context: &ChiselContext,
+    path: &str,
+    verbosity: Verbosity,
+    backend: Backend,
+    fail_fast: bool,
+    cache: &ModuleCache,
+    log: &mut dyn Write,
+) -> Result<(Option<Arc<Module>>, Vec<ModuleResult>), ChiselError> {
+    let pipeline = context.pipeline()?;
+    let format = context.input_format();
+    if backend == Backend::Fast && !is_text(path, format) {
+        let (module, results) =
+            chisel_execute_binary(context, &pipeline, path, verbosity, fail_fast, log)?;
+        return Ok((module.map(Arc::new), results));
+    }
+
+    if backend == Backend::Stream && !is_text(path, format) && interface_only(&pipeline) {
+        let results = chisel_execute_stream(context, &pipeline, path, verbosity, fail_fast, log)?;
+        return Ok((None, results));
+    }
+
+    let shared = cache.load(path, format, verbosity, log)?;
+    let stages = context.get_modules().iter().zip(pipeline.stages());
+    if pipeline.stages().any(|(_, stage)| stage.is_translator()) {
+        let mut module = (*shared).clone();
+        let results = collect_results(
+            stages.map(|(ctx, (_, stage))| execute_module(ctx, stage, &mut module, verbosity, log)),
+            fail_fast,
+        )?;
+        print_module_size(&module, verbosity, log)?;
+        return Ok((Some(Arc::new(module)), results));
+    }
+
+    let results = collect_results(
+        stages.map(|(ctx, (_, stage))| {
+            execute_stage(ctx, stage, verbosity, log, |_| match stage {
+                Stage::Validator(validator) => {
+                    validator.validate(&shared).map(StageOutcome::Validated)
+                }
+                Stage::Translator(_) => unreachable!("the ruleset has no translator"),
+            })
+        }),
+        fail_fast,
+    )?;
+    print_module_size(&shared, verbosity, log)?;
+    Ok((Some(shared), results))
+}
+
+/// Returns whether every stage of the pipeline is a validator which only needs the interface of
+/// the module, so that the binary can be streamed rather than read into memory.
+fn interface_only(pipeline: &Chisel) -> bool {
+    pipeline.stages().all(|(_, stage)| match stage {
+        Stage::Validator(validator) => validator.interface_only(),
+        Stage::Translator(_) => false,
+    })
+}
+
+/// Runs the pipeline with the stream backend, on the interface of the module read incrementally
+/// from the file, so that its code and data are never held in memory.
+fn chisel_execute_stream(
+    context: &ChiselContext,
+    pipeline: &Chisel,
+    path: &str,
+    verbosity: Verbosity,
+    fail_fast: bool,
+    log: &mut dyn Write,
+) -> Result<Vec<ModuleResult>, ChiselError> {
+    let module = if path == STDIN_PATH {
+        stream::read_interface(io::stdin().lock())
+    } else {
+        let file = File::open(path).map_err(ChiselError::OpenBinary)?;
+        stream::read_interface(BufReader::new(file))
+    }
+    .map_err(ChiselError::Deserialize)?;
+
+    let stages = context.get_modules().iter().zip(pipeline.stages());
+    collect_results(
+        stages.map(|(ctx, (_, stage))| {
+            execute_stage(ctx, stage, verbosity, log, |_| match stage {
+                Stage::Validator(validator) => {
+                    validator.validate(&module).map(StageOutcome::Validated)
+                }
+                Stage::Translator(_) => unreachable!("the ruleset has no translator"),
+            })
+        }),
+        fail_fast,
+    )
+}
+
+/// Runs the pipeline on the binary with the fast backend. Stages run on the binary until one
+/// needs the deserialized module, which is then deserialized once for it and every later stage.
+fn chisel_execute_binary(
+    context: &ChiselContext,
+    pipeline: &Chisel,
+    path: &str,
+    verbosity: Verbosity,
+    fail_fast: bool,
+    log: &mut dyn Write,
+) -> Result<(Option<Module>, Vec<ModuleResult>), ChiselError> {
+    let buffer = read_input(path, verbosity, log)?;
+    let mut module: Option<Module> = None;
+    let mut results = Vec::new();
+    for (ctx, (_, stage)) in context.get_modules().iter().zip(pipeline.stages()) {
+        let mut load_error = None;
+        let result = execute_stage(ctx, stage, verbosity, log, |log| {
+            if module.is_none() {
+                if let Some(outcome) = stage.run_binary(&buffer) {
+                    return outcome;
+                }
+                let parsed =
+                    parse_module(path, context.input_format(), &buffer).and_then(|parsed| {
+                        print_module_summary(path, &parsed, verbosity, log)?;
+                        Ok(parsed)
+                    });
+                match parsed {
+                    Ok(parsed) => module = Some(parsed),
+                    Err(err) => {
+                        load_error = Some(err);
+                        return Err(String::new());
+                    }
+                }
+            }
+            stage.run(module.as_mut().expect("module was deserialized"))
+        })?;
+        if let Some(err) = load_error {
+            return Err(err);
+        }
+        let failed = result.failed();
+        results.push(result);
+        if failed && fail_fast {
+            break;
+        }
+    }
+    if let Some(ref module) = module {
+        print_module_size(module, verbosity, log)?;
+    }
+    Ok((module, results))
+}
+
+/// Runs the ruleset against each of its files, using up to `jobs` threads. The results are
+/// returned in the order of the files regardless of which finishes first. Fails only if the files
+/// cannot be resolved; a binary which fails to load is reported in its own results. With
+/// `fail_fast`, no file is started once one has failed, and the results of the files which were
+/// not run are left out. The progress of each file is collected while it runs, and written to
+/// `log` in the order of the files once all of them are done.
+pub fn chisel_execute(
+    context: &ChiselContext,
+    verbosity: Verbosity,
+    backend: Backend,
+    jobs: usize,
+    fail_fast: bool,
+    cache: &ModuleCache,
+    log: &mut dyn Write,
+) -> Result<Vec<FileResults>, ChiselError> {
+    let files = context.resolve_files()?;
+    let workers = jobs.clamp(1, files.len().max(1));
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+
+    let mut results: Vec<(usize, FileResults, Vec<u8>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        if failed.load(Ordering::Relaxed) {
+                            return done;
+                        }
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let file = match files.get(index) {
+                            Some(file) => file.clone(),
+                            None => return done,
+                        };
+                        let mut file_log = Vec::new();
+                        let outcome = chisel_execute_file(
+                            context,
+                            &file,
+                            verbosity,
+                            backend,
+                            fail_fast,
+                            cache,
+                            &mut file_log,
+                        );
+                        let passed = outcome
+                            .as_ref()
+                            .is_ok_and(|(_, results)| !results.iter().any(|r| r.failed()));
+                        if fail_fast && !passed {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        done.push((index, FileResults { file, outcome }, file_log));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(index, _, _)| *index);
+    let mut files = Vec::with_capacity(results.len());
+    for (_, result, file_log) in results {
+        log.write_all(&file_log)?;
+        files.push(result);
+    }
+    Ok(files)
+}
+
+/// Serializes the module to the given path. The binary is written to a temporary file first and
+/// then renamed, so that the destination is never left half-written even when it is the input.
+pub fn write_module(module: Module, path: &str) -> Result<(), ChiselError> {
+    let buffer = serialize(module).map_err(|err| {
+        ChiselError::WriteBinary(io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    })?;
+    let tmp_path = format!("{}.chisel-tmp", path);
+    if let Err(err) = write(&tmp_path, buffer).and_then(|_| rename(&tmp_path, path)) {
+        let _ = remove_file(&tmp_path);
+        return Err(ChiselError::WriteBinary(err));
+    }
+    Ok(())
+}
+
+/// Runs every module of the ruleset in order on the module, so that each validator checks the
+/// module as the translators before it left it. Returns the transformed module only if all
+/// validators passed and all translators succeeded. On a dry run, or if `diff` is set, the changes
+/// made by each translator are collected as well. Progress is written to `log` in verbose mode.
+pub fn chisel_translate(
+    context: &ChiselContext,
+    verbosity: Verbosity,
+    dry_run: bool,
+    diff: bool,
+    log: &mut dyn Write,
+) -> Result<TranslateResults, ChiselError> {
+    let pipeline = context.pipeline()?;
+    let mut module = load_module(
+        context.single_file()?,
+        context.input_format(),
+        verbosity,
+        log,
+    )?;
+
+    let mut diffs = Vec::new();
+    let mut section_diffs = Vec::new();
+    let results = context
+        .get_modules()
+        .iter()
+        .zip(pipeline.stages())
+        .map(|(ctx, (_, stage))| {
+            if !stage.is_translator() || (!dry_run && !diff) {
+                return execute_module(ctx, stage, &mut module, verbosity, log);
+            }
+            let before = module.clone();
+            let result = execute_module(ctx, stage, &mut module, verbosity, log);
+            if dry_run {
+                diffs.push(ModuleDiff::between(&before, &module));
+            }
+            if diff {
+                section_diffs.push(SectionDiff::between(&before, &module));
+            }
+            result
+        })
+        .collect::<Result<Vec<ModuleResult>, ChiselError>>()?;
+    let succeeded = !results.iter().any(|r| r.failed());
+    print_module_size(&module, verbosity, log)?;
+
+    Ok(TranslateResults {
+        module: if succeeded { Some(module) } else { None },
+        results,
+        diffs,
+        section_diffs,
+    })
+}
+
+/// Runs the module, discarding its log.
+#[cfg(test)]
+pub fn execute(ctx: &ModuleContext, module: &mut Module, verbosity: Verbosity) -> ModuleResult {
+    execute_module(
+        ctx,
+        &ctx.stage().unwrap(),
+        module,
+        verbosity,
+        &mut io::sink(),
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{ConfigFormat, ConfigValue};
+    use libchisel::diff::Change;
+    use report::Verbosity;
+    use ruleset::{configure, parse_config, ChiselContext, ModuleContext, Severity};
+    use std::sync::OnceLock;
+
+    #[test]
+    fn backends_agree() {
+        let dir = std::env::temp_dir().join("chisel_backends_test");
+        let _ = std::fs::create_dir(&dir);
+        let file = dir.join("a.wasm").to_string_lossy().into_owned();
+        let module = FromWat::new(
+            r#"(module
+                (import "ethereum" "useGas" (func (param i64)))
+                (import "env" "abort" (func))
+                (func (export "main") (drop (f32.const 1.5))))"#,
+        )
+        .create()
+        .unwrap();
+        write(&file, serialize(module).unwrap()).unwrap();
+
+        let yaml = format!(
+            "ewasm:\n  file: \"{}\"\n  verifyimports:\n    preset: \"ewasm\"\n  checkfloat: {{}}\n  checkstartfunc: {{}}\n  verifyexports:\n    preset: \"ewasm\"\n",
+            file
+        );
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let outcomes: Vec<_> = [Backend::Parity, Backend::Fast]
+            .iter()
+            .map(|backend| {
+                let results = chisel_execute(
+                    &ctx,
+                    Verbosity::Quiet,
+                    *backend,
+                    1,
+                    false,
+                    &ModuleCache::default(),
+                    &mut io::sink(),
+                )
+                .unwrap();
+                let (module, results) = results.into_iter().next().unwrap().outcome.unwrap();
+                let results: Vec<(String, Vec<String>)> = results
+                    .into_iter()
+                    .map(|result| (result.status(), result.reasons))
+                    .collect();
+                (module.is_some(), results)
+            })
+            .collect();
+        assert_eq!(outcomes[0].1, outcomes[1].1);
+        assert_eq!(outcomes[0].1[1].0, "BAD");
+        assert_eq!(outcomes[0].1[3].0, "BAD");
+        // checkstartfunc needs the deserialized module, so the fast backend loads it as well.
+        assert!(outcomes[0].0 && outcomes[1].0);
+
+        let yaml = format!("ewasm:\n  file: \"{}\"\n  checkfloat: {{}}\n", file);
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let results = chisel_execute(
+            &ctx,
+            Verbosity::Quiet,
+            Backend::Fast,
+            1,
+            false,
+            &ModuleCache::default(),
+            &mut io::sink(),
+        )
+        .unwrap();
+        let (module, results) = results.into_iter().next().unwrap().outcome.unwrap();
+        assert!(module.is_none());
+        assert_eq!(results[0].status(), "BAD");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn translate_collects_section_diffs() {
+        let dir = std::env::temp_dir().join("chisel_translate_diff_test");
+        let _ = std::fs::create_dir(&dir);
+        let file = dir.join("a.wasm").to_string_lossy().into_owned();
+        let module = FromWat::new(
+            r#"(module
+                (func (export "main"))
+                (func (export "helper")))"#,
+        )
+        .create()
+        .unwrap();
+        write(&file, serialize(module).unwrap()).unwrap();
+
+        let yaml = format!(
+            "ewasm:\n  file: \"{}\"\n  trimexports:\n    preset: \"ewasm\"\n",
+            file
+        );
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let results =
+            chisel_translate(&ctx, Verbosity::Quiet, false, false, &mut io::sink()).unwrap();
+        assert!(results.section_diffs.is_empty());
+
+        let results =
+            chisel_translate(&ctx, Verbosity::Quiet, false, true, &mut io::sink()).unwrap();
+        assert!(results.diffs.is_empty());
+        let diff = results.section_diffs[0].as_ref().unwrap();
+        assert_eq!(diff.exports, vec![Change::Removed("helper".to_string())]);
+        assert!(diff
+            .to_string()
+            .starts_with("~ section export (19 -> 10 bytes)\n- export `helper`\n"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn translate_runs_modules_in_order() {
+        let dir = std::env::temp_dir().join("chisel_translate_order_test");
+        let _ = std::fs::create_dir(&dir);
+        let file = dir.join("a.wasm").to_string_lossy().into_owned();
+        let module = FromWat::new(
+            r#"(module
+                (import "env" "ethereum_finish" (func (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "main")))"#,
+        )
+        .create()
+        .unwrap();
+        write(&file, serialize(module).unwrap()).unwrap();
+
+        let translate = |modules: &[&str]| {
+            let mut yaml = format!("ewasm:\n  file: \"{}\"\n", file);
+            for module in modules {
+                yaml.push_str(&format!("  {}:\n    preset: \"ewasm\"\n", module));
+            }
+            let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+            let results =
+                chisel_translate(&ctx, Verbosity::Quiet, false, false, &mut io::sink()).unwrap();
+            let statuses: Vec<(String, String)> = results
+                .results
+                .iter()
+                .map(|result| (result.module_name.clone(), result.status()))
+                .collect();
+            (results.module.is_some(), statuses)
+        };
+
+        // verifyimports checks the imports as remapimports left them.
+        let (written, statuses) = translate(&["remapimports", "verifyimports"]);
+        assert!(written);
+        assert_eq!(
+            statuses[0],
+            ("remapimports".to_string(), "MODIFIED".to_string())
+        );
+        assert_eq!(
+            statuses[1],
+            ("verifyimports".to_string(), "GOOD".to_string())
+        );
+
+        let (written, statuses) = translate(&["verifyimports", "remapimports"]);
+        assert!(!written);
+        assert_eq!(
+            statuses[0],
+            ("verifyimports".to_string(), "BAD".to_string())
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cached_module_shared_by_rulesets() {
+        let dir = std::env::temp_dir().join("chisel_module_cache_test");
+        let _ = std::fs::create_dir(&dir);
+        let file = dir.join("a.wasm").to_string_lossy().into_owned();
+        let module = FromWat::new(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "main"))
+                (func (export "other")))"#,
+        )
+        .create()
+        .unwrap();
+        write(&file, serialize(module).unwrap()).unwrap();
+
+        // The translator of the first ruleset must not leak into the module seen by the others.
+        let yaml = format!(
+            "check:\n  file: \"{file}\"\n  verifyexports:\n    preset: \"ewasm\"\n\
+             trim:\n  file: \"{file}\"\n  trimexports:\n    preset: \"ewasm\"\n  verifyexports:\n    preset: \"ewasm\"\n\
+             recheck:\n  file: \"{file}\"\n  verifyexports:\n    preset: \"ewasm\"\n",
+            file = file
+        );
+        let config = parse_config(&yaml, ConfigFormat::Yaml).unwrap();
+        let run = |cache: &ModuleCache, name: &str| {
+            let ctx = ChiselContext::from_ruleset(&config, Some(name)).unwrap();
+            let results = chisel_execute(
+                &ctx,
+                Verbosity::Quiet,
+                Backend::Parity,
+                1,
+                false,
+                cache,
+                &mut io::sink(),
+            )
+            .unwrap();
+            let (module, results) = results.into_iter().next().unwrap().outcome.unwrap();
+            let statuses: Vec<String> = results.iter().map(|result| result.status()).collect();
+            (module.unwrap(), statuses)
+        };
+
+        let cache = ModuleCache::default();
+        let rulesets = ["check", "trim", "recheck"];
+        let cached: Vec<_> = rulesets.iter().map(|name| run(&cache, name)).collect();
+        let naive: Vec<_> = rulesets
+            .iter()
+            .map(|name| run(&ModuleCache::default(), name))
+            .collect();
+        for (cached, naive) in cached.iter().zip(naive.iter()) {
+            assert_eq!(cached.1, naive.1);
+            assert_eq!(*cached.0, *naive.0);
+        }
+        assert_eq!(cached[0].1, ["BAD"]);
+        assert_eq!(cached[1].1, ["MODIFIED", "GOOD"]);
+        assert_eq!(cached[2].1, ["BAD"]);
+
+        // Validators only borrow the cached module, while translators work on a copy.
+        assert!(Arc::ptr_eq(&cached[0].0, &cached[2].0));
+        assert!(!Arc::ptr_eq(&cached[0].0, &cached[1].0));
+        assert_eq!(cache.lock().len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn execute_files_in_order() {
+        let dir = std::env::temp_dir().join("chisel_execute_files_test");
+        let _ = std::fs::create_dir(&dir);
+        let dir = dir.to_str().unwrap();
+        let files: Vec<String> = (0..8).map(|i| format!("{}/{}.wasm", dir, i)).collect();
+        for (i, file) in files.iter().enumerate() {
+            // Every third file is not a valid binary.
+            if i % 3 == 0 {
+                write(file, b"not wasm").unwrap();
+            } else {
+                write(file, serialize(Module::default()).unwrap()).unwrap();
+            }
+        }
+
+        let ctx = ChiselContext {
+            ruleset_name: "ewasm".to_string(),
+            files: files.clone(),
+            input_format: None,
+            modules: vec![],
+        };
+        for jobs in [1, 3, 16].iter() {
+            let results = chisel_execute(
+                &ctx,
+                Verbosity::Quiet,
+                Backend::Parity,
+                *jobs,
+                false,
+                &ModuleCache::default(),
+                &mut io::sink(),
+            )
+            .unwrap();
+            assert_eq!(
+                results.iter().map(|r| r.file.clone()).collect::<Vec<_>>(),
+                files
+            );
+            for (i, result) in results.iter().enumerate() {
+                assert_eq!(result.outcome.is_err(), i % 3 == 0);
+            }
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fail_fast_stops_at_first_failure() {
+        let dir = std::env::temp_dir().join("chisel_fail_fast_test");
+        let _ = std::fs::create_dir(&dir);
+        let files: Vec<String> = (0..2)
+            .map(|i| {
+                dir.join(format!("{}.wasm", i))
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        for file in files.iter() {
+            write(file, serialize(Module::default()).unwrap()).unwrap();
+        }
+
+        let yaml = format!(
+            "ewasm:\n  file: [\"{}\", \"{}\"]\n  verifyexports:\n    preset: \"ewasm\"\n  checkstartfunc: {{}}\n",
+            files[0], files[1]
+        );
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let run = |fail_fast| {
+            chisel_execute(
+                &ctx,
+                Verbosity::Quiet,
+                Backend::Parity,
+                1,
+                fail_fast,
+                &ModuleCache::default(),
+                &mut io::sink(),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|file| file.outcome.unwrap().1.len())
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(run(false), vec![2, 2]);
+        // verifyexports fails on the first file, so neither checkstartfunc nor the second file
+        // runs.
+        assert_eq!(run(true), vec![1]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn translator_reports_mutation() {
+        let mut module = parity_wasm::builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("foo")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        let ctx = ModuleContext {
+            module_name: "trimexports".to_string(),
+            preset: None,
+            severity: Severity::Error,
+            enabled: true,
+            options: ConfigValue::Map(vec![]),
+            loaded_options: OnceLock::new(),
+        };
+
+        let result = execute(&ctx, &mut module, Verbosity::Normal);
+        assert_eq!(result.status(), "MODIFIED");
+        let result = execute(&ctx, &mut module, Verbosity::Normal);
+        assert_eq!(result.status(), "UNCHANGED");
+    }
+
+    #[test]
+    fn module_diff_summary() {
+        let before = parity_wasm::builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("foo")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("bar")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        let mut after = before.clone();
+        let ctx = ModuleContext {
+            module_name: "trimexports".to_string(),
+            preset: None,
+            severity: Severity::Error,
+            enabled: true,
+            options: ConfigValue::Map(vec![]),
+            loaded_options: OnceLock::new(),
+        };
+        execute(&ctx, &mut after, Verbosity::Normal);
+
+        let diff = ModuleDiff::between(&before, &after);
+        assert_eq!(diff.removed_exports, vec!["foo", "bar"]);
+        assert!(diff.added_exports.is_empty());
+        assert!(diff.summary().starts_with("would remove 2 exports ("));
+        assert_eq!(
+            ModuleDiff::between(&after, &after).summary(),
+            "would not change the module"
+        );
+    }
+
+    #[test]
+    fn timing_only_in_verbose_mode() {
+        let ctx = ModuleContext {
+            module_name: "verifyexports".to_string(),
+            preset: None,
+            severity: Severity::Error,
+            enabled: true,
+            options: ConfigValue::Map(vec![]),
+            loaded_options: OnceLock::new(),
+        };
+        let mut module = Module::default();
+        assert!(execute(&ctx, &mut module, Verbosity::Normal)
+            .elapsed
+            .is_none());
+        assert!(execute(&ctx, &mut module, Verbosity::Verbose)
+            .elapsed
+            .is_some());
+    }
+
+    #[test]
+    fn write_module_overwrites_atomically() {
+        let path = std::env::temp_dir().join("chisel_write_module_test.wasm");
+        let path = path.to_str().unwrap();
+        write(path, b"not wasm").unwrap();
+
+        write_module(Module::default(), path).unwrap();
+        assert_eq!(read(path).unwrap(), serialize(Module::default()).unwrap());
+        assert!(read(format!("{}.chisel-tmp", path)).is_err());
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_module_from_text() {
+        let path = std::env::temp_dir().join("chisel_load_module_test.wat");
+        let path = path.to_str().unwrap();
+        write(path, "(module (memory 1) (export \"memory\" (memory 0)))").unwrap();
+        let module = load_module(path, None, Verbosity::Normal, &mut io::sink()).unwrap();
+        assert_eq!(export_names(&module), vec!["memory"]);
+
+        write(path, "(module\n  (memory))").unwrap();
+        let err = load_module(path, None, Verbosity::Normal, &mut io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            ChiselError::Create(libchisel::error::ChiselError::ParseText(_))
+        ));
+        assert!(err.to_string().contains(":2:"));
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn input_format_overrides_extension() {
+        let path = std::env::temp_dir().join("chisel_input_format_test");
+        let path = path.to_str().unwrap();
+        write(path, "(module (memory 1) (export \"memory\" (memory 0)))").unwrap();
+        let err = load_module(path, None, Verbosity::Normal, &mut io::sink()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to read the input as wasm, as it does not start with the wasm magic number. \
+             Set its format with --input-format."
+        );
+        let module = load_module(
+            path,
+            Some(InputFormat::Wat),
+            Verbosity::Normal,
+            &mut io::sink(),
+        )
+        .unwrap();
+        assert_eq!(export_names(&module), vec!["memory"]);
+
+        write(path, serialize(module).unwrap()).unwrap();
+        assert!(load_module(
+            path,
+            Some(InputFormat::Wasm),
+            Verbosity::Normal,
+            &mut io::sink()
+        )
+        .is_ok());
+        let err = load_module(
+            path,
+            Some(InputFormat::Wat),
+            Verbosity::Normal,
+            &mut io::sink(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to read the input as wat, as it is a wasm binary. \
+             Set its format with --input-format."
+        );
+        remove_file(path).unwrap();
+    }
+}
@@ -1,30 +1,543 @@
+#[macro_use]
+extern crate clap;
+extern crate glob;
 extern crate libchisel;
 extern crate parity_wasm;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_yaml;
+extern crate toml;
+
+mod config;
+mod disassemble;
+mod error;
+mod execute;
+mod hash;
+mod registry;
+mod report;
+mod ruleset;
+mod subcommands;
+mod summary;
 
 use std::env;
+use std::fmt;
+use std::io::{self, Write};
+use std::process;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use error::ChiselError;
+use report::{Verbosity, OUTPUT_FORMATS};
+use subcommands::{
+    chisel_explain, chisel_subcommand_diff, chisel_subcommand_disassemble, chisel_subcommand_hash,
+    chisel_subcommand_init, chisel_subcommand_list_modules, chisel_subcommand_run,
+    chisel_subcommand_translate, color_from_args, verbosity_from_args,
+};
+
+/// Exit code for fatal errors, such as a configuration which cannot be parsed.
+static EXIT_FATAL: i32 = 255;
+/// Number of exit code bits used to report failing modules. The last bit is shared by every
+/// module from that index onwards, so that the bitmask never reaches `EXIT_FATAL`.
+static EXIT_MODULE_BITS: usize = 7;
 
-use libchisel::*;
+fn err_exit<E: fmt::Display>(err: E) -> ! {
+    eprintln!("{}: {}", crate_name!(), err);
+    process::exit(EXIT_FATAL);
+}
+
+/// Declares the command line interface, with every subcommand and its arguments.
+fn app() -> App<'static, 'static> {
+    App::new("chisel")
+        .version(crate_version!())
+        .about(crate_description!())
+        .arg(
+            Arg::with_name("VERBOSE")
+                .short("v")
+                .long("verbose")
+                .help("Prints module presets and sizes in addition to the results")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("QUIET")
+                .short("q")
+                .long("quiet")
+                .help("Prints nothing, only reporting the result in the exit code")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("NO_COLOR")
+                .long("no-color")
+                .help("Disables colored output")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("EXPLAIN")
+                .long("explain")
+                .help("Prints the rules a validator applies, without running it")
+                .value_name("MODULE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("PRESET")
+                .long("preset")
+                .help("Sets the preset of the module to explain")
+                .value_name("NAME")
+                .takes_value(true)
+                .requires("EXPLAIN"),
+        )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Runs chisel with the closest configuration file.")
+                .arg(
+                    Arg::with_name("CONFIG")
+                        .short("c")
+                        .long("config")
+                        .help("Sets a custom configuration file")
+                        .value_name("CONF_FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("CONFIG_FORMAT")
+                        .long("config-format")
+                        .help("Sets the format of the configuration file, instead of detecting it from the extension")
+                        .possible_values(&["yaml", "toml", "json"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("STRICT_CONFIG")
+                        .long("strict-config")
+                        .help("Rejects options which a module does not accept, rather than ignoring them"),
+                )
+                .arg(
+                    Arg::with_name("RULESET")
+                        .short("r")
+                        .long("ruleset")
+                        .help("Selects the ruleset to run, instead of the first one")
+                        .value_name("NAME")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("PRESET")
+                        .long("preset")
+                        .help("Overrides the preset of every module which takes one")
+                        .value_name("NAME")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .long("format")
+                        .help("Sets the output format of the results")
+                        .value_name("FORMAT")
+                        .possible_values(OUTPUT_FORMATS)
+                        .default_value("text")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("REPORT")
+                        .long("report")
+                        .help("Also writes the results to a file in the given format, e.g. 'results.xml:junit'")
+                        .value_name("FILE:FORMAT")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("STDIN")
+                        .long("stdin")
+                        .help("Reads the wasm binary from stdin instead of the configured file"),
+                )
+                .arg(
+                    Arg::with_name("INPUT_FORMAT")
+                        .long("input-format")
+                        .help("Reads the input in the given format, instead of detecting it from the extension")
+                        .possible_values(&["wasm", "wat"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("ALL")
+                        .long("all")
+                        .help("Runs every ruleset in the configuration file")
+                        .conflicts_with("RULESET"),
+                )
+                .arg(
+                    Arg::with_name("OUTPUT")
+                        .short("o")
+                        .long("output")
+                        .help("Writes the resulting binary to a file if all modules pass")
+                        .value_name("OUT_FILE")
+                        .takes_value(true)
+                        .conflicts_with("ALL"),
+                )
+                .arg(
+                    Arg::with_name("JOBS")
+                        .short("j")
+                        .long("jobs")
+                        .help("Sets the number of files processed concurrently (default: number of CPUs)")
+                        .value_name("N")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("BACKEND")
+                        .long("backend")
+                        .help("Sets how the binary is read: 'fast' lets validators which support it skip deserializing the whole module (default: parity)")
+                        .value_name("BACKEND")
+                        .possible_values(&["parity", "fast", "stream"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("FAIL_FAST")
+                        .long("fail-fast")
+                        .help("Stops at the first failing module, skipping the modules, files and rulesets after it")
+                        .overrides_with("KEEP_GOING"),
+                )
+                .arg(
+                    Arg::with_name("KEEP_GOING")
+                        .long("keep-going")
+                        .help("Runs every module even after one fails, to report all failures (default)")
+                        .overrides_with("FAIL_FAST"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("translate")
+                .about("Runs the configured translators and writes the transformed binary.")
+                .arg(
+                    Arg::with_name("CONFIG")
+                        .short("c")
+                        .long("config")
+                        .help("Sets a custom configuration file")
+                        .value_name("CONF_FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("CONFIG_FORMAT")
+                        .long("config-format")
+                        .help("Sets the format of the configuration file, instead of detecting it from the extension")
+                        .possible_values(&["yaml", "toml", "json"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("STRICT_CONFIG")
+                        .long("strict-config")
+                        .help("Rejects options which a module does not accept, rather than ignoring them"),
+                )
+                .arg(
+                    Arg::with_name("RULESET")
+                        .short("r")
+                        .long("ruleset")
+                        .help("Selects the ruleset to run, instead of the first one")
+                        .value_name("NAME")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("PRESET")
+                        .long("preset")
+                        .help("Overrides the preset of every module which takes one")
+                        .value_name("NAME")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("OUTPUT")
+                        .short("o")
+                        .long("output")
+                        .help("Sets the output file, overwriting the input file by default")
+                        .value_name("OUT_FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("STDIN")
+                        .long("stdin")
+                        .help("Reads the wasm binary from stdin instead of the configured file"),
+                )
+                .arg(
+                    Arg::with_name("INPUT_FORMAT")
+                        .long("input-format")
+                        .help("Reads the input in the given format, instead of detecting it from the extension")
+                        .possible_values(&["wasm", "wat"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("DRY_RUN")
+                        .long("dry-run")
+                        .help("Prints the changes the translators would make without writing them")
+                        .conflicts_with("OUTPUT"),
+                )
+                .arg(
+                    Arg::with_name("DIFF")
+                        .long("diff")
+                        .help("Prints the sections, imports and exports changed by each translator"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list-modules")
+                .about("Lists the available chisel modules and their presets.")
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .long("format")
+                        .help("Sets the output format")
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("PRESETS")
+                        .long("presets")
+                        .help("Lists the presets each module accepts as well"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("disassemble")
+                .about("Prints the structure of a wasm binary in the text format.")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Sets the binary to disassemble, reading it from stdin if it is '-'")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("INPUT_FORMAT")
+                        .long("input-format")
+                        .help("Reads the input in the given format, instead of detecting it from the extension")
+                        .possible_values(&["wasm", "wat"])
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("hash")
+                .about("Prints digests of a wasm binary, to pin it in a manifest.")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Sets the binary to hash, reading it from stdin if it is '-'")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("INPUT_FORMAT")
+                        .long("input-format")
+                        .help("Reads the input in the given format, instead of detecting it from the extension")
+                        .possible_values(&["wasm", "wat"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("REPACK")
+                        .long("repack")
+                        .help("Hashes the module re-encoded with its sections in canonical order"),
+                )
+                .arg(
+                    Arg::with_name("KECCAK")
+                        .long("keccak")
+                        .help("Prints the Keccak-256 digest as well"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compares the sections, imports and exports of two wasm binaries.")
+                .arg(
+                    Arg::with_name("BEFORE")
+                        .help("Sets the binary to compare against, reading it from stdin if it is '-'")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("AFTER")
+                        .help("Sets the binary to compare, reading it from stdin if it is '-'")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::with_name("INPUT_FORMAT")
+                        .long("input-format")
+                        .help("Reads the input in the given format, instead of detecting it from the extension")
+                        .possible_values(&["wasm", "wat"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .long("format")
+                        .help("Sets the output format")
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("init")
+                .about("Writes a sample configuration file.")
+                .arg(
+                    Arg::with_name("CONFIG")
+                        .short("c")
+                        .long("config")
+                        .help("Sets the path of the configuration file to write")
+                        .value_name("CONF_FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("FORCE")
+                        .long("force")
+                        .help("Overwrites the configuration file if it already exists"),
+                ),
+        )
+}
 
 pub fn main() {
-    let args = env::args().collect::<Vec<_>>();
-    if args.len() != 3 {
-        println!("Usage: {} in.wasm out.wasm", args[0]);
-        return;
+    let cli_matches = app().get_matches();
+
+    let code =
+        verbosity_from_args(&cli_matches, cli_matches.subcommand().1).and_then(|verbosity| {
+            let color = color_from_args(&cli_matches, cli_matches.subcommand().1);
+            // Output goes to stdout, while diagnostics and errors go to stderr.
+            chisel_dispatch(
+                &cli_matches,
+                verbosity,
+                color,
+                &mut io::stdout(),
+                &mut io::stderr(),
+            )
+        });
+    // Errors are only reported here, so that the subcommands never exit by themselves.
+    process::exit(code.unwrap_or_else(|err| err_exit(err)));
+}
+
+/// Runs the subcommand, writing its output to `out` and its diagnostics, such as warnings and
+/// the progress printed in verbose mode, to `log`. Returns the exit code, or the error which
+/// stopped the subcommand.
+fn chisel_dispatch(
+    matches: &ArgMatches,
+    verbosity: Verbosity,
+    color: bool,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    if let Some(name) = matches.value_of("EXPLAIN") {
+        return chisel_explain(name, matches.value_of("PRESET"), out);
     }
 
-    let code = std::fs::read(&args[1]).expect("Failed to open and read file");
+    match matches.subcommand() {
+        ("run", Some(args)) => chisel_subcommand_run(args, verbosity, color, out, log),
+        ("translate", Some(args)) => chisel_subcommand_translate(args, verbosity, color, out, log),
+        ("list-modules", Some(args)) => chisel_subcommand_list_modules(args, out),
+        ("disassemble", Some(args)) => chisel_subcommand_disassemble(args, verbosity, out, log),
+        ("hash", Some(args)) => chisel_subcommand_hash(args, verbosity, out, log),
+        ("diff", Some(args)) => chisel_subcommand_diff(args, verbosity, out, log),
+        ("init", Some(args)) => chisel_subcommand_init(args, verbosity, out),
+        _ => Err(ChiselError::NoSubcommand),
+    }
+}
 
-    let mut module = parity_wasm::deserialize_buffer(&code).expect("Failed to load module");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libchisel::fromwat::FromWat;
+    use libchisel::ModuleCreator;
+    use parity_wasm::elements::serialize;
+    use registry::MODULES;
+    use report::Verbosity;
+    use std::fs::write;
 
-    let trimexports = trimexports::TrimExports::with_preset("ewasm").unwrap();
-    trimexports
-        .translate(&mut module)
-        .expect("Failed to trim exports");
+    /// Runs chisel with the arguments, returning its exit code and output.
+    fn dispatch(args: &[&str]) -> (i32, String) {
+        let matches = app().get_matches_from(args);
+        let mut out = Vec::new();
+        let code = chisel_dispatch(
+            &matches,
+            Verbosity::Normal,
+            false,
+            &mut out,
+            &mut io::sink(),
+        )
+        .unwrap();
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn subcommand_output_captured() {
+        let (code, out) = dispatch(&["chisel", "list-modules"]);
+        assert_eq!(code, 0);
+        assert_eq!(out.lines().next(), Some("verifystructure (validator)"));
+        assert_eq!(out.lines().count(), MODULES.len());
+
+        let (_, out) = dispatch(&["chisel", "--explain", "verifyglobals"]);
+        assert!(out.starts_with("verifyglobals:\n\t- "));
+
+        let dir = std::env::temp_dir().join("chisel_dispatch_test");
+        let _ = std::fs::create_dir(&dir);
+        let module = FromWat::new(r#"(module (func (export "main")))"#)
+            .create()
+            .unwrap();
+        write(dir.join("contract.wasm"), serialize(module).unwrap()).unwrap();
+        let config = dir.join("chisel.yml");
+        write(
+            &config,
+            "ewasm:\n  file: \"contract.wasm\"\n  verifyexports:\n    preset: \"ewasm\"\n",
+        )
+        .unwrap();
+        let (code, out) = dispatch(&["chisel", "run", "-c", config.to_str().unwrap()]);
+        assert_eq!(code, 1);
+        assert!(out.contains("verifyexports"));
+        assert!(out.contains("memory"));
+
+        // Progress goes to the log, and errors are returned rather than exiting.
+        let matches = app().get_matches_from(["chisel", "run", "-c", config.to_str().unwrap()]);
+        let (mut out, mut log) = (Vec::new(), Vec::new());
+        let code = chisel_dispatch(&matches, Verbosity::Verbose, false, &mut out, &mut log);
+        assert_eq!(code.unwrap(), 1);
+        let log = String::from_utf8(log).unwrap();
+        assert!(log.contains("Running verifyexports with preset ewasm\n"));
+        assert!(!String::from_utf8(out).unwrap().contains("Running"));
 
-    let remapimports = remapimports::RemapImports::with_preset("ewasm").unwrap();
-    remapimports
-        .translate(&mut module)
-        .expect("Failed to remap imports");
+        // An unknown preset is an error of the configuration, not a failing module.
+        let typo = dir.join("typo.yml");
+        write(
+            &typo,
+            "ewasm:\n  file: \"contract.wasm\"\n  verifyexports:\n    preset: \"ewasmm\"\n",
+        )
+        .unwrap();
+        let matches = app().get_matches_from(["chisel", "run", "-c", typo.to_str().unwrap()]);
+        assert!(matches!(
+            chisel_dispatch(&matches, Verbosity::Normal, false, &mut io::sink(), &mut io::sink()),
+            Err(ChiselError::InvalidPreset(_, ref preset, _)) if preset == "ewasmm"
+        ));
 
-    parity_wasm::serialize_to_file(&args[2], module).expect("Failed to write module");
+        let missing = dir.join("missing.yml");
+        let matches = app().get_matches_from(["chisel", "run", "-c", missing.to_str().unwrap()]);
+        assert!(matches!(
+            chisel_dispatch(
+                &matches,
+                Verbosity::Normal,
+                false,
+                &mut io::sink(),
+                &mut io::sink()
+            ),
+            Err(ChiselError::OpenConfig(_))
+        ));
+    }
+
+    #[test]
+    fn diff_subcommand() {
+        let dir = std::env::temp_dir().join("chisel_diff_test");
+        let _ = std::fs::create_dir(&dir);
+        let before = dir.join("before.wat");
+        let after = dir.join("after.wat");
+        write(&before, r#"(module (func (export "_main")))"#).unwrap();
+        write(&after, r#"(module (func (export "main")))"#).unwrap();
+        let (before, after) = (before.to_str().unwrap(), after.to_str().unwrap());
+
+        let (code, out) = dispatch(&["chisel", "diff", before, before]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("size "));
+        let (code, out) = dispatch(&["chisel", "diff", before, after]);
+        assert_eq!(code, 1);
+        assert_eq!(out.lines().nth(1), Some("~ export `_main` -> `main`"));
+
+        let (code, out) = dispatch(&["chisel", "diff", "--format", "json", before, after]);
+        assert_eq!(code, 1);
+        let diff: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            diff["exports"],
+            json!([{ "change": "renamed", "from": "_main", "to": "main" }])
+        );
+        assert_eq!(diff["sections"][0]["name"], "export");
+        assert_eq!(
+            diff["size_after"].as_u64().unwrap() + 1,
+            diff["size_before"].as_u64().unwrap()
+        );
+    }
 }
@@ -0,0 +1,2022 @@
+//! The chisel modules available in the configuration, and how each is built from its options.
+
+use std::fs::read_to_string;
+
+use libchisel::{
+    addsection::*,
+    checkfloat::*,
+    checksimdatomics::*,
+    checkstartfunc::*,
+    dedupdata::*,
+    deployer::*,
+    dropsection::*,
+    injectstart::*,
+    lowerexports::*,
+    maxsize::*,
+    mergetypes::*,
+    pipeline::Stage,
+    presets::{Feature, Preset},
+    remapexports::*,
+    remapimports::*,
+    remapstart::*,
+    repack::*,
+    setmemorylimits::*,
+    snip::*,
+    sortimports::*,
+    stripnames::*,
+    trimexports::*,
+    verifycustomsections::*,
+    verifydatabounds::*,
+    verifyexports::*,
+    verifyfuncsize::*,
+    verifyglobals::*,
+    verifyimports::*,
+    verifyindexspace::*,
+    verifylimits::*,
+    verifymemory::*,
+    verifymemoryexport::*,
+    verifymvp::*,
+    verifyoffsets::*,
+    verifystructure::*,
+    verifytable::*,
+    ModulePreset,
+};
+
+use config::{ConfigFormat, ConfigValue};
+use error::ChiselError;
+use parity_wasm::elements::{ExportEntry, FunctionType, Internal, ValueType};
+
+pub static DEFAULT_PRESET: Preset = Preset::Ewasm;
+
+/// Every chisel module available in the configuration, from which the pipeline is built.
+pub static MODULES: &[ModuleInfo] = &[
+    ModuleInfo {
+        name: "verifystructure",
+        kind: ModuleKind::Validator,
+        presets: VerifyStructure::presets,
+        options: &[],
+        build: build_verifystructure,
+    },
+    ModuleInfo {
+        name: "verifyexports",
+        kind: ModuleKind::Validator,
+        presets: VerifyExports::presets,
+        options: &["required", "strict"],
+        build: build_verifyexports,
+    },
+    ModuleInfo {
+        name: "verifyimports",
+        kind: ModuleKind::Validator,
+        presets: VerifyImports::presets,
+        options: &["interface", "strict", "namespaces", "forbidden"],
+        build: build_verifyimports,
+    },
+    ModuleInfo {
+        name: "verifyglobals",
+        kind: ModuleKind::Validator,
+        presets: no_presets,
+        options: &["maximum"],
+        build: build_verifyglobals,
+    },
+    ModuleInfo {
+        name: "verifymemory",
+        kind: ModuleKind::Validator,
+        presets: VerifyMemory::presets,
+        options: &["initial", "maximum"],
+        build: build_verifymemory,
+    },
+    ModuleInfo {
+        name: "verifylimits",
+        kind: ModuleKind::Validator,
+        presets: VerifyLimits::presets,
+        options: &["functions", "imported_functions", "locals", "params"],
+        build: build_verifylimits,
+    },
+    ModuleInfo {
+        name: "verifymemoryexport",
+        kind: ModuleKind::Validator,
+        presets: no_presets,
+        options: &[],
+        build: build_verifymemoryexport,
+    },
+    ModuleInfo {
+        name: "verifytable",
+        kind: ModuleKind::Validator,
+        presets: VerifyTable::presets,
+        options: &["single_table"],
+        build: build_verifytable,
+    },
+    ModuleInfo {
+        name: "verifymvp",
+        kind: ModuleKind::Validator,
+        presets: VerifyMvp::presets,
+        options: &["features"],
+        build: build_verifymvp,
+    },
+    ModuleInfo {
+        name: "verifyoffsets",
+        kind: ModuleKind::Validator,
+        presets: VerifyOffsets::presets,
+        options: &["imported_globals"],
+        build: build_verifyoffsets,
+    },
+    ModuleInfo {
+        name: "verifydatabounds",
+        kind: ModuleKind::Validator,
+        presets: no_presets,
+        options: &[],
+        build: build_verifydatabounds,
+    },
+    ModuleInfo {
+        name: "verifyindexspace",
+        kind: ModuleKind::Validator,
+        presets: no_presets,
+        options: &[],
+        build: build_verifyindexspace,
+    },
+    ModuleInfo {
+        name: "verifycustomsections",
+        kind: ModuleKind::Validator,
+        presets: VerifyCustomSections::presets,
+        options: &["allow"],
+        build: build_verifycustomsections,
+    },
+    ModuleInfo {
+        name: "maxsize",
+        kind: ModuleKind::Validator,
+        presets: MaxSize::presets,
+        options: &["limit"],
+        build: build_maxsize,
+    },
+    ModuleInfo {
+        name: "verifyfuncsize",
+        kind: ModuleKind::Validator,
+        presets: VerifyFuncSize::presets,
+        options: &["limit"],
+        build: build_verifyfuncsize,
+    },
+    ModuleInfo {
+        name: "checkstartfunc",
+        kind: ModuleKind::Validator,
+        presets: no_presets,
+        options: &["required"],
+        build: build_checkstartfunc,
+    },
+    ModuleInfo {
+        name: "checkfloat",
+        kind: ModuleKind::Validator,
+        presets: no_presets,
+        options: &[],
+        build: build_checkfloat,
+    },
+    ModuleInfo {
+        name: "checksimdatomics",
+        kind: ModuleKind::Validator,
+        presets: no_presets,
+        options: &[],
+        build: build_checksimdatomics,
+    },
+    ModuleInfo {
+        name: "deployer",
+        kind: ModuleKind::Translator,
+        presets: DeployerTranslator::presets,
+        options: &["kind"],
+        build: build_deployer,
+    },
+    ModuleInfo {
+        name: "trimexports",
+        kind: ModuleKind::Translator,
+        presets: TrimExports::presets,
+        options: &["keep"],
+        build: build_trimexports,
+    },
+    ModuleInfo {
+        name: "remapimports",
+        kind: ModuleKind::Translator,
+        presets: RemapImports::presets,
+        options: &["mapping"],
+        build: build_remapimports,
+    },
+    ModuleInfo {
+        name: "remapexports",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &["mapping"],
+        build: build_remapexports,
+    },
+    ModuleInfo {
+        name: "remapstart",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &[],
+        build: build_remapstart,
+    },
+    ModuleInfo {
+        name: "injectstart",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &["export", "function", "remove_export", "force"],
+        build: build_injectstart,
+    },
+    ModuleInfo {
+        name: "lowerexports",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &["mode"],
+        build: build_lowerexports,
+    },
+    ModuleInfo {
+        name: "dropsection",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &["all", "sections"],
+        build: build_dropsection,
+    },
+    ModuleInfo {
+        name: "dedupdata",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &[],
+        build: build_dedupdata,
+    },
+    ModuleInfo {
+        name: "repack",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &["sort"],
+        build: build_repack,
+    },
+    ModuleInfo {
+        name: "setmemorylimits",
+        kind: ModuleKind::Translator,
+        presets: SetMemoryLimits::presets,
+        options: &["initial", "maximum"],
+        build: build_setmemorylimits,
+    },
+    ModuleInfo {
+        name: "snip",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &[],
+        build: build_snip,
+    },
+    ModuleInfo {
+        name: "stripnames",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &[],
+        build: build_stripnames,
+    },
+    ModuleInfo {
+        name: "addsection",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &["name", "payload", "replace"],
+        build: build_addsection,
+    },
+    ModuleInfo {
+        name: "mergetypes",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &[],
+        build: build_mergetypes,
+    },
+    ModuleInfo {
+        name: "sortimports",
+        kind: ModuleKind::Translator,
+        presets: no_presets,
+        options: &[],
+        build: build_sortimports,
+    },
+];
+/// Presets of the modules which do not take one.
+fn no_presets() -> Vec<Preset> {
+    vec![]
+}
+
+/// Options accepted by every module besides its own.
+pub static COMMON_OPTIONS: &[&str] = &["preset", "severity", "enabled"];
+/// Largest edit distance at which an unknown module name is assumed to be a typo.
+pub static MAX_SUGGESTION_DISTANCE: usize = 3;
+/// A chisel module available in the configuration.
+pub struct ModuleInfo {
+    pub name: &'static str,
+    pub kind: ModuleKind,
+    /// Returns the presets accepted by the module, as reported by its `ModulePreset`
+    /// implementation, or none if it does not take one.
+    pub presets: fn() -> Vec<Preset>,
+    /// Options accepted by the module besides its preset.
+    pub options: &'static [&'static str],
+    /// Constructs the module with the given preset and options as a pipeline stage.
+    pub build: for<'a> fn(Preset, &'a ConfigValue) -> Result<Stage<'a>, ChiselError>,
+}
+
+/// Kinds of chisel modules.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ModuleKind {
+    /// Inspects the module without modifying it.
+    Validator,
+    /// Transforms the module.
+    Translator,
+}
+
+impl ModuleKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ModuleKind::Validator => "validator",
+            ModuleKind::Translator => "translator",
+        }
+    }
+}
+
+/// Returns the named chisel module, or None if no such module exists.
+pub fn module_info(name: &str) -> Option<&'static ModuleInfo> {
+    MODULES.iter().find(|module| module.name == name)
+}
+
+/// Returns the presets accepted by the named chisel module, or none if it does not take one or
+/// does not exist.
+pub fn module_presets(name: &str) -> Vec<Preset> {
+    module_info(name).map_or_else(Vec::new, |module| (module.presets)())
+}
+
+/// The error for a preset the named module does not support, listing the presets it accepts,
+/// or all of them if it does not take one.
+pub fn invalid_preset(name: &str, preset: &str) -> ChiselError {
+    let mut accepted = module_presets(name);
+    if accepted.is_empty() {
+        accepted = Preset::ALL.to_vec();
+    }
+    ChiselError::InvalidPreset(name.to_string(), preset.to_string(), accepted)
+}
+
+/// Returns the kind of the named chisel module, or None if no such module exists.
+pub fn module_kind(name: &str) -> Option<ModuleKind> {
+    module_info(name).map(|module| module.kind)
+}
+
+/// Returns the known module with the name closest to the given one, if any is close enough to
+/// be a likely typo.
+pub fn closest_module_name(name: &str) -> Option<&'static str> {
+    MODULES
+        .iter()
+        .map(|module| (module.name, edit_distance(name, module.name)))
+        .filter(|(_, distance)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(module, _)| module)
+}
+
+/// Returns the Levenshtein distance between two strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn build_verifystructure<'a>(
+    preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let chisel = VerifyStructure::with_preset(preset)
+        .map_err(|_| invalid_preset("verifystructure", preset.name()))?;
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_verifyexports<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let strict = match options.get("strict") {
+        Some(strict) => Some(strict.as_bool().ok_or_else(|| {
+            ChiselError::InvalidOption("verifyexports".to_string(), "strict".to_string())
+        })?),
+        None => None,
+    };
+
+    let mut chisel = match options.get("required") {
+        // A list of required exports takes the place of the preset.
+        Some(required) => {
+            VerifyExports::with_exports(required_exports(required).ok_or_else(|| {
+                ChiselError::InvalidOption("verifyexports".to_string(), "required".to_string())
+            })?)
+        }
+        None => VerifyExports::with_preset(preset)
+            .map_err(|_| invalid_preset("verifyexports", preset.name()))?,
+    };
+    if let Some(strict) = strict {
+        chisel.set_allow_unlisted(!strict);
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+/// Parses the `required` option of verifyexports. Each export is either the name of a function
+/// taking and returning nothing, or a map with its `name`, `kind`, and for functions `params`
+/// and `result`. Returns None if any of them is malformed.
+fn required_exports(required: &ConfigValue) -> Option<Vec<ExportType<'_>>> {
+    let entries = match required {
+        ConfigValue::List(entries) => entries,
+        _ => return None,
+    };
+    entries
+        .iter()
+        .map(|entry| match entry {
+            ConfigValue::String(name) => Some(ExportType::Function(name, FunctionType::default())),
+            ConfigValue::Map(_) => {
+                let name = entry.get("name")?.as_str()?;
+                match entry
+                    .get("kind")
+                    .map_or(Some("function"), |kind| kind.as_str())?
+                {
+                    "function" => Some(ExportType::Function(name, function_type(entry)?)),
+                    "global" => Some(ExportType::Global(name)),
+                    "memory" => Some(ExportType::Memory(name)),
+                    "table" => Some(ExportType::Table(name)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parses the `params` and `result` of a function listed in the configuration, both optional.
+fn function_type(entry: &ConfigValue) -> Option<FunctionType> {
+    let params = match entry.get("params") {
+        Some(ConfigValue::List(params)) => params
+            .iter()
+            .map(value_type)
+            .collect::<Option<Vec<ValueType>>>()?,
+        Some(_) => return None,
+        None => vec![],
+    };
+    let result = match entry.get("result") {
+        Some(result) => Some(value_type(result)?),
+        None => None,
+    };
+    Some(FunctionType::new(params, result))
+}
+
+/// Parses the name of a wasm value type, such as `i32`.
+fn value_type(name: &ConfigValue) -> Option<ValueType> {
+    match name.as_str()? {
+        "i32" => Some(ValueType::I32),
+        "i64" => Some(ValueType::I64),
+        "f32" => Some(ValueType::F32),
+        "f64" => Some(ValueType::F64),
+        _ => None,
+    }
+}
+
+fn build_verifyimports<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    // A host interface file replaces the imports and namespaces of the preset. The module
+    // context has already read it, leaving its contents in place of its path.
+    let mut chisel = match options.get("interface") {
+        Some(interface) => interface_imports(interface)
+            .ok_or_else(|| {
+                ChiselError::InvalidOption("verifyimports".to_string(), "interface".to_string())
+            })?
+            .allow_extra(false)
+            .build(),
+        None => VerifyImports::with_preset(preset)
+            .map_err(|_| invalid_preset("verifyimports", preset.name()))?,
+    };
+    if let Some(strict) = options.get("strict") {
+        chisel.set_allow_unlisted(!strict.as_bool().ok_or_else(|| {
+            ChiselError::InvalidOption("verifyimports".to_string(), "strict".to_string())
+        })?);
+    }
+    // Namespaces given in the configuration replace those allowed by the preset.
+    if let Some(namespaces) = options.get("namespaces") {
+        let namespaces = match namespaces {
+            ConfigValue::List(namespaces) => namespaces
+                .iter()
+                .map(|namespace| namespace.as_str())
+                .collect::<Option<Vec<&str>>>(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            ChiselError::InvalidOption("verifyimports".to_string(), "namespaces".to_string())
+        })?;
+        chisel.set_namespaces(Some(namespaces));
+    }
+    if let Some(forbidden) = options.get("forbidden") {
+        let forbidden = match forbidden {
+            ConfigValue::List(names) => names
+                .iter()
+                .map(import_name)
+                .collect::<Option<Vec<(&str, &str)>>>(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            ChiselError::InvalidOption("verifyimports".to_string(), "forbidden".to_string())
+        })?;
+        chisel.set_forbidden(forbidden);
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+/// Reads the JSON host interface file at the path, failing if it is not a valid interface.
+pub fn load_interface(path: &str) -> Result<ConfigValue, ChiselError> {
+    let contents =
+        read_to_string(path).map_err(|err| ChiselError::OpenInterface(path.to_string(), err))?;
+    ConfigFormat::Json
+        .parse(&contents)
+        .filter(|interface| interface_imports(interface).is_some())
+        .ok_or_else(|| ChiselError::InvalidInterface(path.to_string()))
+}
+
+/// Parses a host interface into the rules of verifyimports, allowing the namespaces it uses.
+/// The interface is a list of imports, each either the `module::field` name of a function taking
+/// and returning nothing, or a map with its `name`, `kind`, and for functions `params` and
+/// `result`, as in the `required` option of verifyexports. Returns None if any of them is
+/// malformed.
+fn interface_imports(interface: &ConfigValue) -> Option<VerifyImportsBuilder<'_>> {
+    let entries = match interface {
+        ConfigValue::List(entries) => entries,
+        _ => return None,
+    };
+    let mut namespaces = Vec::new();
+    let mut builder = VerifyImports::builder();
+    for entry in entries {
+        let (module, field) = match entry {
+            ConfigValue::String(_) => import_name(entry)?,
+            ConfigValue::Map(_) => import_name(entry.get("name")?)?,
+            _ => return None,
+        };
+        let import = match entry
+            .get("kind")
+            .map_or(Some("function"), |kind| kind.as_str())?
+        {
+            "function" => ImportType::Function(module, field, function_type(entry)?),
+            "global" => ImportType::Global(module, field),
+            "memory" => ImportType::Memory(module, field),
+            "table" => ImportType::Table(module, field),
+            _ => return None,
+        };
+        if !namespaces.contains(&module) {
+            namespaces.push(module);
+            builder = builder.namespace(module);
+        }
+        builder = builder.import(import);
+    }
+    Some(builder)
+}
+
+fn build_verifyglobals<'a>(
+    _preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel = VerifyGlobals::new();
+    if let Some(maximum) = options.get("maximum") {
+        chisel.set_max_globals(Some(maximum.as_u32().ok_or_else(|| {
+            ChiselError::InvalidOption("verifyglobals".to_string(), "maximum".to_string())
+        })?));
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_verifymemory<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel = VerifyMemory::with_preset(preset)
+        .map_err(|_| invalid_preset("verifymemory", preset.name()))?;
+    // Page limits given in the configuration replace those of the preset.
+    if let Some(initial) = options.get("initial") {
+        chisel.set_max_initial_pages(initial.as_u32().ok_or_else(|| {
+            ChiselError::InvalidOption("verifymemory".to_string(), "initial".to_string())
+        })?);
+    }
+    if let Some(maximum) = options.get("maximum") {
+        chisel.set_max_pages(maximum.as_u32().ok_or_else(|| {
+            ChiselError::InvalidOption("verifymemory".to_string(), "maximum".to_string())
+        })?);
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_verifylimits<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel = VerifyLimits::with_preset(preset)
+        .map_err(|_| invalid_preset("verifylimits", preset.name()))?;
+    // Caps given in the configuration replace those of the preset.
+    let cap = |option: &str| {
+        options
+            .get(option)
+            .map(|max| {
+                max.as_u32().ok_or_else(|| {
+                    ChiselError::InvalidOption("verifylimits".to_string(), option.to_string())
+                })
+            })
+            .transpose()
+    };
+    if let Some(max) = cap("functions")? {
+        chisel.set_max_functions(Some(max));
+    }
+    if let Some(max) = cap("imported_functions")? {
+        chisel.set_max_imported_functions(Some(max));
+    }
+    if let Some(max) = cap("locals")? {
+        chisel.set_max_locals(Some(max));
+    }
+    if let Some(max) = cap("params")? {
+        chisel.set_max_params(Some(max));
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_verifymemoryexport<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Validator(Box::new(VerifyMemoryExport::new())))
+}
+
+fn build_verifymvp<'a>(preset: Preset, options: &'a ConfigValue) -> Result<Stage<'a>, ChiselError> {
+    // Features given in the configuration replace those allowed by the preset.
+    let chisel = match options.get("features") {
+        Some(ConfigValue::List(features)) => VerifyMvp::with_features(
+            features
+                .iter()
+                .map(|feature| {
+                    feature
+                        .as_str()
+                        .and_then(|name| name.parse::<Feature>().ok())
+                })
+                .collect::<Option<Vec<Feature>>>()
+                .ok_or_else(|| {
+                    ChiselError::InvalidOption("verifymvp".to_string(), "features".to_string())
+                })?,
+        ),
+        Some(_) => {
+            return Err(ChiselError::InvalidOption(
+                "verifymvp".to_string(),
+                "features".to_string(),
+            ))
+        }
+        None => VerifyMvp::with_preset(preset)
+            .map_err(|_| invalid_preset("verifymvp", preset.name()))?,
+    };
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_verifytable<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel = VerifyTable::with_preset(preset)
+        .map_err(|_| invalid_preset("verifytable", preset.name()))?;
+    if let Some(single_table) = options.get("single_table") {
+        chisel.set_single_table(single_table.as_bool().ok_or_else(|| {
+            ChiselError::InvalidOption("verifytable".to_string(), "single_table".to_string())
+        })?);
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_verifyoffsets<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel = VerifyOffsets::with_preset(preset)
+        .map_err(|_| invalid_preset("verifyoffsets", preset.name()))?;
+    if let Some(allow) = options.get("imported_globals") {
+        chisel.set_allow_imported_globals(allow.as_bool().ok_or_else(|| {
+            ChiselError::InvalidOption("verifyoffsets".to_string(), "imported_globals".to_string())
+        })?);
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_verifydatabounds<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Validator(Box::new(VerifyDataBounds::new())))
+}
+
+fn build_verifyindexspace<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Validator(Box::new(VerifyIndexSpace::new())))
+}
+
+fn build_verifycustomsections<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel = VerifyCustomSections::with_preset(preset)
+        .map_err(|_| invalid_preset("verifycustomsections", preset.name()))?;
+    // Sections given in the configuration are allowed along with those of the preset.
+    let invalid =
+        || ChiselError::InvalidOption("verifycustomsections".to_string(), "allow".to_string());
+    match options.get("allow") {
+        Some(ConfigValue::List(names)) => {
+            for name in names {
+                chisel.allow(name.as_str().ok_or_else(invalid)?);
+            }
+        }
+        Some(_) => return Err(invalid()),
+        None => (),
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_maxsize<'a>(preset: Preset, options: &'a ConfigValue) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel =
+        MaxSize::with_preset(preset).map_err(|_| invalid_preset("maxsize", preset.name()))?;
+    // A limit given in the configuration replaces that of the preset.
+    if let Some(limit) = options.get("limit") {
+        chisel.set_limit(
+            limit.as_u32().ok_or_else(|| {
+                ChiselError::InvalidOption("maxsize".to_string(), "limit".to_string())
+            })? as usize,
+        );
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_verifyfuncsize<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel = VerifyFuncSize::with_preset(preset)
+        .map_err(|_| invalid_preset("verifyfuncsize", preset.name()))?;
+    // A limit given in the configuration replaces that of the preset, and `false` disables it.
+    if let Some(limit) = options.get("limit") {
+        let limit = match limit.as_bool() {
+            Some(false) => None,
+            _ => Some(limit.as_u32().ok_or_else(|| {
+                ChiselError::InvalidOption("verifyfuncsize".to_string(), "limit".to_string())
+            })? as usize),
+        };
+        chisel.set_max_size(limit);
+    }
+    Ok(Stage::Validator(Box::new(chisel)))
+}
+
+fn build_checkstartfunc<'a>(
+    _preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    // A start function is forbidden unless it is required.
+    let required = match options.get("required") {
+        Some(required) => required.as_bool().ok_or_else(|| {
+            ChiselError::InvalidOption("checkstartfunc".to_string(), "required".to_string())
+        })?,
+        None => false,
+    };
+    Ok(Stage::Validator(Box::new(CheckStartFunc::new(required))))
+}
+
+fn build_checkfloat<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Validator(Box::new(CheckFloat::new())))
+}
+
+fn build_checksimdatomics<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Validator(Box::new(CheckSimdAtomics::new())))
+}
+
+fn build_deployer<'a>(preset: Preset, options: &'a ConfigValue) -> Result<Stage<'a>, ChiselError> {
+    // The deployer wraps the module as it stands at that point of the pipeline. Its kind takes
+    // the place of the preset.
+    let chisel = match options.get("kind").map(|kind| kind.as_str()) {
+        Some(Some("memory")) => DeployerTranslator::new(DeployerKind::Memory),
+        Some(Some("customsection")) => DeployerTranslator::new(DeployerKind::CustomSection),
+        Some(_) => {
+            return Err(ChiselError::InvalidOption(
+                "deployer".to_string(),
+                "kind".to_string(),
+            ))
+        }
+        None => DeployerTranslator::with_preset(preset)
+            .map_err(|_| invalid_preset("deployer", preset.name()))?,
+    };
+    Ok(Stage::Translator(Box::new(chisel)))
+}
+
+fn build_trimexports<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let chisel = match options.get("keep") {
+        // A list of exports to keep takes the place of the preset.
+        Some(keep) => TrimExports::with_exports(kept_exports(keep).ok_or_else(|| {
+            ChiselError::InvalidOption("trimexports".to_string(), "keep".to_string())
+        })?),
+        None => TrimExports::with_preset(preset)
+            .map_err(|_| invalid_preset("trimexports", preset.name()))?,
+    };
+    Ok(Stage::Translator(Box::new(chisel)))
+}
+
+/// Parses the `keep` option of trimexports. Each export is either the name of a function, or a
+/// map with its `name` and `kind`. Returns None if any of them is malformed.
+fn kept_exports(keep: &ConfigValue) -> Option<Vec<ExportEntry>> {
+    let entries = match keep {
+        ConfigValue::List(entries) => entries,
+        _ => return None,
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let (name, kind) = match entry {
+                ConfigValue::String(name) => (name.as_str(), "function"),
+                ConfigValue::Map(_) => (
+                    entry.get("name")?.as_str()?,
+                    entry
+                        .get("kind")
+                        .map_or(Some("function"), |kind| kind.as_str())?,
+                ),
+                _ => return None,
+            };
+            // Only the kind of the internal reference is matched, not its index.
+            let internal = match kind {
+                "function" => Internal::Function(0),
+                "global" => Internal::Global(0),
+                "memory" => Internal::Memory(0),
+                "table" => Internal::Table(0),
+                _ => return None,
+            };
+            Some(ExportEntry::new(name.to_string(), internal))
+        })
+        .collect()
+}
+
+fn build_remapimports<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut translations = Translations::with_preset(preset)
+        .map_err(|_| invalid_preset("remapimports", preset.name()))?;
+    // Renames given in the configuration are added to those of the preset, taking precedence.
+    if let Some(mapping) = options.get("mapping") {
+        insert_mapping(&mut translations, mapping).ok_or_else(|| {
+            ChiselError::InvalidOption("remapimports".to_string(), "mapping".to_string())
+        })?;
+    }
+    Ok(Stage::Translator(Box::new(
+        RemapImports::with_translations(translations),
+    )))
+}
+
+fn build_remapexports<'a>(
+    _preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let invalid = || ChiselError::InvalidOption("remapexports".to_string(), "mapping".to_string());
+    let entries = match options.get("mapping") {
+        Some(ConfigValue::Map(entries)) => entries,
+        Some(_) => return Err(invalid()),
+        None => {
+            return Err(ChiselError::MissingOption(
+                "remapexports".to_string(),
+                "mapping".to_string(),
+            ))
+        }
+    };
+    let mut chisel = RemapExports::new();
+    for (from, to) in entries {
+        chisel.insert(
+            from.as_str().ok_or_else(invalid)?,
+            to.as_str().ok_or_else(invalid)?,
+        );
+    }
+    Ok(Stage::Translator(Box::new(chisel)))
+}
+
+fn build_remapstart<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Translator(Box::new(RemapStart::new())))
+}
+
+fn build_injectstart<'a>(
+    _preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let invalid =
+        |option: &str| ChiselError::InvalidOption("injectstart".to_string(), option.to_string());
+    let function = match (options.get("export"), options.get("function")) {
+        (Some(export), None) => {
+            StartFunction::Export(export.as_str().ok_or_else(|| invalid("export"))?)
+        }
+        (None, Some(function)) => {
+            StartFunction::Index(function.as_u32().ok_or_else(|| invalid("function"))?)
+        }
+        // The function is either named by its export or given by its index, not both.
+        (Some(_), Some(_)) => return Err(invalid("function")),
+        (None, None) => {
+            return Err(ChiselError::MissingOption(
+                "injectstart".to_string(),
+                "export".to_string(),
+            ))
+        }
+    };
+    let mut chisel = InjectStart::new(function);
+    if let Some(remove_export) = options.get("remove_export") {
+        chisel.set_remove_export(
+            remove_export
+                .as_bool()
+                .ok_or_else(|| invalid("remove_export"))?,
+        );
+    }
+    if let Some(force) = options.get("force") {
+        chisel.set_force(force.as_bool().ok_or_else(|| invalid("force"))?);
+    }
+    Ok(Stage::Translator(Box::new(chisel)))
+}
+
+fn build_lowerexports<'a>(
+    _preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mode = match options.get("mode").map(|mode| mode.as_str()) {
+        Some(Some("wrap")) | None => LowerMode::Wrap,
+        Some(Some("reject")) => LowerMode::Reject,
+        Some(_) => {
+            return Err(ChiselError::InvalidOption(
+                "lowerexports".to_string(),
+                "mode".to_string(),
+            ))
+        }
+    };
+    Ok(Stage::Translator(Box::new(LowerExports::new(mode))))
+}
+
+fn build_dropsection<'a>(
+    _preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let all = match options.get("all") {
+        Some(all) => all.as_bool().ok_or_else(|| {
+            ChiselError::InvalidOption("dropsection".to_string(), "all".to_string())
+        })?,
+        None => false,
+    };
+    let chisel = match options.get("sections") {
+        _ if all => DropSection::all(),
+        Some(ConfigValue::List(sections)) => DropSection::with_names(
+            sections
+                .iter()
+                .map(|section| section.as_str())
+                .collect::<Option<Vec<&str>>>()
+                .ok_or_else(|| {
+                    ChiselError::InvalidOption("dropsection".to_string(), "sections".to_string())
+                })?,
+        ),
+        Some(_) => {
+            return Err(ChiselError::InvalidOption(
+                "dropsection".to_string(),
+                "sections".to_string(),
+            ))
+        }
+        None => DropSection::default(),
+    };
+    Ok(Stage::Translator(Box::new(chisel)))
+}
+
+fn build_repack<'a>(_preset: Preset, options: &'a ConfigValue) -> Result<Stage<'a>, ChiselError> {
+    let sort = match options.get("sort") {
+        Some(sort) => sort
+            .as_bool()
+            .ok_or_else(|| ChiselError::InvalidOption("repack".to_string(), "sort".to_string()))?,
+        None => false,
+    };
+    Ok(Stage::Translator(Box::new(Repack::new(sort))))
+}
+
+fn build_setmemorylimits<'a>(
+    preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    let mut chisel = SetMemoryLimits::with_preset(preset)
+        .map_err(|_| invalid_preset("setmemorylimits", preset.name()))?;
+    // The initial size is only set if configured, and a configured maximum replaces that of the
+    // preset.
+    if let Some(initial) = options.get("initial") {
+        chisel.set_initial(initial.as_u32().ok_or_else(|| {
+            ChiselError::InvalidOption("setmemorylimits".to_string(), "initial".to_string())
+        })?);
+    }
+    if let Some(maximum) = options.get("maximum") {
+        chisel.set_maximum(maximum.as_u32().ok_or_else(|| {
+            ChiselError::InvalidOption("setmemorylimits".to_string(), "maximum".to_string())
+        })?);
+    }
+    Ok(Stage::Translator(Box::new(chisel)))
+}
+
+fn build_snip<'a>(_preset: Preset, _options: &'a ConfigValue) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Translator(Box::new(Snip::new())))
+}
+
+fn build_dedupdata<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Translator(Box::new(DedupData::new())))
+}
+
+fn build_stripnames<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Translator(Box::new(StripNames::new())))
+}
+
+fn build_addsection<'a>(
+    _preset: Preset,
+    options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    // The name and payload are both required, as there is no sensible default for either.
+    let string_option = |option: &str| match options.get(option) {
+        Some(value) => value.as_str().ok_or_else(|| {
+            ChiselError::InvalidOption("addsection".to_string(), option.to_string())
+        }),
+        None => Err(ChiselError::MissingOption(
+            "addsection".to_string(),
+            option.to_string(),
+        )),
+    };
+    let mut chisel = AddSection::new(
+        string_option("name")?,
+        string_option("payload")?.as_bytes().to_vec(),
+    );
+    if let Some(replace) = options.get("replace") {
+        chisel.set_replace(replace.as_bool().ok_or_else(|| {
+            ChiselError::InvalidOption("addsection".to_string(), "replace".to_string())
+        })?);
+    }
+    Ok(Stage::Translator(Box::new(chisel)))
+}
+
+fn build_mergetypes<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Translator(Box::new(MergeTypes::new())))
+}
+
+fn build_sortimports<'a>(
+    _preset: Preset,
+    _options: &'a ConfigValue,
+) -> Result<Stage<'a>, ChiselError> {
+    Ok(Stage::Translator(Box::new(SortImports::new())))
+}
+
+/// Adds the renames of the `mapping` option of remapimports, a map from each import to rename
+/// to its new name, both written as `module::field`. Returns None if any of them is malformed.
+fn insert_mapping(translations: &mut Translations, mapping: &ConfigValue) -> Option<()> {
+    let entries = match mapping {
+        ConfigValue::Map(entries) => entries,
+        _ => return None,
+    };
+    for (from, to) in entries {
+        let ((from_module, from_field), (to_module, to_field)) =
+            (import_name(from)?, import_name(to)?);
+        translations.insert(from_module, from_field, to_module, to_field);
+    }
+    Some(())
+}
+
+/// Splits an import name written as `module::field`.
+fn import_name(name: &ConfigValue) -> Option<(&str, &str)> {
+    let mut parts = name.as_str()?.splitn(2, "::");
+    match (parts.next(), parts.next()) {
+        (Some(module), Some(field)) if !module.is_empty() && !field.is_empty() => {
+            Some((module, field))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use execute::{execute, export_names, import_names};
+    use libchisel::fromwat::FromWat;
+    use libchisel::ModuleCreator;
+    use parity_wasm::elements::Module;
+    use report::Verbosity;
+    use ruleset::{configure, ModuleContext, Severity};
+    use std::fs::{remove_file, write};
+    use std::sync::OnceLock;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("verifyexports", "verifyexports"), 0);
+        assert_eq!(edit_distance("verifyexport", "verifyexports"), 1);
+        assert_eq!(edit_distance("trimexprots", "trimexports"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn registered_presets_are_accepted() {
+        for info in MODULES.iter() {
+            let presets = (info.presets)();
+            for preset in Preset::ALL.iter() {
+                let rejected = matches!(
+                    (info.build)(*preset, &ConfigValue::Map(vec![])),
+                    Err(ChiselError::InvalidPreset(..))
+                );
+                // A module without presets builds the same whatever the preset.
+                let listed = presets.is_empty() || presets.contains(preset);
+                assert_eq!(
+                    listed, !rejected,
+                    "{} lists preset {} inconsistently",
+                    info.name, preset
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn verifyexports_required_option() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyexports:
+    required:
+      - name: \"run\"
+        params: [\"i32\"]
+        result: \"i64\"
+      - name: \"memory\"
+        kind: \"memory\"
+    strict: true
+";
+        let mut module = parity_wasm::builder::module()
+            .function()
+            .signature()
+            .with_param(ValueType::I32)
+            .with_return_type(Some(ValueType::I64))
+            .build()
+            .body()
+            .build()
+            .build()
+            .memory()
+            .build()
+            .export()
+            .field("run")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("memory")
+            .internal()
+            .memory(0)
+            .build()
+            .build();
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal).passed());
+
+        let yaml = yaml.replace("\"i64\"", "\"i32\"");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["export `run` has signature (i32) -> i64, expected (i32) -> i32"]
+        );
+
+        let yaml = yaml.replace("\"i32\"]", "\"u8\"]");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifyimports_namespaces_option() {
+        let mut module = parity_wasm::builder::module()
+            .import()
+            .module("env")
+            .field("memory")
+            .external()
+            .memory(1, None)
+            .build()
+            .build();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyimports:
+    preset: \"ewasm\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["import `env::memory` is not from an allowed namespace"]
+        );
+
+        let yaml = format!("{}    namespaces: [\"ethereum\", \"env\"]\n", yaml);
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.reasons, vec!["unexpected import `env::memory`"]);
+
+        let yaml = yaml.replace("[\"ethereum\", \"env\"]", "\"env\"");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifyimports_strict_option() {
+        let mut module = parity_wasm::builder::module()
+            .import()
+            .module("ethereum")
+            .field("foo")
+            .external()
+            .memory(1, None)
+            .build()
+            .build();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyimports:
+    preset: \"ewasm\"
+    strict: false
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal).passed());
+
+        let yaml = yaml.replace("false", "true");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.reasons, vec!["unexpected import `ethereum::foo`"]);
+
+        let yaml = yaml.replace("true", "\"yes\"");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifyimports_forbidden_option() {
+        let mut module =
+            FromWat::new(r#"(module (import "ethereum" "selfDestruct" (func (param i32))))"#)
+                .create()
+                .unwrap();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyimports:
+    preset: \"ewasm\"
+    forbidden: [\"ethereum::selfDestruct\", \"ethereum::call\"]
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["import `ethereum::selfDestruct` is forbidden"]
+        );
+
+        let yaml = yaml.replace("\"ethereum::call\"", "\"call\"");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifyimports_interface_option() {
+        let dir = std::env::temp_dir().join("chisel_interface_test");
+        let _ = std::fs::create_dir(&dir);
+        write(
+            dir.join("eth2.json"),
+            r#"[
+                {"name": "eth2::loadPreStateRoot", "params": ["i32"]},
+                {"name": "eth2::memory", "kind": "memory"},
+                "eth2::debugPrint"
+            ]"#,
+        )
+        .unwrap();
+        write(dir.join("broken.json"), r#"[{"name": "loadPreStateRoot"}]"#).unwrap();
+
+        let yaml = "
+eth2:
+  file: \"contract.wasm\"
+  verifyimports:
+    interface: \"eth2.json\"
+";
+        let mut ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        ctx.set_base_dir(&dir);
+        let mut module = FromWat::new(
+            r#"(module
+                (import "eth2" "loadPreStateRoot" (func (param i64)))
+                (import "eth2" "memory" (memory 1))
+                (import "env" "abort" (func)))"#,
+        )
+        .create()
+        .unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.reasons.len(), 2);
+        assert!(result.reasons[0].contains("eth2::loadPreStateRoot"));
+        assert!(result.reasons[1].contains("env::abort"));
+
+        let mut module = FromWat::new(
+            r#"(module
+                (import "eth2" "loadPreStateRoot" (func (param i32)))
+                (import "eth2" "debugPrint" (func)))"#,
+        )
+        .create()
+        .unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert!(result.reasons.is_empty());
+
+        // The interface is kept by the module context once read.
+        remove_file(dir.join("eth2.json")).unwrap();
+        assert!(ctx.get_modules()[0].stage().is_ok());
+        let ctx = configure(
+            &yaml.replace("\"eth2.json\"", "[\"eth2::debugPrint\"]"),
+            ConfigFormat::Yaml,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, ref option)) if option == "interface"
+        ));
+
+        let mut ctx = configure(
+            &yaml.replace("eth2.json", "broken.json"),
+            ConfigFormat::Yaml,
+            None,
+        )
+        .unwrap();
+        ctx.set_base_dir(&dir);
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidInterface(_))
+        ));
+        let mut ctx = configure(
+            &yaml.replace("eth2.json", "missing.json"),
+            ConfigFormat::Yaml,
+            None,
+        )
+        .unwrap();
+        ctx.set_base_dir(&dir);
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::OpenInterface(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifycustomsections_allow_option() {
+        let mut module = FromWat::new("(module)").create().unwrap();
+        for name in ["name", "producers"].iter() {
+            module
+                .sections_mut()
+                .push(parity_wasm::elements::Section::Custom(
+                    parity_wasm::elements::CustomSection::new(name.to_string(), vec![]),
+                ));
+        }
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifycustomsections:
+    preset: \"ewasm-debug\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            ["custom section `producers` is not allowed"]
+        );
+
+        let yaml = format!("{}    allow: [\"producers\"]\n", yaml);
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.status(), "GOOD");
+
+        let yaml = yaml.replace("[\"producers\"]", "\"producers\"");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifyfuncsize_limit_option() {
+        let mut module = FromWat::new(r#"(module (func nop nop nop nop))"#)
+            .create()
+            .unwrap();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyfuncsize:
+    preset: \"ewasm\"
+    limit: 4
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["function 0 is 6 bytes, expected at most 4 bytes"]
+        );
+
+        let yaml = yaml.replace("limit: 4", "limit: false");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal).passed());
+    }
+
+    #[test]
+    fn verifytable_single_table_option() {
+        let mut module = FromWat::new(r#"(module (table 1 funcref) (table 1 funcref))"#)
+            .create()
+            .unwrap();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifytable:
+    preset: \"ewasm\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["module has 2 tables, expected at most 1"]
+        );
+
+        let yaml = format!("{}    single_table: false\n", yaml);
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal).passed());
+    }
+
+    #[test]
+    fn verifylimits_options_replace_preset() {
+        let mut module = FromWat::new(r#"(module (func (param i32 i32 i32)))"#)
+            .create()
+            .unwrap();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifylimits:
+    preset: \"ewasm\"
+    params: 2
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["function 0 has 3 parameters, expected at most 2"]
+        );
+
+        let yaml = yaml.replace("params: 2", "locals: many");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, ref option)) if option == "locals"
+        ));
+    }
+
+    #[test]
+    fn injectstart_options() {
+        let mut module = FromWat::new(r#"(module (func (export "init")))"#)
+            .create()
+            .unwrap();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  injectstart:
+    export: \"init\"
+    remove_export: true
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert!(matches!(result.outcome, Ok(true)));
+        assert_eq!(module.start_section(), Some(0));
+        assert!(module.export_section().unwrap().entries().is_empty());
+
+        let yaml = "ewasm:\n  file: \"a.wasm\"\n  injectstart: { export: \"init\", function: 0 }\n";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+        let yaml = "ewasm:\n  file: \"a.wasm\"\n  injectstart: {}\n";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::MissingOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifyglobals_maximum_option() {
+        let mut module = FromWat::new(
+            r#"(module
+                (global (mut i32) (i32.const 0))
+                (global i32 (i32.const 1))
+                (export "counter" (global 0)))"#,
+        )
+        .create()
+        .unwrap();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyglobals:
+    maximum: 1
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec![
+                "module defines 2 globals, expected at most 1",
+                "export `counter` refers to mutable global 0",
+            ]
+        );
+
+        let yaml = yaml.replace("1", "-1");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifyoffsets_imported_globals_option() {
+        let mut module = FromWat::new(
+            r#"(module
+                (import "env" "memoryBase" (global i32))
+                (memory 1)
+                (data (global.get 0) "data"))"#,
+        )
+        .create()
+        .unwrap();
+        let yaml = "
+pwasm:
+  file: \"target/contract.wasm\"
+  verifyoffsets:
+    preset: \"pwasm\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal).passed());
+
+        let yaml = format!("{}    imported_globals: false\n", yaml);
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["data segment 0 has a non-constant offset"]
+        );
+    }
+
+    #[test]
+    fn verifymemory_limits_option() {
+        let mut module = parity_wasm::builder::module()
+            .memory()
+            .with_min(20)
+            .build()
+            .build();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifymemory:
+    preset: \"ewasm\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert!(result.reasons.is_empty());
+
+        let yaml = format!("{}    initial: 16\n", yaml);
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["memory 0 declares 20 initial pages and no maximum, expected at most 16 initial and 1024 maximum pages"]
+        );
+
+        let yaml = yaml.replace("16", "-1");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn verifymvp_features_option() {
+        let mut module = parity_wasm::builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .with_instructions(parity_wasm::elements::Instructions::new(vec![
+                parity_wasm::elements::Instruction::I32Const(0),
+                parity_wasm::elements::Instruction::I32Extend8S,
+                parity_wasm::elements::Instruction::Drop,
+                parity_wasm::elements::Instruction::End,
+            ]))
+            .build()
+            .build()
+            .build();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifymvp: {}
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["function 0 uses `i32.extend8_s` of the sign-extension proposal"]
+        );
+
+        let yaml = yaml.replace("{}", "{ features: [\"sign-extension\"] }");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert!(result.passed());
+
+        let yaml = yaml.replace("sign-extension", "signext");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn setmemorylimits_options() {
+        let mut module = parity_wasm::builder::module()
+            .memory()
+            .with_min(1)
+            .build()
+            .build();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  setmemorylimits: { initial: 2, maximum: 16 }
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.status(), "MODIFIED");
+        let limits = module.memory_section().unwrap().entries()[0].limits();
+        assert_eq!((limits.initial(), limits.maximum()), (2, Some(16)));
+    }
+
+    #[test]
+    fn addsection_options() {
+        let mut module = Module::default();
+        module.set_custom_section("chisel", b"0.0.1".to_vec());
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  addsection: { name: \"chisel\", payload: \"0.1.0\", replace: true }
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.status(), "MODIFIED");
+        let payloads: Vec<&[u8]> = module
+            .custom_sections()
+            .map(|custom| custom.payload())
+            .collect();
+        assert_eq!(payloads, vec![&b"0.1.0"[..]]);
+
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  addsection: { name: \"chisel\" }
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let error = ctx.get_modules()[0].stage().err();
+        assert_eq!(
+            error.map(|err| err.to_string()),
+            Some(
+                "The configuration of module 'addsection' is missing the required 'payload' option."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn maxsize_limit_option() {
+        let mut module = Module::default();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  maxsize:
+    limit: 4
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(
+            result.reasons,
+            vec!["module is 8 bytes, expected at most 4 bytes"]
+        );
+
+        let yaml = yaml.replace("4", "8");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert!(result.passed());
+    }
+
+    #[test]
+    fn checkstartfunc_required_option() {
+        let mut module = Module::default();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  checkstartfunc: {}
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert!(result.reasons.is_empty());
+
+        let yaml = yaml.replace("{}", "{ required: true }");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.reasons, vec!["missing start function"]);
+
+        let yaml = yaml.replace("true", "\"yes\"");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn trimexports_keep_option() {
+        let module = parity_wasm::builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("deploy")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  trimexports:
+    keep: [\"deploy\", { name: \"memory\", kind: \"memory\" }]
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let mut trimmed = module.clone();
+        let result = execute(&ctx.get_modules()[0], &mut trimmed, Verbosity::Normal);
+        assert_eq!(result.status(), "MODIFIED");
+        assert_eq!(export_names(&trimmed), vec!["deploy"]);
+
+        let yaml = yaml.replace("\"memory\" }", "\"data\" }");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn remapexports_mapping_option() {
+        let mut module = FromWat::new(r#"(module (func (export "_main")))"#)
+            .create()
+            .unwrap();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  remapexports:
+    mapping:
+      \"_main\": \"main\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.status(), "MODIFIED");
+        assert_eq!(export_names(&module), vec!["main"]);
+
+        let ctx = configure(
+            "ewasm:\n  file: \"a.wasm\"\n  remapexports: {}\n",
+            ConfigFormat::Yaml,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::MissingOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn lowerexports_mode_option() {
+        let module = FromWat::new(r#"(module (func (export "add") (param i64)))"#)
+            .create()
+            .unwrap();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  lowerexports: {}
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let mut wrapped = module.clone();
+        let result = execute(&ctx.get_modules()[0], &mut wrapped, Verbosity::Normal);
+        assert_eq!(result.status(), "MODIFIED");
+        assert_eq!(wrapped.functions_space(), 2);
+
+        let ctx = configure(
+            &yaml.replace("{}", "{ mode: \"reject\" }"),
+            ConfigFormat::Yaml,
+            None,
+        )
+        .unwrap();
+        let mut rejected = module.clone();
+        let result = execute(&ctx.get_modules()[0], &mut rejected, Verbosity::Normal);
+        assert_eq!(
+            result.status(),
+            "lowerexports: Exports use i64, which the host ABI does not allow: `add` has type (i64) -> ()"
+        );
+        assert_eq!(rejected, module);
+
+        let ctx = configure(
+            &yaml.replace("{}", "{ mode: \"split\" }"),
+            ConfigFormat::Yaml,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn remapimports_mapping_option() {
+        let mut module = parity_wasm::builder::module()
+            .import()
+            .module("env")
+            .field("ethereum_finish")
+            .external()
+            .func(0)
+            .build()
+            .import()
+            .module("env")
+            .field("abort")
+            .external()
+            .func(0)
+            .build()
+            .build();
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  remapimports:
+    preset: \"ewasm\"
+    mapping:
+      \"env::abort\": \"ethereum::revert\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.status(), "MODIFIED");
+        assert_eq!(
+            import_names(&module),
+            vec!["ethereum.finish", "ethereum.revert"]
+        );
+
+        let yaml = yaml.replace("\"ethereum::revert\"", "\"revert\"");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn dropsection_sections_option() {
+        let mut module = Module::default();
+        module.set_custom_section("name", vec![]);
+        module.set_custom_section("deployer", vec![]);
+        let custom_names = |module: &Module| -> Vec<String> {
+            module
+                .custom_sections()
+                .map(|custom| custom.name().to_string())
+                .collect()
+        };
+
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  dropsection: {}
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let mut dropped = module.clone();
+        execute(&ctx.get_modules()[0], &mut dropped, Verbosity::Normal);
+        assert_eq!(custom_names(&dropped), vec!["deployer"]);
+
+        let yaml = yaml.replace("{}", "{ sections: [\"deployer\"] }");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let mut dropped = module.clone();
+        execute(&ctx.get_modules()[0], &mut dropped, Verbosity::Normal);
+        assert_eq!(custom_names(&dropped), vec!["name"]);
+
+        let yaml = yaml.replace("sections: [\"deployer\"]", "all: true");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        let mut dropped = module.clone();
+        execute(&ctx.get_modules()[0], &mut dropped, Verbosity::Normal);
+        assert!(custom_names(&dropped).is_empty());
+
+        let yaml = yaml.replace("all: true", "sections: \"name\"");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.get_modules()[0].stage(),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+
+    #[test]
+    fn repack_sort_option() {
+        let mut module = Module::default();
+        module.set_custom_section("name", vec![]);
+        module
+            .sections_mut()
+            .push(parity_wasm::elements::Section::Start(0));
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  repack: { sort: true }
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let result = execute(&ctx.get_modules()[0], &mut module, Verbosity::Normal);
+        assert_eq!(result.status(), "MODIFIED");
+        assert!(matches!(
+            module.sections()[0],
+            parity_wasm::elements::Section::Start(_)
+        ));
+    }
+
+    #[test]
+    fn deployer_replaces_module() {
+        let mut module = Module::default();
+        let ctx = ModuleContext {
+            module_name: "deployer".to_string(),
+            preset: None,
+            severity: Severity::Error,
+            enabled: true,
+            options: ConfigValue::Map(vec![(
+                ConfigValue::String("kind".to_string()),
+                ConfigValue::String("customsection".to_string()),
+            )]),
+            loaded_options: OnceLock::new(),
+        };
+
+        let result = execute(&ctx, &mut module, Verbosity::Normal);
+        assert!(result.passed());
+        assert_eq!(result.status(), "MODIFIED");
+
+        let mut invalid = ctx.options.clone();
+        if let ConfigValue::Map(ref mut entries) = invalid {
+            entries[0].1 = ConfigValue::String("preset".to_string());
+        }
+        assert!(matches!(
+            build_deployer(Preset::Ewasm, &invalid),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+
+        let ctx = ModuleContext {
+            module_name: "verifyexports".to_string(),
+            preset: None,
+            severity: Severity::Error,
+            enabled: true,
+            options: ConfigValue::Map(vec![]),
+            loaded_options: OnceLock::new(),
+        };
+        assert!(execute(&ctx, &mut module, Verbosity::Normal).passed());
+    }
+}
@@ -0,0 +1,761 @@
+//! Results of the modules, and the reports rendering them in each output format.
+
+use std::env;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use error::ChiselError;
+use execute::TranslateResults;
+use registry::ModuleKind;
+use ruleset::Severity;
+use EXIT_MODULE_BITS;
+
+static COLOR_GREEN: &str = "32";
+static COLOR_RED: &str = "31";
+static COLOR_YELLOW: &str = "33";
+
+/// Outcome of running a single chisel module against a wasm binary.
+pub struct ModuleResult {
+    pub module_name: String,
+    pub kind: ModuleKind,
+    /// The preset the module ran with, or None if it does not take one.
+    pub preset: Option<String>,
+    /// Whether the validator passed or the translator mutated the module, or an error message if
+    /// the module could not be run at all.
+    pub outcome: Result<bool, ChiselError>,
+    /// Why the validator failed, empty if it passed or for translators.
+    pub reasons: Vec<String>,
+    /// Time taken to run the module, only measured in verbose mode.
+    pub elapsed: Option<Duration>,
+    pub severity: Severity,
+}
+
+/// Amount of output printed by chisel.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    /// Prints nothing but fatal errors, leaving the exit code to report the result.
+    Quiet,
+    Normal,
+    /// Additionally prints module presets and sizes.
+    Verbose,
+}
+
+/// Output formats supported by the `run` subcommand.
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    /// JUnit XML, as read by CI servers, with each module as a test case.
+    Junit,
+    /// SARIF 2.1.0, as read by code scanning dashboards, with each failure as a result.
+    Sarif,
+}
+
+/// Names of the output formats, as given on the command line.
+pub static OUTPUT_FORMATS: &[&str] = &["text", "json", "junit", "sarif"];
+
+/// Results of a ruleset on a single file.
+pub struct FileReport {
+    pub ruleset: String,
+    pub file: String,
+    pub results: Vec<ModuleResult>,
+}
+
+/// Results of every ruleset on every file of a `run`, collected once and then rendered in each
+/// of the requested formats.
+#[derive(Default)]
+pub struct RunReport {
+    pub files: Vec<FileReport>,
+    /// Number of files checked, including those which failed to load.
+    pub files_total: usize,
+    /// Whether every ruleset was run, in which case the JSON report is always a list.
+    pub all_rulesets: bool,
+    /// Whether a ruleset was run against several files.
+    pub multiple_files: bool,
+}
+
+impl ModuleResult {
+    /// Returns true if the validator passed, or the translator ran successfully.
+    pub fn passed(&self) -> bool {
+        match self.kind {
+            ModuleKind::Validator => matches!(self.outcome, Ok(true)),
+            ModuleKind::Translator => self.outcome.is_ok(),
+        }
+    }
+
+    /// Returns true if the module did not pass and its severity is an error, so that the ruleset
+    /// fails.
+    pub fn failed(&self) -> bool {
+        !self.passed() && self.severity == Severity::Error
+    }
+
+    /// Returns true if the module did not pass but its failure is only a warning.
+    pub fn is_warning(&self) -> bool {
+        !self.passed() && self.severity == Severity::Warn
+    }
+
+    /// Human-readable status of the module.
+    pub fn status(&self) -> String {
+        match (self.kind, &self.outcome) {
+            (ModuleKind::Validator, Ok(true)) => "GOOD".to_string(),
+            (ModuleKind::Validator, Ok(false)) => "BAD".to_string(),
+            (ModuleKind::Translator, Ok(true)) => "MODIFIED".to_string(),
+            (ModuleKind::Translator, Ok(false)) => "UNCHANGED".to_string(),
+            (_, Err(err)) => err.to_string(),
+        }
+    }
+
+    /// Status of the module, colored for terminal output if requested: GOOD in green, BAD and
+    /// errors in red, or in yellow and marked as such if they are warnings.
+    pub fn colored_status(&self, color: bool) -> String {
+        if self.is_warning() {
+            let status = format!("{} (warning)", self.status());
+            return paint(&status, if color { Some(COLOR_YELLOW) } else { None });
+        }
+        let code = match (self.kind, &self.outcome) {
+            _ if !color => None,
+            (ModuleKind::Validator, Ok(true)) => Some(COLOR_GREEN),
+            (ModuleKind::Validator, Ok(false)) | (_, Err(_)) => Some(COLOR_RED),
+            (ModuleKind::Translator, Ok(_)) => None,
+        };
+        paint(&self.status(), code)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut ret = json!({
+            "module": self.module_name,
+            "preset": self.preset,
+            "severity": self.severity.name(),
+            "result": match self.outcome {
+                Ok(_) if self.passed() => "pass",
+                Ok(_) => "fail",
+                Err(_) => "error",
+            },
+        });
+        match (self.kind, &self.outcome) {
+            (_, Err(err)) => ret["error"] = json!(err.to_string()),
+            (ModuleKind::Translator, Ok(modified)) => ret["modified"] = json!(modified),
+            _ => (),
+        }
+        if !self.reasons.is_empty() {
+            ret["reasons"] = json!(self.reasons);
+        }
+        ret
+    }
+
+    /// Formats the result as a JUnit test case. Failing validators list their reasons in the
+    /// failure, and modules which could not run are reported as errors. Warnings are neither,
+    /// and are only written to the output of the test case.
+    pub fn to_junit(&self, file: &str) -> String {
+        let mut testcase = format!(
+            "<testcase name=\"{}\" classname=\"{}\"",
+            xml_escape(&self.module_name),
+            xml_escape(file)
+        );
+        if let Some(elapsed) = self.elapsed {
+            testcase.push_str(&format!(" time=\"{:.6}\"", elapsed.as_secs_f64()));
+        }
+        match self.outcome {
+            _ if self.is_warning() => {
+                let messages = match self.outcome {
+                    Err(ref err) => vec![err.to_string()],
+                    Ok(_) => self.reasons.clone(),
+                };
+                testcase.push_str(&format!(
+                    ">\n      <system-out>{}</system-out>\n    </testcase>",
+                    xml_escape(
+                        &messages
+                            .iter()
+                            .map(|message| format!("warning: {}", message))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    )
+                ))
+            }
+            Err(ref err) => testcase.push_str(&format!(
+                ">\n      <error message=\"{}\"/>\n    </testcase>",
+                xml_escape(&err.to_string())
+            )),
+            Ok(_) if !self.passed() => testcase.push_str(&format!(
+                ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                xml_escape(&self.reasons.join("; ")),
+                xml_escape(&self.reasons.join("\n"))
+            )),
+            Ok(_) => testcase.push_str("/>"),
+        }
+        testcase
+    }
+
+    /// Formats the failures of the module as SARIF results located in the file: one for each
+    /// reason a validator failed, or one for the error if the module could not run. Their level
+    /// is the severity of the module.
+    pub fn to_sarif(&self, file: &str) -> Vec<serde_json::Value> {
+        let messages = match self.outcome {
+            Err(ref err) => vec![err.to_string()],
+            Ok(_) => self.reasons.clone(),
+        };
+        messages
+            .into_iter()
+            .map(|message| {
+                json!({
+                    "ruleId": self.module_name,
+                    "level": match self.severity {
+                        Severity::Error => "error",
+                        Severity::Warn => "warning",
+                    },
+                    "message": { "text": message },
+                    "locations": [{
+                        "physicalLocation": { "artifactLocation": { "uri": file } }
+                    }],
+                })
+            })
+            .collect()
+    }
+}
+
+impl OutputFormat {
+    pub fn from_str(format: &str) -> Self {
+        match format {
+            "json" => OutputFormat::Json,
+            "junit" => OutputFormat::Junit,
+            "sarif" => OutputFormat::Sarif,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+/// Wraps the text in the ANSI escape sequence for the given color code, if any.
+fn paint(text: &str, code: Option<&str>) -> String {
+    match code {
+        Some(code) => format!("\x1b[{}m{}\x1b[0m", code, text),
+        None => text.to_string(),
+    }
+}
+
+/// Returns the exit code for the results of a ruleset: the module at index N sets bit N, with
+/// the last available bit standing for any module past it.
+pub fn failure_mask(results: &[ModuleResult]) -> i32 {
+    results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| result.failed())
+        .fold(0, |mask, (index, _)| {
+            mask | 1 << index.min(EXIT_MODULE_BITS - 1)
+        })
+}
+
+/// Formats the results of a ruleset on a single file in human-readable form.
+fn text_results(
+    ruleset: &str,
+    file: &str,
+    results: &[ModuleResult],
+    verbosity: Verbosity,
+    color: bool,
+) -> String {
+    let mut text = format!("{}: {}\n", ruleset, file);
+    for (index, result) in results.iter().enumerate() {
+        text.push_str(&text_module_result(index, result, verbosity, color));
+    }
+    text
+}
+
+/// Formats a duration in milliseconds.
+pub fn format_millis(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Sums up the time taken by the modules, if it was measured.
+pub fn total_elapsed<'a, I: Iterator<Item = &'a ModuleResult>>(results: I) -> Duration {
+    results.filter_map(|result| result.elapsed).sum()
+}
+
+pub fn text_module_result(
+    index: usize,
+    result: &ModuleResult,
+    verbosity: Verbosity,
+    color: bool,
+) -> String {
+    let mut text = if verbosity == Verbosity::Verbose {
+        let preset = match result.preset {
+            Some(ref preset) => format!(" ({})", preset),
+            None => String::new(),
+        };
+        format!(
+            "\t[{}] {}{}: {} ({})\n",
+            index,
+            result.module_name,
+            preset,
+            result.colored_status(color),
+            format_millis(result.elapsed.unwrap_or_default())
+        )
+    } else {
+        format!(
+            "\t[{}] {}: {}\n",
+            index,
+            result.module_name,
+            result.colored_status(color)
+        )
+    };
+    for reason in result.reasons.iter() {
+        text.push_str(&format!("\t\t- {}\n", reason));
+    }
+    text
+}
+
+/// Returns the results of a ruleset on a single file as a JSON report.
+fn json_results(ruleset: &str, file: &str, results: &[ModuleResult]) -> serde_json::Value {
+    json!({
+        "ruleset": ruleset,
+        "file": file,
+        "passed": !results.iter().any(|r| r.failed()),
+        "results": results
+            .iter()
+            .enumerate()
+            .map(|(index, r)| {
+                let mut ret = r.to_json();
+                ret["index"] = json!(index);
+                ret
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Returns the results of a ruleset on a single file as a JUnit test suite.
+pub fn junit_results(ruleset: &str, file: &str, results: &[ModuleResult]) -> String {
+    let errors = results
+        .iter()
+        .filter(|r| r.failed() && r.outcome.is_err())
+        .count();
+    let failures = results.iter().filter(|r| r.failed()).count() - errors;
+    let mut suite = format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\"",
+        xml_escape(ruleset),
+        results.len(),
+        failures,
+        errors
+    );
+    if results.iter().any(|r| r.elapsed.is_some()) {
+        suite.push_str(&format!(
+            " time=\"{:.6}\"",
+            total_elapsed(results.iter()).as_secs_f64()
+        ));
+    }
+    suite.push_str(">\n");
+    for result in results {
+        suite.push_str(&format!("    {}\n", result.to_junit(file)));
+    }
+    suite.push_str("  </testsuite>");
+    suite
+}
+
+/// Returns a SARIF log of a single run of chisel, with a rule for each module which ran.
+fn sarif_log(rules: &[String], results: Vec<serde_json::Value>) -> serde_json::Value {
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": crate_name!(),
+                    "version": crate_version!(),
+                    "informationUri": "https://github.com/wasmx/wasm-chisel",
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }],
+    })
+}
+
+impl RunReport {
+    /// Renders the whole report in the format, ending with a newline.
+    pub fn render(&self, format: OutputFormat, verbosity: Verbosity, color: bool) -> String {
+        match format {
+            OutputFormat::Text => {
+                let mut text = "========== RESULTS ==========\n".to_string();
+                for file in self.files.iter() {
+                    text.push_str(&text_results(
+                        &file.ruleset,
+                        &file.file,
+                        &file.results,
+                        verbosity,
+                        color,
+                    ));
+                }
+                if self.multiple_files {
+                    let passed = self
+                        .files
+                        .iter()
+                        .filter(|file| failure_mask(&file.results) == 0)
+                        .count();
+                    text.push_str(&format!(
+                        "Passed {} of {} files.\n",
+                        passed, self.files_total
+                    ));
+                }
+                if verbosity == Verbosity::Verbose {
+                    let elapsed =
+                        total_elapsed(self.files.iter().flat_map(|file| file.results.iter()));
+                    text.push_str(&format!("Total time: {}\n", format_millis(elapsed)));
+                }
+                text
+            }
+            OutputFormat::Json => {
+                let mut reports: Vec<serde_json::Value> = self
+                    .files
+                    .iter()
+                    .map(|file| json_results(&file.ruleset, &file.file, &file.results))
+                    .collect();
+                if self.all_rulesets || self.multiple_files {
+                    format!("{}\n", serde_json::Value::Array(reports))
+                } else {
+                    reports
+                        .pop()
+                        .map_or_else(String::new, |report| format!("{}\n", report))
+                }
+            }
+            OutputFormat::Junit => {
+                // A single suite is wrapped as well, which CI servers accept either way.
+                let mut xml =
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n".to_string();
+                for file in self.files.iter() {
+                    xml.push_str(&junit_results(&file.ruleset, &file.file, &file.results));
+                    xml.push('\n');
+                }
+                xml.push_str("</testsuites>\n");
+                xml
+            }
+            OutputFormat::Sarif => {
+                let (mut rules, mut findings) = (Vec::new(), Vec::new());
+                for file in self.files.iter() {
+                    for result in file.results.iter() {
+                        if !rules.contains(&result.module_name) {
+                            rules.push(result.module_name.clone());
+                        }
+                        findings.extend(result.to_sarif(&file.file));
+                    }
+                }
+                format!("{}\n", sarif_log(&rules, findings))
+            }
+        }
+    }
+}
+
+/// Parses a `--report FILE:FORMAT` option into the path and the format of the report.
+pub fn parse_report(report: &str) -> Result<(&str, OutputFormat), ChiselError> {
+    match report.rsplit_once(':') {
+        Some((path, format)) if !path.is_empty() && OUTPUT_FORMATS.contains(&format) => {
+            Ok((path, OutputFormat::from_str(format)))
+        }
+        _ => Err(ChiselError::InvalidReport(report.to_string())),
+    }
+}
+
+/// Escapes the characters which are not allowed verbatim in XML text and attribute values.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Prints the changes each translator made to the sections, imports and exports of the module.
+pub fn print_section_diffs(results: &TranslateResults, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "========== DIFF ==========")?;
+    for (result, diff) in results
+        .translation_results()
+        .zip(results.section_diffs.iter())
+    {
+        writeln!(out, "{}:", result.module_name)?;
+        match diff {
+            Ok(diff) => {
+                for line in diff.to_string().lines() {
+                    writeln!(out, "\t{}", line)?;
+                }
+            }
+            Err(msg) => writeln!(out, "\t{}", msg)?,
+        }
+    }
+    Ok(())
+}
+
+/// Prints the changes each translator would make, without writing any output.
+pub fn print_dry_run(
+    results: &TranslateResults,
+    verbosity: Verbosity,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "========== DRY RUN ==========")?;
+    for (result, diff) in results.translation_results().zip(results.diffs.iter()) {
+        writeln!(out, "{}: {}", result.module_name, diff.summary())?;
+        if verbosity == Verbosity::Verbose {
+            for (action, names) in [
+                ("removed export", &diff.removed_exports),
+                ("added export", &diff.added_exports),
+                ("removed import", &diff.removed_imports),
+                ("added import", &diff.added_imports),
+            ]
+            .iter()
+            {
+                for name in names.iter() {
+                    writeln!(out, "\t{}: {}", action, name)?;
+                }
+            }
+        }
+    }
+    writeln!(out, "Dry run, not writing output.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::ConfigFormat;
+    use execute::execute;
+    use parity_wasm::elements::Module;
+    use registry::ModuleKind;
+    use ruleset::{configure, Severity};
+    use EXIT_FATAL;
+
+    #[test]
+    fn run_report_rendered_in_each_format() {
+        let result = ModuleResult {
+            module_name: "verifyexports".to_string(),
+            kind: ModuleKind::Validator,
+            preset: Some("ewasm".to_string()),
+            outcome: Ok(false),
+            reasons: vec!["missing export `main`".to_string()],
+            elapsed: None,
+            severity: Severity::Error,
+        };
+        let report = RunReport {
+            files: vec![FileReport {
+                ruleset: "ewasm".to_string(),
+                file: "contract.wasm".to_string(),
+                results: vec![result],
+            }],
+            files_total: 1,
+            ..RunReport::default()
+        };
+        assert_eq!(
+            report.render(OutputFormat::Text, Verbosity::Normal, false),
+            "========== RESULTS ==========\newasm: contract.wasm\n\t[0] verifyexports: BAD\n\t\t- missing export `main`\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&report.render(OutputFormat::Json, Verbosity::Normal, false))
+                .unwrap();
+        assert_eq!(json["passed"], false);
+        assert!(report
+            .render(OutputFormat::Junit, Verbosity::Normal, false)
+            .contains("<testsuite name=\"ewasm\" tests=\"1\" failures=\"1\" errors=\"0\">"));
+    }
+
+    #[test]
+    fn report_option_parsed() {
+        let (path, format) = parse_report("out/results.xml:junit").unwrap();
+        assert_eq!(path, "out/results.xml");
+        assert!(format == OutputFormat::Junit);
+        assert!(parse_report("C:\\results.json:json").is_ok());
+        assert!(parse_report("results.xml").is_err());
+        assert!(parse_report("results.xml:xml").is_err());
+        assert!(parse_report(":json").is_err());
+    }
+
+    #[test]
+    fn preset_reported_only_when_taken() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyexports: {}
+  checkstartfunc: {}
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let mut module = Module::default();
+        let results: Vec<ModuleResult> = ctx
+            .get_modules()
+            .iter()
+            .map(|module_ctx| execute(module_ctx, &mut module, Verbosity::Normal))
+            .collect();
+        assert_eq!(results[0].preset, Some("ewasm".to_string()));
+        assert_eq!(results[0].to_json()["preset"], "ewasm");
+        assert_eq!(results[1].preset, None);
+        assert!(results[1].to_json()["preset"].is_null());
+    }
+
+    #[test]
+    fn module_result_json() {
+        let result = ModuleResult {
+            module_name: "verifyexports".to_string(),
+            kind: ModuleKind::Validator,
+            preset: Some("ewasm".to_string()),
+            outcome: Ok(false),
+            reasons: vec!["missing export `main`".to_string()],
+            elapsed: None,
+            severity: Severity::Error,
+        };
+        assert_eq!(
+            result.to_json(),
+            json!({
+                "module": "verifyexports",
+                "preset": "ewasm",
+                "severity": "error",
+                "result": "fail",
+                "reasons": ["missing export `main`"]
+            })
+        );
+    }
+
+    #[test]
+    fn module_result_junit() {
+        let result = ModuleResult {
+            module_name: "verifyexports".to_string(),
+            kind: ModuleKind::Validator,
+            preset: Some("ewasm".to_string()),
+            outcome: Ok(false),
+            reasons: vec![
+                "missing export `main`".to_string(),
+                "export \"<init>\" is not allowed".to_string(),
+            ],
+            elapsed: None,
+            severity: Severity::Error,
+        };
+        assert_eq!(
+            result.to_junit("contract.wasm"),
+            "<testcase name=\"verifyexports\" classname=\"contract.wasm\">\n      \
+             <failure message=\"missing export `main`; export &quot;&lt;init&gt;&quot; is not allowed\">\
+             missing export `main`\nexport &quot;&lt;init&gt;&quot; is not allowed</failure>\n    \
+             </testcase>"
+        );
+
+        let ctx = configure(
+            "ewasm:\n  file: \"contract.wasm\"\n  verifyexports: { preset: \"ewasm\" }\n",
+            ConfigFormat::Yaml,
+            None,
+        )
+        .unwrap();
+        let passed = ModuleResult {
+            outcome: Ok(true),
+            reasons: vec![],
+            ..result
+        };
+        let errored = ModuleResult {
+            module_name: "verifyimports".to_string(),
+            kind: ModuleKind::Validator,
+            preset: Some("ewasm".to_string()),
+            outcome: Err(ChiselError::Deserialize(
+                "Unexpected end of input in the code section at offset 52".to_string(),
+            )),
+            reasons: vec![],
+            elapsed: None,
+            severity: Severity::Error,
+        };
+        assert_eq!(
+            junit_results(ctx.name(), "contract.wasm", &[passed, errored]),
+            "  <testsuite name=\"ewasm\" tests=\"2\" failures=\"0\" errors=\"1\">
+    <testcase name=\"verifyexports\" classname=\"contract.wasm\"/>
+    <testcase name=\"verifyimports\" classname=\"contract.wasm\">
+      <error message=\"Failed to deserialize the wasm binary: Unexpected end of input in the code section at offset 52\"/>
+    </testcase>
+  </testsuite>"
+        );
+    }
+
+    #[test]
+    fn module_result_sarif() {
+        let result = ModuleResult {
+            module_name: "verifyexports".to_string(),
+            kind: ModuleKind::Validator,
+            preset: Some("ewasm".to_string()),
+            outcome: Ok(false),
+            reasons: vec![
+                "missing export `main`".to_string(),
+                "missing export `memory`".to_string(),
+            ],
+            elapsed: None,
+            severity: Severity::Error,
+        };
+        let findings = result.to_sarif("contract.wasm");
+        assert_eq!(findings.len(), 2);
+        assert_eq!(
+            findings[1],
+            json!({
+                "ruleId": "verifyexports",
+                "level": "error",
+                "message": { "text": "missing export `memory`" },
+                "locations": [{
+                    "physicalLocation": { "artifactLocation": { "uri": "contract.wasm" } }
+                }],
+            })
+        );
+
+        let passed = ModuleResult {
+            outcome: Ok(true),
+            reasons: vec![],
+            ..result
+        };
+        assert!(passed.to_sarif("contract.wasm").is_empty());
+
+        let log = sarif_log(&["verifyexports".to_string()], findings);
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(
+            log["runs"][0]["tool"]["driver"]["rules"],
+            json!([{ "id": "verifyexports" }])
+        );
+        assert_eq!(log["runs"][0]["results"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn failure_mask_bits() {
+        let result = |outcome| ModuleResult {
+            module_name: "verifyexports".to_string(),
+            kind: ModuleKind::Validator,
+            preset: Some("ewasm".to_string()),
+            outcome,
+            reasons: vec![],
+            elapsed: None,
+            severity: Severity::Error,
+        };
+        assert_eq!(failure_mask(&[result(Ok(true)), result(Ok(true))]), 0);
+        assert_eq!(failure_mask(&[result(Ok(true)), result(Ok(false))]), 0b10);
+        assert_eq!(
+            failure_mask(&[
+                result(Err(ChiselError::Deserialize(String::new()))),
+                result(Ok(true))
+            ]),
+            0b1
+        );
+
+        let mut results: Vec<ModuleResult> = (0..10).map(|_| result(Ok(true))).collect();
+        results[9] = result(Ok(false));
+        assert_eq!(failure_mask(&results), 1 << (EXIT_MODULE_BITS - 1));
+        results = (0..10).map(|_| result(Ok(false))).collect();
+        assert!(failure_mask(&results) < EXIT_FATAL);
+    }
+
+    #[test]
+    fn colored_status() {
+        let result = |kind, outcome| ModuleResult {
+            module_name: "verifyexports".to_string(),
+            kind,
+            preset: Some("ewasm".to_string()),
+            outcome,
+            reasons: vec![],
+            elapsed: None,
+            severity: Severity::Error,
+        };
+        let good = result(ModuleKind::Validator, Ok(true));
+        assert_eq!(good.colored_status(false), "GOOD");
+        assert_eq!(good.colored_status(true), "\x1b[32mGOOD\x1b[0m");
+        let bad = result(ModuleKind::Validator, Ok(false));
+        assert_eq!(bad.colored_status(true), "\x1b[31mBAD\x1b[0m");
+        let modified = result(ModuleKind::Translator, Ok(true));
+        assert_eq!(modified.colored_status(true), "MODIFIED");
+    }
+}
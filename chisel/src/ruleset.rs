@@ -0,0 +1,1323 @@
+//! Rulesets read from the configuration: the binaries each applies to and the modules it runs,
+//! along with their options.
+
+use std::env;
+use std::fs::read_to_string;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use libchisel::{
+    pipeline::{Chisel, Stage},
+    presets::Preset,
+};
+
+use clap::ArgMatches;
+use config::{ConfigFormat, ConfigValue};
+use error::ChiselError;
+use execute::InputFormat;
+use registry::{
+    closest_module_name, edit_distance, invalid_preset, load_interface, module_info, module_kind,
+    module_presets, COMMON_OPTIONS, DEFAULT_PRESET, MAX_SUGGESTION_DISTANCE,
+};
+use report::Verbosity;
+
+pub static DEFAULT_CONFIG_PATH: &str = "chisel.yml";
+/// File path which denotes that the binary is read from stdin.
+pub static STDIN_PATH: &str = "-";
+/// Configuration written by `chisel init`.
+pub static CONFIG_TEMPLATE: &str = r#"# Configuration for chisel, see https://github.com/wasmx/wasm-chisel
+#
+# Each top-level key is a ruleset. `chisel run` uses the first one unless another is selected with
+# --ruleset NAME, or all of them with --all.
+ewasm:
+  # Path to the wasm binary. It can also be a glob pattern or a list of paths.
+  file: "target/wasm32-unknown-unknown/release/contract.wasm"
+  # Checks that the binary only exports the functions expected by ewasm.
+  verifyexports:
+    preset: "ewasm"
+  # Checks that the binary only imports functions of the ewasm environment interface.
+  verifyimports:
+    preset: "ewasm"
+  # Checks that the binary has no start function.
+  checkstartfunc: {}
+"#;
+
+/// Chisel configuration structure. Contains the files to chisel and a list of modules
+/// configurations.
+pub struct ChiselContext {
+    pub ruleset_name: String,
+    /// Paths or glob patterns of the binaries, as given in the configuration.
+    pub files: Vec<String>,
+    /// Format in which the binaries are read, or None to detect it from the extension of each.
+    pub input_format: Option<InputFormat>,
+    pub modules: Vec<ModuleContext>,
+}
+
+/// Configuration of a single chisel module within a ruleset.
+pub struct ModuleContext {
+    pub module_name: String,
+    pub preset: Option<Preset>,
+    /// Whether a failure of the module fails the ruleset or is only reported as a warning.
+    pub severity: Severity,
+    /// Whether the module runs at all, so that it can be turned off without removing it.
+    pub enabled: bool,
+    /// Every option of the module as given in the configuration, including the preset.
+    pub options: ConfigValue,
+    /// The options with the host interface file they name read in place of its path, filled in
+    /// when the module is first constructed.
+    pub loaded_options: OnceLock<ConfigValue>,
+}
+
+/// How a failing module is reported, as set by its "severity" option.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// The failure fails the ruleset.
+    Error,
+    /// The failure is reported, but does not affect the exit code.
+    Warn,
+}
+
+/// Contents of a configuration file, with what is needed to interpret them.
+struct ConfigFile {
+    contents: String,
+    format: ConfigFormat,
+    /// Directory which the paths in the configuration are relative to, if not the current one.
+    base_dir: Option<PathBuf>,
+}
+
+impl ChiselContext {
+    /// Configures chisel from the named ruleset, or the first valid ruleset if no name is given.
+    pub fn from_ruleset(
+        ruleset: &ConfigValue,
+        selected: Option<&str>,
+    ) -> Result<Self, ChiselError> {
+        if let ConfigValue::Map(rules) = ruleset {
+            let (name, options) = if let Some(selected) = selected {
+                match ruleset.get(selected) {
+                    Some(ConfigValue::Map(options)) => (selected, options),
+                    Some(_) => return Err(ChiselError::RulesetTypeMismatch(selected.to_string())),
+                    None => {
+                        return Err(ChiselError::RulesetNotFound(
+                            selected.to_string(),
+                            ruleset_names(ruleset),
+                        ))
+                    }
+                }
+            } else {
+                // Use the first ruleset which is a key pointing to a map of options.
+                match rules
+                    .iter()
+                    .find(|(name, options)| name.is_string() && options.is_map())
+                {
+                    Some((ConfigValue::String(name), ConfigValue::Map(options))) => {
+                        (name.as_str(), options)
+                    }
+                    _ => return Err(ChiselError::NoRuleset),
+                }
+            };
+
+            // Every mistake in the files and modules is collected, so that all of them can be
+            // reported at once.
+            let mut errors = Vec::new();
+            let files = match options.iter().find(|(key, _)| key.as_str() == Some("file")) {
+                Some((_, ConfigValue::String(path))) => {
+                    collect_errors(vec![expand_env(path)], &mut errors)
+                }
+                Some((_, ConfigValue::List(paths))) if paths.is_empty() => {
+                    errors.push(ChiselError::MissingFile(name.to_string()));
+                    vec![]
+                }
+                Some((_, ConfigValue::List(paths))) => collect_errors(
+                    paths.iter().map(|path| match path {
+                        ConfigValue::String(path) => expand_env(path),
+                        _ => Err(ChiselError::FileTypeMismatch),
+                    }),
+                    &mut errors,
+                ),
+                Some(_) => {
+                    errors.push(ChiselError::FileTypeMismatch);
+                    vec![]
+                }
+                None => {
+                    errors.push(ChiselError::MissingFile(name.to_string()));
+                    vec![]
+                }
+            };
+
+            // The modules are either listed in order under "modules", or every key other than
+            // "file" is a module configuration.
+            let modules = match options
+                .iter()
+                .find(|(key, _)| key.as_str() == Some("modules"))
+            {
+                Some((_, ConfigValue::List(entries))) => {
+                    if options.iter().any(|(key, _)| {
+                        key.as_str() != Some("file") && key.as_str() != Some("modules")
+                    }) {
+                        errors.push(ChiselError::ConfigInvalid);
+                    }
+                    collect_errors(entries.iter().map(ModuleContext::from_entry), &mut errors)
+                }
+                Some(_) => {
+                    errors.push(ChiselError::ConfigInvalid);
+                    vec![]
+                }
+                None => collect_errors(
+                    options
+                        .iter()
+                        .filter(|(key, _)| key.as_str() != Some("file"))
+                        .map(|(key, value)| ModuleContext::from_config(key, value)),
+                    &mut errors,
+                ),
+            };
+            if !errors.is_empty() {
+                return Err(ChiselError::from_config_errors(errors));
+            }
+
+            Ok(ChiselContext {
+                ruleset_name: name.to_string(),
+                files,
+                input_format: None,
+                modules: modules
+                    .into_iter()
+                    .filter(|module| module.enabled)
+                    .collect(),
+            })
+        } else {
+            Err(ChiselError::NoRuleset)
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.ruleset_name
+    }
+
+    fn set_file(&mut self, file: &str) {
+        self.files = vec![file.to_string()];
+    }
+
+    pub fn input_format(&self) -> Option<InputFormat> {
+        self.input_format
+    }
+
+    /// Reads every binary in the given format, regardless of the extension of its path.
+    fn set_input_format(&mut self, format: InputFormat) {
+        self.input_format = Some(format);
+    }
+
+    /// Overrides the preset of every module which takes one. Fails if any of them does not
+    /// support the preset.
+    fn set_preset(&mut self, name: &str) -> Result<(), ChiselError> {
+        let preset = name.parse::<Preset>().ok();
+        for module in self.modules.iter_mut() {
+            let accepted = module_presets(&module.module_name);
+            if accepted.is_empty() {
+                continue;
+            }
+            match preset {
+                Some(preset) if accepted.contains(&preset) => module.preset = Some(preset),
+                _ => return Err(invalid_preset(&module.module_name, name)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the relative paths of the ruleset relative to the given directory, including the
+    /// host interface files of its modules.
+    pub fn set_base_dir(&mut self, dir: &Path) {
+        for file in self.files.iter_mut() {
+            if file != STDIN_PATH && Path::new(file).is_relative() {
+                *file = dir.join(&file).to_string_lossy().into_owned();
+            }
+        }
+        for module in self.modules.iter_mut() {
+            module.loaded_options = OnceLock::new();
+            if let ConfigValue::Map(ref mut options) = module.options {
+                for (key, value) in options.iter_mut() {
+                    match (key.as_str(), value) {
+                        (Some("interface"), &mut ConfigValue::String(ref mut path))
+                            if Path::new(path).is_relative() =>
+                        {
+                            *path = dir.join(&path).to_string_lossy().into_owned();
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether the ruleset may apply to more than one binary, either by listing several files or
+    /// by using a glob pattern.
+    pub fn has_multiple_files(&self) -> bool {
+        self.files.len() > 1 || self.files.iter().any(|file| is_glob(file))
+    }
+
+    /// Returns the only file of the ruleset, or an error if it applies to several binaries.
+    pub fn single_file(&self) -> Result<&String, ChiselError> {
+        if self.has_multiple_files() {
+            Err(ChiselError::MultipleFiles)
+        } else {
+            Ok(&self.files[0])
+        }
+    }
+
+    /// Expands the glob patterns of the ruleset into the paths they match, in order. A pattern
+    /// which matches no files is an error.
+    pub fn resolve_files(&self) -> Result<Vec<String>, ChiselError> {
+        let mut resolved = Vec::new();
+        for file in self.files.iter() {
+            if !is_glob(file) {
+                resolved.push(file.clone());
+                continue;
+            }
+            let matches: Vec<String> = glob::glob(file)
+                .map_err(|_| ChiselError::InvalidGlob(file.clone()))?
+                .filter_map(|path| path.ok())
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            if matches.is_empty() {
+                return Err(ChiselError::GlobNoMatch(file.clone()));
+            }
+            resolved.extend(matches);
+        }
+        Ok(resolved)
+    }
+
+    pub fn get_modules(&self) -> &Vec<ModuleContext> {
+        &self.modules
+    }
+
+    /// Fails on the first option which its module does not accept, as it would otherwise be
+    /// ignored. Unknown keys of the ruleset itself are already rejected as unknown modules.
+    fn check_options(&self) -> Result<(), ChiselError> {
+        let mut errors = Vec::new();
+        collect_errors(
+            self.modules.iter().map(ModuleContext::check_options),
+            &mut errors,
+        );
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ChiselError::from_config_errors(errors))
+        }
+    }
+
+    /// Constructs the ruleset's modules as a pipeline, with stages in the same order as
+    /// `get_modules`. Fails with every module which cannot be constructed.
+    pub fn pipeline(&self) -> Result<Chisel<'_>, ChiselError> {
+        let mut errors = Vec::new();
+        let stages = collect_errors(
+            self.modules
+                .iter()
+                .map(|ctx| ctx.stage().map(|stage| (&ctx.module_name, stage))),
+            &mut errors,
+        );
+        if !errors.is_empty() {
+            return Err(ChiselError::from_config_errors(errors));
+        }
+        Ok(stages
+            .into_iter()
+            .fold(Chisel::new(), |chisel, (name, stage)| {
+                chisel.stage(name, stage)
+            }))
+    }
+}
+
+impl ModuleContext {
+    fn from_config(name: &ConfigValue, options: &ConfigValue) -> Result<Self, ChiselError> {
+        // Catch typos in module names before any binary is read.
+        if let Some(name) = name.as_str() {
+            if module_kind(name).is_none() {
+                return Err(ChiselError::UnknownModule(
+                    name.to_string(),
+                    closest_module_name(name).map(|module| module.to_string()),
+                ));
+            }
+        }
+
+        match (name, options) {
+            (ConfigValue::String(name), ConfigValue::Map(_)) => {
+                // A preset would be silently ignored by a module which does not take one.
+                let accepted = module_presets(name);
+                if accepted.is_empty() && options.get("preset").is_some() {
+                    return Err(ChiselError::PresetNotTaken(name.clone()));
+                }
+                // Catch typos in presets as well, rather than only when the module runs.
+                let preset = match options.get("preset") {
+                    Some(ConfigValue::String(preset)) => Some(
+                        preset
+                            .parse::<Preset>()
+                            .map_err(|_| invalid_preset(name, preset))?,
+                    ),
+                    Some(_) => return Err(ChiselError::PresetTypeMismatch(name.clone())),
+                    None => None,
+                };
+                let effective = preset.unwrap_or(DEFAULT_PRESET);
+                if !accepted.is_empty() && !accepted.contains(&effective) {
+                    return Err(invalid_preset(name, effective.name()));
+                }
+
+                let severity =
+                    match options.get("severity") {
+                        Some(severity) => severity
+                            .as_str()
+                            .and_then(Severity::from_str)
+                            .ok_or_else(|| {
+                                ChiselError::InvalidOption(name.clone(), "severity".to_string())
+                            })?,
+                        None => Severity::Error,
+                    };
+                let enabled = match options.get("enabled") {
+                    Some(enabled) => enabled.as_bool().ok_or_else(|| {
+                        ChiselError::InvalidOption(name.clone(), "enabled".to_string())
+                    })?,
+                    None => true,
+                };
+
+                Ok(ModuleContext {
+                    module_name: name.clone(),
+                    preset,
+                    severity,
+                    enabled,
+                    options: options.clone(),
+                    loaded_options: OnceLock::new(),
+                })
+            }
+            _ => Err(ChiselError::ModuleTypeMismatch(
+                name.as_str()
+                    .map_or_else(|| format!("{:?}", name), |name| name.to_string()),
+            )),
+        }
+    }
+
+    /// Constructs the module from an entry of the "modules" list, which is either a map of its
+    /// name to its options or its name alone.
+    fn from_entry(entry: &ConfigValue) -> Result<Self, ChiselError> {
+        match entry {
+            ConfigValue::Map(module) if module.len() == 1 => {
+                ModuleContext::from_config(&module[0].0, &module[0].1)
+            }
+            ConfigValue::String(_) => ModuleContext::from_config(entry, &ConfigValue::Map(vec![])),
+            _ => Err(ChiselError::ModuleTypeMismatch(format!("{:?}", entry))),
+        }
+    }
+
+    /// Fails with every option which the module does not accept, suggesting the closest one.
+    fn check_options(&self) -> Result<(), ChiselError> {
+        let accepted = module_info(&self.module_name).map_or(&[][..], |info| info.options);
+        let entries = match &self.options {
+            ConfigValue::Map(entries) => entries,
+            _ => return Ok(()),
+        };
+        let mut errors = Vec::new();
+        for (key, _) in entries {
+            let key = key
+                .as_str()
+                .map_or_else(|| format!("{:?}", key), |key| key.to_string());
+            if COMMON_OPTIONS.contains(&key.as_str()) || accepted.contains(&key.as_str()) {
+                continue;
+            }
+            let suggestion = COMMON_OPTIONS
+                .iter()
+                .chain(accepted.iter())
+                .map(|option| (option, edit_distance(&key, option)))
+                .filter(|(_, distance)| *distance <= MAX_SUGGESTION_DISTANCE)
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(option, _)| option.to_string());
+            errors.push(ChiselError::UnknownOption(
+                self.module_name.clone(),
+                key,
+                suggestion,
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ChiselError::from_config_errors(errors))
+        }
+    }
+
+    pub fn fields(&self) -> (&String, Option<Preset>) {
+        (&self.module_name, self.preset)
+    }
+
+    /// Constructs the module with its configured preset, or the default one.
+    pub fn stage(&self) -> Result<Stage<'_>, ChiselError> {
+        let info = module_info(&self.module_name)
+            .ok_or_else(|| ChiselError::UnknownModule(self.module_name.clone(), None))?;
+        (info.build)(
+            self.preset.unwrap_or(DEFAULT_PRESET),
+            self.loaded_options()?,
+        )
+    }
+
+    /// Returns the options of the module, with the host interface file they name, if any, read
+    /// in place of its path. The file is only read on the first call, so that every binary of the
+    /// ruleset is checked against the same interface.
+    fn loaded_options(&self) -> Result<&ConfigValue, ChiselError> {
+        if let Some(options) = self.loaded_options.get() {
+            return Ok(options);
+        }
+        let takes_interface =
+            module_info(&self.module_name).is_some_and(|info| info.options.contains(&"interface"));
+        let mut options = self.options.clone();
+        if let ConfigValue::Map(ref mut entries) = options {
+            for (key, value) in entries.iter_mut() {
+                if takes_interface && key.as_str() == Some("interface") {
+                    let path = value.as_str().ok_or_else(|| {
+                        ChiselError::InvalidOption(
+                            self.module_name.clone(),
+                            "interface".to_string(),
+                        )
+                    })?;
+                    *value = load_interface(path)?;
+                }
+            }
+        }
+        Ok(self.loaded_options.get_or_init(|| options))
+    }
+}
+
+impl Severity {
+    fn from_str(name: &str) -> Option<Self> {
+        match name {
+            "error" => Some(Severity::Error),
+            "warn" => Some(Severity::Warn),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warn => "warn",
+        }
+    }
+}
+
+/// Whether the path is a glob pattern rather than a plain path.
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Expands the `${VAR}` references in a path of the configuration with the value of each
+/// environment variable. A variable which is not set is an error.
+fn expand_env(path: &str) -> Result<String, ChiselError> {
+    expand_vars(path, |name| env::var(name).ok())
+}
+
+/// Expands the `${VAR}` references in the text with the values given by `lookup`. A `$` which
+/// does not start a complete reference is kept as is.
+fn expand_vars<F: Fn(&str) -> Option<String>>(
+    text: &str,
+    lookup: F,
+) -> Result<String, ChiselError> {
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = &rest[start + 2..end];
+        let value = lookup(name).ok_or_else(|| ChiselError::UndefinedVariable(name.to_string()))?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Returns the values which were successfully read from the configuration, moving every error
+/// into `errors` instead of stopping at the first one.
+fn collect_errors<T, I: IntoIterator<Item = Result<T, ChiselError>>>(
+    results: I,
+    errors: &mut Vec<ChiselError>,
+) -> Vec<T> {
+    let mut values = Vec::new();
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(err) => errors.push(err),
+        }
+    }
+    values
+}
+
+pub fn parse_config(conf: &str, format: ConfigFormat) -> Result<ConfigValue, ChiselError> {
+    format.parse(conf).ok_or(ChiselError::ConfigParse)
+}
+
+pub fn configure(
+    conf: &str,
+    format: ConfigFormat,
+    ruleset: Option<&str>,
+) -> Result<ChiselContext, ChiselError> {
+    let rulesets = parse_config(conf, format)?;
+    ChiselContext::from_ruleset(&rulesets, ruleset)
+}
+
+/// Returns the names of all rulesets in the configuration.
+fn ruleset_names(config: &ConfigValue) -> Vec<String> {
+    match config {
+        ConfigValue::Map(rules) => rules
+            .iter()
+            .filter(|(_, options)| options.is_map())
+            .filter_map(|(name, _)| name.as_str().map(|name| name.to_string()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Searches the directory and its parents for the default configuration file, returning the
+/// path of the closest one.
+fn find_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(DEFAULT_CONFIG_PATH))
+        .find(|path| path.is_file())
+}
+
+/// Reads the configuration file given with `--config`, or the closest default configuration file
+/// up from the current directory. The format is given by `--config-format` or detected from the
+/// extension.
+fn read_config(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<ConfigFile, ChiselError> {
+    let (path, base_dir) = match args.value_of("CONFIG") {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            // Paths in the configuration file are relative to its directory.
+            let base_dir = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .map(Path::to_path_buf);
+            (path, base_dir)
+        }
+        None => {
+            let cwd = env::current_dir().map_err(ChiselError::OpenConfig)?;
+            let path = find_config(&cwd).ok_or(ChiselError::ConfigNotFound)?;
+            if verbosity != Verbosity::Quiet {
+                writeln!(log, "Using configuration file {}", path.display())?;
+            }
+            // Paths in a configuration file found in a parent directory are relative to it.
+            let base_dir = path
+                .parent()
+                .filter(|dir| *dir != cwd)
+                .map(Path::to_path_buf);
+            (path, base_dir)
+        }
+    };
+
+    let format = args.value_of("CONFIG_FORMAT").map_or_else(
+        || ConfigFormat::from_path(&path.to_string_lossy()),
+        ConfigFormat::from_str,
+    );
+    let contents = read_to_string(&path).map_err(ChiselError::OpenConfig)?;
+    Ok(ConfigFile {
+        contents,
+        format,
+        base_dir,
+    })
+}
+
+/// Applies the location of the configuration file and the overrides given on the command line to
+/// a loaded ruleset.
+fn apply_overrides(
+    mut ctx: ChiselContext,
+    config: &ConfigFile,
+    args: &ArgMatches,
+) -> Result<ChiselContext, ChiselError> {
+    if let Some(ref dir) = config.base_dir {
+        ctx.set_base_dir(dir);
+    }
+    if args.is_present("STRICT_CONFIG") {
+        ctx.check_options()?;
+    }
+    if args.is_present("STDIN") {
+        ctx.set_file(STDIN_PATH);
+    }
+    if let Some(format) = input_format_from_args(args) {
+        ctx.set_input_format(format);
+    }
+    if let Some(preset) = args.value_of("PRESET") {
+        ctx.set_preset(preset)?;
+    }
+    Ok(ctx)
+}
+
+/// Returns the format given by `--input-format`, if any.
+pub fn input_format_from_args(args: &ArgMatches) -> Option<InputFormat> {
+    args.value_of("INPUT_FORMAT").map(InputFormat::from_str)
+}
+
+/// Warns that the ruleset has no modules, as every binary would pass it without being checked.
+fn warn_no_modules(
+    ctx: &ChiselContext,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<(), ChiselError> {
+    if ctx.get_modules().is_empty() && verbosity != Verbosity::Quiet {
+        writeln!(
+            log,
+            "{}: warning: ruleset '{}' has no modules, so every binary passes unchecked",
+            crate_name!(),
+            ctx.name()
+        )?;
+    }
+    Ok(())
+}
+
+/// Loads the configuration file selected by the subcommand arguments, applying any overrides
+/// given on the command line.
+pub fn load_config(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<ChiselContext, ChiselError> {
+    let config = read_config(args, verbosity, log)?;
+    let ctx = configure(&config.contents, config.format, args.value_of("RULESET"))?;
+    warn_no_modules(&ctx, verbosity, log)?;
+    apply_overrides(ctx, &config, args)
+}
+
+/// A ruleset of the configuration file, or the error which prevented loading it alongside its
+/// name.
+type LoadedRuleset = Result<ChiselContext, (String, ChiselError)>;
+
+/// Loads every ruleset in the configuration file. Rulesets which fail to parse are returned as
+/// errors alongside their names, so that the caller can report them without skipping the others.
+/// Fails only if the configuration file itself cannot be read or parsed.
+pub fn load_all_configs(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<Vec<LoadedRuleset>, ChiselError> {
+    let file = read_config(args, verbosity, log)?;
+    let config = parse_config(&file.contents, file.format)?;
+    let rulesets = match config {
+        ConfigValue::Map(ref rulesets) if !rulesets.is_empty() => rulesets,
+        _ => return Err(ChiselError::NoRuleset),
+    };
+
+    let mut contexts = Vec::with_capacity(rulesets.len());
+    for (name, _) in rulesets.iter() {
+        let name = match name.as_str() {
+            Some(name) => name,
+            None => {
+                contexts.push(Err((format!("{:?}", name), ChiselError::ConfigInvalid)));
+                continue;
+            }
+        };
+        let ctx = match ChiselContext::from_ruleset(&config, Some(name)) {
+            Ok(ctx) => ctx,
+            Err(msg) => {
+                contexts.push(Err((name.to_string(), msg)));
+                continue;
+            }
+        };
+        warn_no_modules(&ctx, verbosity, log)?;
+        contexts.push(apply_overrides(ctx, &file, args).map_err(|msg| (name.to_string(), msg)));
+    }
+    Ok(contexts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use execute::execute;
+    use parity_wasm::elements::Module;
+    use report::{failure_mask, junit_results, ModuleResult, Verbosity};
+    use std::fs::write;
+
+    #[test]
+    fn yaml_configure_first_ruleset() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyexports:
+    preset: \"ewasm\"
+  checkstartfunc: {}
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        assert_eq!(ctx.name(), "ewasm");
+        assert_eq!(ctx.files, vec!["target/contract.wasm".to_string()]);
+        assert_eq!(ctx.get_modules().len(), 2);
+        assert_eq!(ctx.get_modules()[0].fields().1, Some(Preset::Ewasm));
+        assert!(ctx.get_modules()[1].fields().1.is_none());
+    }
+
+    #[test]
+    fn yaml_configure_selected_ruleset() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyexports:
+    preset: \"ewasm\"
+strict:
+  file: \"target/strict.wasm\"
+  verifyimports:
+    preset: \"ewasm\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, Some("strict")).unwrap();
+        assert_eq!(ctx.name(), "strict");
+        assert_eq!(ctx.files, vec!["target/strict.wasm".to_string()]);
+        assert!(matches!(
+            configure(yaml, ConfigFormat::Yaml, Some("debug")),
+            Err(ChiselError::RulesetNotFound(..))
+        ));
+        assert_eq!(
+            ruleset_names(&parse_config(yaml, ConfigFormat::Yaml).unwrap()),
+            vec!["ewasm", "strict"]
+        );
+    }
+
+    #[test]
+    fn toml_configure() {
+        let toml = "
+[ewasm]
+file = \"target/contract.wasm\"
+verifyexports = { preset = \"ewasm\" }
+
+[strict]
+file = \"target/strict.wasm\"
+
+[strict.checkstartfunc]
+";
+        let ctx = configure(toml, ConfigFormat::Toml, None).unwrap();
+        assert_eq!(ctx.name(), "ewasm");
+        assert_eq!(ctx.files, vec!["target/contract.wasm".to_string()]);
+        assert_eq!(ctx.get_modules()[0].fields().0, "verifyexports");
+
+        let ctx = configure(toml, ConfigFormat::Toml, Some("strict")).unwrap();
+        assert_eq!(ctx.get_modules()[0].fields().0, "checkstartfunc");
+        assert!(matches!(
+            configure(toml, ConfigFormat::Yaml, None),
+            Err(ChiselError::ConfigParse)
+        ));
+    }
+
+    #[test]
+    fn module_list_configure() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  modules:
+    - remapimports:
+        preset: \"ewasm\"
+    - verifyimports:
+        preset: \"ewasm\"
+    - checkstartfunc
+    - remapimports:
+        preset: \"ewasm\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let names: Vec<&String> = ctx
+            .get_modules()
+            .iter()
+            .map(|module| module.fields().0)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "remapimports",
+                "verifyimports",
+                "checkstartfunc",
+                "remapimports"
+            ]
+        );
+
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  modules:
+    - checkstartfunc
+  verifyimports:
+    preset: \"ewasm\"
+";
+        assert!(matches!(
+            configure(yaml, ConfigFormat::Yaml, None),
+            Err(ChiselError::ConfigInvalid)
+        ));
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  modules:
+    - checkstartfunc: {}
+      verifyimports: {}
+";
+        assert!(matches!(
+            configure(yaml, ConfigFormat::Yaml, None),
+            Err(ChiselError::ModuleTypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn json_configure() {
+        let json = r#"{
+            "ewasm": {
+                "file": ["target/a.wasm", "target/b.wasm"],
+                "verifyexports": {"preset": "ewasm"}
+            }
+        }"#;
+        let ctx = configure(json, ConfigFormat::Json, None).unwrap();
+        assert_eq!(ctx.name(), "ewasm");
+        assert_eq!(
+            ctx.files,
+            vec!["target/a.wasm".to_string(), "target/b.wasm".to_string()]
+        );
+
+        let json = r#"{"ewasm": {"file": "target/a.wasm", "verifyexports": {"preset": 1}}}"#;
+        assert!(matches!(
+            configure(json, ConfigFormat::Json, None),
+            Err(ChiselError::PresetTypeMismatch(_))
+        ));
+        let json = r#"{"ewasm": {"verifyexports": {"preset": "ewasm"}}}"#;
+        assert!(matches!(
+            configure(json, ConfigFormat::Json, None),
+            Err(ChiselError::MissingFile(_))
+        ));
+    }
+
+    #[test]
+    fn yaml_configure_missing_file() {
+        let yaml = "
+ewasm:
+  verifyexports:
+    preset: \"ewasm\"
+";
+        assert!(matches!(
+            configure(yaml, ConfigFormat::Yaml, None),
+            Err(ChiselError::MissingFile(_))
+        ));
+    }
+
+    #[test]
+    fn config_without_ruleset_distinguished() {
+        for yaml in [
+            "{}",
+            "- \"target/contract.wasm\"",
+            "ewasm: \"target/contract.wasm\"",
+        ]
+        .iter()
+        {
+            assert!(matches!(
+                configure(yaml, ConfigFormat::Yaml, None),
+                Err(ChiselError::NoRuleset)
+            ));
+        }
+        assert!(matches!(
+            configure("ewasm: 1", ConfigFormat::Yaml, Some("ewasm")),
+            Err(ChiselError::RulesetTypeMismatch(_))
+        ));
+        // A ruleset without modules loads, and is only warned about.
+        let ctx = configure(
+            "ewasm:\n  file: \"target/contract.wasm\"\n",
+            ConfigFormat::Yaml,
+            None,
+        )
+        .unwrap();
+        assert!(ctx.get_modules().is_empty());
+    }
+
+    #[test]
+    fn yaml_configure_file_list() {
+        let yaml = "
+ewasm:
+  file:
+    - \"target/a.wasm\"
+    - \"target/b.wasm\"
+  verifyexports:
+    preset: \"ewasm\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        assert_eq!(
+            ctx.files,
+            vec!["target/a.wasm".to_string(), "target/b.wasm".to_string()]
+        );
+        assert!(ctx.has_multiple_files());
+
+        let yaml = "
+ewasm:
+  file: [1]
+";
+        assert!(matches!(
+            configure(yaml, ConfigFormat::Yaml, None),
+            Err(ChiselError::FileTypeMismatch)
+        ));
+    }
+
+    #[test]
+    fn expand_file_variables() {
+        let lookup = |name: &str| match name {
+            "TARGET" => Some("release".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            expand_vars("target/${TARGET}/${TARGET}.wasm", lookup).unwrap(),
+            "target/release/release.wasm"
+        );
+        assert_eq!(
+            expand_vars("$TARGET/${TARGET", lookup).unwrap(),
+            "$TARGET/${TARGET"
+        );
+        assert!(matches!(
+            expand_vars("${UNSET}/contract.wasm", lookup),
+            Err(ChiselError::UndefinedVariable(ref name)) if name == "UNSET"
+        ));
+    }
+
+    #[test]
+    fn relative_files_based_on_config_dir() {
+        let yaml = "
+ewasm:
+  file: [\"contract.wasm\", \"/abs/contract.wasm\", \"-\"]
+";
+        let mut ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        ctx.set_base_dir(Path::new("config"));
+        assert_eq!(
+            ctx.files,
+            vec!["config/contract.wasm", "/abs/contract.wasm", "-"]
+        );
+    }
+
+    #[test]
+    fn preset_overridden() {
+        let yaml = "
+ewasm:
+  file: \"contract.wasm\"
+  verifyexports:
+    preset: \"ewasm\"
+  trimexports: {}
+  checkstartfunc: {}
+";
+        let mut ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        ctx.set_preset("pwasm").unwrap();
+        let presets: Vec<Option<Preset>> = ctx
+            .get_modules()
+            .iter()
+            .map(|module| module.fields().1)
+            .collect();
+        assert_eq!(
+            presets,
+            vec![Some(Preset::PWasm), Some(Preset::PWasm), None]
+        );
+
+        assert!(matches!(
+            ctx.set_preset("memory"),
+            Err(ChiselError::InvalidPreset(ref name, ref preset, _))
+                if name == "verifyexports" && preset == "memory"
+        ));
+    }
+
+    #[test]
+    fn resolve_file_glob() {
+        let dir = std::env::temp_dir().join("chisel_resolve_files_test");
+        let _ = std::fs::create_dir(&dir);
+        write(dir.join("b.wasm"), b"").unwrap();
+        write(dir.join("a.wasm"), b"").unwrap();
+        let dir = dir.to_str().unwrap();
+
+        let yaml = format!("ewasm:\n  file: \"{}/*.wasm\"\n", dir);
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(ctx.has_multiple_files());
+        assert_eq!(
+            ctx.resolve_files().unwrap(),
+            vec![format!("{}/a.wasm", dir), format!("{}/b.wasm", dir)]
+        );
+
+        let yaml = format!("ewasm:\n  file: \"{}/*.wat\"\n", dir);
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.resolve_files(),
+            Err(ChiselError::GlobNoMatch(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn yaml_configure_unknown_module() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyexport:
+    preset: \"ewasm\"
+";
+        match configure(yaml, ConfigFormat::Yaml, None) {
+            Err(ChiselError::UnknownModule(name, suggestion)) => {
+                assert_eq!(name, "verifyexport");
+                assert_eq!(suggestion, Some("verifyexports".to_string()));
+            }
+            _ => panic!("expected an unknown module error"),
+        }
+
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  optimizer:
+    preset: \"ewasm\"
+";
+        assert!(matches!(
+            configure(yaml, ConfigFormat::Yaml, None),
+            Err(ChiselError::UnknownModule(_, None))
+        ));
+    }
+
+    #[test]
+    fn find_config_in_parents() {
+        let root = std::env::temp_dir().join("chisel_find_config_test");
+        let nested = root.join("src").join("contracts");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_ne!(find_config(&nested), Some(root.join(DEFAULT_CONFIG_PATH)));
+
+        write(root.join(DEFAULT_CONFIG_PATH), "").unwrap();
+        assert_eq!(find_config(&nested), Some(root.join(DEFAULT_CONFIG_PATH)));
+        assert_eq!(find_config(&root), Some(root.join(DEFAULT_CONFIG_PATH)));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn base_dir_applies_to_relative_paths() {
+        let mut ctx = ChiselContext {
+            ruleset_name: "ewasm".to_string(),
+            files: vec![
+                "target/a.wasm".to_string(),
+                "/abs/b.wasm".to_string(),
+                STDIN_PATH.to_string(),
+            ],
+            input_format: None,
+            modules: vec![],
+        };
+        ctx.set_base_dir(Path::new("/project"));
+        assert_eq!(
+            ctx.files,
+            vec![
+                "/project/target/a.wasm".to_string(),
+                "/abs/b.wasm".to_string(),
+                STDIN_PATH.to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn config_template_is_valid() {
+        let ctx = configure(CONFIG_TEMPLATE, ConfigFormat::Yaml, None).unwrap();
+        assert_eq!(ctx.name(), "ewasm");
+        let modules: Vec<&String> = ctx.get_modules().iter().map(|m| m.fields().0).collect();
+        assert_eq!(
+            modules,
+            vec!["verifyexports", "verifyimports", "checkstartfunc"]
+        );
+    }
+
+    #[test]
+    fn pipeline_follows_ruleset() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  trimexports:
+    preset: \"pwasm\"
+  verifyexports:
+    preset: \"pwasm\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let pipeline = ctx.pipeline().unwrap();
+        let stages: Vec<(&str, bool)> = pipeline
+            .stages()
+            .map(|(name, stage)| (name, stage.is_translator()))
+            .collect();
+        assert_eq!(
+            stages,
+            vec![("trimexports", true), ("verifyexports", false)]
+        );
+    }
+
+    #[test]
+    fn strict_config_rejects_unknown_options() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyexports:
+    persets: \"pwasm\"
+  maxsize:
+    limit: 1024
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        match ctx.check_options() {
+            Err(ChiselError::UnknownOption(module, option, suggestion)) => {
+                assert_eq!(module, "verifyexports");
+                assert_eq!(option, "persets");
+                assert_eq!(suggestion.as_deref(), Some("preset"));
+            }
+            _ => panic!("unknown option accepted"),
+        }
+
+        let yaml = yaml.replace("persets", "strict");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(ctx.check_options().is_ok());
+        let yaml = yaml.replace("limit", "maximum");
+        let ctx = configure(&yaml, ConfigFormat::Yaml, None).unwrap();
+        assert!(matches!(
+            ctx.check_options(),
+            Err(ChiselError::UnknownOption(_, _, None))
+        ));
+    }
+
+    #[test]
+    fn config_errors_reported_together() {
+        let yaml = "
+ewasm:
+  file: [\"target/contract.wasm\", 1]
+  verifyexprts:
+    preset: \"ewasm\"
+  verifyimports:
+    preset: \"wasm\"
+  remapexports: {}
+  trimexports: \"ewasm\"
+";
+        let errors = match configure(yaml, ConfigFormat::Yaml, None) {
+            Err(ChiselError::ConfigErrors(errors)) => errors,
+            _ => panic!("configuration accepted"),
+        };
+        assert!(matches!(errors[0], ChiselError::FileTypeMismatch));
+        assert!(matches!(errors[1], ChiselError::UnknownModule(_, Some(_))));
+        assert!(matches!(errors[2], ChiselError::InvalidPreset(..)));
+        assert!(matches!(errors[3], ChiselError::ModuleTypeMismatch(_)));
+        assert_eq!(errors.len(), 4);
+
+        // Options are only checked once every module is known.
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  remapexports: {}
+  verifyfuncsize:
+    limit: \"large\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let errors = match ctx.pipeline() {
+            Err(ChiselError::ConfigErrors(errors)) => errors,
+            _ => panic!("invalid options accepted"),
+        };
+        assert!(matches!(errors[0], ChiselError::MissingOption(_, _)));
+        assert!(matches!(errors[1], ChiselError::InvalidOption(_, _)));
+    }
+
+    #[test]
+    fn module_context_carries_options() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  modules:
+    - remapimports:
+        preset: \"ewasm\"
+        mapping:
+          \"env::abort\": \"ethereum::revert\"
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        let module = &ctx.get_modules()[0];
+        // The preset keeps its special casing, while the other options are left to the module.
+        assert_eq!(
+            module.fields(),
+            (&"remapimports".to_string(), Some(Preset::Ewasm))
+        );
+        let mapping = match module.options.get("mapping") {
+            Some(ConfigValue::Map(entries)) => entries,
+            _ => panic!("mapping not carried"),
+        };
+        assert_eq!(mapping.len(), 1);
+        assert_eq!(mapping[0].0.as_str(), Some("env::abort"));
+        assert_eq!(mapping[0].1.as_str(), Some("ethereum::revert"));
+    }
+
+    #[test]
+    fn yaml_configure_bad_preset_type() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyexports:
+    preset: 1
+";
+        assert!(matches!(
+            configure(yaml, ConfigFormat::Yaml, None),
+            Err(ChiselError::PresetTypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn yaml_configure_invalid_preset() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyimports:
+    preset: \"pwasm\"
+";
+        let err = match configure(yaml, ConfigFormat::Yaml, None) {
+            Err(err @ ChiselError::InvalidPreset(..)) => err,
+            _ => panic!("preset accepted"),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Module 'verifyimports' does not support the preset 'pwasm'. Accepted presets: ewasm, ewasm-debug."
+        );
+
+        // A module without presets would silently ignore one.
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  checkstartfunc:
+    preset: \"ewasm\"
+";
+        assert!(matches!(
+            configure(yaml, ConfigFormat::Yaml, None),
+            Err(ChiselError::PresetNotTaken(ref name)) if name == "checkstartfunc"
+        ));
+    }
+
+    #[test]
+    fn severity_and_enabled_options() {
+        let yaml = "
+ewasm:
+  file: \"target/contract.wasm\"
+  verifyexports:
+    preset: \"ewasm\"
+    severity: \"warn\"
+  verifyimports:
+    preset: \"ewasm\"
+    enabled: false
+  checkstartfunc: {}
+";
+        let ctx = configure(yaml, ConfigFormat::Yaml, None).unwrap();
+        assert_eq!(ctx.get_modules().len(), 2);
+        assert!(ctx.check_options().is_ok());
+
+        let mut module = Module::default();
+        let results: Vec<ModuleResult> = ctx
+            .get_modules()
+            .iter()
+            .map(|module_ctx| execute(module_ctx, &mut module, Verbosity::Normal))
+            .collect();
+        assert!(results[0].is_warning());
+        assert_eq!(failure_mask(&results), 0);
+        assert_eq!(
+            results[0].colored_status(true),
+            "\x1b[33mBAD (warning)\x1b[0m"
+        );
+        assert_eq!(results[0].to_json()["severity"], "warn");
+        assert!(results[0]
+            .to_junit("a.wasm")
+            .contains("<system-out>warning: "));
+        assert_eq!(results[0].to_sarif("a.wasm")[0]["level"], "warning");
+        assert!(junit_results("ewasm", "a.wasm", &results)
+            .contains("tests=\"2\" failures=\"0\" errors=\"0\""));
+
+        let yaml = yaml.replace("\"warn\"", "\"info\"");
+        assert!(matches!(
+            configure(&yaml, ConfigFormat::Yaml, None),
+            Err(ChiselError::InvalidOption(_, _))
+        ));
+    }
+}
@@ -0,0 +1,578 @@
+//! The subcommands of the CLI, each writing its output and log to the given sinks.
+
+use std::env;
+use std::fs::write;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+use libchisel::{
+    diff::{Change, SectionDiff},
+    pipeline::Stage,
+    presets::Preset,
+    repack::*,
+    ModuleTranslator,
+};
+
+use clap::ArgMatches;
+use config::ConfigValue;
+use disassemble::disassemble;
+use error::ChiselError;
+use execute::{
+    chisel_execute, chisel_translate, is_text, load_module, read_input, write_module, Backend,
+    InputFormat, ModuleCache,
+};
+use hash::{keccak256, sha256, to_hex};
+use parity_wasm::elements::serialize;
+use registry::{closest_module_name, invalid_preset, module_info, DEFAULT_PRESET, MODULES};
+use report::{
+    failure_mask, format_millis, parse_report, print_dry_run, print_section_diffs,
+    text_module_result, total_elapsed, FileReport, OutputFormat, RunReport, Verbosity,
+};
+use ruleset::{
+    input_format_from_args, load_all_configs, load_config, CONFIG_TEMPLATE, DEFAULT_CONFIG_PATH,
+    STDIN_PATH,
+};
+use EXIT_FATAL;
+
+/// Returns the number of files to process concurrently, defaulting to the number of CPUs.
+fn jobs_from_args(args: &ArgMatches) -> Result<usize, ChiselError> {
+    match args.value_of("JOBS") {
+        Some(jobs) => match jobs.parse::<usize>() {
+            Ok(jobs) if jobs > 0 => Ok(jobs),
+            _ => Err(ChiselError::InvalidJobs),
+        },
+        None => Ok(thread::available_parallelism().map_or(1, |jobs| jobs.get())),
+    }
+}
+
+pub fn chisel_subcommand_run(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    color: bool,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    let format = OutputFormat::from_str(args.value_of("FORMAT").unwrap_or("text"));
+    let backend = Backend::from_str(args.value_of("BACKEND").unwrap_or("parity"));
+    if backend != Backend::Parity && args.is_present("OUTPUT") {
+        return Err(ChiselError::BackendOutput);
+    }
+    let jobs = jobs_from_args(args)?;
+    let fail_fast = args.is_present("FAIL_FAST");
+    let run_all = args.is_present("ALL");
+    let rulesets = if run_all {
+        load_all_configs(args, verbosity, log)?
+    } else {
+        vec![Ok(load_config(args, verbosity, log)?)]
+    };
+
+    let reports = args
+        .values_of("REPORT")
+        .into_iter()
+        .flatten()
+        .map(parse_report)
+        .collect::<Result<Vec<(&str, OutputFormat)>, ChiselError>>()?;
+
+    let cache = ModuleCache::default();
+    let mut exit_code = 0;
+    let mut report = RunReport {
+        all_rulesets: run_all,
+        ..RunReport::default()
+    };
+    for ruleset in rulesets {
+        if let Ok(ref ctx) = ruleset {
+            if args.is_present("OUTPUT") && ctx.has_multiple_files() {
+                return Err(ChiselError::MultipleFiles);
+            }
+        }
+        let outcome = ruleset.and_then(|ctx| {
+            match chisel_execute(&ctx, verbosity, backend, jobs, fail_fast, &cache, log) {
+                Ok(files) => Ok((ctx, files)),
+                Err(msg) => Err((ctx.name().clone(), msg)),
+            }
+        });
+
+        match outcome {
+            Ok((ctx, files)) => {
+                report.multiple_files |= ctx.has_multiple_files();
+                for file in files {
+                    report.files_total += 1;
+                    let (module, results) = match file.outcome {
+                        Ok(outcome) => outcome,
+                        // A single broken binary must not hide the results of the others.
+                        Err(msg) if run_all || ctx.has_multiple_files() => {
+                            writeln!(
+                                log,
+                                "{}: warning: file '{}': {}",
+                                crate_name!(),
+                                file.file,
+                                msg
+                            )?;
+                            exit_code = EXIT_FATAL;
+                            continue;
+                        }
+                        Err(msg) => return Err(msg),
+                    };
+
+                    let mask = failure_mask(&results);
+                    exit_code |= mask;
+                    if let (0, Some(module), Some(output_path)) =
+                        (mask, module, args.value_of("OUTPUT"))
+                    {
+                        let module =
+                            Arc::try_unwrap(module).unwrap_or_else(|module| (*module).clone());
+                        write_module(module, output_path)?;
+                    }
+                    report.files.push(FileReport {
+                        ruleset: ctx.name().clone(),
+                        file: file.file,
+                        results,
+                    });
+                }
+            }
+            // A single broken ruleset must not hide the results of the others.
+            Err((name, msg)) if run_all => {
+                writeln!(
+                    log,
+                    "{}: warning: ruleset '{}': {}",
+                    crate_name!(),
+                    name,
+                    msg
+                )?;
+                exit_code = EXIT_FATAL;
+            }
+            Err((_, msg)) => return Err(msg),
+        }
+        // The rulesets after a failing one are not run either.
+        if fail_fast && exit_code != 0 {
+            break;
+        }
+    }
+
+    if verbosity != Verbosity::Quiet {
+        write!(out, "{}", report.render(format, verbosity, color))?;
+    }
+    // Report files are written even when quiet, as they were asked for explicitly.
+    let file_verbosity = match verbosity {
+        Verbosity::Quiet => Verbosity::Normal,
+        verbosity => verbosity,
+    };
+    for (path, format) in reports {
+        write(path, report.render(format, file_verbosity, false))
+            .map_err(|err| ChiselError::WriteReport(path.to_string(), err))?;
+    }
+
+    Ok(exit_code)
+}
+
+pub fn chisel_subcommand_translate(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    color: bool,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    let ctx = load_config(args, verbosity, log)?;
+    let file = ctx.single_file()?;
+    let dry_run = args.is_present("DRY_RUN");
+    if (file == STDIN_PATH || is_text(file, ctx.input_format()))
+        && !args.is_present("OUTPUT")
+        && !dry_run
+    {
+        return Err(ChiselError::OutputRequired);
+    }
+
+    let diff = args.is_present("DIFF");
+    let results = chisel_translate(&ctx, verbosity, dry_run, diff, log)?;
+    let quiet = verbosity == Verbosity::Quiet;
+    if !quiet {
+        writeln!(out, "========== RESULTS ==========")?;
+        writeln!(out, "{}: {}", ctx.name(), file)?;
+        for (index, result) in results.results.iter().enumerate() {
+            write!(
+                out,
+                "{}",
+                text_module_result(index, result, verbosity, color)
+            )?;
+        }
+        if verbosity == Verbosity::Verbose {
+            let elapsed = total_elapsed(results.results.iter());
+            writeln!(out, "Total time: {}", format_millis(elapsed))?;
+        }
+        if diff {
+            print_section_diffs(&results, out)?;
+        }
+    }
+
+    if dry_run {
+        if !quiet {
+            print_dry_run(&results, verbosity, out)?;
+        }
+        return Ok(if results.module.is_some() { 0 } else { 1 });
+    }
+
+    let modified = results.modified();
+    if let Some(module) = results.module {
+        // An unchanged module is not written back over its input.
+        if !modified && !args.is_present("OUTPUT") {
+            if !quiet {
+                writeln!(out, "{} is unchanged, not rewriting it.", file)?;
+            }
+            return Ok(0);
+        }
+        let output_path = args.value_of("OUTPUT").unwrap_or(file);
+        write_module(module, output_path)?;
+        if !quiet {
+            writeln!(out, "Wrote {}", output_path)?;
+        }
+        Ok(0)
+    } else {
+        if !quiet {
+            writeln!(out, "Not writing output due to failures.")?;
+        }
+        Ok(1)
+    }
+}
+
+pub fn chisel_subcommand_list_modules(
+    args: &ArgMatches,
+    out: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    let with_presets = args.is_present("PRESETS");
+    match OutputFormat::from_str(args.value_of("FORMAT").unwrap_or("text")) {
+        // JUnit and SARIF are only offered by `run`, as there are no results to report.
+        OutputFormat::Text | OutputFormat::Junit | OutputFormat::Sarif => {
+            for module in MODULES.iter() {
+                if !with_presets {
+                    writeln!(out, "{} ({})", module.name, module.kind.name())?;
+                    continue;
+                }
+                let presets: Vec<&str> = (module.presets)()
+                    .iter()
+                    .map(|preset| preset.name())
+                    .collect();
+                let presets = if presets.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    presets.join(", ")
+                };
+                writeln!(
+                    out,
+                    "{} ({}): presets: {}",
+                    module.name,
+                    module.kind.name(),
+                    presets
+                )?;
+            }
+        }
+        OutputFormat::Json => {
+            let modules: Vec<serde_json::Value> = MODULES
+                .iter()
+                .map(|module| {
+                    let mut entry = json!({
+                        "name": module.name,
+                        "kind": module.kind.name(),
+                    });
+                    if with_presets {
+                        entry["presets"] = json!((module.presets)()
+                            .iter()
+                            .map(|preset| preset.name())
+                            .collect::<Vec<_>>());
+                    }
+                    entry
+                })
+                .collect();
+            writeln!(out, "{}", serde_json::Value::Array(modules))?;
+        }
+    }
+    Ok(0)
+}
+
+/// Returns the rules the named validator applies with the preset, as reported by the validator
+/// itself once built from the preset.
+fn explain_module(name: &str, preset: Option<&str>) -> Result<Vec<String>, ChiselError> {
+    let info = module_info(name).ok_or_else(|| {
+        ChiselError::UnknownModule(
+            name.to_string(),
+            closest_module_name(name).map(|module| module.to_string()),
+        )
+    })?;
+    let preset = match preset {
+        Some(preset) => preset
+            .parse::<Preset>()
+            .map_err(|_| invalid_preset(name, preset))?,
+        None => DEFAULT_PRESET,
+    };
+    let accepted = (info.presets)();
+    if !accepted.is_empty() && !accepted.contains(&preset) {
+        return Err(invalid_preset(name, preset.name()));
+    }
+    let options = ConfigValue::Map(vec![]);
+    let stage = (info.build)(preset, &options)?;
+    match stage {
+        Stage::Validator(validator) => Ok(validator.rules()),
+        Stage::Translator(_) => Err(ChiselError::ExplainTranslator(name.to_string())),
+    }
+}
+
+pub fn chisel_explain(
+    name: &str,
+    preset: Option<&str>,
+    out: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    let rules = explain_module(name, preset)?;
+    match module_info(name).map(|info| (info.presets)().is_empty()) {
+        Some(false) => writeln!(
+            out,
+            "{} (preset {}):",
+            name,
+            preset.unwrap_or_else(|| DEFAULT_PRESET.name())
+        )?,
+        _ => writeln!(out, "{}:", name)?,
+    }
+    for rule in rules {
+        writeln!(out, "\t- {}", rule)?;
+    }
+    Ok(0)
+}
+
+pub fn chisel_subcommand_disassemble(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    let path = args.value_of("FILE").unwrap();
+    let module = load_module(path, input_format_from_args(args), verbosity, log)?;
+    write!(out, "{}", disassemble(&module))?;
+    Ok(0)
+}
+
+/// Compares two modules, exiting with 1 if they differ so that it can serve as a regression
+/// check.
+pub fn chisel_subcommand_diff(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    let format = input_format_from_args(args);
+    let before = load_module(args.value_of("BEFORE").unwrap(), format, verbosity, log)?;
+    let after = load_module(args.value_of("AFTER").unwrap(), format, verbosity, log)?;
+    let diff = SectionDiff::between(&before, &after)
+        .map_err(|err| ChiselError::ModuleFailed("diff".to_string(), err))?;
+    if verbosity != Verbosity::Quiet {
+        match OutputFormat::from_str(args.value_of("FORMAT").unwrap_or("text")) {
+            OutputFormat::Json => writeln!(out, "{}", section_diff_json(&diff))?,
+            _ => writeln!(out, "{}", diff)?,
+        }
+    }
+    Ok(if diff.is_empty() { 0 } else { 1 })
+}
+
+/// Renders the diff as JSON, with each change to an import or export as an object naming the
+/// kind of change and the items involved.
+fn section_diff_json(diff: &SectionDiff) -> serde_json::Value {
+    let changes = |changes: &[Change]| -> Vec<serde_json::Value> {
+        changes
+            .iter()
+            .map(|change| match change {
+                Change::Added(name) => json!({ "change": "added", "name": name }),
+                Change::Removed(name) => json!({ "change": "removed", "name": name }),
+                Change::Renamed(from, to) => json!({ "change": "renamed", "from": from, "to": to }),
+            })
+            .collect()
+    };
+    let sections: Vec<serde_json::Value> = diff
+        .sections
+        .iter()
+        .map(|section| {
+            json!({
+                "name": section.name,
+                "size_before": section.size_before,
+                "size_after": section.size_after,
+            })
+        })
+        .collect();
+    json!({
+        "size_before": diff.size_before,
+        "size_after": diff.size_after,
+        "sections": sections,
+        "imports": changes(&diff.imports),
+        "exports": changes(&diff.exports),
+    })
+}
+
+pub fn chisel_subcommand_hash(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    out: &mut dyn Write,
+    log: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    let path = args.value_of("FILE").unwrap();
+    let format = input_format_from_args(args);
+    let binary = hashed_binary(path, format, args.is_present("REPACK"), verbosity, log)?;
+    write!(out, "{}", digests(&binary, args.is_present("KECCAK")))?;
+    Ok(0)
+}
+
+/// Returns the bytes to hash: those of the file as is, or of the module re-encoded with its
+/// sections in canonical order if `repack` is set. A module in the text format is always converted
+/// to a binary.
+fn hashed_binary(
+    path: &str,
+    format: Option<InputFormat>,
+    repack: bool,
+    verbosity: Verbosity,
+    log: &mut dyn Write,
+) -> Result<Vec<u8>, ChiselError> {
+    if !repack && !is_text(path, format) {
+        return read_input(path, verbosity, log);
+    }
+    let mut module = load_module(path, format, verbosity, log)?;
+    if repack {
+        Repack::new(true)
+            .translate_inplace(&mut module)
+            .map_err(|err| ChiselError::ModuleFailed("repack".to_string(), err))?;
+    }
+    serialize(module).map_err(|err| {
+        ChiselError::ModuleFailed(
+            "repack".to_string(),
+            format!("Failed to serialize the module: {}", err),
+        )
+    })
+}
+
+/// Formats the SHA-256 digest of the binary, and its Keccak-256 digest if `keccak` is set, one
+/// per line.
+pub fn digests(binary: &[u8], keccak: bool) -> String {
+    let mut lines = format!("sha256:{}\n", to_hex(&sha256(binary)));
+    if keccak {
+        lines.push_str(&format!("keccak256:{}\n", to_hex(&keccak256(binary))));
+    }
+    lines
+}
+
+pub fn chisel_subcommand_init(
+    args: &ArgMatches,
+    verbosity: Verbosity,
+    out: &mut dyn Write,
+) -> Result<i32, ChiselError> {
+    let path = args.value_of("CONFIG").unwrap_or(DEFAULT_CONFIG_PATH);
+    if Path::new(path).exists() && !args.is_present("FORCE") {
+        return Err(ChiselError::ConfigExists(path.to_string()));
+    }
+    write(path, CONFIG_TEMPLATE).map_err(ChiselError::WriteConfig)?;
+    if verbosity != Verbosity::Quiet {
+        writeln!(out, "Wrote {}", path)?;
+    }
+    Ok(0)
+}
+
+/// Determines the verbosity from the global flags, which may be given before or after the
+/// subcommand.
+pub fn verbosity_from_args(
+    global: &ArgMatches,
+    subcommand: Option<&ArgMatches>,
+) -> Result<Verbosity, ChiselError> {
+    let is_present = |name: &str| {
+        global.is_present(name) || subcommand.is_some_and(|args| args.is_present(name))
+    };
+    match (is_present("QUIET"), is_present("VERBOSE")) {
+        (true, true) => Err(ChiselError::ConflictingVerbosity),
+        (true, false) => Ok(Verbosity::Quiet),
+        (false, true) => Ok(Verbosity::Verbose),
+        (false, false) => Ok(Verbosity::Normal),
+    }
+}
+
+/// Colors are only used when printing to a terminal, and can be disabled with `--no-color` or
+/// by setting `NO_COLOR`.
+pub fn color_from_args(global: &ArgMatches, subcommand: Option<&ArgMatches>) -> bool {
+    let no_color = global.is_present("NO_COLOR")
+        || subcommand.is_some_and(|args| args.is_present("NO_COLOR"))
+        || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !no_color && io::stdout().is_terminal()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use execute::load_module;
+    use report::Verbosity;
+    use std::fs::remove_file;
+
+    #[test]
+    fn explain_lists_rules_of_preset() {
+        let rules = explain_module("verifyimports", Some("ewasm")).unwrap();
+        assert!(
+            rules.contains(&"allows import function `ethereum::useGas` (i64) -> ()".to_string())
+        );
+        assert_eq!(
+            rules[rules.len() - 2..],
+            [
+                "requires every import to come from `ethereum`".to_string(),
+                "rejects unlisted imports".to_string(),
+            ]
+        );
+        assert!(explain_module("verifyimports", Some("ewasm-debug"))
+            .unwrap()
+            .contains(&"requires every import to come from `ethereum`, `debug`".to_string()));
+        assert_eq!(
+            explain_module("checkstartfunc", None).unwrap(),
+            ["rejects a start function"]
+        );
+
+        assert!(matches!(
+            explain_module("verifyimport", None),
+            Err(ChiselError::UnknownModule(_, Some(_)))
+        ));
+        assert!(matches!(
+            explain_module("verifymemory", Some("pwasm")),
+            Err(ChiselError::InvalidPreset(..))
+        ));
+        assert!(matches!(
+            explain_module("trimexports", None),
+            Err(ChiselError::ExplainTranslator(_))
+        ));
+    }
+
+    #[test]
+    fn hash_of_repacked_module() {
+        let path = std::env::temp_dir().join("chisel_hash_test.wat");
+        let path = path.to_str().unwrap();
+        write(path, "(module (memory 1) (export \"memory\" (memory 0)))").unwrap();
+        let text = hashed_binary(path, None, false, Verbosity::Normal, &mut io::sink()).unwrap();
+        assert_eq!(
+            text,
+            serialize(load_module(path, None, Verbosity::Normal, &mut io::sink()).unwrap())
+                .unwrap()
+        );
+        remove_file(path).unwrap();
+
+        // Encoding noise, such as an overlong size of a section, is only hashed as is.
+        let path = std::env::temp_dir().join("chisel_hash_test.wasm");
+        let path = path.to_str().unwrap();
+        let mut padded = text[..8].to_vec();
+        padded.extend_from_slice(&[5, 0x83, 0x80, 0x80, 0x80, 0, 1, 0, 1]);
+        padded.extend_from_slice(&text[13..]);
+        write(path, &padded).unwrap();
+        assert_eq!(
+            hashed_binary(path, None, false, Verbosity::Normal, &mut io::sink()).unwrap(),
+            padded
+        );
+        assert_eq!(
+            hashed_binary(path, None, true, Verbosity::Normal, &mut io::sink()).unwrap(),
+            text
+        );
+        remove_file(path).unwrap();
+
+        assert_eq!(
+            digests(b"", true),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\n\
+             keccak256:c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470\n"
+        );
+    }
+}
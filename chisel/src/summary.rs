@@ -0,0 +1,177 @@
+//! A short overview of the structure of a module, printed in verbose mode so that a failing
+//! result can be related to the shape of the module without disassembling it.
+
+use std::fmt;
+
+use parity_wasm::elements::{External, ImportCountType, Module, ResizableLimits, Section};
+
+/// The structure of a module, as counted and named by `summarize`.
+#[derive(Debug, PartialEq)]
+pub struct ModuleSummary {
+    /// Number of functions the module defines.
+    pub functions: usize,
+    /// Number of functions the module imports.
+    pub imported_functions: usize,
+    /// Names of the imports, grouped by namespace in the order each namespace first appears.
+    pub imports: Vec<(String, Vec<String>)>,
+    /// Names of the exports, in order.
+    pub exports: Vec<String>,
+    /// Initial and maximum number of pages of each memory, imported ones first.
+    pub memories: Vec<(u32, Option<u32>)>,
+    /// Index of the start function, if there is one.
+    pub start: Option<u32>,
+    /// Names of the custom sections, in order.
+    pub custom_sections: Vec<String>,
+}
+
+/// Summarizes the structure of the module.
+pub fn summarize(module: &Module) -> ModuleSummary {
+    let mut imports: Vec<(String, Vec<String>)> = Vec::new();
+    let mut memories = Vec::new();
+    let entries = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries());
+    for entry in entries {
+        match imports
+            .iter_mut()
+            .find(|(namespace, _)| namespace == entry.module())
+        {
+            Some((_, fields)) => fields.push(entry.field().to_string()),
+            None => imports.push((entry.module().to_string(), vec![entry.field().to_string()])),
+        }
+        if let External::Memory(memory) = entry.external() {
+            memories.push(pages(memory.limits()));
+        }
+    }
+    if let Some(section) = module.memory_section() {
+        memories.extend(
+            section
+                .entries()
+                .iter()
+                .map(|memory| pages(memory.limits())),
+        );
+    }
+
+    let custom_sections = module
+        .sections()
+        .iter()
+        .filter_map(|section| match section {
+            Section::Custom(section) => Some(section.name().to_string()),
+            Section::Name(_) => Some("name".to_string()),
+            Section::Reloc(section) => Some(section.name().to_string()),
+            _ => None,
+        })
+        .collect();
+
+    ModuleSummary {
+        functions: module
+            .function_section()
+            .map_or(0, |section| section.entries().len()),
+        imported_functions: module.import_count(ImportCountType::Function),
+        imports,
+        exports: module.export_section().map_or(vec![], |section| {
+            section
+                .entries()
+                .iter()
+                .map(|entry| entry.field().to_string())
+                .collect()
+        }),
+        memories,
+        start: module.start_section(),
+        custom_sections,
+    }
+}
+
+fn pages(limits: &ResizableLimits) -> (u32, Option<u32>) {
+    (limits.initial(), limits.maximum())
+}
+
+/// Joins the names, or returns `none` if there are none.
+fn list(names: &[String]) -> String {
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+impl fmt::Display for ModuleSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "\tfunctions: {} defined, {} imported",
+            self.functions, self.imported_functions
+        )?;
+        let imports: Vec<String> = self
+            .imports
+            .iter()
+            .map(|(namespace, fields)| format!("{} ({})", namespace, fields.join(", ")))
+            .collect();
+        writeln!(f, "\timports: {}", list(&imports))?;
+        writeln!(f, "\texports: {}", list(&self.exports))?;
+        let memories: Vec<String> = self
+            .memories
+            .iter()
+            .map(|(initial, maximum)| match maximum {
+                Some(maximum) => format!("{} to {} pages", initial, maximum),
+                None => format!("{} pages, unbounded", initial),
+            })
+            .collect();
+        writeln!(f, "\tmemory: {}", list(&memories))?;
+        match self.start {
+            Some(start) => writeln!(f, "\tstart function: {}", start)?,
+            None => writeln!(f, "\tstart function: none")?,
+        }
+        writeln!(f, "\tcustom sections: {}", list(&self.custom_sections))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libchisel::fromwat::FromWat;
+    use libchisel::ModuleCreator;
+
+    #[test]
+    fn module_summarized() {
+        let mut module = FromWat::new(
+            r#"(module
+                (import "ethereum" "useGas" (func (param i64)))
+                (import "debug" "print32" (func (param i32)))
+                (import "ethereum" "finish" (func (param i32 i32)))
+                (memory 1 2)
+                (func $init)
+                (func (export "main"))
+                (export "memory" (memory 0))
+                (start $init))"#,
+        )
+        .create()
+        .unwrap();
+        module
+            .sections_mut()
+            .push(Section::Custom(parity_wasm::elements::CustomSection::new(
+                "producers".to_string(),
+                vec![],
+            )));
+        let summary = summarize(&module);
+        assert_eq!(
+            summary.imports,
+            [
+                (
+                    "ethereum".to_string(),
+                    vec!["useGas".to_string(), "finish".to_string()]
+                ),
+                ("debug".to_string(), vec!["print32".to_string()])
+            ]
+        );
+        assert_eq!(
+            summary.to_string(),
+            "\tfunctions: 2 defined, 3 imported\n\
+             \timports: ethereum (useGas, finish), debug (print32)\n\
+             \texports: main, memory\n\
+             \tmemory: 1 to 2 pages\n\
+             \tstart function: 3\n\
+             \tcustom sections: name, producers\n"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Runs the chisel binary over the fixtures in `tests/fixtures`, checking the result of each
+//! validator of the ruleset along with its diagnostics.
+
+extern crate serde_json;
+
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+/// Runs the ruleset of the fixtures configuration, returning the exit code and the reasons given
+/// by each module, empty for those which passed.
+fn run(ruleset: &str) -> (i32, Vec<Vec<String>>) {
+    let config = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/chisel.yml");
+    let output = Command::new(env!("CARGO_BIN_EXE_chisel"))
+        .args(["run", "--format", "json", "-r", ruleset, "-c"])
+        .arg(config)
+        .output()
+        .unwrap();
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let reasons = report["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| {
+            result["reasons"].as_array().map_or(vec![], |reasons| {
+                reasons
+                    .iter()
+                    .map(|reason| reason.as_str().unwrap().to_string())
+                    .collect()
+            })
+        })
+        .collect();
+    (output.status.code().unwrap(), reasons)
+}
+
+#[test]
+fn passing_module() {
+    let (code, reasons) = run("passing");
+    assert_eq!(code, 0);
+    assert!(reasons.iter().all(|reasons| reasons.is_empty()));
+}
+
+#[test]
+fn missing_main() {
+    let (code, reasons) = run("missing_main");
+    assert_eq!(code, 1);
+    assert_eq!(
+        reasons[0],
+        ["missing export `main`", "unexpected export `run`"]
+    );
+}
+
+#[test]
+fn missing_memory() {
+    let (code, reasons) = run("missing_memory");
+    assert_eq!(code, 1);
+    assert_eq!(reasons[0], ["missing export `memory`"]);
+}
+
+#[test]
+fn bad_import_namespace() {
+    let (code, reasons) = run("bad_namespace");
+    assert_eq!(code, 2);
+    assert_eq!(
+        reasons[1],
+        ["import `env::abort` is not from an allowed namespace"]
+    );
+}
+
+#[test]
+fn start_function() {
+    let (code, reasons) = run("start_function");
+    assert_eq!(code, 4);
+    assert_eq!(reasons[2], ["unexpected start function"]);
+}
+
+#[test]
+fn unknown_preset() {
+    let config = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/chisel.yml");
+    let output = Command::new(env!("CARGO_BIN_EXE_chisel"))
+        .args(["run", "--format", "json", "-r", "typo_preset", "-c"])
+        .arg(config)
+        .output()
+        .unwrap();
+    // Exits as a fatal error, with no result and no failure bit set.
+    assert_eq!(output.status.code(), Some(255));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Module 'verifyexports' does not support the preset 'ewasmm'."));
+}
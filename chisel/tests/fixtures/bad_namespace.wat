@@ -0,0 +1,6 @@
+;; Imports from the namespace of a C toolchain rather than the EEI.
+(module
+  (import "env" "abort" (func))
+  (memory 1)
+  (export "memory" (memory 0))
+  (func (export "main")))
@@ -0,0 +1,5 @@
+;; Exports its entry point under another name.
+(module
+  (memory 1)
+  (export "memory" (memory 0))
+  (func (export "run")))
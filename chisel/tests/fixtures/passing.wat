@@ -0,0 +1,7 @@
+;; A minimal ewasm contract, which only uses gas through the EEI.
+(module
+  (import "ethereum" "useGas" (func $useGas (param i64)))
+  (memory 1)
+  (export "memory" (memory 0))
+  (func (export "main")
+    (call $useGas (i64.const 1))))
@@ -0,0 +1,7 @@
+;; Runs code when instantiated, before `main` is called.
+(module
+  (memory 1)
+  (export "memory" (memory 0))
+  (func $init)
+  (func (export "main"))
+  (start $init))
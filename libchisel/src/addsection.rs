@@ -0,0 +1,124 @@
+use super::{translate_copy, ModuleTranslator};
+use parity_wasm::elements::{CustomSection, Module, Section};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Appends a custom section to the module, e.g. to record the version of the tools which built
+/// it. An existing custom section of the same name is an error, unless it is to be replaced.
+pub struct AddSection<'a> {
+    /// Name of the custom section.
+    name: &'a str,
+    /// Contents of the custom section.
+    payload: Vec<u8>,
+    /// Whether existing custom sections of the same name are removed instead of failing.
+    replace: bool,
+}
+
+impl<'a> AddSection<'a> {
+    pub fn new(name: &'a str, payload: Vec<u8>) -> Self {
+        AddSection {
+            name,
+            payload,
+            replace: false,
+        }
+    }
+
+    pub fn set_replace(&mut self, replace: bool) {
+        self.replace = replace;
+    }
+
+    fn is_same_name(&self, section: &Section) -> bool {
+        match section {
+            Section::Custom(custom) => custom.name() == self.name,
+            Section::Name(_) => self.name == "name",
+            Section::Reloc(reloc) => reloc.name() == self.name,
+            _ => false,
+        }
+    }
+
+    /// Appends the custom section after every other section. Returns its size in bytes, counting
+    /// its header.
+    pub fn add_section(&self, module: &mut Module) -> Result<usize, String> {
+        if module
+            .sections()
+            .iter()
+            .any(|section| self.is_same_name(section))
+        {
+            if !self.replace {
+                return Err(format!("custom section {} already exists", self.name));
+            }
+            module
+                .sections_mut()
+                .retain(|section| !self.is_same_name(section));
+        }
+
+        let section = Section::Custom(CustomSection::new(
+            self.name.to_string(),
+            self.payload.clone(),
+        ));
+        let size = parity_wasm::serialize(section.clone())
+            .map_err(|_| "Failed to serialize the custom section".to_string())?
+            .len();
+        module.sections_mut().push(section);
+        Ok(size)
+    }
+}
+
+impl<'a> ModuleTranslator for AddSection<'a> {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        self.add_section(module)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+
+    fn custom_sections(module: &Module) -> Vec<(&str, &[u8])> {
+        module
+            .custom_sections()
+            .map(|custom| (custom.name(), custom.payload()))
+            .collect()
+    }
+
+    #[test]
+    fn section_added() {
+        let mut module = builder::module().build();
+        module.set_custom_section("producers", vec![0x00]);
+
+        // A byte of id, a byte of size, and the name prefixed by its length before the payload.
+        let translator = AddSection::new("chisel", b"0.1.0".to_vec());
+        assert_eq!(translator.add_section(&mut module), Ok(2 + 7 + 5));
+        assert_eq!(
+            custom_sections(&module),
+            vec![("producers", &[0x00][..]), ("chisel", &b"0.1.0"[..])]
+        );
+
+        let serialized = parity_wasm::serialize(module).unwrap();
+        let module = parity_wasm::deserialize_buffer::<Module>(&serialized).unwrap();
+        assert_eq!(custom_sections(&module).len(), 2);
+    }
+
+    #[test]
+    fn existing_section_replaced() {
+        let mut module = builder::module().build();
+        module.set_custom_section("chisel", b"0.0.1".to_vec());
+        module.set_custom_section("producers", vec![0x00]);
+
+        let mut translator = AddSection::new("chisel", b"0.1.0".to_vec());
+        assert!(translator.translate_inplace(&mut module).is_err());
+        assert_eq!(custom_sections(&module)[0], ("chisel", &b"0.0.1"[..]));
+
+        translator.set_replace(true);
+        assert!(translator.translate_inplace(&mut module).unwrap());
+        assert_eq!(
+            custom_sections(&module),
+            vec![("producers", &[0x00][..]), ("chisel", &b"0.1.0"[..])]
+        );
+    }
+}
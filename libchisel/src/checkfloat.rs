@@ -0,0 +1,457 @@
+use super::streaming::{for_each_payload, parse_error};
+use super::{ModuleValidator, ValidationResult};
+use parity_wasm::elements::{
+    BlockType, External, FuncBody, FunctionType, Instruction, Module, Type, ValueType,
+};
+
+/// Struct on which ModuleValidator is implemented.
+#[derive(Default)]
+pub struct CheckFloat {}
+
+impl CheckFloat {
+    pub fn new() -> Self {
+        CheckFloat {}
+    }
+}
+
+impl ModuleValidator for CheckFloat {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let mut reasons = Vec::new();
+
+        for (index, global_type) in global_types(module).into_iter().enumerate() {
+            if is_float(global_type) {
+                reasons.push(format!(
+                    "global {} has floating-point type {}",
+                    index, global_type
+                ));
+            }
+        }
+
+        let types = module
+            .type_section()
+            .map_or(&[][..], |section| section.types());
+        let signature = |type_ref: u32| match types.get(type_ref as usize) {
+            Some(Type::Function(func_type)) => Ok(func_type),
+            None => Err(format!("type {} does not exist", type_ref)),
+        };
+
+        let mut index = 0;
+        if let Some(section) = module.import_section() {
+            for entry in section.entries() {
+                if let External::Function(type_ref) = entry.external() {
+                    if has_float_signature(signature(*type_ref)?) {
+                        reasons.push(float_function_reason(index));
+                    }
+                    index += 1;
+                }
+            }
+        }
+
+        let functions = module
+            .function_section()
+            .map_or(&[][..], |section| section.entries());
+        let bodies = module
+            .code_section()
+            .map_or(&[][..], |section| section.bodies());
+        if functions.len() != bodies.len() {
+            return Err("function and code section have inconsistent lengths".to_string());
+        }
+
+        for (function, body) in functions.iter().zip(bodies) {
+            if has_float_signature(signature(function.type_ref())?) || has_float_body(body) {
+                reasons.push(float_function_reason(index));
+            }
+            index += 1;
+        }
+
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn validate_binary(&self, binary: &[u8]) -> Option<Result<ValidationResult, String>> {
+        Some(validate_streaming(binary))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        vec!["rejects floating-point instructions and types".to_string()]
+    }
+}
+
+/// Checks the binary as `validate` does, reading each section once with wasmparser instead of
+/// deserializing the module. The globals are reported before the functions, as in `validate`.
+fn validate_streaming(binary: &[u8]) -> Result<ValidationResult, String> {
+    use wasmparser::{Payload, TypeRef};
+
+    let mut global_reasons = Vec::new();
+    let mut function_reasons = Vec::new();
+    let mut types = Vec::new();
+    let mut functions = Vec::new();
+    let mut global_index = 0;
+    let mut function_index = 0;
+    let mut body_index = 0;
+
+    for_each_payload(binary, true, |payload| {
+        match payload {
+            Payload::TypeSection(reader) => {
+                for func_type in reader.into_iter_err_on_gc_types() {
+                    types.push(func_type.map_err(parse_error)?);
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader.into_imports() {
+                    match import.map_err(parse_error)?.ty {
+                        TypeRef::Func(type_ref) | TypeRef::FuncExact(type_ref) => {
+                            if has_float_func_type(streaming_signature(&types, type_ref)?) {
+                                function_reasons.push(float_function_reason(function_index));
+                            }
+                            function_index += 1;
+                        }
+                        TypeRef::Global(global_type) => {
+                            if let Some(name) = float_name(global_type.content_type) {
+                                global_reasons.push(format!(
+                                    "global {} has floating-point type {}",
+                                    global_index, name
+                                ));
+                            }
+                            global_index += 1;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for type_ref in reader {
+                    functions.push(type_ref.map_err(parse_error)?);
+                }
+            }
+            Payload::GlobalSection(reader) => {
+                for global in reader {
+                    let content_type = global.map_err(parse_error)?.ty.content_type;
+                    if let Some(name) = float_name(content_type) {
+                        global_reasons.push(format!(
+                            "global {} has floating-point type {}",
+                            global_index, name
+                        ));
+                    }
+                    global_index += 1;
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let type_ref = *functions
+                    .get(body_index)
+                    .ok_or("function and code section have inconsistent lengths")?;
+                body_index += 1;
+                if has_float_func_type(streaming_signature(&types, type_ref)?)
+                    || has_float_func_body(&body).map_err(parse_error)?
+                {
+                    function_reasons.push(float_function_reason(function_index));
+                }
+                function_index += 1;
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    if functions.len() != body_index {
+        return Err("function and code section have inconsistent lengths".to_string());
+    }
+    global_reasons.extend(function_reasons);
+    Ok(ValidationResult::from_reasons(global_reasons))
+}
+
+fn streaming_signature(
+    types: &[wasmparser::FuncType],
+    type_ref: u32,
+) -> Result<&wasmparser::FuncType, String> {
+    types
+        .get(type_ref as usize)
+        .ok_or_else(|| format!("type {} does not exist", type_ref))
+}
+
+/// Returns the name of a floating-point type, or None for any other type.
+fn float_name(value_type: wasmparser::ValType) -> Option<&'static str> {
+    match value_type {
+        wasmparser::ValType::F32 => Some("f32"),
+        wasmparser::ValType::F64 => Some("f64"),
+        _ => None,
+    }
+}
+
+fn has_float_func_type(sig: &wasmparser::FuncType) -> bool {
+    sig.params()
+        .iter()
+        .chain(sig.results())
+        .any(|value_type| float_name(*value_type).is_some())
+}
+
+fn has_float_func_body(
+    body: &wasmparser::FunctionBody,
+) -> Result<bool, wasmparser::BinaryReaderError> {
+    for local in body.get_locals_reader()? {
+        if float_name(local?.1).is_some() {
+            return Ok(true);
+        }
+    }
+    let mut operators = body.get_operators_reader()?;
+    while !operators.eof() {
+        if is_float_operator(&operators.read()?) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Returns true if the operator operates on, produces or yields a floating-point value, as
+/// `is_float_instruction` does for parity-wasm instructions.
+fn is_float_operator(operator: &wasmparser::Operator) -> bool {
+    use wasmparser::BlockType;
+    use wasmparser::Operator::*;
+
+    match operator {
+        Block {
+            blockty: BlockType::Type(value_type),
+        }
+        | Loop {
+            blockty: BlockType::Type(value_type),
+        }
+        | If {
+            blockty: BlockType::Type(value_type),
+        } => float_name(*value_type).is_some(),
+        F32Load { .. }
+        | F64Load { .. }
+        | F32Store { .. }
+        | F64Store { .. }
+        | F32Const { .. }
+        | F64Const { .. } => true,
+        F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt | F64Le
+        | F64Ge => true,
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt | F32Add
+        | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign => true,
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt | F64Add
+        | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign => true,
+        I32TruncF32S | I32TruncF32U | I32TruncF64S | I32TruncF64U | I64TruncF32S | I64TruncF32U
+        | I64TruncF64S | I64TruncF64U => true,
+        I32TruncSatF32S | I32TruncSatF32U | I32TruncSatF64S | I32TruncSatF64U | I64TruncSatF32S
+        | I64TruncSatF32U | I64TruncSatF64S | I64TruncSatF64U => true,
+        F32ConvertI32S | F32ConvertI32U | F32ConvertI64S | F32ConvertI64U | F32DemoteF64
+        | F64ConvertI32S | F64ConvertI32U | F64ConvertI64S | F64ConvertI64U | F64PromoteF32 => true,
+        I32ReinterpretF32 | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 => true,
+        _ => false,
+    }
+}
+
+fn float_function_reason(index: usize) -> String {
+    format!(
+        "function {} uses floating-point types or instructions",
+        index
+    )
+}
+
+/// Returns the types of every global in the index space, imported ones first.
+fn global_types(module: &Module) -> Vec<ValueType> {
+    let imported = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Global(global_type) => Some(global_type.content_type()),
+            _ => None,
+        });
+    let defined = module
+        .global_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|entry| entry.global_type().content_type());
+    imported.chain(defined).collect()
+}
+
+fn is_float(value_type: ValueType) -> bool {
+    matches!(value_type, ValueType::F32 | ValueType::F64)
+}
+
+fn has_float_signature(sig: &FunctionType) -> bool {
+    sig.params().iter().cloned().any(is_float) || sig.return_type().is_some_and(is_float)
+}
+
+fn has_float_body(body: &FuncBody) -> bool {
+    body.locals()
+        .iter()
+        .any(|local| is_float(local.value_type()))
+        || body.code().elements().iter().any(is_float_instruction)
+}
+
+/// Returns true if the instruction operates on, produces or yields a floating-point value.
+fn is_float_instruction(instruction: &Instruction) -> bool {
+    use parity_wasm::elements::Instruction::*;
+
+    match instruction {
+        Block(BlockType::Value(value_type))
+        | Loop(BlockType::Value(value_type))
+        | If(BlockType::Value(value_type)) => is_float(*value_type),
+        F32Load(_, _)
+        | F64Load(_, _)
+        | F32Store(_, _)
+        | F64Store(_, _)
+        | F32Const(_)
+        | F64Const(_) => true,
+        F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt | F64Le
+        | F64Ge => true,
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt | F32Add
+        | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign => true,
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt | F64Add
+        | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign => true,
+        I32TruncSF32 | I32TruncUF32 | I32TruncSF64 | I32TruncUF64 | I64TruncSF32 | I64TruncUF32
+        | I64TruncSF64 | I64TruncUF64 => true,
+        F32ConvertSI32 | F32ConvertUI32 | F32ConvertSI64 | F32ConvertUI64 | F32DemoteF64
+        | F64ConvertSI32 | F64ConvertUI32 | F64ConvertSI64 | F64ConvertUI64 | F64PromoteF32 => true,
+        I32ReinterpretF32 | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use parity_wasm::builder;
+    use parity_wasm::elements::{deserialize_buffer, Instructions, Local};
+    use ModuleCreator;
+
+    #[test]
+    fn no_float_good() {
+        let module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .with_locals(vec![Local::new(1, ValueType::I32)])
+            .with_instructions(Instructions::new(vec![
+                Instruction::I32Const(1),
+                Instruction::I32Const(2),
+                Instruction::I32Add,
+                Instruction::Drop,
+                Instruction::End,
+            ]))
+            .build()
+            .build()
+            .build();
+
+        let checker = CheckFloat::new();
+        let result = checker.validate(&module).unwrap();
+        assert!(result.passed());
+    }
+
+    #[test]
+    fn float_instruction_bad() {
+        // wast:
+        // (module
+        //   (import "ethereum" "finish" (func $finish (param i32 i32)))
+        //   (func $main (drop (i32.trunc_s/f32 (f32.const 1.5))))
+        //   (export "main" (func $main))
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x09, 0x02, 0x60, 0x02, 0x7f,
+            0x7f, 0x00, 0x60, 0x00, 0x00, 0x02, 0x13, 0x01, 0x08, 0x65, 0x74, 0x68, 0x65, 0x72,
+            0x65, 0x75, 0x6d, 0x06, 0x66, 0x69, 0x6e, 0x69, 0x73, 0x68, 0x00, 0x00, 0x03, 0x02,
+            0x01, 0x01, 0x07, 0x08, 0x01, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x01, 0x0a, 0x0b,
+            0x01, 0x09, 0x00, 0x43, 0x00, 0x00, 0xc0, 0x3f, 0xa8, 0x1a, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let checker = CheckFloat::new();
+        let result = checker.validate(&module).unwrap();
+        assert_eq!(
+            result.reasons(),
+            ["function 1 uses floating-point types or instructions"]
+        );
+    }
+
+    #[test]
+    fn float_types_bad() {
+        let module = builder::module()
+            .function()
+            .signature()
+            .param()
+            .f64()
+            .build()
+            .body()
+            .build()
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .with_locals(vec![Local::new(1, ValueType::F32)])
+            .build()
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .global()
+            .with_type(ValueType::I64)
+            .init_expr(Instruction::I64Const(0))
+            .build()
+            .global()
+            .with_type(ValueType::F64)
+            .init_expr(Instruction::F64Const(0))
+            .build()
+            .build();
+
+        let checker = CheckFloat::new();
+        let result = checker.validate(&module).unwrap();
+        assert_eq!(
+            result.reasons(),
+            [
+                "global 1 has floating-point type f64",
+                "function 0 uses floating-point types or instructions",
+                "function 1 uses floating-point types or instructions",
+            ]
+        );
+    }
+
+    #[test]
+    fn binary_agrees_with_module() {
+        let sources = [
+            r#"(module (func (drop (i32.add (i32.const 1) (i32.const 2)))))"#,
+            r#"(module
+                (import "env" "sqrt" (func (param f64) (result f64)))
+                (import "env" "scale" (global f32))
+                (global i64 (i64.const 0))
+                (global f64 (f64.const 0))
+                (func (local f32))
+                (func (drop (i32.trunc_f32_s (f32.const 1.5))))
+                (func (result i32) (block (result f32) (f32.const 0)) (drop) (i32.const 0))
+                (func))"#,
+        ];
+        for source in sources.iter() {
+            let module = FromWat::new(source).create().unwrap();
+            let binary = parity_wasm::serialize(module.clone()).unwrap();
+            let checker = CheckFloat::new();
+            assert_eq!(
+                checker.validate_binary(&binary),
+                Some(checker.validate(&module))
+            );
+        }
+
+        let reasons = CheckFloat::new()
+            .validate_binary(
+                &parity_wasm::serialize(FromWat::new(sources[1]).create().unwrap()).unwrap(),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            reasons.reasons(),
+            [
+                "global 0 has floating-point type f32",
+                "global 2 has floating-point type f64",
+                "function 0 uses floating-point types or instructions",
+                "function 1 uses floating-point types or instructions",
+                "function 2 uses floating-point types or instructions",
+                "function 3 uses floating-point types or instructions",
+            ]
+        );
+    }
+}
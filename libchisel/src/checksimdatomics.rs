@@ -0,0 +1,229 @@
+use super::presets::Feature;
+use super::verifymvp::{function_bodies, instruction_feature};
+use super::{ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{BlockType, External, Instruction, Module, Type, ValueType};
+
+/// Struct on which ModuleValidator is implemented.
+/// Checks that the module uses neither SIMD, including the `v128` type, nor atomic instructions,
+/// which are not supported or not deterministic on ewasm hosts.
+#[derive(Default)]
+pub struct CheckSimdAtomics {}
+
+impl CheckSimdAtomics {
+    pub fn new() -> Self {
+        CheckSimdAtomics {}
+    }
+}
+
+impl ModuleValidator for CheckSimdAtomics {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let types = module
+            .type_section()
+            .map_or(&[][..], |section| section.types());
+        let has_v128_signature = |type_ref: u32| match types.get(type_ref as usize) {
+            Some(Type::Function(sig)) => Ok(sig.params().contains(&ValueType::V128)
+                || sig.return_type() == Some(ValueType::V128)),
+            None => Err(format!("type {} does not exist", type_ref)),
+        };
+
+        let mut simd_functions = Vec::new();
+        let mut atomic_functions = Vec::new();
+        let mut index = 0;
+        if let Some(section) = module.import_section() {
+            for entry in section.entries() {
+                if let External::Function(type_ref) = entry.external() {
+                    if has_v128_signature(*type_ref)? {
+                        simd_functions.push(index);
+                    }
+                    index += 1;
+                }
+            }
+        }
+
+        let functions = module
+            .function_section()
+            .map_or(&[][..], |section| section.entries());
+        let bodies = module
+            .code_section()
+            .map_or(&[][..], |section| section.bodies());
+        if functions.len() != bodies.len() {
+            return Err("function and code section have inconsistent lengths".to_string());
+        }
+        for ((index, instructions), (function, body)) in function_bodies(module)
+            .into_iter()
+            .zip(functions.iter().zip(bodies))
+        {
+            let mut simd = has_v128_signature(function.type_ref())?
+                || body
+                    .locals()
+                    .iter()
+                    .any(|local| local.value_type() == ValueType::V128);
+            let mut atomic = false;
+            for instruction in instructions {
+                match instruction_feature(instruction)? {
+                    Some(Feature::Simd) => simd = true,
+                    Some(Feature::Threads) => atomic = true,
+                    _ => simd |= yields_v128(instruction),
+                }
+            }
+            if simd {
+                simd_functions.push(index);
+            }
+            if atomic {
+                atomic_functions.push(index);
+            }
+        }
+
+        let mut reasons = Vec::new();
+        if !simd_functions.is_empty() {
+            reasons.push(format!(
+                "SIMD used by {}",
+                list("function", &simd_functions)
+            ));
+        }
+        let simd_globals: Vec<usize> = global_types(module)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, value_type)| *value_type == ValueType::V128)
+            .map(|(index, _)| index)
+            .collect();
+        if !simd_globals.is_empty() {
+            reasons.push(format!("SIMD used by {}", list("global", &simd_globals)));
+        }
+        if !atomic_functions.is_empty() {
+            reasons.push(format!(
+                "atomics used by {}",
+                list("function", &atomic_functions)
+            ));
+        }
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        vec!["rejects SIMD instructions, the `v128` type and atomic instructions".to_string()]
+    }
+}
+
+/// Returns true if the instruction opens a block which yields a `v128`.
+fn yields_v128(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::Block(BlockType::Value(value_type))
+        | Instruction::Loop(BlockType::Value(value_type))
+        | Instruction::If(BlockType::Value(value_type)) => *value_type == ValueType::V128,
+        _ => false,
+    }
+}
+
+/// Returns the types of every global in the index space, imported ones first.
+fn global_types(module: &Module) -> Vec<ValueType> {
+    let imported = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Global(global_type) => Some(global_type.content_type()),
+            _ => None,
+        });
+    let defined = module
+        .global_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|entry| entry.global_type().content_type());
+    imported.chain(defined).collect()
+}
+
+/// Formats indices as e.g. `functions 1, 3`, or `function 1` if there is only one.
+fn list(kind: &str, indices: &[usize]) -> String {
+    let indices: Vec<String> = indices.iter().map(|index| index.to_string()).collect();
+    let plural = if indices.len() == 1 { "" } else { "s" };
+    format!("{}{} {}", kind, plural, indices.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use parity_wasm::elements::{Instructions, MemArg};
+
+    fn module_with_code(functions: Vec<Vec<Instruction>>) -> Module {
+        functions
+            .into_iter()
+            .fold(builder::module(), |module, code| {
+                module
+                    .function()
+                    .signature()
+                    .build()
+                    .body()
+                    .with_instructions(Instructions::new(code))
+                    .build()
+                    .build()
+            })
+            .build()
+    }
+
+    #[test]
+    fn no_simd_atomics_good() {
+        let module = module_with_code(vec![vec![Instruction::Nop, Instruction::End]]);
+        assert!(CheckSimdAtomics::new().validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn simd_and_atomics_bad() {
+        let module = module_with_code(vec![
+            vec![
+                Instruction::I32Const(0),
+                Instruction::V128Load(MemArg {
+                    align: 4,
+                    offset: 0,
+                }),
+                Instruction::Drop,
+                Instruction::End,
+            ],
+            vec![
+                Instruction::I32Const(0),
+                Instruction::I32AtomicLoad(MemArg {
+                    align: 2,
+                    offset: 0,
+                }),
+                Instruction::Drop,
+                Instruction::End,
+            ],
+        ]);
+
+        let result = CheckSimdAtomics::new().validate(&module).unwrap();
+        assert_eq!(
+            result.reasons(),
+            ["SIMD used by function 0", "atomics used by function 1"]
+        );
+    }
+
+    #[test]
+    fn v128_types_bad() {
+        let module = builder::module()
+            .import()
+            .module("env")
+            .field("splat")
+            .external()
+            .func(0)
+            .build()
+            .function()
+            .signature()
+            .with_return_type(Some(ValueType::V128))
+            .build()
+            .body()
+            .build()
+            .build()
+            .global()
+            .with_type(ValueType::V128)
+            .init_expr(Instruction::V128Const(Box::new([0; 16])))
+            .build()
+            .build();
+
+        let result = CheckSimdAtomics::new().validate(&module).unwrap();
+        assert_eq!(
+            result.reasons(),
+            ["SIMD used by functions 0, 1", "SIMD used by global 0"]
+        );
+    }
+}
@@ -1,7 +1,12 @@
-use super::ModuleValidator;
-use parity_wasm::elements::Module;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::{format_signature, ModuleValidator, ValidationResult};
+use parity_wasm::elements::{External, Module, Type};
 
 /// Struct on which ModuleValidator is implemented.
+/// Checks for the presence or absence of a start function. A start function which is present
+/// must also exist and take and return nothing, as malformed modules may otherwise slip through.
 pub struct CheckStartFunc {
     start_required: bool,
 }
@@ -15,15 +20,107 @@ impl CheckStartFunc {
 }
 
 impl ModuleValidator for CheckStartFunc {
-    fn validate(self, module: &Module) -> Result<bool, String> {
-        Ok(module.start_section().is_some() == self.start_required)
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let mut reasons = match (module.start_section().is_some(), self.start_required) {
+            (false, true) => vec!["missing start function".to_string()],
+            (true, false) => vec!["unexpected start function".to_string()],
+            _ => vec![],
+        };
+        if let Some(index) = module.start_section() {
+            reasons.extend(check_signature(module, index));
+        }
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn interface_only(&self) -> bool {
+        true
+    }
+
+    fn rules(&self) -> Vec<String> {
+        if self.start_required {
+            vec![
+                "requires a start function".to_string(),
+                "requires the start function to have type () -> ()".to_string(),
+            ]
+        } else {
+            vec!["rejects a start function".to_string()]
+        }
+    }
+}
+
+/// Describes why the start function cannot be called, if it does not exist or takes or returns
+/// anything.
+fn check_signature(module: &Module, index: u32) -> Option<String> {
+    let imported = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Function(type_ref) => Some(*type_ref),
+            _ => None,
+        });
+    let defined = module
+        .function_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|func| func.type_ref());
+    let type_ref = match imported.chain(defined).nth(index as usize) {
+        Some(type_ref) => type_ref,
+        None => return Some(format!("start function {} does not exist", index)),
+    };
+    let types = module
+        .type_section()
+        .map_or(&[][..], |section| section.types());
+    match types.get(type_ref as usize) {
+        Some(Type::Function(sig)) if sig.params().is_empty() && sig.return_type().is_none() => None,
+        Some(Type::Function(sig)) => Some(format!(
+            "start function {} has type {} (type {}), expected () -> ()",
+            index,
+            format_signature(sig),
+            type_ref
+        )),
+        None => Some(format!(
+            "start function {} has nonexistent type {}",
+            index, type_ref
+        )),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use fromwat::FromWat;
     use parity_wasm::elements::deserialize_buffer;
+    use ModuleCreator;
+
+    #[test]
+    fn start_signature_checked() {
+        // An imported function taking a parameter, and a defined one returning a value.
+        let mut module = FromWat::new(
+            r#"(module
+                (import "env" "init" (func (param i32)))
+                (func (result i32) (i32.const 0))
+                (start 1))"#,
+        )
+        .create()
+        .unwrap();
+        let checker = CheckStartFunc::new(true);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["start function 1 has type () -> i32 (type 1), expected () -> ()"]
+        );
+
+        module.set_start_section(0);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["start function 0 has type (i32) -> () (type 0), expected () -> ()"]
+        );
+        module.set_start_section(5);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["start function 5 does not exist"]
+        );
+    }
 
     #[test]
     fn start_required_good() {
@@ -36,8 +133,8 @@ mod tests {
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
         let checker = CheckStartFunc::new(true);
 
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -51,8 +148,8 @@ mod tests {
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
         let checker = CheckStartFunc::new(false);
 
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -66,8 +163,8 @@ mod tests {
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
         let checker = CheckStartFunc::new(false);
 
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -81,7 +178,7 @@ mod tests {
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
         let checker = CheckStartFunc::new(true);
 
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 }
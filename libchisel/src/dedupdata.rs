@@ -0,0 +1,440 @@
+use std::collections::BTreeMap;
+
+use super::presets::Feature;
+use super::verifymvp::{function_bodies, instruction_feature};
+use super::{translate_copy, ModuleTranslator};
+use parity_wasm::elements::{DataSegment, Instruction, Module};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Shrinks the data section by deduplicating its segments. A segment which writes bytes that an
+/// earlier segment already wrote to the same addresses, such as a string literal duplicated when
+/// linking, is removed. A segment whose bytes an earlier segment holds at another address is
+/// merged into it: it is removed, and the references to it are rewritten to the earlier one.
+///
+/// The references are the constant addresses in the code and in the initializers of globals.
+/// Segments are only merged if their start is the only address within them which the module
+/// uses, as for a string literal, and no other segment writes over either of them. The merged
+/// segments then share their memory, so their contents are assumed never to be written to.
+#[derive(Default)]
+pub struct DedupData;
+
+impl DedupData {
+    pub fn new() -> Self {
+        DedupData
+    }
+
+    /// Removes the redundant segments and merges the identical ones. Returns the number of bytes
+    /// saved in the binary.
+    pub fn dedup(&self, module: &mut Module) -> Result<usize, String> {
+        if module.data_section().is_none() {
+            return Ok(0);
+        }
+        let before = module_size(module)?;
+        if !self.translate_inplace(module)? {
+            return Ok(0);
+        }
+        Ok(before.saturating_sub(module_size(module)?))
+    }
+}
+
+impl ModuleTranslator for DedupData {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        let segments = match module.data_section_mut() {
+            Some(section) => section.entries_mut(),
+            None => return Ok(false),
+        };
+        // Passive segments are referred to by index, which removing any segment would shift.
+        if segments.iter().any(|segment| segment.passive()) {
+            return Ok(false);
+        }
+        let removed = remove_redundant(segments);
+        Ok(merge_identical(module)? || removed)
+    }
+}
+
+fn module_size(module: &Module) -> Result<usize, String> {
+    parity_wasm::serialize(module.clone())
+        .map(|buffer| buffer.len())
+        .map_err(|err| format!("Failed to serialize the module: {}", err))
+}
+
+/// Removes the segments which only write bytes already in memory. Returns whether any was.
+fn remove_redundant(segments: &mut Vec<DataSegment>) -> bool {
+    let count = segments.len();
+    let mut kept: Vec<DataSegment> = Vec::with_capacity(count);
+    for segment in segments.drain(..) {
+        if !is_redundant(&segment, &kept) {
+            kept.push(segment);
+        }
+    }
+    *segments = kept;
+    segments.len() < count
+}
+
+/// Returns the address range the segment writes to, if its offset is a constant.
+fn range(segment: &DataSegment) -> Option<(u64, u64)> {
+    match segment.offset().as_ref()?.code() {
+        [Instruction::I32Const(offset), Instruction::End] => {
+            let start = *offset as u32 as u64;
+            Some((start, start + segment.value().len() as u64))
+        }
+        _ => None,
+    }
+}
+
+/// Whether an earlier segment, which no later one overwrote in the meantime, already wrote the
+/// bytes of the segment at the same addresses. Those earlier segments were bounds checked when
+/// instantiating, so the segment would not have trapped either.
+fn is_redundant(segment: &DataSegment, earlier: &[DataSegment]) -> bool {
+    let (start, end) = match range(segment) {
+        Some(range) => range,
+        None => return false,
+    };
+    for previous in earlier.iter().rev() {
+        if previous.index() != segment.index() {
+            continue;
+        }
+        let (previous_start, previous_end) = match range(previous) {
+            Some(range) => range,
+            // The segment may have written anywhere.
+            None => return false,
+        };
+        if previous_end <= start || end <= previous_start {
+            continue;
+        }
+        // The latest segment overlapping this one determines the memory content.
+        if previous_start <= start && end <= previous_end {
+            let from = (start - previous_start) as usize;
+            return previous.value()[from..from + segment.value().len()] == *segment.value();
+        }
+        return false;
+    }
+    false
+}
+
+/// The constant addresses the module uses: the `i32.const` values in the code and in the
+/// initializers of globals, and the offsets of the memory accesses in the code.
+struct References {
+    constants: Vec<u32>,
+    offsets: Vec<u32>,
+}
+
+/// Collects the constant addresses of the module, or returns None if it uses SIMD or atomic
+/// instructions, whose memory accesses are not inspected.
+fn references(module: &Module) -> Result<Option<References>, String> {
+    let mut references = References {
+        constants: Vec::new(),
+        offsets: Vec::new(),
+    };
+    for (_, instructions) in function_bodies(module) {
+        for instruction in instructions {
+            match instruction_feature(instruction)? {
+                Some(Feature::Simd) | Some(Feature::Threads) => return Ok(None),
+                _ => (),
+            }
+            if let Instruction::I32Const(value) = *instruction {
+                references.constants.push(value as u32);
+            }
+            if let Some(offset) = access_offset(instruction) {
+                references.offsets.push(offset);
+            }
+        }
+    }
+    for entry in module
+        .global_section()
+        .map_or(&[][..], |section| section.entries())
+    {
+        for instruction in entry.init_expr().code() {
+            if let Instruction::I32Const(value) = *instruction {
+                references.constants.push(value as u32);
+            }
+        }
+    }
+    Ok(Some(references))
+}
+
+/// Returns the offset of the memory access, which is the address accessed when its base is 0.
+fn access_offset(instruction: &Instruction) -> Option<u32> {
+    use parity_wasm::elements::Instruction::*;
+
+    match *instruction {
+        I32Load(_, offset)
+        | I64Load(_, offset)
+        | F32Load(_, offset)
+        | F64Load(_, offset)
+        | I32Load8S(_, offset)
+        | I32Load8U(_, offset)
+        | I32Load16S(_, offset)
+        | I32Load16U(_, offset)
+        | I64Load8S(_, offset)
+        | I64Load8U(_, offset)
+        | I64Load16S(_, offset)
+        | I64Load16U(_, offset)
+        | I64Load32S(_, offset)
+        | I64Load32U(_, offset)
+        | I32Store(_, offset)
+        | I64Store(_, offset)
+        | F32Store(_, offset)
+        | F64Store(_, offset)
+        | I32Store8(_, offset)
+        | I32Store16(_, offset)
+        | I64Store8(_, offset)
+        | I64Store16(_, offset)
+        | I64Store32(_, offset) => Some(offset),
+        _ => None,
+    }
+}
+
+/// Merges each segment into an earlier one holding the same bytes at another address, where the
+/// module only refers to it by its start. Returns whether any segment was merged.
+fn merge_identical(module: &mut Module) -> Result<bool, String> {
+    let segments = module
+        .data_section()
+        .map_or(&[][..], |section| section.entries());
+    // A segment placed at a computed offset may have written over any other.
+    let ranges = match segments.iter().map(range).collect::<Option<Vec<_>>>() {
+        Some(ranges) => ranges,
+        None => return Ok(false),
+    };
+    let references = match references(module)? {
+        Some(references) => references,
+        None => return Ok(false),
+    };
+    let isolated = |index: usize| {
+        let (start, end) = ranges[index];
+        segments.iter().zip(ranges.iter()).enumerate().all(
+            |(other, (segment, &(other_start, other_end)))| {
+                other == index
+                    || segment.index() != segments[index].index()
+                    || other_end <= start
+                    || end <= other_start
+            },
+        )
+    };
+
+    // The start of every merged segment, mapped to the start of the segment it was merged into.
+    let mut targets = BTreeMap::new();
+    let mut merged = vec![false; segments.len()];
+    for (index, segment) in segments.iter().enumerate() {
+        let (start, end) = ranges[index];
+        if segment.value().is_empty()
+            || !isolated(index)
+            || references
+                .constants
+                .iter()
+                .any(|&address| start < address as u64 && (address as u64) < end)
+            || references
+                .offsets
+                .iter()
+                .any(|&address| start <= address as u64 && (address as u64) < end)
+        {
+            continue;
+        }
+        let target = match (0..index).find(|&earlier| {
+            !merged[earlier]
+                && segments[earlier].index() == segment.index()
+                && segments[earlier].value() == segment.value()
+                && isolated(earlier)
+        }) {
+            Some(target) => target,
+            None => continue,
+        };
+        // Rewriting a reference may take more bytes than the merged segment saves.
+        let (start, target_start) = (start as u32, ranges[target].0 as u32);
+        let count = references
+            .constants
+            .iter()
+            .filter(|&&address| address == start)
+            .count();
+        let growth = count
+            * signed_leb_size(target_start as i32).saturating_sub(signed_leb_size(start as i32));
+        let size = parity_wasm::serialize(segment.clone())
+            .map_err(|_| "Failed to serialize a data segment".to_string())?
+            .len();
+        if growth < size {
+            merged[index] = true;
+            targets.insert(start, target_start);
+        }
+    }
+    if targets.is_empty() {
+        return Ok(false);
+    }
+
+    if let Some(section) = module.code_section_mut() {
+        for body in section.bodies_mut() {
+            rewrite_references(body.code_mut().elements_mut(), &targets);
+        }
+    }
+    if let Some(section) = module.global_section_mut() {
+        for entry in section.entries_mut() {
+            rewrite_references(entry.init_expr_mut().code_mut(), &targets);
+        }
+    }
+    if let Some(section) = module.data_section_mut() {
+        let mut merged = merged.into_iter();
+        section
+            .entries_mut()
+            .retain(|_| !merged.next().unwrap_or(false));
+    }
+    Ok(true)
+}
+
+/// Replaces the constant addresses of merged segments with those they were merged into.
+fn rewrite_references(instructions: &mut [Instruction], targets: &BTreeMap<u32, u32>) {
+    for instruction in instructions.iter_mut() {
+        if let Instruction::I32Const(ref mut value) = *instruction {
+            if let Some(&target) = targets.get(&(*value as u32)) {
+                *value = target as i32;
+            }
+        }
+    }
+}
+
+/// Returns the number of bytes of the value as a signed LEB128 integer.
+fn signed_leb_size(value: i32) -> usize {
+    let mut value = value as i64;
+    let mut size = 1;
+    while !(-64..64).contains(&value) {
+        value >>= 7;
+        size += 1;
+    }
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    fn data(module: &Module) -> Vec<&[u8]> {
+        module
+            .data_section()
+            .map_or(&[][..], |section| section.entries())
+            .iter()
+            .map(|segment| segment.value())
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_literals_merged() {
+        // The same string literal, from two object files linked at the same address, and a
+        // suffix of a literal written again.
+        let mut module = FromWat::new(
+            r#"(module
+                (memory 1)
+                (data (i32.const 16) "hello world")
+                (data (i32.const 16) "hello world")
+                (data (i32.const 22) "world"))"#,
+        )
+        .create()
+        .unwrap();
+        let chisel = DedupData::new();
+        assert_eq!(chisel.dedup(&mut module).unwrap(), 26);
+        assert_eq!(data(&module), [&b"hello world"[..]]);
+        assert!(!chisel.translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn identical_literals_merged() {
+        // The same string literal, compiled into two segments at different addresses.
+        let mut module = FromWat::new(
+            r#"(module
+                (import "env" "print" (func $print (param i32 i32)))
+                (memory 1)
+                (data (i32.const 16) "hello world")
+                (data (i32.const 32) "hello world")
+                (global i32 (i32.const 32))
+                (func (export "main")
+                    (call $print (i32.const 16) (i32.const 11))
+                    (call $print (i32.const 32) (i32.const 11))))"#,
+        )
+        .create()
+        .unwrap();
+        let chisel = DedupData::new();
+        assert_eq!(chisel.dedup(&mut module).unwrap(), 16);
+        assert_eq!(data(&module), [&b"hello world"[..]]);
+        let constants: Vec<&Instruction> = module.code_section().unwrap().bodies()[0]
+            .code()
+            .elements()
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::I32Const(_)))
+            .collect();
+        assert_eq!(
+            constants,
+            [
+                &Instruction::I32Const(16),
+                &Instruction::I32Const(11),
+                &Instruction::I32Const(16),
+                &Instruction::I32Const(11)
+            ]
+        );
+        assert_eq!(
+            module.global_section().unwrap().entries()[0]
+                .init_expr()
+                .code()[0],
+            Instruction::I32Const(16)
+        );
+        assert!(!chisel.translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn identical_literals_kept() {
+        // The second literal is referred to within, accessed at a constant offset, or written
+        // over by another segment.
+        for (data, code) in [
+            ("", "(drop (i32.const 38))"),
+            ("", "(drop (i32.load offset=32 (i32.const 0)))"),
+            (r#"(data (i32.const 40) "!!")"#, ""),
+        ]
+        .iter()
+        {
+            let source = format!(
+                r#"(module
+                    (memory 1)
+                    (data (i32.const 16) "hello world")
+                    (data (i32.const 32) "hello world")
+                    {}
+                    (func (export "main") (drop (i32.const 32)) {}))"#,
+                data, code
+            );
+            let mut module = FromWat::new(&source).create().unwrap();
+            assert!(!DedupData::new().translate_inplace(&mut module).unwrap());
+        }
+    }
+
+    #[test]
+    fn distinct_content_kept() {
+        let source = r#"(module
+            (global $base (import "env" "base") i32)
+            (memory 1)
+            (data (i32.const 0) "hello")
+            (data (i32.const 8) "hello")
+            (data (i32.const 2) "LL")
+            (data (i32.const 2) "ll")
+            (data (i32.const 8) "hello")
+            (data (global.get $base) "hello")
+            (data (i32.const 0) "hello"))"#;
+        let mut module = FromWat::new(source).create().unwrap();
+        // Only the second "hello" at 8 goes, as the others are at another address, or follow a
+        // segment which wrote other bytes or may have written anywhere. None is merged, as the
+        // segment at a computed offset may have written over any of them.
+        assert_eq!(DedupData::new().dedup(&mut module).unwrap(), 10);
+        assert_eq!(data(&module).len(), 6);
+
+        let mut module = FromWat::new(
+            r#"(module
+                (memory 1)
+                (data (i32.const 0) "hello")
+                (data (i32.const 0) "hello")
+                (data "hello"))"#,
+        )
+        .create()
+        .unwrap();
+        assert!(!DedupData::new().translate_inplace(&mut module).unwrap());
+    }
+}
@@ -5,18 +5,69 @@ use super::*;
 use byteorder::{LittleEndian, WriteBytesExt};
 use rustc_hex::FromHex;
 
-/// Enum on which ModuleCreator is implemented.
+/// A deployer of a payload, of one of the kinds of `DeployerKind`. ModuleCreator is implemented
+/// on it, and `DeployerTranslator` builds one from the module it translates.
 pub enum Deployer<'a> {
     Memory(&'a [u8]),
     CustomSection(&'a [u8]),
 }
 
+/// Ways in which a deployer holds its payload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeployerKind {
+    /// The payload is written to memory by a data segment.
+    Memory,
+    /// The payload is appended to the deployer as a custom section, which it reads from its own
+    /// code.
+    CustomSection,
+}
+
+/// Struct on which ModuleTranslator is implemented. Replaces the module with a deployer whose
+/// payload is the module as it stands.
+///
+/// Wrapping always produces a different module, so the translator cannot be idempotent in the
+/// usual sense. Instead, a module which is already a deployer of either kind is left unchanged,
+/// so that running a pipeline again does not wrap it twice.
+pub struct DeployerTranslator {
+    kind: DeployerKind,
+}
+
 impl<'a> Deployer<'a> {
-    pub fn with_preset(preset: &str, payload: &'a [u8]) -> Result<Self, ()> {
+    /// Constructs a deployer of the payload, of the given kind.
+    pub fn new(kind: DeployerKind, payload: &'a [u8]) -> Self {
+        match kind {
+            DeployerKind::Memory => Deployer::Memory(payload),
+            DeployerKind::CustomSection => Deployer::CustomSection(payload),
+        }
+    }
+}
+
+impl DeployerKind {
+    /// The name of the kind as written in configuration files.
+    pub fn name(self) -> &'static str {
+        match self {
+            DeployerKind::Memory => "memory",
+            DeployerKind::CustomSection => "customsection",
+        }
+    }
+}
+
+impl DeployerTranslator {
+    /// Constructs a translator deploying with a deployer of the given kind.
+    pub fn new(kind: DeployerKind) -> Self {
+        DeployerTranslator { kind }
+    }
+}
+
+impl ModulePreset for DeployerTranslator {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        // Ewasm deploys the payload from memory, the other kind is only chosen explicitly.
         match preset {
-            "memory" => Ok(Deployer::Memory(payload)),
-            "customsection" => Ok(Deployer::CustomSection(payload)),
-            _ => Err(()),
+            Preset::Ewasm | Preset::EwasmDebug => Ok(DeployerTranslator::new(DeployerKind::Memory)),
+            Preset::PWasm => Err(format!(
+                "Preset {} is not supported, as the deployer returns its payload through the ethereum interface",
+                preset
+            )),
         }
     }
 }
@@ -60,23 +111,29 @@ fn deployer_code() -> Vec<u8> {
         4100410020001001200041046b2802002102200041046b20026b21012001200210
         020b
     ",
-    ).unwrap()
+    )
+    .unwrap()
 }
 
-/// Returns a module which contains the deployable bytecode as a custom section.
-fn create_custom_deployer(payload: &[u8]) -> Module {
+/// Returns a module which contains the deployable bytecode as a custom section, or the reason it
+/// could not be created.
+fn create_custom_deployer(payload: &[u8]) -> Result<Module, String> {
     // The standard deployer code, which expects a 32 bit little endian as the trailing content
     // immediately following the payload, placed in a custom section.
     let code = deployer_code();
 
     // This is the pre-written deployer code.
-    let mut module: Module = parity_wasm::deserialize_buffer(&code).expect("Failed to load module");
+    let mut module: Module = parity_wasm::deserialize_buffer(&code)
+        .map_err(|err| format!("Failed to load the deployer code: {}", err))?;
 
     // Prepare payload (append length).
+    if payload.len() > i32::MAX as usize {
+        return Err("The payload is too large for a 32 bit length".to_string());
+    }
     let mut custom_payload = payload.to_vec();
     custom_payload
         .write_i32::<LittleEndian>(payload.len() as i32)
-        .unwrap();
+        .map_err(|err| err.to_string())?;
 
     // Prepare and append custom section.
     let mut custom = CustomSection::default();
@@ -87,11 +144,11 @@ fn create_custom_deployer(payload: &[u8]) -> Module {
         .sections_mut()
         .push(parity_wasm::elements::Section::Custom(custom));
 
-    module
+    Ok(module)
 }
 
 /// Returns a module which contains the deployable bytecode as a data segment.
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 fn create_memory_deployer(payload: &[u8]) -> Module {
     // Instructions calling finish(0, payload_len)
     let instructions = vec![
@@ -101,14 +158,14 @@ fn create_memory_deployer(payload: &[u8]) -> Module {
         parity_wasm::elements::Instruction::End,
     ];
 
-    let module = builder::module()
-        // Create a func/type for the ethereum::finish
-        .function()
+    builder::module()
+        // Create a type for the ethereum::finish
+        .functions()
             .signature()
               .param().i32()
               .param().i32()
               .build()
-            .build()
+            .bind()
         .import()
             .module("ethereum")
             .field("finish")
@@ -143,33 +200,71 @@ fn create_memory_deployer(payload: &[u8]) -> Module {
             .offset(parity_wasm::elements::Instruction::I32Const(0))
             .value(payload.to_vec())
             .build()
-        .build();
+        .build()
+}
 
-    module
+/// Returns true if the module is exactly a deployer created by `Deployer`, of any payload.
+fn is_deployer(module: &Module) -> bool {
+    let custom_payload = module
+        .custom_sections()
+        .find(|custom| custom.name() == "deployer")
+        .map(|custom| custom.payload())
+        .filter(|payload| payload.len() >= 4);
+    if let Some(payload) = custom_payload {
+        if create_custom_deployer(&payload[..payload.len() - 4])
+            .is_ok_and(|deployer| deployer == *module)
+        {
+            return true;
+        }
+    }
+
+    match module.data_section().map(|section| section.entries()) {
+        Some([segment]) => create_memory_deployer(segment.value()) == *module,
+        _ => false,
+    }
 }
 
 impl<'a> ModuleCreator for Deployer<'a> {
-    fn create(self) -> Result<Module, String> {
-        let output = match self {
-            Deployer::Memory(payload) => create_memory_deployer(&payload),
-            Deployer::CustomSection(payload) => create_custom_deployer(&payload),
+    fn create(&self) -> Result<Module, ChiselError> {
+        let output = match *self {
+            Deployer::Memory(payload) => create_memory_deployer(payload),
+            Deployer::CustomSection(payload) => {
+                create_custom_deployer(payload).map_err(ChiselError::Deserialize)?
+            }
         };
 
         Ok(output)
     }
 }
 
+impl ModuleTranslator for DeployerTranslator {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        if is_deployer(module) {
+            return Ok(None);
+        }
+        // The deployer wraps the entire module, so the payload is the module as it stands.
+        let payload = parity_wasm::serialize(module.clone())
+            .map_err(|_| "Failed to serialize the payload".to_string())?;
+        Ok(Some(
+            Deployer::new(self.kind, &payload)
+                .create()
+                .map_err(|err| err.to_string())?,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use parity_wasm;
     use rustc_hex::FromHex;
+    use verifyexports::VerifyExports;
+    use verifyimports::VerifyImports;
 
     #[test]
     fn zero_payload() {
         let payload = vec![];
-        let module = Deployer::with_preset("customsection", &payload)
-            .unwrap()
+        let module = Deployer::new(DeployerKind::CustomSection, &payload)
             .create()
             .unwrap();
         let expected = FromHex::from_hex(
@@ -183,7 +278,8 @@ mod tests {
 
             000d086465706c6f79657200000000
         ",
-        ).unwrap();
+        )
+        .unwrap();
         let output = parity_wasm::serialize(module).expect("Failed to serialize");
         assert_eq!(output, expected);
     }
@@ -191,8 +287,7 @@ mod tests {
     #[test]
     fn nonzero_payload() {
         let payload = FromHex::from_hex("80ff007faa550011").unwrap();
-        let module = Deployer::with_preset("customsection", &payload)
-            .unwrap()
+        let module = Deployer::new(DeployerKind::CustomSection, &payload)
             .create()
             .unwrap();
         let expected = FromHex::from_hex(
@@ -206,7 +301,8 @@ mod tests {
 
             0015086465706c6f79657280ff007faa55001108000000
         ",
-        ).unwrap();
+        )
+        .unwrap();
         let output = parity_wasm::serialize(module).expect("Failed to serialize");
         assert_eq!(output, expected);
     }
@@ -214,17 +310,17 @@ mod tests {
     #[test]
     fn memory_zero_payload() {
         let payload = vec![];
-        let module = Deployer::with_preset("memory", &payload)
-            .unwrap()
+        let module = Deployer::new(DeployerKind::Memory, &payload)
             .create()
             .unwrap();
         let expected = FromHex::from_hex(
             "
             0061736d0100000001090260027f7f0060000002130108657468657265756d0666
-            696e697368000003030200010503010001071102046d61696e0001066d656d6f72
-            7902000a0d0202000b08004100410010000b0b06010041000b00
+            696e6973680000030201010503010001071102046d61696e0001066d656d6f7279
+            02000a0a0108004100410010000b0b06010041000b00
         ",
-        ).unwrap();
+        )
+        .unwrap();
         let output = parity_wasm::serialize(module).expect("Failed to serialize");
         assert_eq!(output, expected);
     }
@@ -232,19 +328,90 @@ mod tests {
     #[test]
     fn memory_nonzero_payload() {
         let payload = FromHex::from_hex("80ff007faa550011").unwrap();
-        let module = Deployer::with_preset("memory", &payload)
-            .unwrap()
+        let module = Deployer::new(DeployerKind::Memory, &payload)
             .create()
             .unwrap();
         let expected = FromHex::from_hex(
             "
             0061736d0100000001090260027f7f0060000002130108657468657265756d0666
-            696e697368000003030200010503010001071102046d61696e0001066d656d6f72
-            7902000a0d0202000b08004100410810000b0b0e010041000b0880ff007faa5500
-            11
+            696e6973680000030201010503010001071102046d61696e0001066d656d6f7279
+            02000a0a0108004100410810000b0b0e010041000b0880ff007faa550011
         ",
-        ).unwrap();
+        )
+        .unwrap();
         let output = parity_wasm::serialize(module).expect("Failed to serialize");
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn translator_output_verifies() {
+        let runtime = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .build();
+
+        for kind in &[DeployerKind::Memory, DeployerKind::CustomSection] {
+            let translator = DeployerTranslator::new(*kind);
+            let module = translator.translate(&runtime).unwrap().unwrap();
+
+            let exports = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+            assert_eq!(
+                exports.validate(&module).unwrap().reasons(),
+                [] as [&str; 0]
+            );
+            let imports = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+            assert_eq!(
+                imports.validate(&module).unwrap().reasons(),
+                [] as [&str; 0]
+            );
+        }
+    }
+
+    #[test]
+    fn translator_does_not_wrap_twice() {
+        let runtime = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .build();
+
+        for kind in &[DeployerKind::Memory, DeployerKind::CustomSection] {
+            let translator = DeployerTranslator::new(*kind);
+            let module = translator.translate(&runtime).unwrap().unwrap();
+            // Deployers are recognised after a round trip through the binary format.
+            let module =
+                parity_wasm::deserialize_buffer::<Module>(&parity_wasm::serialize(module).unwrap())
+                    .unwrap();
+            assert_eq!(translator.translate(&module), Ok(None));
+            assert_eq!(
+                DeployerTranslator::new(DeployerKind::Memory).translate(&module),
+                Ok(None)
+            );
+        }
+    }
+
+    #[test]
+    fn translator_presets() {
+        assert_eq!(
+            DeployerTranslator::presets(),
+            [Preset::Ewasm, Preset::EwasmDebug]
+        );
+        assert_eq!(
+            DeployerTranslator::with_preset(Preset::EwasmDebug)
+                .unwrap()
+                .kind,
+            DeployerKind::Memory
+        );
+        assert!(DeployerTranslator::with_preset(Preset::PWasm)
+            .err()
+            .unwrap()
+            .contains("ethereum interface"));
+    }
 }
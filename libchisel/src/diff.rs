@@ -0,0 +1,331 @@
+//! Comparison of a module before and after a translator ran, at the level of its sections,
+//! imports and exports.
+
+use std::fmt;
+
+use parity_wasm::elements::{External, Internal, Module, Section};
+
+use super::{section_id, SECTION_NAMES};
+
+/// A change to an import or export, identified by its name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(String),
+    Removed(String),
+    /// An item of the same kind and type under a new name, from the old one to the new one.
+    Renamed(String, String),
+}
+
+/// A section which was added, removed or changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SectionChange {
+    /// The name of the section, as `type` or `custom:name` for a custom section.
+    pub name: String,
+    /// The serialized size of the section before, or None if it was added.
+    pub size_before: Option<usize>,
+    /// The serialized size of the section after, or None if it was removed.
+    pub size_after: Option<usize>,
+}
+
+/// Changes between two versions of a module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SectionDiff {
+    pub size_before: usize,
+    pub size_after: usize,
+    pub sections: Vec<SectionChange>,
+    pub imports: Vec<Change>,
+    pub exports: Vec<Change>,
+}
+
+impl SectionDiff {
+    /// Compares the module before and after it was changed.
+    pub fn between(before: &Module, after: &Module) -> Result<Self, String> {
+        let (sections_before, sections_after) =
+            (section_contents(before)?, section_contents(after)?);
+        Ok(SectionDiff {
+            size_before: module_size(before)?,
+            size_after: module_size(after)?,
+            sections: section_changes(&sections_before, &sections_after),
+            imports: changes(&imports(before), &imports(after)),
+            exports: changes(&exports(before), &exports(after)),
+        })
+    }
+
+    /// Returns true if the module was not changed in any way this diff records.
+    pub fn is_empty(&self) -> bool {
+        self.size_before == self.size_after
+            && self.sections.is_empty()
+            && self.imports.is_empty()
+            && self.exports.is_empty()
+    }
+}
+
+/// Renders one change per line, prefixed with `+` for additions, `-` for removals and `~` for
+/// renames and changed sections, followed by the change in size of the whole module.
+impl fmt::Display for SectionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for section in self.sections.iter() {
+            match (section.size_before, section.size_after) {
+                (Some(size), None) => writeln!(f, "- section {} ({} bytes)", section.name, size)?,
+                (None, Some(size)) => writeln!(f, "+ section {} ({} bytes)", section.name, size)?,
+                (Some(before), Some(after)) => writeln!(
+                    f,
+                    "~ section {} ({} -> {} bytes)",
+                    section.name, before, after
+                )?,
+                (None, None) => (),
+            }
+        }
+        for (kind, changes) in [("import", &self.imports), ("export", &self.exports)].iter() {
+            for change in changes.iter() {
+                match change {
+                    Change::Removed(name) => writeln!(f, "- {} `{}`", kind, name)?,
+                    Change::Added(name) => writeln!(f, "+ {} `{}`", kind, name)?,
+                    Change::Renamed(from, to) => writeln!(f, "~ {} `{}` -> `{}`", kind, from, to)?,
+                }
+            }
+        }
+        write!(
+            f,
+            "size {} -> {} bytes ({:+})",
+            self.size_before,
+            self.size_after,
+            self.size_after as i64 - self.size_before as i64
+        )
+    }
+}
+
+fn module_size(module: &Module) -> Result<usize, String> {
+    parity_wasm::serialize(module.clone())
+        .map(|buffer| buffer.len())
+        .map_err(|err| format!("Failed to serialize the module: {}", err))
+}
+
+/// Returns the name and serialized bytes of every section, in order.
+fn section_contents(module: &Module) -> Result<Vec<(String, Vec<u8>)>, String> {
+    module
+        .sections()
+        .iter()
+        .map(|section| {
+            let bytes = parity_wasm::serialize(section.clone())
+                .map_err(|err| format!("Failed to serialize a section: {}", err))?;
+            Ok((section_name(section), bytes))
+        })
+        .collect()
+}
+
+fn section_name(section: &Section) -> String {
+    match section {
+        Section::Custom(custom) => format!("custom:{}", custom.name()),
+        Section::Name(_) => "custom:name".to_string(),
+        Section::Reloc(reloc) => format!("custom:{}", reloc.name()),
+        _ => {
+            let id = section_id(section);
+            SECTION_NAMES
+                .get(id as usize)
+                .map_or_else(|| format!("{}", id), |name| name.to_string())
+        }
+    }
+}
+
+/// Matches the sections by name, in order, as custom sections may share a name. A section is
+/// changed if its contents differ, even if its size does not.
+fn section_changes(
+    before: &[(String, Vec<u8>)],
+    after: &[(String, Vec<u8>)],
+) -> Vec<SectionChange> {
+    let mut matched = vec![false; after.len()];
+    let mut sections = Vec::new();
+    for (name, bytes_before) in before.iter() {
+        let index = (0..after.len()).find(|&index| !matched[index] && after[index].0 == *name);
+        let bytes_after = index.map(|index| {
+            matched[index] = true;
+            &after[index].1
+        });
+        if bytes_after != Some(bytes_before) {
+            sections.push(SectionChange {
+                name: name.clone(),
+                size_before: Some(bytes_before.len()),
+                size_after: bytes_after.map(|bytes| bytes.len()),
+            });
+        }
+    }
+    sections.extend(
+        after
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|((name, bytes), _)| SectionChange {
+                name: name.clone(),
+                size_before: None,
+                size_after: Some(bytes.len()),
+            }),
+    );
+    sections
+}
+
+fn imports(module: &Module) -> Vec<(String, External)> {
+    module.import_section().map_or(vec![], |section| {
+        section
+            .entries()
+            .iter()
+            .map(|entry| {
+                (
+                    format!("{}::{}", entry.module(), entry.field()),
+                    *entry.external(),
+                )
+            })
+            .collect()
+    })
+}
+
+fn exports(module: &Module) -> Vec<(String, Internal)> {
+    module.export_section().map_or(vec![], |section| {
+        section
+            .entries()
+            .iter()
+            .map(|entry| (entry.field().to_string(), *entry.internal()))
+            .collect()
+    })
+}
+
+/// Compares the items by name. An item which disappeared is taken to be renamed if one of the
+/// same kind and type appeared.
+fn changes<K: PartialEq>(before: &[(String, K)], after: &[(String, K)]) -> Vec<Change> {
+    let removed = before
+        .iter()
+        .filter(|(name, _)| !after.iter().any(|(other, _)| other == name));
+    let added: Vec<&(String, K)> = after
+        .iter()
+        .filter(|(name, _)| !before.iter().any(|(other, _)| other == name))
+        .collect();
+
+    let mut renamed = vec![false; added.len()];
+    let mut changes: Vec<Change> = removed
+        .map(|(name, kind)| {
+            match (0..added.len()).find(|&index| !renamed[index] && added[index].1 == *kind) {
+                Some(index) => {
+                    renamed[index] = true;
+                    Change::Renamed(name.clone(), added[index].0.clone())
+                }
+                None => Change::Removed(name.clone()),
+            }
+        })
+        .collect();
+    changes.extend(
+        added
+            .iter()
+            .zip(renamed)
+            .filter(|(_, renamed)| !renamed)
+            .map(|((name, _), _)| Change::Added(name.clone())),
+    );
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    fn sample_module() -> Module {
+        FromWat::new(
+            r#"(module
+                (import "env" "abort" (func))
+                (import "env" "memory" (memory 1))
+                (func (export "main"))
+                (func (export "helper")))"#,
+        )
+        .create()
+        .unwrap()
+    }
+
+    #[test]
+    fn unchanged_module_empty() {
+        let module = sample_module();
+        let diff = SectionDiff::between(&module, &module).unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(
+            diff.to_string(),
+            format!("size {0} -> {0} bytes (+0)", diff.size_before)
+        );
+    }
+
+    #[test]
+    fn changes_rendered() {
+        let before = sample_module();
+        let mut after = before.clone();
+        after.import_section_mut().unwrap().entries_mut()[0] =
+            parity_wasm::elements::ImportEntry::new(
+                "ethereum".to_string(),
+                "revert".to_string(),
+                External::Function(0),
+            );
+        after.export_section_mut().unwrap().entries_mut().pop();
+        after.set_custom_section("deployer", vec![0x01, 0x02]);
+
+        let diff = SectionDiff::between(&before, &after).unwrap();
+        assert_eq!(
+            diff.imports,
+            vec![Change::Renamed(
+                "env::abort".to_string(),
+                "ethereum::revert".to_string()
+            )]
+        );
+        assert_eq!(diff.exports, vec![Change::Removed("helper".to_string())]);
+        let rendered = diff.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            &lines[..lines.len() - 1],
+            [
+                "~ section import (29 -> 35 bytes)",
+                "~ section export (19 -> 10 bytes)",
+                "+ section custom:deployer (13 bytes)",
+                "~ import `env::abort` -> `ethereum::revert`",
+                "- export `helper`",
+            ]
+        );
+        assert_eq!(
+            lines[lines.len() - 1],
+            format!(
+                "size {} -> {} bytes (+{})",
+                diff.size_before,
+                diff.size_after,
+                diff.size_after - diff.size_before
+            )
+        );
+    }
+
+    #[test]
+    fn removed_section_reported() {
+        let mut before = sample_module();
+        before.set_custom_section("name", vec![0x00]);
+        let after = sample_module();
+        let diff = SectionDiff::between(&before, &after).unwrap();
+        assert_eq!(
+            diff.sections,
+            vec![SectionChange {
+                name: "custom:name".to_string(),
+                size_before: Some(8),
+                size_after: None,
+            }]
+        );
+        assert!(diff.imports.is_empty() && diff.exports.is_empty());
+    }
+
+    #[test]
+    fn changed_contents_reported() {
+        let before = FromWat::new("(module (func (result i32) (i32.const 1)))")
+            .create()
+            .unwrap();
+        let after = FromWat::new("(module (func (result i32) (i32.const 2)))")
+            .create()
+            .unwrap();
+        let diff = SectionDiff::between(&before, &after).unwrap();
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.to_string().lines().next(),
+            Some("~ section code (8 -> 8 bytes)")
+        );
+    }
+}
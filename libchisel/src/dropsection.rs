@@ -0,0 +1,139 @@
+use super::{translate_copy, ModuleTranslator};
+use parity_wasm::elements::{Module, Section};
+
+/// Custom sections dropped unless others are given: debugging names, and the toolchain which
+/// produced the module.
+static DEFAULT_SECTIONS: &[&str] = &["name", "producers"];
+
+/// Struct on which ModuleTranslator is implemented.
+/// Removes custom sections, which are not needed to run the module.
+pub struct DropSection<'a> {
+    /// Names of the custom sections to drop, or None to drop all of them.
+    names: Option<Vec<&'a str>>,
+}
+
+impl<'a> Default for DropSection<'a> {
+    fn default() -> Self {
+        DropSection::with_names(DEFAULT_SECTIONS.to_vec())
+    }
+}
+
+impl<'a> DropSection<'a> {
+    /// Constructs a context dropping the custom sections of the given names.
+    pub fn with_names(names: Vec<&'a str>) -> Self {
+        DropSection { names: Some(names) }
+    }
+
+    /// Constructs a context dropping every custom section.
+    pub fn all() -> Self {
+        DropSection { names: None }
+    }
+
+    fn is_dropped(&self, section: &Section) -> bool {
+        let name = match section {
+            Section::Custom(custom) => custom.name(),
+            Section::Name(_) => "name",
+            Section::Reloc(reloc) => reloc.name(),
+            _ => return false,
+        };
+        self.names
+            .as_ref()
+            .is_none_or(|names| names.contains(&name))
+    }
+
+    /// Removes the matching custom sections. Returns the number of bytes saved, counting the
+    /// header of every removed section.
+    pub fn drop_sections(&self, module: &mut Module) -> Result<usize, String> {
+        let mut saved = 0;
+        let mut kept = Vec::new();
+        for section in module.sections_mut().drain(..) {
+            if self.is_dropped(&section) {
+                saved += parity_wasm::serialize(section)
+                    .map_err(|_| "Failed to serialize a custom section".to_string())?
+                    .len();
+            } else {
+                kept.push(section);
+            }
+        }
+        *module.sections_mut() = kept;
+        Ok(saved)
+    }
+}
+
+impl<'a> ModuleTranslator for DropSection<'a> {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        Ok(self.drop_sections(module)? > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+
+    fn module_with_custom_sections() -> Module {
+        let mut module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        module.set_custom_section("name", vec![0x00, 0x01, 0x00]);
+        module.set_custom_section("producers", vec![0x00]);
+        module.set_custom_section("deployer", vec![0x01, 0x02]);
+        module
+    }
+
+    fn custom_names(module: &Module) -> Vec<&str> {
+        module
+            .custom_sections()
+            .map(|custom| custom.name())
+            .collect()
+    }
+
+    #[test]
+    fn default_sections_dropped() {
+        let mut module = module_with_custom_sections();
+        let size = parity_wasm::serialize(module.clone()).unwrap().len();
+
+        // Each section has a byte of id, a byte of size, and its name prefixed by its length
+        // before the payload: 2 + 5 + 3 bytes for "name", and 2 + 10 + 1 bytes for "producers".
+        let saved = DropSection::default().drop_sections(&mut module).unwrap();
+        assert_eq!(saved, 23);
+        assert_eq!(custom_names(&module), vec!["deployer"]);
+
+        let serialized = parity_wasm::serialize(module).unwrap();
+        assert_eq!(serialized.len(), size - saved);
+        let module = parity_wasm::deserialize_buffer::<Module>(&serialized).unwrap();
+        assert_eq!(module.export_section().unwrap().entries().len(), 1);
+        assert_eq!(module.code_section().unwrap().bodies().len(), 1);
+    }
+
+    #[test]
+    fn all_sections_dropped() {
+        let mut module = module_with_custom_sections();
+        assert!(DropSection::all().translate_inplace(&mut module).unwrap());
+        assert!(custom_names(&module).is_empty());
+        assert!(!DropSection::all().translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn named_sections_dropped() {
+        let mut module = module_with_custom_sections();
+        let dropper = DropSection::with_names(vec!["deployer", "missing"]);
+        assert!(dropper.translate_inplace(&mut module).unwrap());
+        assert_eq!(custom_names(&module), vec!["name", "producers"]);
+        assert_eq!(dropper.translate(&module), Ok(None));
+    }
+}
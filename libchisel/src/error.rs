@@ -0,0 +1,44 @@
+//! Errors reported by the library functions which create a module or run a set of chisel
+//! modules.
+
+use alloc::fmt;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use presets::Preset;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChiselError {
+    /// The wasm binary could not be deserialized, with the reason given by parity-wasm.
+    Deserialize(String),
+    /// The WebAssembly text could not be parsed, with the location of the error.
+    ParseText(String),
+    /// The named preset does not exist. Its message lists the presets which do.
+    InvalidPreset(String),
+    /// The named module failed to run, with the reason it gave.
+    ModuleFailed(String, String),
+}
+
+impl fmt::Display for ChiselError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChiselError::Deserialize(reason) => {
+                write!(f, "Failed to deserialize the wasm binary: {}", reason)
+            }
+            ChiselError::ParseText(reason) => write!(f, "Failed to parse the text: {}", reason),
+            ChiselError::InvalidPreset(name) => {
+                let presets: Vec<&str> = Preset::ALL.iter().map(|preset| preset.name()).collect();
+                write!(
+                    f,
+                    "Invalid preset: {}. Accepted presets: {}.",
+                    name,
+                    presets.join(", ")
+                )
+            }
+            ChiselError::ModuleFailed(name, reason) => write!(f, "{}: {}", name, reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChiselError {}
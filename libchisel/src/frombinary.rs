@@ -0,0 +1,46 @@
+use super::{deserialize_module, ModuleCreator};
+use error::ChiselError;
+use parity_wasm::elements::Module;
+
+/// Struct on which ModuleCreator is implemented.
+/// Deserializes a module from its binary format, describing where a malformed binary went wrong.
+pub struct FromBinary<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> FromBinary<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        FromBinary { bytes }
+    }
+}
+
+impl<'a> ModuleCreator for FromBinary<'a> {
+    fn create(&self) -> Result<Module, ChiselError> {
+        deserialize_module(self.bytes).map_err(ChiselError::Deserialize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+
+    #[test]
+    fn module_created() {
+        let module = FromWat::new(r#"(module (func (export "main")))"#)
+            .create()
+            .unwrap();
+        let binary = parity_wasm::serialize(module.clone()).unwrap();
+        assert_eq!(FromBinary::new(&binary).create().unwrap(), module);
+    }
+
+    #[test]
+    fn malformed_binary_described() {
+        assert_eq!(
+            FromBinary::new(b"\0asm\x02\0\0\0").create(),
+            Err(ChiselError::Deserialize(
+                "unsupported wasm version 2, expected 1".to_string()
+            ))
+        );
+    }
+}
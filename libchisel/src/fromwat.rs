@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use super::ModuleCreator;
+use error::ChiselError;
+use parity_wasm::elements::{deserialize_buffer, Module};
+
+/// Struct on which ModuleCreator is implemented.
+/// Parses a module from its WebAssembly text format.
+pub struct FromWat<'a> {
+    source: &'a str,
+    /// Path of the text, named in parse errors.
+    path: Option<&'a Path>,
+}
+
+impl<'a> FromWat<'a> {
+    pub fn new(source: &'a str) -> Self {
+        FromWat { source, path: None }
+    }
+
+    /// Constructs a creator of the text read from the given path.
+    pub fn with_path(source: &'a str, path: &'a Path) -> Self {
+        FromWat {
+            source,
+            path: Some(path),
+        }
+    }
+}
+
+impl<'a> ModuleCreator for FromWat<'a> {
+    fn create(&self) -> Result<Module, ChiselError> {
+        // The error points at the line and column of the malformed text.
+        let binary = wat::Parser::new()
+            .parse_str(self.path, self.source)
+            .map_err(|err| ChiselError::ParseText(err.to_string()))?;
+        deserialize_buffer::<Module>(&binary)
+            .map_err(|err| ChiselError::Deserialize(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_created() {
+        let source = r#"
+(module
+  (import "ethereum" "finish" (func $finish (param i32 i32)))
+  (memory 1)
+  (export "memory" (memory 0))
+  (export "main" (func $main))
+  (func $main
+    (call $finish (i32.const 0) (i32.const 0))))
+"#;
+        let module = FromWat::new(source).create().unwrap();
+        assert_eq!(
+            module.import_count(parity_wasm::elements::ImportCountType::Function),
+            1
+        );
+        let exports: Vec<&str> = module
+            .export_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| entry.field())
+            .collect();
+        assert_eq!(exports, vec!["memory", "main"]);
+    }
+
+    #[test]
+    fn parse_error_located() {
+        let source = "(module\n  (func $main (unknown)))";
+        let err = FromWat::with_path(source, Path::new("contract.wat"))
+            .create()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("contract.wat:2:"), "{}", err);
+    }
+}
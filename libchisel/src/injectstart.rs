@@ -0,0 +1,229 @@
+use super::{format_signature, translate_copy, ModuleTranslator};
+use parity_wasm::elements::{External, FunctionType, Internal, Module, Section, Type};
+
+/// The function which becomes the start function.
+pub enum StartFunction<'a> {
+    /// The function exported under the name.
+    Export(&'a str),
+    /// The function at the index of the function index space, imported functions included.
+    Index(u32),
+}
+
+/// Struct on which ModuleTranslator is implemented.
+/// Sets the start function to an exported initialization function, as the inverse of
+/// `RemapStart`.
+pub struct InjectStart<'a> {
+    function: StartFunction<'a>,
+    /// Whether the export naming the function is removed.
+    remove_export: bool,
+    /// Whether a start function the module already has is replaced.
+    force: bool,
+}
+
+impl<'a> InjectStart<'a> {
+    /// Constructs a translator setting the start function, which keeps the export and refuses to
+    /// replace an existing start function.
+    pub fn new(function: StartFunction<'a>) -> Self {
+        InjectStart {
+            function,
+            remove_export: false,
+            force: false,
+        }
+    }
+
+    /// Sets whether the export naming the start function is removed. Has no effect when the
+    /// function is given by its index.
+    pub fn set_remove_export(&mut self, remove_export: bool) {
+        self.remove_export = remove_export;
+    }
+
+    /// Sets whether a start function the module already has is replaced, rather than failing.
+    pub fn set_force(&mut self, force: bool) {
+        self.force = force;
+    }
+
+    /// Returns the index of the function to start with.
+    fn resolve(&self, module: &Module) -> Result<u32, String> {
+        let name = match self.function {
+            StartFunction::Index(index) => return Ok(index),
+            StartFunction::Export(name) => name,
+        };
+        let export = module
+            .export_section()
+            .and_then(|section| section.entries().iter().find(|e| e.field() == name))
+            .ok_or_else(|| format!("Module does not export `{}`", name))?;
+        match export.internal() {
+            Internal::Function(index) => Ok(*index),
+            _ => Err(format!("Export `{}` is not a function", name)),
+        }
+    }
+}
+
+impl<'a> ModuleTranslator for InjectStart<'a> {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        let index = self.resolve(module)?;
+        let sig = function_type(module, index)
+            .ok_or_else(|| format!("Function {} does not exist", index))?;
+        if !sig.params().is_empty() || sig.return_type().is_some() {
+            return Err(format!(
+                "Function {} has signature {}, expected () -> ()",
+                index,
+                format_signature(sig)
+            ));
+        }
+
+        let mut modified = false;
+        match module.start_section() {
+            Some(start) if start == index => (),
+            Some(start) if !self.force => {
+                return Err(format!(
+                    "Module already has start function {}, which is only replaced when forced",
+                    start
+                ))
+            }
+            _ => {
+                set_start(module, index);
+                modified = true;
+            }
+        }
+
+        if let (true, StartFunction::Export(name)) = (self.remove_export, &self.function) {
+            if let Some(section) = module.export_section_mut() {
+                let exports = section.entries().len();
+                section.entries_mut().retain(|e| e.field() != *name);
+                modified |= section.entries().len() != exports;
+            }
+        }
+        Ok(modified)
+    }
+}
+
+/// Returns the type of the function at the index, imported functions first.
+fn function_type(module: &Module, index: u32) -> Option<&FunctionType> {
+    let imported = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Function(type_ref) => Some(*type_ref),
+            _ => None,
+        });
+    let defined = module
+        .function_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|func| func.type_ref());
+    let type_ref = imported.chain(defined).nth(index as usize)?;
+    match module.type_section()?.types().get(type_ref as usize)? {
+        Type::Function(sig) => Some(sig),
+    }
+}
+
+/// Sets the start section, which parity-wasm would place first in a module without a code,
+/// element or data section.
+fn set_start(module: &mut Module, index: u32) {
+    if module.start_section().is_some() {
+        module.set_start_section(index);
+        return;
+    }
+    // The start section follows the type, import, function, table, memory, global and export
+    // sections.
+    let position = module
+        .sections()
+        .iter()
+        .rposition(|section| {
+            matches!(
+                section,
+                Section::Type(_)
+                    | Section::Import(_)
+                    | Section::Function(_)
+                    | Section::Table(_)
+                    | Section::Memory(_)
+                    | Section::Global(_)
+                    | Section::Export(_)
+            )
+        })
+        .map_or(0, |position| position + 1);
+    module
+        .sections_mut()
+        .insert(position, Section::Start(index));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    /// Exports an initialization function and a function taking a parameter.
+    static SAMPLE: &str = r#"(module
+        (import "env" "log" (func (param i32)))
+        (func (export "init"))
+        (func (export "run") (param i32)))"#;
+
+    fn sample() -> Module {
+        FromWat::new(SAMPLE).create().unwrap()
+    }
+
+    #[test]
+    fn export_becomes_start() {
+        let mut module = sample();
+        let mut chisel = InjectStart::new(StartFunction::Export("init"));
+        assert!(chisel.translate_inplace(&mut module).unwrap());
+        assert_eq!(module.start_section(), Some(1));
+        assert_eq!(module.export_section().unwrap().entries().len(), 2);
+
+        chisel.set_remove_export(true);
+        assert!(chisel.translate_inplace(&mut module).unwrap());
+        let exports = module.export_section().unwrap().entries();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].field(), "run");
+
+        // The start section is placed where wasm expects it.
+        let serialized = parity_wasm::serialize(module).unwrap();
+        let module = parity_wasm::deserialize_buffer::<Module>(&serialized).unwrap();
+        assert_eq!(module.start_section(), Some(1));
+    }
+
+    #[test]
+    fn signature_checked() {
+        let mut module = sample();
+        assert_eq!(
+            InjectStart::new(StartFunction::Export("run"))
+                .translate_inplace(&mut module)
+                .unwrap_err(),
+            "Function 2 has signature (i32) -> (), expected () -> ()"
+        );
+        assert!(InjectStart::new(StartFunction::Index(0))
+            .translate_inplace(&mut module)
+            .is_err());
+        assert!(InjectStart::new(StartFunction::Index(3))
+            .translate_inplace(&mut module)
+            .is_err());
+        assert!(InjectStart::new(StartFunction::Export("missing"))
+            .translate_inplace(&mut module)
+            .is_err());
+        assert!(module.start_section().is_none());
+    }
+
+    #[test]
+    fn existing_start_only_replaced_when_forced() {
+        let mut module = sample();
+        module.set_start_section(1);
+        let mut chisel = InjectStart::new(StartFunction::Index(1));
+        assert!(!chisel.translate_inplace(&mut module).unwrap());
+
+        let mut module = FromWat::new("(module (func) (func) (start 0))")
+            .create()
+            .unwrap();
+        chisel.function = StartFunction::Index(1);
+        assert!(chisel.translate_inplace(&mut module).is_err());
+        chisel.set_force(true);
+        assert!(chisel.translate_inplace(&mut module).unwrap());
+        assert_eq!(module.start_section(), Some(1));
+    }
+}
@@ -1,26 +1,376 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+// Helpers shared between modules go unused when only some of the modules are built.
+#![cfg_attr(not(feature = "cli"), allow(dead_code, unused_imports))]
+
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "deployer")]
 extern crate byteorder;
 extern crate parity_wasm;
+#[cfg(feature = "deployer")]
 extern crate rustc_hex;
+#[cfg(feature = "std")]
+extern crate wasmparser;
+#[cfg(feature = "std")]
+extern crate wat;
 
+#[cfg(feature = "addsection")]
+pub mod addsection;
+#[cfg(feature = "checkfloat")]
+pub mod checkfloat;
+#[cfg(feature = "checksimdatomics")]
+pub mod checksimdatomics;
+#[cfg(feature = "checkstartfunc")]
 pub mod checkstartfunc;
+#[cfg(feature = "dedupdata")]
+pub mod dedupdata;
+#[cfg(feature = "deployer")]
 pub mod deployer;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "dropsection")]
+pub mod dropsection;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod frombinary;
+#[cfg(feature = "std")]
+pub mod fromwat;
+#[cfg(feature = "injectstart")]
+pub mod injectstart;
+#[cfg(feature = "lowerexports")]
+pub mod lowerexports;
+#[cfg(feature = "maxsize")]
+pub mod maxsize;
+#[cfg(feature = "mergetypes")]
+pub mod mergetypes;
+#[cfg(feature = "std")]
+pub mod pipeline;
+pub mod presets;
+#[cfg(feature = "remapexports")]
+pub mod remapexports;
+#[cfg(feature = "remapimports")]
 pub mod remapimports;
+#[cfg(feature = "remapstart")]
+pub mod remapstart;
+#[cfg(feature = "repack")]
+pub mod repack;
+#[cfg(feature = "setmemorylimits")]
+pub mod setmemorylimits;
+#[cfg(feature = "snip")]
+pub mod snip;
+#[cfg(feature = "sortimports")]
+pub mod sortimports;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "std")]
+mod streaming;
+#[cfg(feature = "stripnames")]
+pub mod stripnames;
+#[cfg(feature = "trimexports")]
 pub mod trimexports;
+pub mod verify;
+#[cfg(feature = "verifycustomsections")]
+pub mod verifycustomsections;
+#[cfg(feature = "verifydatabounds")]
+pub mod verifydatabounds;
+#[cfg(feature = "verifyexports")]
 pub mod verifyexports;
+#[cfg(feature = "verifyfuncsize")]
+pub mod verifyfuncsize;
+#[cfg(feature = "verifyglobals")]
+pub mod verifyglobals;
+#[cfg(feature = "verifyimports")]
 pub mod verifyimports;
+#[cfg(feature = "verifyindexspace")]
+pub mod verifyindexspace;
+#[cfg(feature = "verifylimits")]
+pub mod verifylimits;
+#[cfg(feature = "verifymemory")]
+pub mod verifymemory;
+#[cfg(feature = "verifymemoryexport")]
+pub mod verifymemoryexport;
+#[cfg(feature = "verifymvp")]
+pub mod verifymvp;
+#[cfg(feature = "verifyoffsets")]
+pub mod verifyoffsets;
+#[cfg(feature = "verifystructure")]
+pub mod verifystructure;
+#[cfg(feature = "verifytable")]
+pub mod verifytable;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
 use parity_wasm::elements::*;
 
+use error::ChiselError;
+use presets::Preset;
+
+/// Produces a module from an input source, such as its binary or text format, before any
+/// validator or translator runs on it.
 pub trait ModuleCreator {
-    fn create(self) -> Result<Module, String>;
+    fn create(&self) -> Result<Module, ChiselError>;
 }
 
 pub trait ModuleTranslator {
-    fn translate(self, module: &mut Module) -> Result<bool, String>;
+    /// Returns the translated module, or None if the translator made no change to it.
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String>;
+
+    /// Translates the module in place, and returns whether it changed. Translators which can
+    /// mutate the module cheaply override this, rather than building a new module.
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        match self.translate(module)? {
+            Some(translated) => {
+                *module = translated;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Implements `translate` for translators which mutate in place, by running the translation
+/// on a copy of the module.
+#[cfg(feature = "std")]
+pub(crate) fn translate_copy<F>(module: &Module, translate: F) -> Result<Option<Module>, String>
+where
+    F: FnOnce(&mut Module) -> Result<bool, String>,
+{
+    let mut module = module.clone();
+    Ok(if translate(&mut module)? {
+        Some(module)
+    } else {
+        None
+    })
 }
 
 pub trait ModuleValidator {
-    fn validate(self, module: &Module) -> Result<bool, String>;
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String>;
+
+    /// Validates the serialized module without deserializing all of it, or returns None if the
+    /// validator needs the whole module. The result must agree with `validate`.
+    fn validate_binary(&self, _binary: &[u8]) -> Option<Result<ValidationResult, String>> {
+        None
+    }
+
+    /// Returns true if the validator only inspects the sections declaring the interface of the
+    /// module, up to its start function, so that it can run on a module read by
+    /// `stream::read_interface` without its code and data.
+    fn interface_only(&self) -> bool {
+        false
+    }
+
+    /// Describes the rules the validator applies as configured, one per entry, so that they
+    /// can be explained without running it.
+    fn rules(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Outcome of a validator. The module passes if there is no reason for it to fail.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationResult {
+    reasons: Vec<String>,
+}
+
+impl ValidationResult {
+    /// Constructs a result failing for the given reasons, or passing if there are none.
+    pub fn from_reasons(reasons: Vec<String>) -> Self {
+        ValidationResult { reasons }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.reasons.is_empty()
+    }
+
+    /// Human-readable reasons why the module failed, in the order they were found.
+    pub fn reasons(&self) -> &[String] {
+        &self.reasons
+    }
+}
+
+/// Trait over chisel modules which are configured for a target environment.
+pub trait ModulePreset: Sized {
+    /// Constructs the module for the given preset, failing if it does not support that target.
+    fn with_preset(preset: Preset) -> Result<Self, String>;
+
+    /// Returns the presets which `with_preset` accepts, in the order of `Preset::ALL`.
+    fn presets() -> Vec<Preset> {
+        Preset::ALL
+            .iter()
+            .cloned()
+            .filter(|preset| Self::with_preset(*preset).is_ok())
+            .collect()
+    }
+}
+
+/// Results of the standard validators of a preset, as run by `validate_module`.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationReport {
+    preset: Preset,
+    /// Each validator by name alongside its result, in the order they ran.
+    results: Vec<(String, ValidationResult)>,
+}
+
+#[cfg(feature = "std")]
+impl ValidationReport {
+    pub fn preset(&self) -> Preset {
+        self.preset
+    }
+
+    pub fn results(&self) -> &[(String, ValidationResult)] {
+        &self.results
+    }
+
+    /// Returns true if every validator passed.
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.passed())
+    }
+}
+
+/// Deserializes the binary and runs the standard validators of the named preset over it: the
+/// exports, imports and memory expected by the preset, where it defines them, and the absence
+/// of a start function.
+#[cfg(all(
+    feature = "std",
+    feature = "checkstartfunc",
+    feature = "verifyexports",
+    feature = "verifyimports",
+    feature = "verifymemory"
+))]
+pub fn validate_module(bytes: &[u8], preset: &str) -> Result<ValidationReport, ChiselError> {
+    let preset: Preset = preset
+        .parse()
+        .map_err(|_| ChiselError::InvalidPreset(preset.to_string()))?;
+    let module = deserialize_module(bytes).map_err(ChiselError::Deserialize)?;
+    let results = verify::validate_preset(&module, preset)
+        .map_err(|(name, reason)| ChiselError::ModuleFailed(name.to_string(), reason))?
+        .into_iter()
+        .map(|(name, result)| (name.to_string(), result))
+        .collect();
+    Ok(ValidationReport { preset, results })
+}
+
+/// Deserializes the binary. If it is malformed, the reason given by parity-wasm is completed
+/// with where the binary went wrong: a wrong magic number or version, or the section which could
+/// not be read and its offset in the binary.
+pub fn deserialize_module(bytes: &[u8]) -> Result<Module, String> {
+    deserialize_buffer::<Module>(bytes).map_err(|err| describe_malformed(bytes, err))
+}
+
+/// Names of the known sections, by id.
+pub(crate) static SECTION_NAMES: &[&str] = &[
+    "custom",
+    "type",
+    "import",
+    "function",
+    "table",
+    "memory",
+    "global",
+    "export",
+    "start",
+    "element",
+    "code",
+    "data",
+    "datacount",
+];
+
+/// Returns the id of the section in the binary format, which is 0 for every custom section.
+pub(crate) fn section_id(section: &Section) -> u8 {
+    match section {
+        Section::Unparsed { id, .. } => *id,
+        Section::Custom(_) | Section::Name(_) | Section::Reloc(_) => 0,
+        Section::Type(_) => 1,
+        Section::Import(_) => 2,
+        Section::Function(_) => 3,
+        Section::Table(_) => 4,
+        Section::Memory(_) => 5,
+        Section::Global(_) => 6,
+        Section::Export(_) => 7,
+        Section::Start(_) => 8,
+        Section::Element(_) => 9,
+        Section::Code(_) => 10,
+        Section::Data(_) => 11,
+    }
+}
+
+fn describe_malformed(bytes: &[u8], err: Error) -> String {
+    if bytes.len() < 4 || bytes[..4] != *b"\0asm" {
+        return "not a wasm binary, as it does not start with the magic number `\\0asm`"
+            .to_string();
+    }
+    if bytes.len() < 8 {
+        return "the binary ends within its version, at offset 4".to_string();
+    }
+    let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    if version != 1 {
+        return format!("unsupported wasm version {}, expected 1", version);
+    }
+
+    // Find the first section which cannot be read on its own.
+    let mut offset = 8;
+    while offset < bytes.len() {
+        let id = bytes[offset];
+        let name = SECTION_NAMES.get(id as usize).map_or_else(
+            || format!("unknown section {}", id),
+            |name| format!("{} section", name),
+        );
+        let mut position = offset + 1;
+        let size = match read_var_u32(bytes, &mut position) {
+            Some(size) => size as usize,
+            None => {
+                return format!(
+                    "the binary ends within the header of the {} at offset {}",
+                    name, offset
+                )
+            }
+        };
+        let end = position + size;
+        if end > bytes.len() {
+            return format!(
+                "the binary ends within the {} at offset {}, which declares {} bytes but only {} \
+                 remain",
+                name,
+                offset,
+                size,
+                bytes.len() - position
+            );
+        }
+        if let Err(section_err) = deserialize_buffer::<Section>(&bytes[offset..end]) {
+            return format!("{} in the {} at offset {}", section_err, name, offset);
+        }
+        offset = end;
+    }
+    err.to_string()
+}
+
+/// Reads an unsigned LEB128 integer of at most 32 bits, advancing the position past it.
+pub(crate) fn read_var_u32(bytes: &[u8], position: &mut usize) -> Option<u32> {
+    let mut value: u32 = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*position)?;
+        *position += 1;
+        // The fifth byte only holds the top 4 bits, anything above would overflow.
+        if shift == 28 && byte & 0x70 != 0 {
+            return None;
+        }
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Formats a function signature as e.g. `(i32, i32) -> i64`, or `() -> ()` if it takes and
+/// returns nothing.
+fn format_signature(sig: &FunctionType) -> String {
+    let params: Vec<String> = sig.params().iter().map(|param| param.to_string()).collect();
+    let result = sig
+        .return_type()
+        .map_or_else(|| "()".to_string(), |result| result.to_string());
+    format!("({}) -> {}", params.join(", "), result)
 }
 
 #[cfg(test)]
@@ -30,41 +380,163 @@ mod tests {
     struct SampleModule {}
 
     impl ModuleCreator for SampleModule {
-        fn create(self) -> Result<Module, String> {
+        fn create(&self) -> Result<Module, ChiselError> {
             Ok(Module::default())
         }
     }
 
     impl ModuleTranslator for SampleModule {
-        fn translate(self, module: &mut Module) -> Result<bool, String> {
-            Ok((true))
+        fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+            let mut translated = module.clone();
+            translated.set_custom_section("sample", vec![]);
+            Ok(Some(translated))
         }
     }
 
     impl ModuleValidator for SampleModule {
-        fn validate(self, module: &Module) -> Result<bool, String> {
-            Ok(true)
+        fn validate(&self, _module: &Module) -> Result<ValidationResult, String> {
+            Ok(ValidationResult::default())
         }
     }
 
+    impl ModulePreset for SampleModule {
+        fn with_preset(preset: Preset) -> Result<Self, String> {
+            match preset {
+                Preset::PWasm => Ok(SampleModule {}),
+                _ => Err(format!("Preset {} is not supported", preset)),
+            }
+        }
+    }
+
+    #[test]
+    fn supported_presets_listed() {
+        assert_eq!(SampleModule::presets(), vec![Preset::PWasm]);
+    }
+
+    #[test]
+    fn validation_fails_with_reasons() {
+        let result = ValidationResult::from_reasons(vec!["missing export `main`".to_string()]);
+        assert!(!result.passed());
+        assert_eq!(result.reasons(), ["missing export `main`"]);
+        assert!(ValidationResult::from_reasons(vec![]).passed());
+    }
+
+    #[test]
+    fn module_validated_for_preset() {
+        let module = parity_wasm::builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        let bytes = serialize(module).unwrap();
+
+        let report = validate_module(&bytes, "ewasm").unwrap();
+        assert_eq!(report.preset(), Preset::Ewasm);
+        let names: Vec<&str> = report
+            .results()
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "verifyexports",
+                "verifyimports",
+                "verifymemory",
+                "checkstartfunc"
+            ]
+        );
+        assert!(!report.passed());
+        assert_eq!(report.results()[0].1.reasons(), ["missing export `memory`"]);
+
+        let report = validate_module(&bytes, "pwasm").unwrap();
+        assert_eq!(report.results().len(), 2);
+    }
+
+    #[test]
+    fn validate_module_errors() {
+        assert_eq!(
+            validate_module(&[], "ewsam"),
+            Err(ChiselError::InvalidPreset("ewsam".to_string()))
+        );
+        assert_eq!(
+            ChiselError::InvalidPreset("ewsam".to_string()).to_string(),
+            "Invalid preset: ewsam. Accepted presets: ewasm, ewasm-debug, pwasm."
+        );
+        assert!(matches!(
+            validate_module(b"not wasm", "ewasm"),
+            Err(ChiselError::Deserialize(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_binary_described() {
+        let describe = |bytes: &[u8]| deserialize_module(bytes).err().unwrap();
+        assert_eq!(
+            describe(b"not wasm"),
+            "not a wasm binary, as it does not start with the magic number `\\0asm`"
+        );
+        assert_eq!(
+            describe(b"\0asm\x02\0\0\0"),
+            "unsupported wasm version 2, expected 1"
+        );
+        // A type section declaring 5 bytes, of which only 2 are present.
+        assert_eq!(
+            describe(b"\0asm\x01\0\0\0\x01\x05\x01\x60"),
+            "the binary ends within the type section at offset 8, which declares 5 bytes but only 2 remain"
+        );
+        // An export section with an export of unknown kind 9.
+        assert_eq!(
+            describe(b"\0asm\x01\0\0\0\x07\x05\x01\x01a\x09\x00"),
+            "Unknown internal kind 9 in the export section at offset 8"
+        );
+    }
+
+    #[test]
+    fn var_u32_overflow_rejected() {
+        let read = |bytes: &[u8]| read_var_u32(bytes, &mut 0);
+        assert_eq!(read(&[0xe5, 0x8e, 0x26]), Some(624485));
+        assert_eq!(read(&[0xff, 0xff, 0xff, 0xff, 0x0f]), Some(u32::MAX));
+        assert_eq!(read(&[0xff, 0xff, 0xff, 0xff, 0x7f]), None);
+        assert_eq!(read(&[0x80, 0x80, 0x80, 0x80, 0x10]), None);
+        assert_eq!(read(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]), None);
+    }
+
     #[test]
     fn creator_succeeds() {
         let creator = SampleModule {};
-        let result = creator.create();
-        assert!(result.is_ok());
+        assert!(creator.create().is_ok());
+        // A creator is only borrowed, so it can create the module again.
+        assert!(creator.create().is_ok());
     }
 
     #[test]
     fn translator_succeeds() {
         let translator = SampleModule {};
-        let result = translator.translate(&mut Module::default());
-        assert!(result.is_ok());
+        let result = translator.translate(&Module::default());
+        assert!(result.unwrap().is_some());
+    }
+
+    #[test]
+    fn translator_inplace_replaces_module() {
+        let translator = SampleModule {};
+        let mut module = Module::default();
+        assert_eq!(translator.translate_inplace(&mut module), Ok(true));
+        assert_eq!(module.custom_sections().count(), 1);
     }
 
     #[test]
     fn validator_succeeds() {
         let validator = SampleModule {};
         let result = validator.validate(&Module::default());
-        assert!(result.is_ok());
+        assert!(result.unwrap().passed());
     }
 }
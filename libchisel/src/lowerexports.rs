@@ -0,0 +1,401 @@
+use std::collections::BTreeMap;
+
+use parity_wasm::elements::{
+    ExportEntry, External, Func, FuncBody, FunctionType, GlobalEntry, GlobalType, InitExpr,
+    Instruction, Instructions, Internal, Local, Module, Section, Type, ValueType,
+};
+
+use super::{format_signature, section_id, translate_copy, ModuleTranslator};
+
+/// Name of the function exported to read the upper half of an `i64` returned by a trampoline, as
+/// named by binaryen when it legalizes an interface.
+pub const HIGH_BITS_EXPORT: &str = "getTempRet0";
+
+/// What is done with an exported function using `i64` in its signature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LowerMode {
+    /// Fails, naming each such export and its signature.
+    Reject,
+    /// Exports a trampoline using `i32` instead, in place of the function.
+    Wrap,
+}
+
+/// Struct on which ModuleTranslator is implemented.
+/// Adapts exported functions to hosts whose ABI only passes `i32` values, such as older versions
+/// of ewasm. A trampoline takes each `i64` parameter as two `i32` halves, low half first, and
+/// returns the low half of an `i64` result, while its high half is read by calling the exported
+/// `getTempRet0` afterwards. The original functions are kept for the calls within the module.
+pub struct LowerExports {
+    mode: LowerMode,
+}
+
+impl LowerExports {
+    pub fn new(mode: LowerMode) -> Self {
+        LowerExports { mode }
+    }
+
+    /// Lowers the exports using `i64`, and returns their names. Fails without changing anything
+    /// in `Reject` mode if there are any.
+    pub fn lower_exports(&self, module: &mut Module) -> Result<Vec<String>, String> {
+        let mut lowered = Vec::new();
+        if let Some(section) = module.export_section() {
+            for entry in section.entries() {
+                if let Internal::Function(index) = *entry.internal() {
+                    let sig = function_type(module, index)
+                        .ok_or_else(|| format!("Function {} does not exist", index))?;
+                    if uses_i64(sig) {
+                        lowered.push((entry.field().to_string(), index, sig.clone()));
+                    }
+                }
+            }
+        }
+        if lowered.is_empty() {
+            return Ok(vec![]);
+        }
+
+        if self.mode == LowerMode::Reject {
+            let exports: Vec<String> = lowered
+                .iter()
+                .map(|(name, _, sig)| format!("`{}` has type {}", name, format_signature(sig)))
+                .collect();
+            return Err(format!(
+                "Exports use i64, which the host ABI does not allow: {}",
+                exports.join(", ")
+            ));
+        }
+        let returns_i64 = lowered
+            .iter()
+            .any(|(_, _, sig)| sig.return_type() == Some(ValueType::I64));
+        if returns_i64 && exports(module).any(|name| name == HIGH_BITS_EXPORT) {
+            return Err(format!(
+                "Module already exports `{}`, which would read the high bits of results",
+                HIGH_BITS_EXPORT
+            ));
+        }
+
+        let high_bits = if returns_i64 {
+            Some(add_high_bits(module))
+        } else {
+            None
+        };
+        // A function exported under several names gets a single trampoline.
+        let mut trampolines = BTreeMap::new();
+        for (_, index, sig) in lowered.iter() {
+            if !trampolines.contains_key(index) {
+                let trampoline = add_trampoline(module, *index, sig, high_bits);
+                trampolines.insert(*index, trampoline);
+            }
+        }
+        let section = module
+            .export_section_mut()
+            .expect("module has exports to lower");
+        for entry in section.entries_mut().iter_mut() {
+            if let Internal::Function(index) = *entry.internal() {
+                if let Some(trampoline) = trampolines.get(&index) {
+                    *entry.internal_mut() = Internal::Function(*trampoline);
+                }
+            }
+        }
+        Ok(lowered.into_iter().map(|(name, _, _)| name).collect())
+    }
+}
+
+impl ModuleTranslator for LowerExports {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        Ok(!self.lower_exports(module)?.is_empty())
+    }
+}
+
+fn uses_i64(sig: &FunctionType) -> bool {
+    sig.params().contains(&ValueType::I64) || sig.return_type() == Some(ValueType::I64)
+}
+
+fn exports(module: &Module) -> impl Iterator<Item = &str> {
+    module
+        .export_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|entry| entry.field())
+}
+
+/// Returns the type of the function at the index, imported functions first.
+fn function_type(module: &Module, index: u32) -> Option<&FunctionType> {
+    let imported = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Function(type_ref) => Some(*type_ref),
+            _ => None,
+        });
+    let defined = module
+        .function_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|func| func.type_ref());
+    let type_ref = imported.chain(defined).nth(index as usize)?;
+    match module.type_section()?.types().get(type_ref as usize)? {
+        Type::Function(sig) => Some(sig),
+    }
+}
+
+/// Adds the mutable global holding the high bits of the last `i64` result, and the exported
+/// function reading it. Returns the index of the global.
+fn add_high_bits(module: &mut Module) -> u32 {
+    let global = module.globals_space() as u32;
+    insert_section(module, Section::Global(Default::default()));
+    module
+        .global_section_mut()
+        .expect("global section was inserted")
+        .entries_mut()
+        .push(GlobalEntry::new(
+            GlobalType::new(ValueType::I32, true),
+            InitExpr::new(vec![Instruction::I32Const(0), Instruction::End]),
+        ));
+
+    let getter = add_function(
+        module,
+        FunctionType::new(vec![], Some(ValueType::I32)),
+        FuncBody::new(
+            vec![],
+            Instructions::new(vec![Instruction::GetGlobal(global), Instruction::End]),
+        ),
+    );
+    insert_section(module, Section::Export(Default::default()));
+    module
+        .export_section_mut()
+        .expect("export section was inserted")
+        .entries_mut()
+        .push(ExportEntry::new(
+            HIGH_BITS_EXPORT.to_string(),
+            Internal::Function(getter),
+        ));
+    global
+}
+
+/// Adds a function calling the one at the index with the `i64` parameters joined from their
+/// halves, and returns its index.
+fn add_trampoline(
+    module: &mut Module,
+    index: u32,
+    sig: &FunctionType,
+    high_bits: Option<u32>,
+) -> u32 {
+    let mut params = Vec::new();
+    let mut code = Vec::new();
+    for param in sig.params() {
+        let local = params.len() as u32;
+        if *param == ValueType::I64 {
+            params.extend_from_slice(&[ValueType::I32, ValueType::I32]);
+            code.extend_from_slice(&[
+                Instruction::GetLocal(local),
+                Instruction::I64ExtendUI32,
+                Instruction::GetLocal(local + 1),
+                Instruction::I64ExtendUI32,
+                Instruction::I64Const(32),
+                Instruction::I64Shl,
+                Instruction::I64Or,
+            ]);
+        } else {
+            params.push(*param);
+            code.push(Instruction::GetLocal(local));
+        }
+    }
+    code.push(Instruction::Call(index));
+
+    let mut locals = vec![];
+    let mut result = sig.return_type();
+    if let (Some(ValueType::I64), Some(global)) = (result, high_bits) {
+        // The result is kept in a local while its high half is stored.
+        let local = params.len() as u32;
+        locals.push(Local::new(1, ValueType::I64));
+        code.extend_from_slice(&[
+            Instruction::TeeLocal(local),
+            Instruction::I64Const(32),
+            Instruction::I64ShrU,
+            Instruction::I32WrapI64,
+            Instruction::SetGlobal(global),
+            Instruction::GetLocal(local),
+            Instruction::I32WrapI64,
+        ]);
+        result = Some(ValueType::I32);
+    }
+    code.push(Instruction::End);
+
+    add_function(
+        module,
+        FunctionType::new(params, result),
+        FuncBody::new(locals, Instructions::new(code)),
+    )
+}
+
+/// Adds a function of the given type, reusing an identical type if the module has one, and
+/// returns its index.
+fn add_function(module: &mut Module, sig: FunctionType, body: FuncBody) -> u32 {
+    let index = module.functions_space() as u32;
+    insert_section(module, Section::Type(Default::default()));
+    let types = module
+        .type_section_mut()
+        .expect("type section was inserted")
+        .types_mut();
+    let type_ref = match types
+        .iter()
+        .position(|ty| *ty == Type::Function(sig.clone()))
+    {
+        Some(type_ref) => type_ref,
+        None => {
+            types.push(Type::Function(sig));
+            types.len() - 1
+        }
+    };
+
+    insert_section(module, Section::Function(Default::default()));
+    module
+        .function_section_mut()
+        .expect("function section was inserted")
+        .entries_mut()
+        .push(Func::new(type_ref as u32));
+    insert_section(module, Section::Code(Default::default()));
+    module
+        .code_section_mut()
+        .expect("code section was inserted")
+        .bodies_mut()
+        .push(body);
+    index
+}
+
+/// Inserts the empty section after the known sections which precede it, unless the module
+/// already has one of its kind.
+fn insert_section(module: &mut Module, section: Section) {
+    let id = section_id(&section);
+    if module
+        .sections()
+        .iter()
+        .any(|existing| section_id(existing) == id)
+    {
+        return;
+    }
+    let position = module
+        .sections()
+        .iter()
+        .rposition(|existing| (1..id).contains(&section_id(existing)))
+        .map_or(0, |position| position + 1);
+    module.sections_mut().insert(position, section);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    /// Exports an addition of `i64`s twice, an imported function taking an `i64`, and a function
+    /// using `i32` alone.
+    static SAMPLE: &str = r#"(module
+        (import "env" "log" (func $log (param i64)))
+        (func $add (param i64 i32) (result i64)
+            (i64.add (local.get 0) (i64.extend_i32_u (local.get 1))))
+        (func $id (param i32) (result i32) (local.get 0))
+        (export "add" (func $add))
+        (export "plus" (func $add))
+        (export "log" (func $log))
+        (export "id" (func $id)))"#;
+
+    fn sample() -> Module {
+        FromWat::new(SAMPLE).create().unwrap()
+    }
+
+    fn export_types(module: &Module) -> Vec<(String, String)> {
+        module
+            .export_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| match *entry.internal() {
+                Internal::Function(index) => (
+                    entry.field().to_string(),
+                    format_signature(function_type(module, index).unwrap()),
+                ),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn i64_exports_rejected() {
+        let module = sample();
+        assert_eq!(
+            LowerExports::new(LowerMode::Reject)
+                .translate(&module)
+                .unwrap_err(),
+            "Exports use i64, which the host ABI does not allow: `add` has type (i64, i32) -> i64, \
+             `plus` has type (i64, i32) -> i64, `log` has type (i64) -> ()"
+        );
+        let module = FromWat::new(r#"(module (func (export "main")))"#)
+            .create()
+            .unwrap();
+        assert_eq!(
+            LowerExports::new(LowerMode::Reject).translate(&module),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn i64_exports_wrapped() {
+        let mut module = sample();
+        assert_eq!(
+            LowerExports::new(LowerMode::Wrap)
+                .lower_exports(&mut module)
+                .unwrap(),
+            ["add", "plus", "log"]
+        );
+        let types = |types: &[(&str, &str)]| -> Vec<(String, String)> {
+            types
+                .iter()
+                .map(|(name, ty)| (name.to_string(), ty.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            export_types(&module),
+            types(&[
+                ("add", "(i32, i32, i32) -> i32"),
+                ("plus", "(i32, i32, i32) -> i32"),
+                ("log", "(i32, i32) -> ()"),
+                ("id", "(i32) -> i32"),
+                ("getTempRet0", "() -> i32"),
+            ])
+        );
+        // Both names of `add` share a trampoline, which calls the original function.
+        let entries = module.export_section().unwrap().entries();
+        assert_eq!(entries[0].internal(), entries[1].internal());
+        let bodies = module.code_section().unwrap().bodies();
+        assert!(bodies[3].code().elements().contains(&Instruction::Call(1)));
+        assert!(bodies[4].code().elements().contains(&Instruction::Call(0)));
+
+        // The result is a valid module, in which nothing is left to lower.
+        let serialized = parity_wasm::serialize(module).unwrap();
+        let mut module = parity_wasm::deserialize_buffer::<Module>(&serialized).unwrap();
+        assert!(wasmparser::validate(&serialized).is_ok());
+        assert!(!LowerExports::new(LowerMode::Wrap)
+            .translate_inplace(&mut module)
+            .unwrap());
+    }
+
+    #[test]
+    fn high_bits_export_not_replaced() {
+        let module = FromWat::new(
+            r#"(module
+                (func (export "getTempRet0") (result i32) (i32.const 0))
+                (func (export "big") (result i64) (i64.const 0)))"#,
+        )
+        .create()
+        .unwrap();
+        assert!(LowerExports::new(LowerMode::Wrap)
+            .translate(&module)
+            .is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::io;
+
+use super::presets::Preset;
+use super::{ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{Module, Serialize};
+
+/// Struct on which ModuleValidator is implemented.
+/// Checks that the module does not exceed a number of bytes when serialized.
+pub struct MaxSize {
+    /// Largest size of the module in bytes.
+    limit: usize,
+}
+
+impl ModulePreset for MaxSize {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        let limit = preset
+            .max_size()
+            .ok_or_else(|| format!("Preset {} is not supported", preset))?;
+        Ok(MaxSize::new(limit))
+    }
+}
+
+impl MaxSize {
+    /// Constructs a validator allowing modules of at most `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        MaxSize { limit }
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    /// Checks the size of a binary as it is, without deserializing it. The module is measured
+    /// as it would be deployed, which may differ from the size after re-serialization.
+    pub fn validate_bytes(&self, bytes: &[u8]) -> ValidationResult {
+        self.validate_size(bytes.len())
+    }
+
+    fn validate_size(&self, size: usize) -> ValidationResult {
+        let reasons = if size > self.limit {
+            vec![format!(
+                "module is {} bytes, expected at most {} bytes",
+                size, self.limit
+            )]
+        } else {
+            vec![]
+        };
+        ValidationResult::from_reasons(reasons)
+    }
+}
+
+impl ModuleValidator for MaxSize {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let mut size = ByteCount(0);
+        module
+            .clone()
+            .serialize(&mut size)
+            .map_err(|err| format!("Failed to serialize the module: {}", err))?;
+        Ok(self.validate_size(size.0))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        vec![format!("allows modules of at most {} bytes", self.limit)]
+    }
+}
+
+/// Writer counting the bytes written to it, so that the module is measured without buffering
+/// its serialization.
+struct ByteCount(usize);
+
+impl io::Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+
+    fn module_with_data(len: usize) -> Module {
+        builder::module()
+            .memory()
+            .with_min(1)
+            .with_data(0, vec![0; len])
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn within_limit_good() {
+        let checker = MaxSize::with_preset(Preset::Ewasm).unwrap();
+        assert!(checker.validate(&module_with_data(1024)).unwrap().passed());
+        assert!(checker.validate_bytes(&[0; 24576]).passed());
+    }
+
+    #[test]
+    fn exceeded_limit_bad() {
+        let module = module_with_data(100);
+        let size = parity_wasm::serialize(module.clone()).unwrap().len();
+        let result = MaxSize::new(100).validate(&module).unwrap();
+        assert_eq!(
+            result.reasons(),
+            [format!(
+                "module is {} bytes, expected at most 100 bytes",
+                size
+            )]
+        );
+
+        let result = MaxSize::with_preset(Preset::Ewasm)
+            .unwrap()
+            .validate_bytes(&[0; 24577]);
+        assert_eq!(
+            result.reasons(),
+            ["module is 24577 bytes, expected at most 24576 bytes"]
+        );
+    }
+
+    #[test]
+    fn pwasm_not_supported() {
+        assert!(MaxSize::with_preset(Preset::PWasm).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use super::{translate_copy, ModuleTranslator};
+use parity_wasm::elements::{External, Instruction, Module, Section};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Removes duplicate function types from the type section, which compilers often emit, and
+/// points every reference to a duplicate at the first identical type instead.
+#[derive(Default)]
+pub struct MergeTypes {}
+
+impl MergeTypes {
+    pub fn new() -> Self {
+        MergeTypes {}
+    }
+
+    /// Merges identical types, renumbering the types referenced by functions, imports and
+    /// `call_indirect` instructions. Returns the number of types removed.
+    pub fn merge_types(&self, module: &mut Module) -> Result<usize, String> {
+        let types = module
+            .type_section()
+            .map_or(&[][..], |section| section.types());
+
+        // Each type is mapped to the new index of its first occurrence.
+        let mut kept = Vec::new();
+        let mut type_map = Vec::with_capacity(types.len());
+        for (index, ty) in types.iter().enumerate() {
+            match types[..index].iter().position(|other| other == ty) {
+                Some(first) => type_map.push(type_map[first]),
+                None => {
+                    type_map.push(kept.len() as u32);
+                    kept.push(index);
+                }
+            }
+        }
+        let removed = types.len() - kept.len();
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        let mut sections = module.sections().to_vec();
+        for section in sections.iter_mut() {
+            match section {
+                Section::Type(section) => {
+                    let mut index = 0;
+                    section.types_mut().retain(|_| {
+                        index += 1;
+                        kept.contains(&(index - 1))
+                    });
+                }
+                Section::Import(section) => {
+                    for entry in section.entries_mut() {
+                        if let External::Function(type_ref) = entry.external_mut() {
+                            *type_ref = remap(&type_map, *type_ref)?;
+                        }
+                    }
+                }
+                Section::Function(section) => {
+                    for func in section.entries_mut() {
+                        *func.type_ref_mut() = remap(&type_map, func.type_ref())?;
+                    }
+                }
+                Section::Code(section) => {
+                    for body in section.bodies_mut() {
+                        for instruction in body.code_mut().elements_mut() {
+                            if let Instruction::CallIndirect(type_ref, _) = instruction {
+                                *type_ref = remap(&type_map, *type_ref)?;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        *module.sections_mut() = sections;
+        Ok(removed)
+    }
+}
+
+impl ModuleTranslator for MergeTypes {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        Ok(self.merge_types(module)? > 0)
+    }
+}
+
+fn remap(type_map: &[u32], index: u32) -> Result<u32, String> {
+    type_map
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| format!("type {} does not exist", index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use parity_wasm::elements::{FunctionType, Type, ValueType};
+    use ModuleCreator;
+
+    fn i32_to_i32() -> Type {
+        Type::Function(FunctionType::new(
+            vec![ValueType::I32],
+            Some(ValueType::I32),
+        ))
+    }
+
+    /// A module with the types `(i32) -> i32`, `() -> ()`, `(i32) -> i32` and `(i32) -> i32`, an
+    /// import of the third, and a function of the fourth calling indirectly through the last.
+    fn module_with_duplicates() -> Module {
+        FromWat::new(
+            r#"(module
+                (type $a (func (param i32) (result i32)))
+                (type (func))
+                (type $b (func (param i32) (result i32)))
+                (type $c (func (param i32) (result i32)))
+                (import "env" "abs" (func (type $b)))
+                (table 1 funcref)
+                (func (type $c)
+                    (call_indirect (type $c) (local.get 0) (i32.const 0))))"#,
+        )
+        .create()
+        .unwrap()
+    }
+
+    #[test]
+    fn duplicate_types_merged() {
+        let mut module = module_with_duplicates();
+        assert_eq!(MergeTypes::new().merge_types(&mut module), Ok(2));
+
+        assert_eq!(
+            module.type_section().unwrap().types(),
+            &[
+                i32_to_i32(),
+                Type::Function(FunctionType::new(vec![], None))
+            ]
+        );
+        assert_eq!(
+            module.import_section().unwrap().entries()[0].external(),
+            &External::Function(0)
+        );
+        assert_eq!(
+            module.function_section().unwrap().entries()[0].type_ref(),
+            0
+        );
+        assert_eq!(
+            module.code_section().unwrap().bodies()[0].code().elements()[2],
+            Instruction::CallIndirect(0, 0)
+        );
+        assert!(!MergeTypes::new().translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn missing_type_fails() {
+        let mut module = module_with_duplicates();
+        *module.function_section_mut().unwrap().entries_mut()[0].type_ref_mut() = 7;
+        assert!(MergeTypes::new().translate_inplace(&mut module).is_err());
+        assert_eq!(module.type_section().unwrap().types().len(), 4);
+    }
+}
@@ -0,0 +1,229 @@
+//! A sequence of chisel modules run in order over a wasm module.
+
+use super::{ModuleTranslator, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::Module;
+
+/// A chisel module within a pipeline.
+pub enum Stage<'a> {
+    Validator(Box<dyn ModuleValidator + 'a>),
+    Translator(Box<dyn ModuleTranslator + 'a>),
+}
+
+/// What a stage found or did when it ran successfully.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StageOutcome {
+    Validated(ValidationResult),
+    /// Whether the translator mutated the module.
+    Translated(bool),
+}
+
+/// Outcome of a single stage of the pipeline.
+pub struct StageResult {
+    pub name: String,
+    pub outcome: Result<StageOutcome, String>,
+}
+
+/// Outcomes of every stage of the pipeline, in the order they ran.
+pub struct PipelineResult {
+    pub stages: Vec<StageResult>,
+}
+
+/// Builder and runner of a chisel pipeline.
+#[derive(Default)]
+pub struct Chisel<'a> {
+    stages: Vec<(String, Stage<'a>)>,
+}
+
+impl<'a> Stage<'a> {
+    pub fn is_translator(&self) -> bool {
+        match self {
+            Stage::Validator(_) => false,
+            Stage::Translator(_) => true,
+        }
+    }
+
+    /// Runs the stage over a module. Only translators may modify it.
+    pub fn run(&self, module: &mut Module) -> Result<StageOutcome, String> {
+        match self {
+            Stage::Validator(validator) => validator.validate(module).map(StageOutcome::Validated),
+            Stage::Translator(translator) => translator
+                .translate_inplace(module)
+                .map(StageOutcome::Translated),
+        }
+    }
+
+    /// Runs the stage over the serialized module, or returns None if it needs the deserialized
+    /// module, as translators always do.
+    pub fn run_binary(&self, binary: &[u8]) -> Option<Result<StageOutcome, String>> {
+        match self {
+            Stage::Validator(validator) => validator
+                .validate_binary(binary)
+                .map(|result| result.map(StageOutcome::Validated)),
+            Stage::Translator(_) => None,
+        }
+    }
+}
+
+impl StageResult {
+    /// Returns true if the stage did not fail. Translators pass whether or not they mutated the
+    /// module.
+    pub fn passed(&self) -> bool {
+        match self.outcome {
+            Ok(StageOutcome::Validated(ref result)) => result.passed(),
+            Ok(StageOutcome::Translated(_)) => true,
+            Err(_) => false,
+        }
+    }
+}
+
+impl PipelineResult {
+    pub fn passed(&self) -> bool {
+        self.stages.iter().all(|stage| stage.passed())
+    }
+}
+
+impl<'a> Chisel<'a> {
+    /// Constructs an empty pipeline.
+    pub fn new() -> Self {
+        Chisel { stages: Vec::new() }
+    }
+
+    /// Appends a stage under the given name.
+    pub fn stage(mut self, name: &str, stage: Stage<'a>) -> Self {
+        self.stages.push((name.to_string(), stage));
+        self
+    }
+
+    pub fn validator(self, name: &str, validator: Box<dyn ModuleValidator + 'a>) -> Self {
+        self.stage(name, Stage::Validator(validator))
+    }
+
+    pub fn translator(self, name: &str, translator: Box<dyn ModuleTranslator + 'a>) -> Self {
+        self.stage(name, Stage::Translator(translator))
+    }
+
+    /// Returns the stages and their names, in the order they run.
+    pub fn stages(&self) -> impl Iterator<Item = (&str, &Stage<'a>)> {
+        self.stages
+            .iter()
+            .map(|(name, stage)| (name.as_str(), stage))
+    }
+
+    /// Runs every stage in order over the module. Later stages still run after a failure, and
+    /// see the module as left by the translators before them.
+    pub fn run(&self, module: &mut Module) -> PipelineResult {
+        PipelineResult {
+            stages: self
+                .stages
+                .iter()
+                .map(|(name, stage)| StageResult {
+                    name: name.clone(),
+                    outcome: stage.run(module),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use checkstartfunc::CheckStartFunc;
+    use parity_wasm::builder;
+    use presets::Preset;
+    use trimexports::TrimExports;
+    use verifyexports::VerifyExports;
+    use ModulePreset;
+
+    fn sample_module() -> Module {
+        builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("other")
+            .internal()
+            .func(0)
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let chisel = Chisel::new()
+            .validator(
+                "verifyexports",
+                Box::new(VerifyExports::with_preset(Preset::PWasm).unwrap()),
+            )
+            .translator(
+                "trimexports",
+                Box::new(TrimExports::with_preset(Preset::Ewasm).unwrap()),
+            )
+            .validator("checkstartfunc", Box::new(CheckStartFunc::new(false)));
+
+        let mut module = sample_module();
+        let result = chisel.run(&mut module);
+        let outcomes: Vec<(&str, Result<StageOutcome, String>)> = result
+            .stages
+            .iter()
+            .map(|stage| (stage.name.as_str(), stage.outcome.clone()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (
+                    "verifyexports",
+                    Ok(StageOutcome::Validated(ValidationResult::from_reasons(
+                        vec![
+                            "missing export `_call`".to_string(),
+                            "unexpected export `main`".to_string(),
+                            "unexpected export `other`".to_string(),
+                        ]
+                    )))
+                ),
+                ("trimexports", Ok(StageOutcome::Translated(true))),
+                (
+                    "checkstartfunc",
+                    Ok(StageOutcome::Validated(ValidationResult::default()))
+                ),
+            ]
+        );
+        assert!(!result.passed());
+        assert_eq!(module.export_section().unwrap().entries().len(), 1);
+    }
+
+    #[test]
+    fn stage_run_on_binary() {
+        let mut module = sample_module();
+        let binary = parity_wasm::serialize(module.clone()).unwrap();
+
+        let validator =
+            Stage::Validator(Box::new(VerifyExports::with_preset(Preset::PWasm).unwrap()));
+        assert_eq!(
+            validator.run_binary(&binary),
+            Some(validator.run(&mut module))
+        );
+        let validator = Stage::Validator(Box::new(CheckStartFunc::new(false)));
+        assert_eq!(validator.run_binary(&binary), None);
+        let translator =
+            Stage::Translator(Box::new(TrimExports::with_preset(Preset::Ewasm).unwrap()));
+        assert_eq!(translator.run_binary(&binary), None);
+    }
+
+    #[test]
+    fn empty_pipeline_passes() {
+        let mut module = sample_module();
+        let result = Chisel::new().run(&mut module);
+        assert!(result.stages.is_empty());
+        assert!(result.passed());
+    }
+}
@@ -0,0 +1,569 @@
+//! Named target presets, and the interface each of them expects a wasm module to have.
+//!
+//! Modules implementing `ModulePreset` build their configuration from these expectations, so a
+//! new target only has to be described here.
+
+use alloc::fmt;
+use alloc::str::FromStr;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use parity_wasm::elements::{Internal, ValueType};
+
+/// A known target environment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    Ewasm,
+    /// Ewasm with the additional imports of the debugging module.
+    EwasmDebug,
+    PWasm,
+}
+
+/// A post-MVP proposal of WebAssembly which a target environment may support.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    SignExtension,
+    BulkMemory,
+    Simd,
+    /// Atomic memory accesses, from the threads proposal.
+    Threads,
+}
+
+/// Caps on the functions of a module, each None if it is not limited.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// Largest number of functions the module defines.
+    pub functions: Option<u32>,
+    /// Largest number of functions the module imports.
+    pub imported_functions: Option<u32>,
+    /// Largest number of locals a function declares, not counting its parameters.
+    pub locals: Option<u32>,
+    /// Largest number of parameters a function takes.
+    pub params: Option<u32>,
+}
+
+/// A function which a preset expects to be imported.
+pub struct ImportSignature {
+    pub module: &'static str,
+    pub field: &'static str,
+    pub params: &'static [ValueType],
+    pub result: Option<ValueType>,
+}
+
+/// An export which a preset expects. The index of `internal` is not significant, and the
+/// parameters and result only apply to functions.
+pub struct ExportSignature {
+    pub field: &'static str,
+    pub internal: Internal,
+    pub params: &'static [ValueType],
+    pub result: Option<ValueType>,
+}
+
+static EWASM_EXPORTS: &[ExportSignature] = &[
+    ExportSignature {
+        field: "main",
+        internal: Internal::Function(0),
+        params: &[],
+        result: None,
+    },
+    ExportSignature {
+        field: "memory",
+        internal: Internal::Memory(0),
+        params: &[],
+        result: None,
+    },
+];
+
+static PWASM_EXPORTS: &[ExportSignature] = &[ExportSignature {
+    field: "_call",
+    internal: Internal::Function(0),
+    params: &[],
+    result: None,
+}];
+
+/// The Ethereum Environment Interface.
+static EWASM_IMPORTS: &[ImportSignature] = &[
+    ImportSignature {
+        module: "ethereum",
+        field: "useGas",
+        params: &[ValueType::I64],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getGasLeft",
+        params: &[],
+        result: Some(ValueType::I64),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getAddress",
+        params: &[ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getExternalBalance",
+        params: &[ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getBlockHash",
+        params: &[ValueType::I64, ValueType::I32],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "call",
+        params: &[
+            ValueType::I64,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "callCode",
+        params: &[
+            ValueType::I64,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "callDelegate",
+        params: &[
+            ValueType::I64,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "callStatic",
+        params: &[
+            ValueType::I64,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "create",
+        params: &[
+            ValueType::I64,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "callDataCopy",
+        params: &[ValueType::I32, ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getCallDataSize",
+        params: &[],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getCodeSize",
+        params: &[],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "codeCopy",
+        params: &[ValueType::I32, ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getExternalCodeSize",
+        params: &[ValueType::I32],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "externalCodeCopy",
+        params: &[
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getCaller",
+        params: &[ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getCallValue",
+        params: &[ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getBlockDifficulty",
+        params: &[ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getBlockCoinbase",
+        params: &[ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getBlockNumber",
+        params: &[],
+        result: Some(ValueType::I64),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getBlockGasLimit",
+        params: &[],
+        result: Some(ValueType::I64),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getBlockTimestamp",
+        params: &[],
+        result: Some(ValueType::I64),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getTxGasPrice",
+        params: &[ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getTxOrigin",
+        params: &[ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "storageStore",
+        params: &[ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "storageLoad",
+        params: &[ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "log",
+        params: &[
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "getReturnDataSize",
+        params: &[],
+        result: Some(ValueType::I32),
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "returnDataCopy",
+        params: &[ValueType::I32, ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "finish",
+        params: &[ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "revert",
+        params: &[ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "ethereum",
+        field: "selfDestruct",
+        params: &[ValueType::I32],
+        result: None,
+    },
+];
+
+/// The debugging module available to ewasm contracts in test environments.
+static DEBUG_IMPORTS: &[ImportSignature] = &[
+    ImportSignature {
+        module: "debug",
+        field: "printMem",
+        params: &[ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "debug",
+        field: "printMemHex",
+        params: &[ValueType::I32, ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "debug",
+        field: "printStorage",
+        params: &[ValueType::I32],
+        result: None,
+    },
+    ImportSignature {
+        module: "debug",
+        field: "printStorageHex",
+        params: &[ValueType::I32],
+        result: None,
+    },
+];
+
+/// Pages of 64 KiB an ewasm contract may initially allocate (16 MiB), and grow its memory to
+/// (64 MiB).
+static EWASM_MAX_INITIAL_PAGES: u32 = 256;
+static EWASM_MAX_PAGES: u32 = 1024;
+
+/// Bytes an ewasm contract may take up when deployed, as for EVM contracts by EIP-170.
+static EWASM_MAX_SIZE: usize = 24576;
+
+/// Bytes the encoded body of a single function of an ewasm contract may take up, leaving room
+/// within the EIP-170 limit for the rest of the contract.
+static EWASM_MAX_FUNCTION_SIZE: usize = 8192;
+
+/// Caps keeping an ewasm contract within what the gas and stack height instrumentation of the
+/// host handles. Imported functions are already limited by `verifyimports`.
+static EWASM_LIMITS: Limits = Limits {
+    functions: Some(10_000),
+    imported_functions: None,
+    locals: Some(1024),
+    params: Some(32),
+};
+
+impl Preset {
+    /// Every known preset.
+    pub const ALL: &'static [Preset] = &[Preset::Ewasm, Preset::EwasmDebug, Preset::PWasm];
+
+    /// The name of the preset as written in configuration files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Ewasm => "ewasm",
+            Preset::EwasmDebug => "ewasm-debug",
+            Preset::PWasm => "pwasm",
+        }
+    }
+
+    /// Exports which a module targeting this preset must have.
+    pub fn exports(self) -> &'static [ExportSignature] {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug => EWASM_EXPORTS,
+            Preset::PWasm => PWASM_EXPORTS,
+        }
+    }
+
+    /// Namespaces which every import of a module targeting this preset must come from.
+    pub fn namespaces(self) -> &'static [&'static str] {
+        match self {
+            Preset::Ewasm => &["ethereum"],
+            Preset::EwasmDebug => &["ethereum", "debug"],
+            Preset::PWasm => &["env"],
+        }
+    }
+
+    /// Largest initial and maximum number of pages the memory of a module targeting this preset
+    /// may declare, or None if the preset does not restrict them.
+    pub fn memory_limits(self) -> Option<(u32, u32)> {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug => Some((EWASM_MAX_INITIAL_PAGES, EWASM_MAX_PAGES)),
+            Preset::PWasm => None,
+        }
+    }
+
+    /// Sections which a module targeting this preset must have, by name.
+    pub fn required_sections(self) -> &'static [&'static str] {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug => &["type", "function", "export", "code", "memory"],
+            Preset::PWasm => &["type", "function", "export", "code"],
+        }
+    }
+
+    /// Custom sections which a module targeting this preset may have, by name, or None if the
+    /// preset does not restrict them.
+    pub fn custom_sections(self) -> Option<&'static [&'static str]> {
+        match self {
+            Preset::Ewasm => Some(&[]),
+            Preset::EwasmDebug => Some(&["name"]),
+            Preset::PWasm => None,
+        }
+    }
+
+    /// Whether a module targeting this preset may place its data and element segments at the
+    /// value of an imported global, rather than only at constant offsets.
+    pub fn imported_global_offsets(self) -> bool {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug => false,
+            Preset::PWasm => true,
+        }
+    }
+
+    /// Post-MVP features which a module targeting this preset may use.
+    pub fn features(self) -> &'static [Feature] {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug | Preset::PWasm => &[],
+        }
+    }
+
+    /// Whether a module targeting this preset may have at most one table.
+    pub fn single_table(self) -> bool {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug => true,
+            Preset::PWasm => false,
+        }
+    }
+
+    /// Largest size in bytes of a serialized module targeting this preset, or None if the preset
+    /// does not restrict it.
+    pub fn max_size(self) -> Option<usize> {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug => Some(EWASM_MAX_SIZE),
+            Preset::PWasm => None,
+        }
+    }
+
+    /// Largest size in bytes of the encoded body of a function of a module targeting this
+    /// preset, or None if the preset does not restrict it.
+    pub fn max_function_size(self) -> Option<usize> {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug => Some(EWASM_MAX_FUNCTION_SIZE),
+            Preset::PWasm => None,
+        }
+    }
+
+    /// Caps on the functions of a module targeting this preset.
+    pub fn limits(self) -> Limits {
+        match self {
+            Preset::Ewasm | Preset::EwasmDebug => EWASM_LIMITS,
+            Preset::PWasm => Limits::default(),
+        }
+    }
+
+    /// Imports which a module targeting this preset may use, or None if the preset does not
+    /// restrict them.
+    pub fn imports(self) -> Option<Vec<&'static ImportSignature>> {
+        match self {
+            Preset::Ewasm => Some(EWASM_IMPORTS.iter().collect()),
+            Preset::EwasmDebug => Some(EWASM_IMPORTS.iter().chain(DEBUG_IMPORTS).collect()),
+            Preset::PWasm => None,
+        }
+    }
+}
+
+impl Feature {
+    /// Every known feature.
+    pub const ALL: &'static [Feature] = &[
+        Feature::SignExtension,
+        Feature::BulkMemory,
+        Feature::Simd,
+        Feature::Threads,
+    ];
+
+    /// The name of the proposal as written in configuration files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::SignExtension => "sign-extension",
+            Feature::BulkMemory => "bulk-memory",
+            Feature::Simd => "simd",
+            Feature::Threads => "threads",
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Feature::ALL
+            .iter()
+            .find(|feature| feature.name() == name)
+            .cloned()
+            .ok_or_else(|| format!("Invalid feature: {}", name))
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .iter()
+            .find(|preset| preset.name() == name)
+            .cloned()
+            .ok_or_else(|| format!("Invalid preset: {}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_roundtrip() {
+        for preset in Preset::ALL {
+            assert_eq!(preset.name().parse::<Preset>(), Ok(*preset));
+        }
+        assert!("ewsam".parse::<Preset>().is_err());
+    }
+
+    #[test]
+    fn debug_extends_ewasm() {
+        let ewasm = Preset::Ewasm.imports().unwrap();
+        let debug = Preset::EwasmDebug.imports().unwrap();
+        assert_eq!(debug.len(), ewasm.len() + DEBUG_IMPORTS.len());
+        assert!(debug.iter().any(|import| import.module == "debug"));
+        assert!(Preset::PWasm.imports().is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use parity_wasm::elements::{ExportEntry, Module};
+
+use super::{translate_copy, ModuleTranslator};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Renames exports, such as the mangled `_main` of a compiler to the `main` a host calls. Only
+/// the names in the export section change, so every export keeps referring to the same function,
+/// global, memory or table.
+#[derive(Default)]
+pub struct RemapExports {
+    renames: HashMap<String, String>,
+}
+
+impl RemapExports {
+    /// Constructs a translator renaming nothing.
+    pub fn new() -> Self {
+        RemapExports::default()
+    }
+
+    /// Renames the export `from` to `to`. Renames are applied at once, so that two exports can
+    /// swap their names, and a renamed export is not renamed again.
+    pub fn insert(&mut self, from: &str, to: &str) {
+        self.renames.insert(from.to_string(), to.to_string());
+    }
+
+    /// Renames the exports which are mapped, and returns each applied rename, from the original
+    /// to the new name. Fails without renaming anything if two exports would end up with the
+    /// same name.
+    pub fn remap_exports(&self, module: &mut Module) -> Result<Vec<(String, String)>, String> {
+        let section = match module.export_section_mut() {
+            Some(section) => section,
+            None => return Ok(vec![]),
+        };
+        let names: Vec<&str> = section
+            .entries()
+            .iter()
+            .map(|entry| {
+                self.renames
+                    .get(entry.field())
+                    .map_or(entry.field(), |name| name.as_str())
+            })
+            .collect();
+        for (index, name) in names.iter().enumerate() {
+            if names[..index].contains(name) {
+                return Err(format!(
+                    "renaming exports would duplicate the export `{}`",
+                    name
+                ));
+            }
+        }
+
+        let names: Vec<String> = names.into_iter().map(|name| name.to_string()).collect();
+        let mut renames = Vec::new();
+        for (entry, name) in section.entries_mut().iter_mut().zip(names) {
+            if entry.field() != name {
+                renames.push((entry.field().to_string(), name.clone()));
+                *entry = ExportEntry::new(name, *entry.internal());
+            }
+        }
+        Ok(renames)
+    }
+}
+
+impl ModuleTranslator for RemapExports {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        Ok(!self.remap_exports(module)?.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use parity_wasm::elements::Internal;
+    use ModuleCreator;
+
+    fn exports(module: &Module) -> Vec<(&str, Internal)> {
+        module
+            .export_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| (entry.field(), *entry.internal()))
+            .collect()
+    }
+
+    #[test]
+    fn mapped_exports_renamed() {
+        let mut module = FromWat::new(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "_main"))
+                (func (export "a"))
+                (func (export "b")))"#,
+        )
+        .create()
+        .unwrap();
+        let mut chisel = RemapExports::new();
+        chisel.insert("_main", "main");
+        chisel.insert("a", "b");
+        chisel.insert("b", "a");
+        chisel.insert("missing", "other");
+        assert_eq!(
+            chisel.remap_exports(&mut module).unwrap(),
+            [
+                ("_main".to_string(), "main".to_string()),
+                ("a".to_string(), "b".to_string()),
+                ("b".to_string(), "a".to_string())
+            ]
+        );
+        assert_eq!(
+            exports(&module),
+            [
+                ("memory", Internal::Memory(0)),
+                ("main", Internal::Function(0)),
+                ("b", Internal::Function(1)),
+                ("a", Internal::Function(2))
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_name_rejected() {
+        let module = FromWat::new(
+            r#"(module
+                (func (export "_main"))
+                (func (export "main")))"#,
+        )
+        .create()
+        .unwrap();
+        let mut chisel = RemapExports::new();
+        chisel.insert("_main", "main");
+        assert_eq!(
+            chisel.translate(&module).unwrap_err(),
+            "renaming exports would duplicate the export `main`"
+        );
+    }
+}
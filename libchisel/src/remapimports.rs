@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 
 use parity_wasm::elements::*;
 
@@ -19,15 +20,21 @@ impl ImportPair {
     }
 }
 
+impl fmt::Display for ImportPair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}::{}", self.module, self.field)
+    }
+}
+
 #[derive(Default)]
 pub struct Translations {
     translations: HashMap<ImportPair, ImportPair>,
 }
 
 impl Translations {
-    pub fn with_preset(preset: &str) -> Result<Self, ()> {
+    pub fn with_preset(preset: Preset) -> Result<Self, String> {
         match preset {
-            "ewasm" => {
+            Preset::Ewasm | Preset::EwasmDebug => {
                 let trans: HashMap<ImportPair, ImportPair> = [
                     (
                         ImportPair::new("env", "ethereum_useGas"),
@@ -118,18 +125,18 @@ impl Translations {
                         ImportPair::new("ethereum", "selfDestruct"),
                     ),
                 ]
-                    .iter()
-                    .cloned()
-                    .collect();
+                .iter()
+                .cloned()
+                .collect();
                 Ok(Translations {
                     translations: trans,
                 })
             }
-            _ => Err(()),
+            Preset::PWasm => Err(format!("Preset {} is not supported", preset)),
         }
     }
 
-    fn insert(&mut self, from_module: &str, from_field: &str, to_module: &str, to_field: &str) {
+    pub fn insert(&mut self, from_module: &str, from_field: &str, to_module: &str, to_field: &str) {
         self.translations.insert(
             ImportPair::new(from_module, from_field),
             ImportPair::new(to_module, to_field),
@@ -144,8 +151,26 @@ impl Translations {
     //        }
     //    }
 
-    fn get(&self, pair: &ImportPair) -> Option<&ImportPair> {
-        self.translations.get(&pair)
+    /// Follows the translations from the import to the last name of the chain, so that an import
+    /// renamed to one which is itself renamed is not renamed again by a later run. Returns None
+    /// if the import keeps its name, or fails if the chain loops.
+    fn resolve(&self, pair: &ImportPair) -> Result<Option<ImportPair>, String> {
+        let mut seen = vec![pair];
+        while let Some(next) = self.translations.get(seen[seen.len() - 1]) {
+            if next == seen[seen.len() - 1] {
+                break;
+            }
+            if seen.contains(&next) {
+                return Err(format!("import {} is renamed in a cycle", pair));
+            }
+            seen.push(next);
+        }
+        let last = seen[seen.len() - 1];
+        Ok(if last == pair {
+            None
+        } else {
+            Some(last.clone())
+        })
     }
 }
 
@@ -153,40 +178,67 @@ pub struct RemapImports {
     translations: Translations,
 }
 
+impl ModulePreset for RemapImports {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(RemapImports {
+            translations: Translations::with_preset(preset)?,
+        })
+    }
+}
+
 impl RemapImports {
-    pub fn with_preset(preset: &str) -> Result<Self, ()> {
-        match preset {
-            "ewasm" => Ok(RemapImports {
-                translations: Translations::with_preset("ewasm").unwrap(),
-            }),
-            _ => Err(()),
-        }
+    pub fn with_translations(translations: Translations) -> Self {
+        RemapImports { translations }
+    }
+
+    /// Renames the imports which have a translation, in place so that the index of every import
+    /// is kept. Returns each applied rename, from the original to the new import. Fails without
+    /// renaming anything if the translations of an import loop.
+    pub fn remap_imports(
+        &self,
+        module: &mut Module,
+    ) -> Result<Vec<(ImportPair, ImportPair)>, String> {
+        rename_imports(module, &self.translations)
     }
 }
 
 impl ModuleTranslator for RemapImports {
-    fn translate(self, module: &mut Module) -> Result<bool, String> {
-        Ok(rename_imports(module, self.translations))
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        Ok(!self.remap_imports(module)?.is_empty())
     }
 }
 
-fn rename_imports(module: &mut Module, translations: Translations) -> bool {
-    let mut ret = false;
-    if let Some(section) = module.import_section_mut() {
-        for entry in section.entries_mut().iter_mut() {
-            if let Some(replacement) =
-                translations.get(&ImportPair::new(entry.module(), entry.field()))
-            {
-                ret = true;
-                *entry = ImportEntry::new(
-                    replacement.module.clone(),
-                    replacement.field.clone(),
-                    *entry.external(),
-                )
-            }
+fn rename_imports(
+    module: &mut Module,
+    translations: &Translations,
+) -> Result<Vec<(ImportPair, ImportPair)>, String> {
+    let section = match module.import_section_mut() {
+        Some(section) => section,
+        None => return Ok(vec![]),
+    };
+    let replacements = section
+        .entries()
+        .iter()
+        .map(|entry| translations.resolve(&ImportPair::new(entry.module(), entry.field())))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut renames = Vec::new();
+    for (entry, replacement) in section.entries_mut().iter_mut().zip(replacements) {
+        if let Some(replacement) = replacement {
+            let original = ImportPair::new(entry.module(), entry.field());
+            *entry = ImportEntry::new(
+                replacement.module.clone(),
+                replacement.field.clone(),
+                *entry.external(),
+            );
+            renames.push((original, replacement));
         }
     }
-    ret
+    Ok(renames)
 }
 
 #[cfg(test)]
@@ -194,7 +246,6 @@ mod tests {
     use super::*;
     use parity_wasm;
     use rustc_hex::FromHex;
-    use std::collections::HashMap;
 
     #[test]
     fn smoke_test() {
@@ -203,11 +254,12 @@ mod tests {
             0061736d0100000001050160017e0002170103656e760f65746865726575
             6d5f7573654761730000
         ",
-        ).unwrap();
+        )
+        .unwrap();
         let mut module = parity_wasm::deserialize_buffer(&input).expect("failed");
-        let did_change = RemapImports::with_preset("ewasm")
+        let did_change = RemapImports::with_preset(Preset::Ewasm)
             .unwrap()
-            .translate(&mut module)
+            .translate_inplace(&mut module)
             .unwrap();
         let output = parity_wasm::serialize(module).expect("failed");
         let expected = FromHex::from_hex(
@@ -215,8 +267,117 @@ mod tests {
             0061736d0100000001050160017e0002130108657468657265756d067573
             654761730000
         ",
-        ).unwrap();
+        )
+        .unwrap();
         assert_eq!(output, expected);
         assert!(did_change);
     }
+
+    #[test]
+    fn custom_translations_reported() {
+        let mut module = parity_wasm::builder::module()
+            .import()
+            .module("env")
+            .field("ethereum_finish")
+            .external()
+            .func(0)
+            .build()
+            .import()
+            .module("env")
+            .field("abort")
+            .external()
+            .func(0)
+            .build()
+            .import()
+            .module("env")
+            .field("memory")
+            .external()
+            .memory(1, None)
+            .build()
+            .build();
+
+        let mut translations = Translations::with_preset(Preset::Ewasm).unwrap();
+        translations.insert("env", "memory", "ethereum", "memory");
+        let remapper = RemapImports::with_translations(translations);
+        let renames: Vec<String> = remapper
+            .remap_imports(&mut module)
+            .unwrap()
+            .iter()
+            .map(|(from, to)| format!("{} -> {}", from, to))
+            .collect();
+        assert_eq!(
+            renames,
+            vec![
+                "env::ethereum_finish -> ethereum::finish",
+                "env::memory -> ethereum::memory"
+            ]
+        );
+
+        let names: Vec<(&str, &str)> = module
+            .import_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| (entry.module(), entry.field()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("ethereum", "finish"),
+                ("env", "abort"),
+                ("ethereum", "memory")
+            ]
+        );
+        assert!(!remapper.translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn chained_translations_idempotent() {
+        let mut module = parity_wasm::builder::module()
+            .import()
+            .module("env")
+            .field("ethereum_revert")
+            .external()
+            .func(0)
+            .build()
+            .import()
+            .module("env")
+            .field("abort")
+            .external()
+            .func(0)
+            .build()
+            .build();
+
+        // The preset renames env::ethereum_revert to ethereum::revert, which is then renamed again.
+        let mut translations = Translations::with_preset(Preset::Ewasm).unwrap();
+        translations.insert("ethereum", "revert", "debug", "revert");
+        translations.insert("env", "abort", "env", "abort");
+        let remapper = RemapImports::with_translations(translations);
+        let renames: Vec<String> = remapper
+            .remap_imports(&mut module)
+            .unwrap()
+            .iter()
+            .map(|(from, to)| format!("{} -> {}", from, to))
+            .collect();
+        assert_eq!(renames, vec!["env::ethereum_revert -> debug::revert"]);
+        assert_eq!(remapper.translate(&module), Ok(None));
+    }
+
+    #[test]
+    fn cyclic_translations_fail() {
+        let mut module = parity_wasm::builder::module()
+            .import()
+            .module("env")
+            .field("a")
+            .external()
+            .func(0)
+            .build()
+            .build();
+        let mut translations = Translations::default();
+        translations.insert("env", "a", "env", "b");
+        translations.insert("env", "b", "env", "a");
+        let remapper = RemapImports::with_translations(translations);
+        assert!(remapper.translate_inplace(&mut module).is_err());
+        assert_eq!(module.import_section().unwrap().entries()[0].field(), "a");
+    }
 }
@@ -0,0 +1,159 @@
+use super::{translate_copy, ModuleTranslator};
+use parity_wasm::elements::{ExportEntry, ExportSection, Internal, Module, Section};
+
+/// Name of the export taking the place of the start function.
+static ENTRY_POINT: &str = "main";
+
+/// Struct on which ModuleTranslator is implemented.
+/// Replaces the start function with an exported `main`.
+#[derive(Default)]
+pub struct RemapStart {}
+
+impl RemapStart {
+    pub fn new() -> Self {
+        RemapStart {}
+    }
+}
+
+impl ModuleTranslator for RemapStart {
+    /// Returns whether the module had a start function. Wasm requires the start function to be
+    /// of type `() -> ()`, which is the type of `main`, so it is exported as it is.
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        let start = match module.start_section() {
+            Some(start) => start,
+            None => return Ok(false),
+        };
+
+        if module.export_section().is_some_and(|section| {
+            section
+                .entries()
+                .iter()
+                .any(|entry| entry.field() == ENTRY_POINT)
+        }) {
+            return Err(format!(
+                "Module already exports `{}` alongside its start function",
+                ENTRY_POINT
+            ));
+        }
+
+        module.clear_start_section();
+        let entry = ExportEntry::new(ENTRY_POINT.to_string(), Internal::Function(start));
+        match module.export_section_mut() {
+            Some(section) => section.entries_mut().push(entry),
+            None => {
+                // The export section follows the type, import, function, table, memory and global
+                // sections.
+                let index = module
+                    .sections()
+                    .iter()
+                    .rposition(|section| {
+                        matches!(
+                            section,
+                            Section::Type(_)
+                                | Section::Import(_)
+                                | Section::Function(_)
+                                | Section::Table(_)
+                                | Section::Memory(_)
+                                | Section::Global(_)
+                        )
+                    })
+                    .map_or(0, |index| index + 1);
+                module.sections_mut().insert(
+                    index,
+                    Section::Export(ExportSection::with_entries(vec![entry])),
+                );
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use checkstartfunc::CheckStartFunc;
+    use parity_wasm::builder;
+    use presets::Preset;
+    use verifyexports::VerifyExports;
+    use {ModulePreset, ModuleValidator};
+
+    fn two_functions() -> builder::ModuleBuilder {
+        builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn start_becomes_main() {
+        let mut module = two_functions()
+            .memory()
+            .build()
+            .export()
+            .field("memory")
+            .internal()
+            .memory(0)
+            .build()
+            .build();
+        module.set_start_section(1);
+
+        assert!(RemapStart::new().translate_inplace(&mut module).unwrap());
+        assert!(module.start_section().is_none());
+        let main = module
+            .export_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .find(|entry| entry.field() == "main")
+            .unwrap();
+        assert_eq!(*main.internal(), Internal::Function(1));
+
+        let exports = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        assert!(exports.validate(&module).unwrap().passed());
+        let start = CheckStartFunc::new(false);
+        assert!(start.validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn export_section_created() {
+        let mut module = two_functions().build();
+        module.set_start_section(1);
+        assert!(RemapStart::new().translate_inplace(&mut module).unwrap());
+
+        let serialized = parity_wasm::serialize(module).unwrap();
+        let module = parity_wasm::deserialize_buffer::<Module>(&serialized).unwrap();
+        assert_eq!(module.export_section().unwrap().entries().len(), 1);
+    }
+
+    #[test]
+    fn no_start_unchanged() {
+        let mut module = two_functions().build();
+        assert!(!RemapStart::new().translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn existing_main_fails() {
+        let mut module = two_functions()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        module.set_start_section(1);
+        assert!(RemapStart::new().translate_inplace(&mut module).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use super::{section_id, ModuleTranslator};
+use parity_wasm::elements::{Module, Section};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Re-encodes the module through parity-wasm, so that the output does not depend on how the
+/// producing toolchain encoded it.
+#[derive(Default)]
+pub struct Repack {
+    /// Option to sort the sections into canonical order.
+    sort_sections: bool,
+}
+
+impl Repack {
+    pub fn new(sort_sections: bool) -> Self {
+        Repack { sort_sections }
+    }
+}
+
+/// Position of a section in canonical order: the known sections ordered by id, followed by the
+/// custom sections in the order they appear.
+fn canonical_position(section: &Section) -> u8 {
+    match section_id(section) {
+        0 => u8::MAX,
+        id => id,
+    }
+}
+
+impl ModuleTranslator for Repack {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        let buffer = parity_wasm::serialize(module.clone())
+            .map_err(|err| format!("Failed to serialize the module: {}", err))?;
+        let mut repacked: Module = parity_wasm::deserialize_buffer(&buffer)
+            .map_err(|err| format!("Failed to deserialize the module: {}", err))?;
+
+        if self.sort_sections {
+            // The sort is stable, so custom sections keep their relative order.
+            repacked.sections_mut().sort_by_key(canonical_position);
+        }
+
+        Ok(if repacked == *module {
+            None
+        } else {
+            Some(repacked)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use parity_wasm::elements::CustomSection;
+
+    fn unordered_module() -> Module {
+        let mut module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        module.sections_mut().insert(
+            0,
+            Section::Custom(CustomSection::new("producers".to_string(), vec![0x00])),
+        );
+        module.set_custom_section("name", vec![]);
+        module
+    }
+
+    #[test]
+    fn repack_is_idempotent() {
+        for sort_sections in &[false, true] {
+            let repacker = Repack::new(*sort_sections);
+            let mut once = unordered_module();
+            repacker.translate_inplace(&mut once).unwrap();
+            assert_eq!(repacker.translate(&once), Ok(None));
+            let twice = parity_wasm::deserialize_buffer::<Module>(
+                &parity_wasm::serialize(once.clone()).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(repacker.translate(&twice), Ok(None));
+        }
+    }
+
+    #[test]
+    fn sections_sorted() {
+        let module = Repack::new(true)
+            .translate(&unordered_module())
+            .unwrap()
+            .unwrap();
+        let positions: Vec<u8> = module.sections().iter().map(canonical_position).collect();
+        assert_eq!(positions, vec![1, 3, 7, 10, u8::MAX, u8::MAX]);
+        let names: Vec<&str> = module
+            .custom_sections()
+            .map(|custom| custom.name())
+            .collect();
+        assert_eq!(names, vec!["producers", "name"]);
+    }
+
+    #[test]
+    fn unsorted_sections_kept() {
+        let mut module = unordered_module();
+        assert_eq!(Repack::new(false).translate(&module), Ok(None));
+        assert!(!Repack::new(false).translate_inplace(&mut module).unwrap());
+        assert!(matches!(module.sections()[0], Section::Custom(_)));
+    }
+}
@@ -0,0 +1,146 @@
+use super::presets::Preset;
+use super::{translate_copy, ModulePreset, ModuleTranslator};
+
+use parity_wasm::elements::{MemoryType, Module, ResizableLimits};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Rewrites the page limits declared by the memories of the module, rather than rejecting them
+/// as `verifymemory` does. Imported memories are left alone, as their limits are what the host
+/// has to provide.
+pub struct SetMemoryLimits {
+    /// Number of pages every memory initially allocates, or None to keep it.
+    initial: Option<u32>,
+    /// Largest maximum number of pages a memory may declare. A larger maximum is clamped to it,
+    /// and a memory without one is given it.
+    maximum: u32,
+}
+
+impl ModulePreset for SetMemoryLimits {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        let (_, maximum) = preset
+            .memory_limits()
+            .ok_or_else(|| format!("Preset {} is not supported", preset))?;
+        Ok(SetMemoryLimits::new(None, maximum))
+    }
+}
+
+impl SetMemoryLimits {
+    pub fn new(initial: Option<u32>, maximum: u32) -> Self {
+        SetMemoryLimits { initial, maximum }
+    }
+
+    pub fn set_initial(&mut self, initial: u32) {
+        self.initial = Some(initial);
+    }
+
+    pub fn set_maximum(&mut self, maximum: u32) {
+        self.maximum = maximum;
+    }
+
+    /// Rewrites the limits of every memory defined by the module. Returns the limits of each
+    /// memory which changed, before and after. Fails if the initial size of a memory would
+    /// exceed its maximum.
+    pub fn set_limits(
+        &self,
+        module: &mut Module,
+    ) -> Result<Vec<(ResizableLimits, ResizableLimits)>, String> {
+        let memories = match module.memory_section_mut() {
+            Some(section) => section.entries_mut(),
+            None => return Ok(vec![]),
+        };
+        let mut rewritten = Vec::new();
+        for (index, memory) in memories.iter().enumerate() {
+            let before = memory.limits();
+            let initial = self.initial.unwrap_or_else(|| before.initial());
+            let maximum = before
+                .maximum()
+                .map_or(self.maximum, |maximum| maximum.min(self.maximum));
+            if initial > maximum {
+                return Err(format!(
+                    "memory {} would initially allocate {} pages, more than its maximum of {}",
+                    index, initial, maximum
+                ));
+            }
+            rewritten.push(MemoryType::new(initial, Some(maximum), before.shared()));
+        }
+
+        let mut changed = Vec::new();
+        for (memory, rewritten) in memories.iter_mut().zip(rewritten) {
+            if rewritten != *memory {
+                changed.push((*memory.limits(), *rewritten.limits()));
+                *memory = rewritten;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+impl ModuleTranslator for SetMemoryLimits {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        Ok(!self.set_limits(module)?.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+
+    fn module_with_memory(initial: u32, maximum: Option<u32>) -> Module {
+        builder::module()
+            .memory()
+            .with_min(initial)
+            .with_max(maximum)
+            .build()
+            .build()
+    }
+
+    fn limits(module: &Module) -> (u32, Option<u32>) {
+        let limits = module.memory_section().unwrap().entries()[0].limits();
+        (limits.initial(), limits.maximum())
+    }
+
+    #[test]
+    fn maximum_added() {
+        let mut module = module_with_memory(2, None);
+        let translator = SetMemoryLimits::with_preset(Preset::Ewasm).unwrap();
+        let changed = translator.set_limits(&mut module).unwrap();
+        assert_eq!(
+            changed,
+            vec![(
+                ResizableLimits::new(2, None),
+                ResizableLimits::new(2, Some(1024))
+            )]
+        );
+        assert_eq!(limits(&module), (2, Some(1024)));
+        assert!(!translator.translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn maximum_clamped() {
+        let mut module = module_with_memory(1, Some(4096));
+        assert!(SetMemoryLimits::new(Some(16), 256)
+            .translate_inplace(&mut module)
+            .unwrap());
+        assert_eq!(limits(&module), (16, Some(256)));
+
+        let mut module = module_with_memory(1, Some(8));
+        assert!(SetMemoryLimits::new(None, 256)
+            .set_limits(&mut module)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn initial_above_maximum_fails() {
+        let mut module = module_with_memory(1, Some(8));
+        assert!(SetMemoryLimits::new(Some(16), 256)
+            .translate_inplace(&mut module)
+            .is_err());
+        assert_eq!(limits(&module), (1, Some(8)));
+    }
+}
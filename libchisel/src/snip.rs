@@ -0,0 +1,639 @@
+use super::{translate_copy, ModuleTranslator};
+use parity_wasm::elements::{External, InitExpr, Instruction, Internal, Module, Section};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Removes the functions which cannot be reached from the exports, the start function or the
+/// table, along with the types, globals and data segments which are no longer used.
+#[derive(Default)]
+pub struct Snip {}
+
+/// What was removed from a module by `Snip`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snipped {
+    /// Number of functions removed, imported ones included.
+    pub functions: usize,
+    pub types: usize,
+    /// Number of globals removed, imported ones included.
+    pub globals: usize,
+    pub data_segments: usize,
+    /// Number of bytes by which the serialized module shrank.
+    pub bytes: usize,
+}
+
+impl Snipped {
+    /// Returns true if nothing was removed.
+    pub fn is_empty(&self) -> bool {
+        self.functions == 0 && self.types == 0 && self.globals == 0 && self.data_segments == 0
+    }
+}
+
+/// Items of the module which are referenced from its roots.
+struct Reachable {
+    functions: Vec<bool>,
+    types: Vec<bool>,
+    globals: Vec<bool>,
+    memory: bool,
+}
+
+impl Snip {
+    pub fn new() -> Self {
+        Snip {}
+    }
+
+    /// Removes everything unreachable from the module, renumbering the remaining functions,
+    /// types and globals wherever they are referenced. The `name` section is dropped if anything
+    /// is removed, as it would no longer match the indices.
+    pub fn snip(&self, module: &mut Module) -> Result<Snipped, String> {
+        let size_before = serialized_size(module)?;
+        let reachable = reachable(module)?;
+
+        let function_map = index_map(&reachable.functions);
+        let type_map = index_map(&reachable.types);
+        let global_map = index_map(&reachable.globals);
+        let mut snipped = Snipped {
+            functions: removed(&reachable.functions),
+            types: removed(&reachable.types),
+            globals: removed(&reachable.globals),
+            ..Default::default()
+        };
+        if !reachable.memory {
+            snipped.data_segments = module.data_section().map_or(0, |data| data.entries().len());
+        }
+        if snipped.is_empty() {
+            return Ok(snipped);
+        }
+
+        let imported_functions =
+            imported_count(module, |external| matches!(external, External::Function(_)));
+        let imported_globals =
+            imported_count(module, |external| matches!(external, External::Global(_)));
+
+        let mut kept = Vec::new();
+        for mut section in module.sections().iter().cloned() {
+            match section {
+                Section::Type(ref mut section) => {
+                    retain_indexed(section.types_mut(), 0, &reachable.types);
+                }
+                Section::Import(ref mut section) => {
+                    let (mut function, mut global) = (0, 0);
+                    section
+                        .entries_mut()
+                        .retain(|entry| match entry.external() {
+                            External::Function(_) => {
+                                function += 1;
+                                reachable.functions[function - 1]
+                            }
+                            External::Global(_) => {
+                                global += 1;
+                                reachable.globals[global - 1]
+                            }
+                            _ => true,
+                        });
+                    for entry in section.entries_mut() {
+                        if let External::Function(type_ref) = entry.external_mut() {
+                            *type_ref = remap(&type_map, *type_ref)?;
+                        }
+                    }
+                }
+                Section::Function(ref mut section) => {
+                    retain_indexed(
+                        section.entries_mut(),
+                        imported_functions,
+                        &reachable.functions,
+                    );
+                    for func in section.entries_mut() {
+                        *func.type_ref_mut() = remap(&type_map, func.type_ref())?;
+                    }
+                }
+                Section::Global(ref mut section) => {
+                    retain_indexed(section.entries_mut(), imported_globals, &reachable.globals);
+                    for entry in section.entries_mut() {
+                        remap_init_expr(entry.init_expr_mut(), &global_map)?;
+                    }
+                }
+                Section::Export(ref mut section) => {
+                    for entry in section.entries_mut() {
+                        match entry.internal_mut() {
+                            Internal::Function(index) => *index = remap(&function_map, *index)?,
+                            Internal::Global(index) => *index = remap(&global_map, *index)?,
+                            _ => {}
+                        }
+                    }
+                }
+                Section::Start(ref mut index) => *index = remap(&function_map, *index)?,
+                Section::Element(ref mut section) => {
+                    for segment in section.entries_mut() {
+                        if let Some(offset) = segment.offset_mut() {
+                            remap_init_expr(offset, &global_map)?;
+                        }
+                        for member in segment.members_mut() {
+                            *member = remap(&function_map, *member)?;
+                        }
+                    }
+                }
+                Section::Code(ref mut section) => {
+                    retain_indexed(
+                        section.bodies_mut(),
+                        imported_functions,
+                        &reachable.functions,
+                    );
+                    for body in section.bodies_mut() {
+                        for instruction in body.code_mut().elements_mut() {
+                            remap_instruction(instruction, &function_map, &type_map, &global_map)?;
+                        }
+                    }
+                }
+                Section::Data(ref mut section) => {
+                    if !reachable.memory {
+                        section.entries_mut().clear();
+                    }
+                    for segment in section.entries_mut() {
+                        if let Some(offset) = segment.offset_mut() {
+                            remap_init_expr(offset, &global_map)?;
+                        }
+                    }
+                }
+                Section::Name(_) => continue,
+                Section::Custom(ref custom) if custom.name() == "name" => continue,
+                _ => {}
+            }
+            kept.push(section);
+        }
+        *module.sections_mut() = kept;
+
+        snipped.bytes = size_before.saturating_sub(serialized_size(module)?);
+        Ok(snipped)
+    }
+}
+
+impl ModuleTranslator for Snip {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        Ok(!self.snip(module)?.is_empty())
+    }
+}
+
+fn serialized_size(module: &Module) -> Result<usize, String> {
+    parity_wasm::serialize(module.clone())
+        .map(|buffer| buffer.len())
+        .map_err(|err| format!("Failed to serialize the module: {}", err))
+}
+
+/// Returns the number of imports of the matching kind.
+fn imported_count<F: Fn(&External) -> bool>(module: &Module, is_kind: F) -> usize {
+    module.import_section().map_or(0, |section| {
+        section
+            .entries()
+            .iter()
+            .filter(|entry| is_kind(entry.external()))
+            .count()
+    })
+}
+
+/// Walks the call graph from the exported functions, the start function and the members of the
+/// element segments, marking every function, type and global used on the way.
+fn reachable(module: &Module) -> Result<Reachable, String> {
+    let imports = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries());
+    let functions = module
+        .function_section()
+        .map_or(&[][..], |section| section.entries());
+    let bodies = module
+        .code_section()
+        .map_or(&[][..], |section| section.bodies());
+    if functions.len() != bodies.len() {
+        return Err("function and code section have inconsistent lengths".to_string());
+    }
+
+    // Type of every function in the index space, imported ones first.
+    let function_types: Vec<u32> = imports
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Function(type_ref) => Some(*type_ref),
+            _ => None,
+        })
+        .chain(functions.iter().map(|func| func.type_ref()))
+        .collect();
+    let imported_functions = function_types.len() - functions.len();
+    let global_count = imported_count(module, |external| matches!(external, External::Global(_)))
+        + module
+            .global_section()
+            .map_or(0, |section| section.entries().len());
+
+    let mut reachable = Reachable {
+        functions: vec![false; function_types.len()],
+        types: vec![
+            false;
+            module
+                .type_section()
+                .map_or(0, |section| section.types().len())
+        ],
+        globals: vec![false; global_count],
+        memory: imports
+            .iter()
+            .any(|entry| matches!(entry.external(), External::Memory(_))),
+    };
+
+    let mut pending: Vec<u32> = Vec::new();
+    let mut init_exprs: Vec<&InitExpr> = Vec::new();
+    if let Some(section) = module.export_section() {
+        for entry in section.entries() {
+            match *entry.internal() {
+                Internal::Function(index) => pending.push(index),
+                Internal::Global(index) => mark(&mut reachable.globals, index, "global")?,
+                Internal::Memory(_) => reachable.memory = true,
+                Internal::Table(_) => {}
+            }
+        }
+    }
+    if let Some(index) = module.start_section() {
+        pending.push(index);
+    }
+    if let Some(section) = module.elements_section() {
+        for segment in section.entries() {
+            pending.extend_from_slice(segment.members());
+            init_exprs.extend(segment.offset());
+        }
+    }
+    if let Some(section) = module.data_section() {
+        init_exprs.extend(section.entries().iter().filter_map(|s| s.offset().as_ref()));
+    }
+    for expr in init_exprs {
+        mark_init_expr(expr, &mut reachable.globals)?;
+    }
+
+    while let Some(index) = pending.pop() {
+        let seen = reachable
+            .functions
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| format!("function {} does not exist", index))?;
+        if seen {
+            continue;
+        }
+        reachable.functions[index as usize] = true;
+        mark(&mut reachable.types, function_types[index as usize], "type")?;
+        let body = match (index as usize).checked_sub(imported_functions) {
+            Some(defined) => &bodies[defined],
+            None => continue,
+        };
+        for instruction in body.code().elements() {
+            match *instruction {
+                Instruction::Call(callee) => pending.push(callee),
+                Instruction::CallIndirect(type_ref, _) => {
+                    mark(&mut reachable.types, type_ref, "type")?
+                }
+                Instruction::GetGlobal(global) | Instruction::SetGlobal(global) => {
+                    mark(&mut reachable.globals, global, "global")?
+                }
+                _ if is_memory_instruction(instruction) => reachable.memory = true,
+                _ => {}
+            }
+        }
+    }
+
+    // Defined globals may be initialized from imported ones.
+    if let Some(section) = module.global_section() {
+        let imported_globals = global_count - section.entries().len();
+        for (index, entry) in section.entries().iter().enumerate() {
+            if reachable.globals[imported_globals + index] {
+                mark_init_expr(entry.init_expr(), &mut reachable.globals)?;
+            }
+        }
+    }
+    Ok(reachable)
+}
+
+fn mark(used: &mut [bool], index: u32, kind: &str) -> Result<(), String> {
+    match used.get_mut(index as usize) {
+        Some(used) => {
+            *used = true;
+            Ok(())
+        }
+        None => Err(format!("{} {} does not exist", kind, index)),
+    }
+}
+
+fn mark_init_expr(expr: &InitExpr, globals: &mut [bool]) -> Result<(), String> {
+    for instruction in expr.code() {
+        if let Instruction::GetGlobal(global) = *instruction {
+            mark(globals, global, "global")?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if the instruction reads or writes the memory, or depends on its contents.
+fn is_memory_instruction(instruction: &Instruction) -> bool {
+    use parity_wasm::elements::Instruction::*;
+
+    matches!(
+        instruction,
+        I32Load(_, _)
+            | I64Load(_, _)
+            | F32Load(_, _)
+            | F64Load(_, _)
+            | I32Load8S(_, _)
+            | I32Load8U(_, _)
+            | I32Load16S(_, _)
+            | I32Load16U(_, _)
+            | I64Load8S(_, _)
+            | I64Load8U(_, _)
+            | I64Load16S(_, _)
+            | I64Load16U(_, _)
+            | I64Load32S(_, _)
+            | I64Load32U(_, _)
+            | I32Store(_, _)
+            | I64Store(_, _)
+            | F32Store(_, _)
+            | F64Store(_, _)
+            | I32Store8(_, _)
+            | I32Store16(_, _)
+            | I64Store8(_, _)
+            | I64Store16(_, _)
+            | I64Store32(_, _)
+            | CurrentMemory(_)
+            | GrowMemory(_)
+            | V128Load(_)
+            | V128Store(_)
+            | MemoryInit(_)
+            | MemoryDrop(_)
+            | MemoryCopy
+            | MemoryFill
+            | AtomicWake(_)
+            | I32AtomicWait(_)
+            | I64AtomicWait(_)
+            | I32AtomicLoad(_)
+            | I64AtomicLoad(_)
+            | I32AtomicLoad8u(_)
+            | I32AtomicLoad16u(_)
+            | I64AtomicLoad8u(_)
+            | I64AtomicLoad16u(_)
+            | I64AtomicLoad32u(_)
+            | I32AtomicStore(_)
+            | I64AtomicStore(_)
+            | I32AtomicStore8u(_)
+            | I32AtomicStore16u(_)
+            | I64AtomicStore8u(_)
+            | I64AtomicStore16u(_)
+            | I64AtomicStore32u(_)
+            | I32AtomicRmwAdd(_)
+            | I64AtomicRmwAdd(_)
+            | I32AtomicRmwAdd8u(_)
+            | I32AtomicRmwAdd16u(_)
+            | I64AtomicRmwAdd8u(_)
+            | I64AtomicRmwAdd16u(_)
+            | I64AtomicRmwAdd32u(_)
+            | I32AtomicRmwSub(_)
+            | I64AtomicRmwSub(_)
+            | I32AtomicRmwSub8u(_)
+            | I32AtomicRmwSub16u(_)
+            | I64AtomicRmwSub8u(_)
+            | I64AtomicRmwSub16u(_)
+            | I64AtomicRmwSub32u(_)
+            | I32AtomicRmwAnd(_)
+            | I64AtomicRmwAnd(_)
+            | I32AtomicRmwAnd8u(_)
+            | I32AtomicRmwAnd16u(_)
+            | I64AtomicRmwAnd8u(_)
+            | I64AtomicRmwAnd16u(_)
+            | I64AtomicRmwAnd32u(_)
+            | I32AtomicRmwOr(_)
+            | I64AtomicRmwOr(_)
+            | I32AtomicRmwOr8u(_)
+            | I32AtomicRmwOr16u(_)
+            | I64AtomicRmwOr8u(_)
+            | I64AtomicRmwOr16u(_)
+            | I64AtomicRmwOr32u(_)
+            | I32AtomicRmwXor(_)
+            | I64AtomicRmwXor(_)
+            | I32AtomicRmwXor8u(_)
+            | I32AtomicRmwXor16u(_)
+            | I64AtomicRmwXor8u(_)
+            | I64AtomicRmwXor16u(_)
+            | I64AtomicRmwXor32u(_)
+            | I32AtomicRmwXchg(_)
+            | I64AtomicRmwXchg(_)
+            | I32AtomicRmwXchg8u(_)
+            | I32AtomicRmwXchg16u(_)
+            | I64AtomicRmwXchg8u(_)
+            | I64AtomicRmwXchg16u(_)
+            | I64AtomicRmwXchg32u(_)
+            | I32AtomicRmwCmpxchg(_)
+            | I64AtomicRmwCmpxchg(_)
+            | I32AtomicRmwCmpxchg8u(_)
+            | I32AtomicRmwCmpxchg16u(_)
+            | I64AtomicRmwCmpxchg8u(_)
+            | I64AtomicRmwCmpxchg16u(_)
+            | I64AtomicRmwCmpxchg32u(_)
+    )
+}
+
+/// Maps each old index to its new one, or None if the item is removed.
+fn index_map(used: &[bool]) -> Vec<Option<u32>> {
+    let mut next = 0;
+    used.iter()
+        .map(|used| {
+            if *used {
+                next += 1;
+                Some(next - 1)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn removed(used: &[bool]) -> usize {
+    used.iter().filter(|used| !**used).count()
+}
+
+fn remap(map: &[Option<u32>], index: u32) -> Result<u32, String> {
+    map.get(index as usize)
+        .cloned()
+        .flatten()
+        .ok_or_else(|| format!("index {} refers to a removed item", index))
+}
+
+/// Keeps the entries whose index, offset by the number of imported ones, is used.
+fn retain_indexed<T>(entries: &mut Vec<T>, offset: usize, used: &[bool]) {
+    let mut index = offset;
+    entries.retain(|_| {
+        index += 1;
+        used[index - 1]
+    });
+}
+
+fn remap_init_expr(expr: &mut InitExpr, global_map: &[Option<u32>]) -> Result<(), String> {
+    for instruction in expr.code_mut() {
+        if let Instruction::GetGlobal(global) = instruction {
+            *global = remap(global_map, *global)?;
+        }
+    }
+    Ok(())
+}
+
+fn remap_instruction(
+    instruction: &mut Instruction,
+    function_map: &[Option<u32>],
+    type_map: &[Option<u32>],
+    global_map: &[Option<u32>],
+) -> Result<(), String> {
+    match instruction {
+        Instruction::Call(callee) => *callee = remap(function_map, *callee)?,
+        Instruction::CallIndirect(type_ref, _) => *type_ref = remap(type_map, *type_ref)?,
+        Instruction::GetGlobal(global) | Instruction::SetGlobal(global) => {
+            *global = remap(global_map, *global)?
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use parity_wasm::elements::{Instructions, ValueType};
+
+    /// A module exporting `main`, which calls `helper`, alongside an unreachable function with a
+    /// signature of its own which uses a global.
+    fn module_with_unreachable() -> Module {
+        builder::module()
+            .global()
+            .value_type()
+            .i32()
+            .mutable()
+            .init_expr(Instruction::I32Const(0))
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .with_instructions(Instructions::new(vec![
+                Instruction::Call(2),
+                Instruction::End,
+            ]))
+            .build()
+            .build()
+            .function()
+            .signature()
+            .with_param(ValueType::I64)
+            .build()
+            .body()
+            .with_instructions(Instructions::new(vec![
+                Instruction::I32Const(1),
+                Instruction::SetGlobal(0),
+                Instruction::End,
+            ]))
+            .build()
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn unreachable_function_removed() {
+        let mut module = module_with_unreachable();
+        let snipped = Snip::new().snip(&mut module).unwrap();
+        assert_eq!(snipped.functions, 1);
+        assert_eq!(snipped.types, 1);
+        assert_eq!(snipped.globals, 1);
+        assert!(snipped.bytes > 0);
+
+        assert_eq!(module.function_section().unwrap().entries().len(), 2);
+        assert_eq!(module.type_section().unwrap().types().len(), 1);
+        assert_eq!(module.global_section().unwrap().entries().len(), 0);
+        // The helper is renumbered from 2 to 1.
+        assert_eq!(
+            module.code_section().unwrap().bodies()[0].code().elements()[0],
+            Instruction::Call(1)
+        );
+        assert!(parity_wasm::serialize(module).is_ok());
+    }
+
+    #[test]
+    fn reachable_module_unchanged() {
+        let mut module = module_with_unreachable();
+        Snip::new().translate_inplace(&mut module).unwrap();
+        let snipped = module.clone();
+        assert!(!Snip::new().translate_inplace(&mut module).unwrap());
+        assert_eq!(module, snipped);
+    }
+
+    #[test]
+    fn start_and_table_kept() {
+        let mut module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .table()
+            .with_min(1)
+            .with_element(0, vec![2])
+            .build()
+            .build();
+        module.sections_mut().push(Section::Start(2));
+        let snipped = Snip::new().snip(&mut module).unwrap();
+        assert_eq!(snipped.functions, 2);
+        assert_eq!(module.start_section(), Some(0));
+        assert_eq!(
+            module.elements_section().unwrap().entries()[0].members(),
+            [0]
+        );
+    }
+
+    #[test]
+    fn unused_data_removed() {
+        let mut module = builder::module()
+            .memory()
+            .with_min(1)
+            .with_data(0, vec![1, 2, 3])
+            .build()
+            .build();
+        let snipped = Snip::new().snip(&mut module).unwrap();
+        assert_eq!(snipped.data_segments, 1);
+        assert!(module.data_section().unwrap().entries().is_empty());
+
+        let mut module = builder::module()
+            .memory()
+            .with_min(1)
+            .with_data(0, vec![1, 2, 3])
+            .build()
+            .export()
+            .field("memory")
+            .internal()
+            .memory(0)
+            .build()
+            .build();
+        assert!(!Snip::new().translate_inplace(&mut module).unwrap());
+    }
+}
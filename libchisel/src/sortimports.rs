@@ -0,0 +1,254 @@
+use super::{translate_copy, ModuleTranslator};
+use parity_wasm::elements::{
+    External, ImportEntry, InitExpr, Instruction, Internal, Module, Section,
+};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Sorts the imports by module and then by field, so that the order of the imports does not
+/// depend on the toolchain. As imported functions and globals come first in their index spaces,
+/// every reference to them is renumbered.
+#[derive(Default)]
+pub struct SortImports {}
+
+impl SortImports {
+    pub fn new() -> Self {
+        SortImports {}
+    }
+
+    /// Sorts the imports, renumbering the functions and globals they define wherever they are
+    /// referenced. The `name` section is dropped if the order changes, as it would no longer
+    /// match the indices. Returns true if the order changed.
+    pub fn sort_imports(&self, module: &mut Module) -> Result<bool, String> {
+        let entries = match module.import_section() {
+            Some(section) => section.entries(),
+            None => return Ok(false),
+        };
+        let mut sorted: Vec<&ImportEntry> = entries.iter().collect();
+        sorted.sort_by(|a, b| (a.module(), a.field()).cmp(&(b.module(), b.field())));
+        if sorted.iter().zip(entries).all(|(a, b)| a == &b) {
+            return Ok(false);
+        }
+
+        let function_map = index_map(entries, &sorted, |external| {
+            matches!(external, External::Function(_))
+        });
+        let global_map = index_map(entries, &sorted, |external| {
+            matches!(external, External::Global(_))
+        });
+        let sorted: Vec<ImportEntry> = sorted.into_iter().cloned().collect();
+
+        let mut kept = Vec::new();
+        for mut section in module.sections().iter().cloned() {
+            match section {
+                Section::Import(ref mut section) => *section.entries_mut() = sorted.clone(),
+                Section::Global(ref mut section) => {
+                    for entry in section.entries_mut() {
+                        remap_init_expr(entry.init_expr_mut(), &global_map);
+                    }
+                }
+                Section::Export(ref mut section) => {
+                    for entry in section.entries_mut() {
+                        match entry.internal_mut() {
+                            Internal::Function(index) => *index = remap(&function_map, *index),
+                            Internal::Global(index) => *index = remap(&global_map, *index),
+                            _ => {}
+                        }
+                    }
+                }
+                Section::Start(ref mut index) => *index = remap(&function_map, *index),
+                Section::Element(ref mut section) => {
+                    for segment in section.entries_mut() {
+                        if let Some(offset) = segment.offset_mut() {
+                            remap_init_expr(offset, &global_map);
+                        }
+                        for member in segment.members_mut() {
+                            *member = remap(&function_map, *member);
+                        }
+                    }
+                }
+                Section::Code(ref mut section) => {
+                    for body in section.bodies_mut() {
+                        for instruction in body.code_mut().elements_mut() {
+                            match instruction {
+                                Instruction::Call(callee) => {
+                                    *callee = remap(&function_map, *callee)
+                                }
+                                Instruction::GetGlobal(global) | Instruction::SetGlobal(global) => {
+                                    *global = remap(&global_map, *global)
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                Section::Data(ref mut section) => {
+                    for segment in section.entries_mut() {
+                        if let Some(offset) = segment.offset_mut() {
+                            remap_init_expr(offset, &global_map);
+                        }
+                    }
+                }
+                Section::Name(_) => continue,
+                Section::Custom(ref custom) if custom.name() == "name" => continue,
+                _ => {}
+            }
+            kept.push(section);
+        }
+        *module.sections_mut() = kept;
+        Ok(true)
+    }
+}
+
+impl ModuleTranslator for SortImports {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        self.sort_imports(module)
+    }
+}
+
+/// Maps the old index of each import of a kind to its index once sorted.
+fn index_map<F: Fn(&External) -> bool>(
+    entries: &[ImportEntry],
+    sorted: &[&ImportEntry],
+    is_kind: F,
+) -> Vec<u32> {
+    let old: Vec<&ImportEntry> = entries
+        .iter()
+        .filter(|entry| is_kind(entry.external()))
+        .collect();
+    let new: Vec<&ImportEntry> = sorted
+        .iter()
+        .cloned()
+        .filter(|entry| is_kind(entry.external()))
+        .collect();
+    old.iter()
+        .map(|entry| {
+            new.iter()
+                .position(|sorted| std::ptr::eq(*sorted, *entry))
+                .expect("sorted imports are a permutation") as u32
+        })
+        .collect()
+}
+
+/// Renumbers an imported item, leaving the items defined by the module, which follow the
+/// imported ones, at the same index.
+fn remap(map: &[u32], index: u32) -> u32 {
+    map.get(index as usize).cloned().unwrap_or(index)
+}
+
+fn remap_init_expr(expr: &mut InitExpr, global_map: &[u32]) {
+    for instruction in expr.code_mut() {
+        if let Instruction::GetGlobal(global) = instruction {
+            *global = remap(global_map, *global);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    fn module_with_unsorted_imports() -> Module {
+        FromWat::new(
+            r#"(module
+                (import "env" "log" (func $log (param i32)))
+                (import "ethereum" "revert" (func $revert (param i32 i32)))
+                (import "env" "base" (global $base i32))
+                (import "ethereum" "getGasLeft" (func $gas (result i64)))
+                (import "env" "abort" (func $abort))
+                (import "env" "offset" (global $offset i32))
+                (global $sum i32 (global.get $base))
+                (table 2 funcref)
+                (elem (i32.const 0) $revert $helper)
+                (func $helper
+                    (call $log (global.get $offset))
+                    (drop (call $gas))
+                    (call $revert (global.get $base) (i32.const 0)))
+                (func $main
+                    (call $helper)
+                    (call $abort))
+                (export "main" (func $main))
+                (export "sum" (global $sum))
+                (start $helper))"#,
+        )
+        .create()
+        .unwrap()
+    }
+
+    fn import_names(module: &Module) -> Vec<String> {
+        module
+            .import_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| format!("{}::{}", entry.module(), entry.field()))
+            .collect()
+    }
+
+    #[test]
+    fn imports_sorted() {
+        let mut module = module_with_unsorted_imports();
+        assert!(SortImports::new().translate_inplace(&mut module).unwrap());
+        assert_eq!(
+            import_names(&module),
+            [
+                "env::abort",
+                "env::base",
+                "env::log",
+                "env::offset",
+                "ethereum::getGasLeft",
+                "ethereum::revert",
+            ]
+        );
+
+        // Functions were log, revert, getGasLeft, abort and are now abort, log, getGasLeft,
+        // revert. Globals were base, offset and remain in that order.
+        let code = module.code_section().unwrap().bodies();
+        assert_eq!(
+            code[0].code().elements(),
+            &[
+                Instruction::GetGlobal(1),
+                Instruction::Call(1),
+                Instruction::Call(2),
+                Instruction::Drop,
+                Instruction::GetGlobal(0),
+                Instruction::I32Const(0),
+                Instruction::Call(3),
+                Instruction::End,
+            ]
+        );
+        assert_eq!(
+            code[1].code().elements(),
+            &[Instruction::Call(4), Instruction::Call(0), Instruction::End]
+        );
+        assert_eq!(
+            module.elements_section().unwrap().entries()[0].members(),
+            &[3, 4]
+        );
+        assert_eq!(module.start_section(), Some(4));
+        assert!(module.names_section().is_none());
+
+        let serialized = parity_wasm::serialize(module.clone()).unwrap();
+        assert!(wasmparser::validate(&serialized).is_ok());
+        assert!(!SortImports::new().translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn sorted_imports_unchanged() {
+        let mut module = FromWat::new(
+            r#"(module
+                (import "env" "abort" (func))
+                (import "env" "log" (func (param i32))))"#,
+        )
+        .create()
+        .unwrap();
+        let before = module.clone();
+        assert!(!SortImports::new().translate_inplace(&mut module).unwrap());
+        assert_eq!(module, before);
+    }
+}
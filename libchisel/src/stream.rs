@@ -0,0 +1,215 @@
+//! Validation of a module read incrementally, for binaries too large to be held in memory at
+//! once.
+//!
+//! Only the sections declaring the interface of the module are kept: its types, imports,
+//! functions, tables, memories, globals, exports and start function. The code, element, data and
+//! custom sections, which make up most of a binary, are read through and dropped a chunk at a
+//! time, so that memory use is bounded by the size of the interface rather than of the binary.
+//! This is enough for the validators whose `ModuleValidator::interface_only` is true, such as
+//! `VerifyImports` and `VerifyExports`. Others need the whole module, read into a buffer.
+
+use std::io::{self, Read};
+
+use parity_wasm::elements::{deserialize_buffer, Module, Section};
+
+use super::{ModuleValidator, ValidationResult, SECTION_NAMES};
+
+/// Reads the sections of the module declaring its interface, skipping the others.
+pub fn read_interface<R: Read>(mut reader: R) -> Result<Module, String> {
+    let mut header = [0; 8];
+    reader
+        .read_exact(&mut header)
+        .map_err(|err| read_error(err, "header", 0))?;
+    if header[..4] != *b"\0asm" {
+        return Err(
+            "not a wasm binary, as it does not start with the magic number `\\0asm`".to_string(),
+        );
+    }
+    let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    if version != 1 {
+        return Err(format!("unsupported wasm version {}, expected 1", version));
+    }
+
+    let mut sections = Vec::new();
+    let mut offset = 8;
+    while let Some(id) = read_byte(&mut reader).map_err(|err| read_error(err, "section", offset))? {
+        let name = SECTION_NAMES.get(id as usize).map_or_else(
+            || format!("unknown section {}", id),
+            |name| format!("{} section", name),
+        );
+        // The section is deserialized from its header and contents, as they are in the binary.
+        let mut section = vec![id];
+        let size = read_var_u32(&mut reader, &mut section)
+            .map_err(|err| read_error(err, &name, offset))?;
+        let end = offset + section.len() as u64 + size as u64;
+
+        if (1..=8).contains(&id) {
+            let read = reader
+                .by_ref()
+                .take(size as u64)
+                .read_to_end(&mut section)
+                .map_err(|err| read_error(err, &name, offset))?;
+            if read < size as usize {
+                return Err(truncated(&name, offset));
+            }
+            sections.push(
+                deserialize_buffer::<Section>(&section)
+                    .map_err(|err| format!("{} in the {} at offset {}", err, name, offset))?,
+            );
+        } else {
+            let skipped = io::copy(&mut reader.by_ref().take(size as u64), &mut io::sink())
+                .map_err(|err| read_error(err, &name, offset))?;
+            if skipped < size as u64 {
+                return Err(truncated(&name, offset));
+            }
+        }
+        offset = end;
+    }
+    Ok(Module::new(sections))
+}
+
+/// Runs the validators on the module read from the reader, keeping only its interface in
+/// memory. Fails without reading anything if a validator needs the whole module, in which case
+/// the binary has to be read into a buffer and deserialized instead.
+pub fn validate_reader<R: Read>(
+    reader: R,
+    validators: &[&dyn ModuleValidator],
+) -> Result<Vec<ValidationResult>, String> {
+    if let Some(index) = validators.iter().position(|v| !v.interface_only()) {
+        return Err(format!(
+            "validator {} needs the whole module, which is not kept when streaming",
+            index
+        ));
+    }
+    let module = read_interface(reader)?;
+    validators.iter().map(|v| v.validate(&module)).collect()
+}
+
+/// Reads a single byte, or None at the end of the binary.
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Reads an unsigned LEB128 integer of at most 32 bits, appending its bytes to `bytes`.
+fn read_var_u32<R: Read>(reader: &mut R, bytes: &mut Vec<u8>) -> io::Result<u32> {
+    let mut value: u32 = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = read_byte(reader)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        bytes.push(byte);
+        // The fifth byte only holds the top 4 bits, anything above would overflow.
+        if shift == 28 && byte & 0x70 != 0 {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(io::ErrorKind::InvalidData.into())
+}
+
+fn read_error(err: io::Error, name: &str, offset: u64) -> String {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => truncated(name, offset),
+        _ => format!(
+            "Failed to read the binary in the {} at offset {}: {}",
+            name, offset, err
+        ),
+    }
+}
+
+fn truncated(name: &str, offset: u64) -> String {
+    format!("the binary ends within the {} at offset {}", name, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use verifyexports::VerifyExports;
+    use verifyimports::VerifyImports;
+    use verifylimits::VerifyLimits;
+    use {ModuleCreator, ModulePreset, Preset};
+
+    /// Reads a single byte at a time, as a slow pipe would.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((byte, rest)), Some(out)) => {
+                    *out = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn sample() -> (Module, Vec<u8>) {
+        let module = FromWat::new(
+            r#"(module
+                (import "ethereum" "useGas" (func (param i64)))
+                (memory (export "memory") 1)
+                (func (export "main") (call 0 (i64.const 1)))
+                (data (i32.const 0) "payload"))"#,
+        )
+        .create()
+        .unwrap();
+        let mut binary = parity_wasm::serialize(module.clone()).unwrap();
+        binary.extend_from_slice(b"\0\x05\x04name");
+        (module, binary)
+    }
+
+    #[test]
+    fn interface_read_incrementally() {
+        let (module, binary) = sample();
+        let interface = read_interface(Trickle(&binary)).unwrap();
+        assert_eq!(interface.import_section(), module.import_section());
+        assert_eq!(interface.export_section(), module.export_section());
+        assert!(interface.code_section().is_none());
+        assert!(interface.data_section().is_none());
+        assert_eq!(interface.custom_sections().count(), 0);
+    }
+
+    #[test]
+    fn results_agree_with_module() {
+        let (module, binary) = sample();
+        let imports = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        let exports = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        let results = validate_reader(&binary[..], &[&imports, &exports]).unwrap();
+        assert_eq!(results[0], imports.validate(&module).unwrap());
+        assert_eq!(results[1], exports.validate(&module).unwrap());
+        assert!(results.iter().all(|result| result.passed()));
+
+        let limits = VerifyLimits::with_preset(Preset::Ewasm).unwrap();
+        assert!(validate_reader(&binary[..], &[&imports, &limits]).is_err());
+    }
+
+    #[test]
+    fn malformed_binary_described() {
+        let (_, binary) = sample();
+        assert_eq!(
+            read_interface(&binary[..binary.len() - 3]).unwrap_err(),
+            format!(
+                "the binary ends within the custom section at offset {}",
+                binary.len() - 7
+            )
+        );
+        assert!(read_interface(&b"\0asm\x01\0\0\0\x01\x05"[..]).is_err());
+        assert!(read_interface(&b"not wasm"[..]).is_err());
+        assert_eq!(
+            read_interface(&b"\0asm\x02\0\0\0"[..]).unwrap_err(),
+            "unsupported wasm version 2, expected 1"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Reading of the binary with wasmparser, for validators which only inspect some sections of the
+//! module and need not deserialize all of it.
+
+use parity_wasm::elements::{deserialize_buffer, Module, Section, Serialize, VarUint32};
+use wasmparser::{Chunk, Parser, Payload};
+
+/// Calls `visit` with every payload of the binary in order. The function bodies of the code
+/// section are skipped unless `with_code` is set.
+pub(crate) fn for_each_payload<'a, F>(
+    binary: &'a [u8],
+    with_code: bool,
+    mut visit: F,
+) -> Result<(), String>
+where
+    F: FnMut(Payload<'a>) -> Result<(), String>,
+{
+    let mut parser = Parser::new(0);
+    let mut offset = 0;
+    loop {
+        let payload = match parser.parse(&binary[offset..], true).map_err(parse_error)? {
+            Chunk::Parsed { consumed, payload } => {
+                offset += consumed;
+                payload
+            }
+            Chunk::NeedMoreData(_) => unreachable!("the whole binary is given at once"),
+        };
+        match payload {
+            Payload::CodeSectionStart { size, .. } if !with_code => {
+                parser.skip_section();
+                offset += size as usize;
+            }
+            Payload::End(_) => return Ok(()),
+            payload => visit(payload)?,
+        }
+    }
+}
+
+pub(crate) fn parse_error(err: wasmparser::BinaryReaderError) -> String {
+    format!("Failed to parse the binary: {}", err)
+}
+
+/// Deserializes only the sections of the binary which declare the interface of the module: its
+/// types, imports, functions, tables, memories, globals and exports. The code, data and custom
+/// sections, which make up most of a binary, are skipped without being read.
+pub(crate) fn interface_module(binary: &[u8]) -> Result<Module, String> {
+    let mut sections = Vec::new();
+    for_each_payload(binary, false, |payload| {
+        match payload.as_section() {
+            Some((id, range)) if (1..=7).contains(&id) => {
+                let contents = &binary[range.start as usize..range.end as usize];
+                let mut section = vec![id];
+                VarUint32::from(contents.len())
+                    .serialize(&mut section)
+                    .map_err(|err| err.to_string())?;
+                section.extend_from_slice(contents);
+                sections.push(
+                    deserialize_buffer::<Section>(&section)
+                        .map_err(|err| format!("Failed to deserialize the module: {}", err))?,
+                );
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(Module::new(sections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    #[test]
+    fn interface_sections_kept() {
+        let module = FromWat::new(
+            r#"(module
+                (import "env" "abort" (func))
+                (memory 1)
+                (global i32 (i32.const 0))
+                (func (export "main") (call 0))
+                (data (i32.const 0) "payload"))"#,
+        )
+        .create()
+        .unwrap();
+        let binary = parity_wasm::serialize(module.clone()).unwrap();
+
+        let interface = interface_module(&binary).unwrap();
+        assert_eq!(interface.import_section(), module.import_section());
+        assert_eq!(interface.function_section(), module.function_section());
+        assert_eq!(interface.export_section(), module.export_section());
+        assert_eq!(interface.global_section(), module.global_section());
+        assert!(interface.code_section().is_none());
+        assert!(interface.data_section().is_none());
+    }
+
+    #[test]
+    fn malformed_binary_fails() {
+        assert!(interface_module(b"\0asm\x01\0\0\0\x01\x05").is_err());
+        assert!(interface_module(b"not wasm").is_err());
+    }
+}
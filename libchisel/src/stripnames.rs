@@ -0,0 +1,74 @@
+use super::{translate_copy, ModuleTranslator};
+use parity_wasm::elements::{Module, Section};
+
+/// Struct on which ModuleTranslator is implemented.
+/// Removes the `name` section holding the debugging names of the module, its functions and their
+/// locals, whether or not parity-wasm has parsed it. Other custom sections are kept.
+#[derive(Default)]
+pub struct StripNames {}
+
+impl StripNames {
+    pub fn new() -> Self {
+        StripNames {}
+    }
+}
+
+/// Returns true if the section holds debugging names, either parsed or as raw bytes.
+fn is_name_section(section: &Section) -> bool {
+    match section {
+        Section::Name(_) => true,
+        Section::Custom(custom) => custom.name() == "name",
+        _ => false,
+    }
+}
+
+impl ModuleTranslator for StripNames {
+    /// Returns true if the module had debugging names.
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        let before = module.sections().len();
+        module
+            .sections_mut()
+            .retain(|section| !is_name_section(section));
+        Ok(module.sections().len() != before)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    fn module_with_names() -> Module {
+        FromWat::new("(module (func $main (local $counter i32)) (export \"main\" (func $main)))")
+            .create()
+            .unwrap()
+    }
+
+    #[test]
+    fn raw_names_stripped() {
+        let mut module = module_with_names();
+        module.set_custom_section("producers", vec![]);
+        assert!(StripNames::new().translate_inplace(&mut module).unwrap());
+        let names: Vec<&str> = module
+            .custom_sections()
+            .map(|custom| custom.name())
+            .collect();
+        assert_eq!(names, vec!["producers"]);
+        assert_eq!(StripNames::new().translate(&module), Ok(None));
+        assert!(!StripNames::new().translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn parsed_names_stripped() {
+        let mut module = module_with_names().parse_names().map_err(|_| ()).unwrap();
+        assert!(module.names_section().is_some());
+        assert!(StripNames::new().translate_inplace(&mut module).unwrap());
+        assert!(module.names_section().is_none());
+        assert!(parity_wasm::serialize(module).is_ok());
+    }
+}
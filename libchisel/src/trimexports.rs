@@ -1,6 +1,5 @@
 use super::*;
 
-use parity_wasm::builder::*;
 use parity_wasm::elements::*;
 
 /// Struct containing a list of valid exports.
@@ -27,32 +26,38 @@ impl ExportWhitelist {
         }
     }
 
-    fn with_preset(preset: &str) -> Result<Self, ()> {
-        match preset {
-            "ewasm" => Ok(ExportWhitelist {
-                entries: vec![
-                    //NOTE: function signatures are not checked yet
-                    ExportEntry::new("main".to_string(), Internal::Function(0)),
-                    ExportEntry::new("memory".to_string(), Internal::Memory(0)),
-                ],
-            }),
-            "pwasm" => Ok(ExportWhitelist {
-                entries: vec![ExportEntry::new("_call".to_string(), Internal::Function(0))],
-            }),
-            _ => Err(()),
+    /// Constructs a whitelist of the exports expected by the given preset.
+    fn with_preset(preset: Preset) -> Self {
+        ExportWhitelist {
+            entries: preset
+                .exports()
+                .iter()
+                .map(|export| ExportEntry::new(export.field.to_string(), export.internal))
+                .collect(),
         }
     }
 
     /// Looks up a given export entry in the whitelist and returns true if it is valid.
-    fn lookup(&mut self, export: &ExportEntry) -> bool {
-        if let Some(thing) = self.entries.iter().find(|matched_export| {
+    fn lookup(&self, export: &ExportEntry) -> bool {
+        self.entries.iter().any(|matched_export| {
             export.field() == matched_export.field()
                 && cmp_internal_variant(export.internal(), matched_export.internal())
-        }) {
-            true
-        } else {
-            false
-        }
+        })
+    }
+}
+
+impl Default for TrimExports {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModulePreset for TrimExports {
+    /// Constructs a context with the whitelist of the given preset.
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(TrimExports {
+            whitelist: ExportWhitelist::with_preset(preset),
+        })
     }
 }
 
@@ -64,48 +69,36 @@ impl TrimExports {
         }
     }
 
-    /// Takes a given preset string and constructs a context with the
-    /// corresponding whitelist.
-    pub fn with_preset(preset: &str) -> Result<Self, ()> {
-        match preset {
-            "ewasm" => Ok(TrimExports {
-                whitelist: ExportWhitelist::with_preset("ewasm").unwrap(),
-            }),
-            "pwasm" => Ok(TrimExports {
-                whitelist: ExportWhitelist::with_preset("pwasm").unwrap(),
-            }),
-            _ => Err(()),
+    /// Constructs a context keeping the given exports. Exports are matched by name and kind, so
+    /// the index of each entry is not significant.
+    pub fn with_exports(exports: Vec<ExportEntry>) -> Self {
+        TrimExports {
+            whitelist: ExportWhitelist { entries: exports },
         }
     }
 
     /// Iterates over the export section, if there is one, and removes
-    /// unnecessary entries.
-    fn trim_exports(&mut self, module: &mut Module) -> bool {
+    /// unnecessary entries. Returns the number of removed entries.
+    pub fn trim_exports(&self, module: &mut Module) -> usize {
         if let Some(section) = module.export_section_mut() {
-            let new_section = ExportSection::with_entries(
-                section
-                    .entries()
-                    .iter()
-                    .cloned()
-                    .filter(|entry| self.whitelist.lookup(entry))
-                    .collect(),
-            );
-
-            if new_section.entries().len() < section.entries().len() {
-                *section = new_section;
-                return true;
-            }
-
-            false
+            let before = section.entries().len();
+            section
+                .entries_mut()
+                .retain(|entry| self.whitelist.lookup(entry));
+            before - section.entries().len()
         } else {
-            false
+            0
         }
     }
 }
 
 impl ModuleTranslator for TrimExports {
-    fn translate(mut self, module: &mut Module) -> Result<bool, String> {
-        Ok(self.trim_exports(module))
+    fn translate(&self, module: &Module) -> Result<Option<Module>, String> {
+        translate_copy(module, |module| self.translate_inplace(module))
+    }
+
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, String> {
+        Ok(self.trim_exports(module) > 0)
     }
 }
 
@@ -136,9 +129,9 @@ mod tests {
             .build()
             .build();
 
-        let trimmer = TrimExports::with_preset("ewasm").unwrap();
-        let did_change = trimmer.translate(&mut module).unwrap();
-        assert_eq!(false, did_change);
+        let trimmer = TrimExports::with_preset(Preset::Ewasm).unwrap();
+        let did_change = trimmer.translate_inplace(&mut module).unwrap();
+        assert!(!did_change);
     }
 
     #[test]
@@ -167,9 +160,10 @@ mod tests {
             .build()
             .build();
 
-        let trimmer = TrimExports::with_preset("ewasm").unwrap();
-        let did_change = trimmer.translate(&mut module).unwrap();
-        assert_eq!(true, did_change);
+        let trimmer = TrimExports::with_preset(Preset::Ewasm).unwrap();
+        let did_change = trimmer.translate_inplace(&mut module).unwrap();
+        assert!(did_change);
+        assert_eq!(trimmer.translate(&module), Ok(None));
     }
 
     #[test]
@@ -183,9 +177,9 @@ mod tests {
             .build()
             .build();
 
-        let trimmer = TrimExports::with_preset("ewasm").unwrap();
-        let did_change = trimmer.translate(&mut module).unwrap();
-        assert_eq!(false, did_change);
+        let trimmer = TrimExports::with_preset(Preset::Ewasm).unwrap();
+        let did_change = trimmer.translate_inplace(&mut module).unwrap();
+        assert!(!did_change);
     }
 
     #[test]
@@ -204,8 +198,47 @@ mod tests {
             .build()
             .build();
 
-        let trimmer = TrimExports::with_preset("pwasm").unwrap();
-        let did_change = trimmer.translate(&mut module).unwrap();
-        assert_eq!(false, did_change);
+        let trimmer = TrimExports::with_preset(Preset::PWasm).unwrap();
+        let did_change = trimmer.translate_inplace(&mut module).unwrap();
+        assert!(!did_change);
+    }
+
+    #[test]
+    fn custom_exports_removed_count() {
+        let mut module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("deploy")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("call")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("deploy")
+            .internal()
+            .memory(0)
+            .build()
+            .build();
+
+        let trimmer = TrimExports::with_exports(vec![ExportEntry::new(
+            "deploy".to_string(),
+            Internal::Function(0),
+        )]);
+        assert_eq!(trimmer.trim_exports(&mut module), 2);
+        let exports = module.export_section().unwrap().entries();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].field(), "deploy");
+        // The functions referenced by the removed exports are kept.
+        assert_eq!(module.functions_space(), 1);
+        assert_eq!(trimmer.trim_exports(&mut module), 0);
     }
 }
@@ -0,0 +1,112 @@
+//! Validators which only depend on `alloc` and parity-wasm, for hosts without the standard
+//! library, such as a wasm contract runtime. They are all that libchisel provides when built
+//! without its `std` feature: `VerifyImports`, `VerifyExports`, `VerifyCustomSections`,
+//! `VerifyGlobals`, `VerifyIndexSpace`, `VerifyLimits`, `VerifyMemory`, `VerifyMemoryExport`,
+//! `VerifyStructure`, `VerifyTable` and `CheckStartFunc`, each behind the feature of the same
+//! name, along with the presets configuring them.
+
+#[cfg(feature = "checkstartfunc")]
+pub use checkstartfunc::CheckStartFunc;
+pub use presets::{Limits, Preset};
+#[cfg(feature = "verifycustomsections")]
+pub use verifycustomsections::VerifyCustomSections;
+#[cfg(feature = "verifyexports")]
+pub use verifyexports::{ExportType, VerifyExports};
+#[cfg(feature = "verifyglobals")]
+pub use verifyglobals::VerifyGlobals;
+#[cfg(feature = "verifyimports")]
+pub use verifyimports::{ImportType, VerifyImports, VerifyImportsBuilder};
+#[cfg(feature = "verifyindexspace")]
+pub use verifyindexspace::VerifyIndexSpace;
+#[cfg(feature = "verifylimits")]
+pub use verifylimits::VerifyLimits;
+#[cfg(feature = "verifymemory")]
+pub use verifymemory::VerifyMemory;
+#[cfg(feature = "verifymemoryexport")]
+pub use verifymemoryexport::VerifyMemoryExport;
+#[cfg(feature = "verifystructure")]
+pub use verifystructure::VerifyStructure;
+#[cfg(feature = "verifytable")]
+pub use verifytable::VerifyTable;
+pub use {ModulePreset, ModuleValidator, ValidationResult};
+
+/// Runs the standard validators of the preset over the module: the exports, imports and memory
+/// expected by the preset, where it defines them, and the absence of a start function. Returns
+/// each validator by name alongside its result, in the order they ran, or the name of the first
+/// validator which could not run alongside the reason. Each of these validators must be enabled.
+#[cfg(all(
+    feature = "checkstartfunc",
+    feature = "verifyexports",
+    feature = "verifyimports",
+    feature = "verifymemory"
+))]
+pub fn validate_preset(
+    module: &parity_wasm::elements::Module,
+    preset: Preset,
+) -> Result<alloc::vec::Vec<(&'static str, ValidationResult)>, (&'static str, alloc::string::String)>
+{
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+
+    let mut validators: Vec<(&'static str, Box<dyn ModuleValidator>)> = Vec::new();
+    if let Ok(validator) = VerifyExports::with_preset(preset) {
+        validators.push(("verifyexports", Box::new(validator)));
+    }
+    if let Ok(validator) = VerifyImports::with_preset(preset) {
+        validators.push(("verifyimports", Box::new(validator)));
+    }
+    if let Ok(validator) = VerifyMemory::with_preset(preset) {
+        validators.push(("verifymemory", Box::new(validator)));
+    }
+    validators.push(("checkstartfunc", Box::new(CheckStartFunc::new(false))));
+
+    validators
+        .into_iter()
+        .map(|(name, validator)| {
+            validator
+                .validate(module)
+                .map(|result| (name, result))
+                .map_err(|reason| (name, reason))
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+
+    #[test]
+    fn preset_validators_run() {
+        let module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+
+        let results = validate_preset(&module, Preset::Ewasm).unwrap();
+        let names: Vec<&str> = results.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            [
+                "verifyexports",
+                "verifyimports",
+                "verifymemory",
+                "checkstartfunc"
+            ]
+        );
+        assert_eq!(results[0].1.reasons(), ["missing export `memory`"]);
+        assert!(results[1..].iter().all(|(_, result)| result.passed()));
+
+        let results = validate_preset(&module, Preset::PWasm).unwrap();
+        assert_eq!(results.len(), 2);
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::presets::Preset;
+use super::{ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{Module, Section};
+
+/// Struct on which ModuleValidator is implemented.
+/// Rejects custom sections other than those allowed, for targets which refuse to deploy unknown
+/// custom sections. This is the read-only counterpart of `DropSection`.
+pub struct VerifyCustomSections {
+    /// Names of the custom sections which are allowed, or None to allow any.
+    allowed: Option<Vec<String>>,
+}
+
+impl ModulePreset for VerifyCustomSections {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(VerifyCustomSections {
+            allowed: preset
+                .custom_sections()
+                .map(|names| names.iter().map(|name| name.to_string()).collect()),
+        })
+    }
+}
+
+impl VerifyCustomSections {
+    /// Constructs a validator allowing only the custom sections of the given names.
+    pub fn new(allowed: Vec<String>) -> Self {
+        VerifyCustomSections {
+            allowed: Some(allowed),
+        }
+    }
+
+    /// Allows the custom section of the given name as well. Has no effect if every custom
+    /// section is allowed.
+    pub fn allow(&mut self, name: &str) {
+        if let Some(ref mut allowed) = self.allowed {
+            if !allowed.iter().any(|allowed| allowed == name) {
+                allowed.push(name.to_string());
+            }
+        }
+    }
+}
+
+impl ModuleValidator for VerifyCustomSections {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let allowed = match self.allowed {
+            Some(ref allowed) => allowed,
+            None => return Ok(ValidationResult::from_reasons(Vec::new())),
+        };
+        let reasons = module
+            .sections()
+            .iter()
+            .filter_map(|section| match section {
+                Section::Custom(custom) => Some(custom.name()),
+                Section::Name(_) => Some("name"),
+                Section::Reloc(reloc) => Some(reloc.name()),
+                _ => None,
+            })
+            .filter(|name| !allowed.iter().any(|allowed| allowed == name))
+            .map(|name| format!("custom section `{}` is not allowed", name))
+            .collect();
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        match self.allowed {
+            None => vec!["allows any custom section".to_string()],
+            Some(ref allowed) if allowed.is_empty() => {
+                vec!["rejects every custom section".to_string()]
+            }
+            Some(ref allowed) => vec![format!(
+                "allows only the custom sections {}",
+                allowed.join(", ")
+            )],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::elements::CustomSection;
+
+    fn module_with_sections(names: &[&str]) -> Module {
+        let mut module = Module::default();
+        for name in names {
+            module
+                .sections_mut()
+                .push(Section::Custom(CustomSection::new(
+                    name.to_string(),
+                    vec![],
+                )));
+        }
+        module
+    }
+
+    #[test]
+    fn unknown_sections_reported() {
+        let module = module_with_sections(&["name", "producers", "sourceMappingURL"]);
+        let mut checker = VerifyCustomSections::new(vec!["name".to_string()]);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            [
+                "custom section `producers` is not allowed",
+                "custom section `sourceMappingURL` is not allowed"
+            ]
+        );
+        checker.allow("producers");
+        checker.allow("sourceMappingURL");
+        assert!(checker.validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn presets_allow_sections() {
+        let module = module_with_sections(&["name"]);
+        let validate = |preset| {
+            VerifyCustomSections::with_preset(preset)
+                .unwrap()
+                .validate(&module)
+                .unwrap()
+                .passed()
+        };
+        assert!(!validate(Preset::Ewasm));
+        assert!(validate(Preset::EwasmDebug));
+        assert!(validate(Preset::PWasm));
+
+        let mut checker = VerifyCustomSections::with_preset(Preset::PWasm).unwrap();
+        checker.allow("name");
+        assert_eq!(checker.rules(), ["allows any custom section"]);
+    }
+}
@@ -0,0 +1,121 @@
+use super::{ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, Instruction, Module};
+
+/// Size of a page of linear memory in bytes.
+const PAGE_SIZE: u64 = 65536;
+
+/// Struct on which ModuleValidator is implemented.
+/// Verifies that every data segment at a constant offset fits within the initial size of its
+/// memory, as a segment writing past it makes instantiation trap. Segments at a non-constant
+/// offset are left to `VerifyOffsets`, and modules without a memory pass.
+#[derive(Default)]
+pub struct VerifyDataBounds;
+
+impl VerifyDataBounds {
+    pub fn new() -> Self {
+        VerifyDataBounds
+    }
+}
+
+impl ModuleValidator for VerifyDataBounds {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let memories = initial_sizes(module);
+        let segments = module
+            .data_section()
+            .map_or(&[][..], |section| section.entries());
+        let mut reasons = Vec::new();
+        for (index, segment) in segments.iter().enumerate() {
+            let offset = match segment.offset().as_ref().map(|offset| offset.code()) {
+                Some([Instruction::I32Const(offset), Instruction::End]) => *offset as u32 as u64,
+                // Passive segments are only written by `memory.init`, at runtime.
+                _ => continue,
+            };
+            let size = match memories.get(segment.index() as usize) {
+                Some(size) => *size,
+                None => continue,
+            };
+            let end = offset + segment.value().len() as u64;
+            if end > size {
+                reasons.push(format!(
+                    "data segment {} ends at byte {}, {} bytes past the initial memory of {} bytes",
+                    index,
+                    end,
+                    end - size,
+                    size
+                ));
+            }
+        }
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        vec!["requires data segments to fit within the initial size of their memory".to_string()]
+    }
+}
+
+/// Returns the initial size in bytes of each memory, imported ones first.
+fn initial_sizes(module: &Module) -> Vec<u64> {
+    let imported = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Memory(memory) => Some(memory.limits().initial()),
+            _ => None,
+        });
+    let defined = module
+        .memory_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|memory| memory.limits().initial());
+    imported
+        .chain(defined)
+        .map(|pages| pages as u64 * PAGE_SIZE)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    #[test]
+    fn segments_within_memory_good() {
+        let module = FromWat::new(
+            r#"(module
+                (memory 1)
+                (data (i32.const 0) "start")
+                (data (i32.const 65531) "end!!"))"#,
+        )
+        .create()
+        .unwrap();
+        assert!(VerifyDataBounds::new().validate(&module).unwrap().passed());
+
+        let module = FromWat::new(r#"(module (func))"#).create().unwrap();
+        assert!(VerifyDataBounds::new().validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn overflow_reported() {
+        let module = FromWat::new(
+            r#"(module
+                (import "env" "base" (global i32))
+                (import "env" "memory" (memory 1))
+                (data (i32.const 65530) "fits")
+                (data (i32.const 65534) "past")
+                (data (global.get 0) "unknown")
+                (data (i32.const -1) "wraps"))"#,
+        )
+        .create()
+        .unwrap();
+        assert_eq!(
+            VerifyDataBounds::new().validate(&module).unwrap().reasons(),
+            [
+                "data segment 1 ends at byte 65538, 2 bytes past the initial memory of 65536 bytes",
+                "data segment 3 ends at byte 4294967300, 4294901764 bytes past the initial memory of 65536 bytes"
+            ]
+        );
+    }
+}
@@ -1,8 +1,12 @@
-use super::ModuleValidator;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
-use parity_wasm::elements::{
-    ExportSection, External, FunctionSection, FunctionType, ImportSection, Internal, Module, Type,
-};
+use super::presets::Preset;
+#[cfg(feature = "std")]
+use super::streaming::interface_module;
+use super::{format_signature, ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, FunctionType, Internal, Module, Type};
 
 /// Enum representing a type of export and any extra data to check.
 pub enum ExportType<'a> {
@@ -12,9 +16,10 @@ pub enum ExportType<'a> {
     Table(&'a str),
 }
 
-/// Trait over ExportType that lets a caller check if it is exported in a given module.
-trait IsExported {
-    fn is_exported(&self, module: &Module) -> bool;
+/// Trait over ExportType that checks if it is exported in a given module with the expected kind
+/// and signature, returning the reason if it is not.
+trait ExportCheck {
+    fn check(&self, module: &Module) -> Result<(), String>;
 }
 
 /// Struct on which ModuleValidator is implemented.
@@ -24,183 +29,203 @@ pub struct VerifyExports<'a> {
 }
 
 impl<'a> VerifyExports<'a> {
-    pub fn with_preset(preset: &str) -> Result<Self, ()> {
-        match preset {
-            "ewasm" => Ok(VerifyExports {
-                entries: vec![
-                    ExportType::Function("main", FunctionType::default()),
-                    ExportType::Memory("memory"),
-                ],
-                allow_unlisted: false,
-            }),
-            _ => Err(()),
+    /// Constructs a validator requiring the given exports. Unlisted exports are allowed unless
+    /// disabled with `set_allow_unlisted`.
+    pub fn with_exports(entries: Vec<ExportType<'a>>) -> Self {
+        VerifyExports {
+            entries,
+            allow_unlisted: true,
         }
     }
+
+    /// Sets whether the module may have exports which are not listed.
+    pub fn set_allow_unlisted(&mut self, arg: bool) {
+        self.allow_unlisted = arg;
+    }
+}
+
+impl<'a> ModulePreset for VerifyExports<'a> {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(VerifyExports {
+            entries: preset
+                .exports()
+                .iter()
+                .map(|export| match export.internal {
+                    Internal::Function(_) => ExportType::Function(
+                        export.field,
+                        FunctionType::new(export.params.to_vec(), export.result),
+                    ),
+                    Internal::Global(_) => ExportType::Global(export.field),
+                    Internal::Memory(_) => ExportType::Memory(export.field),
+                    Internal::Table(_) => ExportType::Table(export.field),
+                })
+                .collect(),
+            allow_unlisted: false,
+        })
+    }
 }
 
 impl<'a> ModuleValidator for VerifyExports<'a> {
-    fn validate(self, module: &Module) -> Result<bool, String> {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
         // FIXME: This validating algorithm runs in O(n^2). Needs to be optimized
-        let required_exports_not_found = self
+        let mut reasons: Vec<String> = self
             .entries
             .iter()
-            .map(|e| e.is_exported(module))
-            .find(|e| *e == false)
-            .is_some();
-
-        if required_exports_not_found {
-            return Ok(false);
+            .filter_map(|e| e.check(module).err())
+            .collect();
+
+        if !self.allow_unlisted {
+            if let Some(section) = module.export_section() {
+                reasons.extend(
+                    section
+                        .entries()
+                        .iter()
+                        .filter(|export| !self.entries.iter().any(|e| e.field() == export.field()))
+                        .map(|export| format!("unexpected export `{}`", export.field())),
+                );
+            }
         }
 
-        let module_export_count = if let Some(section) = module.export_section() {
-            section.entries().len()
-        } else {
-            0
-        };
-
-        if self.entries.len() != module_export_count {
-            Ok(self.allow_unlisted)
-        } else {
-            Ok(true)
-        }
+        Ok(ValidationResult::from_reasons(reasons))
     }
-}
 
-impl<'a> IsExported for ExportType<'a> {
-    fn is_exported(&self, module: &Module) -> bool {
-        if let Some(section) = module.export_section() {
-            match self {
-                ExportType::Function(field, sig) => has_func_export(module, field, sig),
-                ExportType::Global(field) => has_global_export(section, field),
-                ExportType::Memory(field) => has_memory_export(section, field),
-                ExportType::Table(field) => has_table_export(section, field),
-            }
-        } else {
-            false
-        }
+    /// Only the sections declaring the interface of the module are deserialized.
+    #[cfg(feature = "std")]
+    fn validate_binary(&self, binary: &[u8]) -> Option<Result<ValidationResult, String>> {
+        Some(interface_module(binary).and_then(|module| self.validate(&module)))
     }
-}
 
-// NOTE: has_*_export is implemented with repeating code because you can't implement a trait for
-// enum variants, as they are not types. Furthermore, having one helper for non-func exports would
-// be ugly because information about the export type must still be passed down to check that an
-// export is of the correct kind.
-
-/// Checks if a global is exported with the given name.
-fn has_global_export(section: &ExportSection, field: &str) -> bool {
-    if let Some(ref export) = section.entries().iter().find(|e| e.field() == field) {
-        match export.internal() {
-            Internal::Global(_index) => true,
-            _ => false,
-        }
-    } else {
-        false
+    fn interface_only(&self) -> bool {
+        true
     }
-}
 
-/// Checks if a memory is exported with the given name.
-fn has_memory_export(section: &ExportSection, field: &str) -> bool {
-    if let Some(ref export) = section.entries().iter().find(|e| e.field() == field) {
-        match export.internal() {
-            Internal::Memory(_index) => true,
-            _ => false,
-        }
-    } else {
-        false
+    fn rules(&self) -> Vec<String> {
+        let mut rules: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                ExportType::Function(field, sig) => format!(
+                    "requires export function `{}` {}",
+                    field,
+                    format_signature(sig)
+                ),
+                _ => format!("requires export {} `{}`", entry.kind(), entry.field()),
+            })
+            .collect();
+        rules.push(if self.allow_unlisted {
+            "allows unlisted exports".to_string()
+        } else {
+            "rejects unlisted exports".to_string()
+        });
+        rules
     }
 }
 
-/// Checks if a table is exported with the given name.
-fn has_table_export(section: &ExportSection, field: &str) -> bool {
-    if let Some(ref export) = section.entries().iter().find(|e| e.field() == field) {
-        match export.internal() {
-            Internal::Table(_index) => true,
-            _ => false,
+impl<'a> ExportType<'a> {
+    /// Returns the name under which the export is expected.
+    fn field(&self) -> &'a str {
+        match self {
+            ExportType::Function(field, _) => field,
+            ExportType::Global(field) => field,
+            ExportType::Memory(field) => field,
+            ExportType::Table(field) => field,
         }
-    } else {
-        false
     }
 }
 
-// NOTE: this is kind of hacked on. It works, but a refactor would make it more in line with the other
-// helpers.
-/// Checks if a function is exported with the given name.
-fn has_func_export(module: &Module, field: &str, sig: &FunctionType) -> bool {
-    if let Some(section) = module.export_section() {
-        match func_export_index_by_name(section, field) {
-            Some(index) => if let Some(resolved) = func_sig_by_index(module, index) {
-                *sig == *resolved
-            } else {
-                false
-            },
-            None => false,
+impl<'a> ExportType<'a> {
+    /// Returns the kind of export as it would be named in the text format.
+    fn kind(&self) -> &'static str {
+        match self {
+            ExportType::Function(_, _) => "function",
+            ExportType::Global(_) => "global",
+            ExportType::Memory(_) => "memory",
+            ExportType::Table(_) => "table",
         }
-    } else {
-        false
     }
 }
 
-/// Resolves a function's signature from its internal index.
-fn func_sig_by_index(module: &Module, index: u32) -> Option<&FunctionType> {
-    if let Some(func_section) = module.function_section() {
-        match (module.type_section(), module.import_section()) {
-            // If we have function imports in the section, subtract them from the function's index
-            // because their signatures are not in the types section.
-            (Some(type_section), Some(import_section)) => match type_section.types()[func_type_ref(
-                &func_section,
-                index - func_import_section_len(import_section),
-            )] {
-                Type::Function(ref ret) => Some(ret),
-                _ => None,
-            },
-            // If no function imports are present, no need to subtract them.
-            (Some(type_section), None) => {
-                match type_section.types()[func_type_ref(&func_section, index)] {
-                    Type::Function(ref ret) => Some(ret),
-                    _ => None,
+impl<'a> ExportCheck for ExportType<'a> {
+    fn check(&self, module: &Module) -> Result<(), String> {
+        let field = self.field();
+        let export = module
+            .export_section()
+            .and_then(|section| section.entries().iter().find(|e| e.field() == field))
+            .ok_or_else(|| format!("missing export `{}`", field))?;
+
+        match (self, export.internal()) {
+            (ExportType::Function(_, expected), Internal::Function(index)) => {
+                match func_sig_by_index(module, *index) {
+                    Some(actual) if actual == expected => Ok(()),
+                    Some(actual) => Err(format!(
+                        "export `{}` has signature {}, expected {}",
+                        field,
+                        format_signature(actual),
+                        format_signature(expected)
+                    )),
+                    None => Err(format!(
+                        "export `{}` refers to a nonexistent function",
+                        field
+                    )),
                 }
             }
-            (None, Some(import_section)) => None,
-            (None, None) => None,
+            (ExportType::Global(_), Internal::Global(_))
+            | (ExportType::Memory(_), Internal::Memory(_))
+            | (ExportType::Table(_), Internal::Table(_)) => Ok(()),
+            (_, internal) => Err(format!(
+                "export `{}` is a {}, expected a {}",
+                field,
+                internal_kind(internal),
+                self.kind()
+            )),
         }
-    } else {
-        None
     }
 }
 
-/// Returns the internal reference to a function's type signature.
-fn func_type_ref(funcs: &FunctionSection, func_index: u32) -> usize {
-    funcs.entries()[func_index as usize].type_ref() as usize
-}
-
-/// Returns the number of functions in the function section that are imported.
-fn func_import_section_len(imports: &ImportSection) -> u32 {
-    imports
-        .entries()
-        .iter()
-        .filter(|e| match e.external() {
-            &External::Function(_) => true,
-            _ => false,
-        }).count() as u32
+/// Returns the kind of an export in the module as it would be named in the text format.
+fn internal_kind(internal: &Internal) -> &'static str {
+    match internal {
+        Internal::Function(_) => "function",
+        Internal::Global(_) => "global",
+        Internal::Memory(_) => "memory",
+        Internal::Table(_) => "table",
+    }
 }
 
-/// Resolves a function export's index by name. Can be trivially adjusted for
-/// all types of exports.
-fn func_export_index_by_name(exports: &ExportSection, field_str: &str) -> Option<u32> {
-    if let Some(entry) = exports.entries().iter().find(|e| e.field() == field_str) {
-        match entry.internal() {
-            Internal::Function(index) => Some(*index),
-            _ => None,
-        }
-    } else {
-        None
+/// Resolves a function's signature from its index in the function index space, where imported
+/// functions come first. Returns None if the index or its type is out of bounds.
+fn func_sig_by_index(module: &Module, index: u32) -> Option<&FunctionType> {
+    let imported: Vec<u32> = module.import_section().map_or(vec![], |section| {
+        section
+            .entries()
+            .iter()
+            .filter_map(|e| match e.external() {
+                External::Function(type_ref) => Some(*type_ref),
+                _ => None,
+            })
+            .collect()
+    });
+
+    let type_ref = match imported.get(index as usize) {
+        Some(type_ref) => *type_ref,
+        None => module
+            .function_section()?
+            .entries()
+            .get(index as usize - imported.len())?
+            .type_ref(),
+    };
+
+    match module.type_section()?.types().get(type_ref as usize)? {
+        Type::Function(ref sig) => Some(sig),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use parity_wasm::elements::deserialize_buffer;
+    use fromwat::FromWat;
+    use parity_wasm::elements::{deserialize_buffer, ValueType};
+    use ModuleCreator;
 
     #[test]
     fn no_exports() {
@@ -209,9 +234,9 @@ mod tests {
         let wasm: Vec<u8> = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -231,9 +256,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -255,9 +280,13 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
         let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        assert!(!result.passed());
+        assert_eq!(
+            result.reasons(),
+            ["export `main` has signature () -> i32, expected () -> ()"]
+        );
     }
 
     #[test]
@@ -277,9 +306,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -297,9 +326,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -317,9 +346,10 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
         let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        assert!(!result.passed());
+        assert_eq!(result.reasons(), ["missing export `main`"]);
     }
 
     #[test]
@@ -339,9 +369,13 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
         let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        assert!(!result.passed());
+        assert_eq!(
+            result.reasons(),
+            ["export `memory` is a function, expected a memory"]
+        );
     }
 
     #[test]
@@ -361,9 +395,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -383,9 +417,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -407,9 +441,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -438,7 +472,114 @@ mod tests {
             ],
             allow_unlisted: true,
         };
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
+    }
+
+    #[test]
+    fn custom_exports() {
+        let module = parity_wasm::builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("foo")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+
+        let mut checker =
+            VerifyExports::with_exports(vec![ExportType::Function("foo", FunctionType::default())]);
+        assert!(checker.validate(&module).unwrap().passed());
+        checker.set_allow_unlisted(false);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["unexpected export `main`"]
+        );
+
+        let checker = VerifyExports::with_exports(vec![
+            ExportType::Memory("memory"),
+            ExportType::Global("g"),
+        ]);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["missing export `memory`", "missing export `g`"]
+        );
+    }
+
+    #[test]
+    fn imported_function_export() {
+        // wast:
+        // (module
+        //   (import "env" "f" (func $f (param i32)))
+        //   (export "main" (func $f))
+        //   (func)
+        // )
+        let mut module = parity_wasm::builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .import()
+            .module("env")
+            .field("f")
+            .external()
+            .func(1)
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        module
+            .type_section_mut()
+            .unwrap()
+            .types_mut()
+            .push(Type::Function(FunctionType::new(
+                vec![ValueType::I32],
+                None,
+            )));
+
+        let checker = VerifyExports::with_exports(vec![ExportType::Function(
+            "main",
+            FunctionType::default(),
+        )]);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["export `main` has signature (i32) -> (), expected () -> ()"]
+        );
+    }
+
+    #[test]
+    fn binary_agrees_with_module() {
+        let sources = [
+            r#"(module (memory (export "memory") 1) (func (export "main")))"#,
+            r#"(module
+                (import "env" "f" (func (param i32)))
+                (memory (export "mem") 1)
+                (func (export "main") (param i32))
+                (export "foo" (func 0)))"#,
+        ];
+        let checker = VerifyExports::with_preset(Preset::Ewasm).unwrap();
+        for source in sources.iter() {
+            let module = FromWat::new(source).create().unwrap();
+            let binary = parity_wasm::serialize(module.clone()).unwrap();
+            assert_eq!(
+                checker.validate_binary(&binary),
+                Some(checker.validate(&module))
+            );
+        }
     }
 }
@@ -0,0 +1,117 @@
+use super::presets::Preset;
+use super::{ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{ImportCountType, Module};
+
+/// Struct on which ModuleValidator is implemented.
+/// Checks that the body of every function is at most a number of bytes once encoded, as some
+/// hosts and instrumenters reject larger functions. The body is measured as the size given to it
+/// in the code section, which covers its locals and instructions.
+pub struct VerifyFuncSize {
+    /// Largest size of a function body in bytes, or None to allow any size.
+    max_size: Option<usize>,
+}
+
+impl ModulePreset for VerifyFuncSize {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(VerifyFuncSize::new(preset.max_function_size()))
+    }
+}
+
+impl VerifyFuncSize {
+    pub fn new(max_size: Option<usize>) -> Self {
+        VerifyFuncSize { max_size }
+    }
+
+    pub fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.max_size = max_size;
+    }
+}
+
+impl ModuleValidator for VerifyFuncSize {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let max_size = match self.max_size {
+            Some(max_size) => max_size,
+            None => return Ok(ValidationResult::from_reasons(vec![])),
+        };
+        let imported = module.import_count(ImportCountType::Function);
+        let bodies = module
+            .code_section()
+            .map_or(&[][..], |section| section.bodies());
+        let mut reasons = Vec::new();
+        for (index, body) in bodies.iter().enumerate() {
+            let encoded = parity_wasm::serialize(body.clone()).map_err(|err| {
+                format!("Failed to serialize function {}: {}", imported + index, err)
+            })?;
+            // The body is preceded by its size, as a LEB128 integer.
+            let prefix = encoded.iter().take_while(|byte| *byte & 0x80 != 0).count() + 1;
+            let size = encoded.len() - prefix;
+            if size > max_size {
+                reasons.push(format!(
+                    "function {} is {} bytes, expected at most {} bytes",
+                    imported + index,
+                    size,
+                    max_size
+                ));
+            }
+        }
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        match self.max_size {
+            Some(max_size) => vec![format!(
+                "allows function bodies of at most {} bytes",
+                max_size
+            )],
+            None => vec!["allows function bodies of any size".to_string()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    #[test]
+    fn oversized_function_reported() {
+        // The second body is its local declarations, 200 `nop`s and `end`.
+        let module = FromWat::new(&format!(
+            r#"(module
+                (import "env" "f" (func))
+                (func)
+                (func {}))"#,
+            "nop ".repeat(200)
+        ))
+        .create()
+        .unwrap();
+        let mut checker = VerifyFuncSize::new(Some(128));
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["function 2 is 202 bytes, expected at most 128 bytes"]
+        );
+        checker.set_max_size(Some(202));
+        assert!(checker.validate(&module).unwrap().passed());
+        checker.set_max_size(None);
+        assert!(checker.validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn presets_default_threshold() {
+        let module = FromWat::new(&format!("(module (func {}))", "nop ".repeat(10000)))
+            .create()
+            .unwrap();
+        assert!(!VerifyFuncSize::with_preset(Preset::Ewasm)
+            .unwrap()
+            .validate(&module)
+            .unwrap()
+            .passed());
+        assert!(VerifyFuncSize::with_preset(Preset::PWasm)
+            .unwrap()
+            .validate(&module)
+            .unwrap()
+            .passed());
+    }
+}
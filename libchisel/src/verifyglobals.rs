@@ -0,0 +1,158 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::{ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, GlobalType, Internal, Module};
+
+/// Struct on which ModuleValidator is implemented.
+/// Rejects exported mutable globals, which a host cannot keep deterministic across calls into
+/// the module.
+pub struct VerifyGlobals {
+    /// Largest number of globals, imported ones included, or None for no limit.
+    max_globals: Option<u32>,
+}
+
+impl Default for VerifyGlobals {
+    fn default() -> Self {
+        VerifyGlobals::new()
+    }
+}
+
+impl VerifyGlobals {
+    /// Constructs a validator which does not limit the number of globals.
+    pub fn new() -> Self {
+        VerifyGlobals { max_globals: None }
+    }
+
+    pub fn set_max_globals(&mut self, max_globals: Option<u32>) {
+        self.max_globals = max_globals;
+    }
+}
+
+impl ModuleValidator for VerifyGlobals {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let globals = global_types(module);
+        let mut reasons = Vec::new();
+
+        if let Some(max_globals) = self.max_globals {
+            if globals.len() > max_globals as usize {
+                reasons.push(format!(
+                    "module defines {} globals, expected at most {}",
+                    globals.len(),
+                    max_globals
+                ));
+            }
+        }
+
+        if let Some(section) = module.export_section() {
+            for export in section.entries().iter() {
+                let index = match export.internal() {
+                    Internal::Global(index) => *index,
+                    _ => continue,
+                };
+                match globals.get(index as usize) {
+                    Some(global_type) if global_type.is_mutable() => reasons.push(format!(
+                        "export `{}` refers to mutable global {}",
+                        export.field(),
+                        index
+                    )),
+                    Some(_) => (),
+                    None => reasons.push(format!(
+                        "export `{}` refers to nonexistent global {}",
+                        export.field(),
+                        index
+                    )),
+                }
+            }
+        }
+
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn interface_only(&self) -> bool {
+        true
+    }
+
+    fn rules(&self) -> Vec<String> {
+        let mut rules = Vec::new();
+        if let Some(max_globals) = self.max_globals {
+            rules.push(format!("allows at most {} globals", max_globals));
+        }
+        rules.push("rejects exports of mutable globals".to_string());
+        rules
+    }
+}
+
+/// Returns the type of every global in the index space, imported ones first.
+fn global_types(module: &Module) -> Vec<&GlobalType> {
+    let imported = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Global(global_type) => Some(global_type),
+            _ => None,
+        });
+    let defined = module
+        .global_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|entry| entry.global_type());
+    imported.chain(defined).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    /// Exports an immutable global, then a mutable one behind an imported global.
+    static MUTABLE_GLOBAL_EXPORT: &str = r#"(module
+        (import "env" "base" (global i32))
+        (global i32 (i32.const 1))
+        (global (mut i64) (i64.const 0))
+        (export "base" (global 0))
+        (export "limit" (global 1))
+        (export "counter" (global 2)))"#;
+
+    #[test]
+    fn mutable_export_rejected() {
+        let module = FromWat::new(MUTABLE_GLOBAL_EXPORT).create().unwrap();
+        let result = VerifyGlobals::new().validate(&module).unwrap();
+        assert_eq!(
+            result.reasons(),
+            ["export `counter` refers to mutable global 2"]
+        );
+    }
+
+    #[test]
+    fn immutable_exports_good() {
+        let module = FromWat::new(
+            r#"(module
+                (global (mut i32) (i32.const 0))
+                (global i32 (i32.const 1))
+                (export "limit" (global 1)))"#,
+        )
+        .create()
+        .unwrap();
+        assert!(VerifyGlobals::new().validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn global_count_capped() {
+        let module = FromWat::new(MUTABLE_GLOBAL_EXPORT).create().unwrap();
+        let mut checker = VerifyGlobals::new();
+        checker.set_max_globals(Some(2));
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            [
+                "module defines 3 globals, expected at most 2",
+                "export `counter` refers to mutable global 2",
+            ]
+        );
+        checker.set_max_globals(Some(3));
+        assert_eq!(checker.validate(&module).unwrap().reasons().len(), 1);
+    }
+}
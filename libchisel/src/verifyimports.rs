@@ -1,6 +1,12 @@
-use super::ModuleValidator;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
-use parity_wasm::elements::{External, FunctionType, ImportSection, Module, Type, ValueType};
+use super::presets::Preset;
+#[cfg(feature = "std")]
+use super::streaming::interface_module;
+use super::{format_signature, ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, FunctionType, Module, Type};
 
 /// Enum representing a type of import and any extra data to check.
 #[derive(Clone)]
@@ -16,13 +22,8 @@ pub enum ImportType<'a> {
 pub enum ImportStatus {
     Good,
     NotFound,
-    Malformed,
-}
-
-/// Trait over ImportType that lets a caller check if it is imported in a given module, and
-/// verifies its type signature is correct.
-trait IsImported {
-    fn is_imported(&self, module: &Module) -> bool;
+    /// The import exists with the wrong kind or signature, as described.
+    Malformed(String),
 }
 
 /// Trait over ImportType that checks an import's type signature in the case that it is imported.
@@ -32,484 +33,346 @@ trait ImportCheck {
 
 /// Struct on which ModuleValidator is implemented.
 pub struct VerifyImports<'a> {
-    /// List of function signatures to check.
-    entries: Vec<ImportType<'a>>,
-    /// Option to require the presence of all listed imports in the module. When false, only the
-    /// validity of existing imports on the list is checked.
-    require_all: bool,
+    /// List of imports to check, each with whether the module must import it. Imports which are
+    /// not required are only checked when the module imports them.
+    entries: Vec<(ImportType<'a>, bool)>,
     /// Option to allow imports that are not listed in `entries`.
     allow_unlisted: bool,
+    /// Namespaces which every import must come from, or None to allow any namespace.
+    namespaces: Option<Vec<&'a str>>,
+    /// Namespaces and names of the imports the module must never have, whatever else allows.
+    forbidden: Vec<(&'a str, &'a str)>,
+}
+
+/// Builder of a `VerifyImports` context from individual rules. With no rules, every module
+/// passes.
+pub struct VerifyImportsBuilder<'a> {
+    entries: Vec<(ImportType<'a>, bool)>,
+    allow_extra: bool,
+    namespaces: Option<Vec<&'a str>>,
+    forbidden: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> ModulePreset for VerifyImports<'a> {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        let imports = preset
+            .imports()
+            .ok_or_else(|| format!("Preset {} is not supported", preset))?;
+        let builder = preset
+            .namespaces()
+            .iter()
+            .fold(VerifyImports::builder(), |builder, namespace| {
+                builder.namespace(namespace)
+            });
+        Ok(imports
+            .iter()
+            .fold(builder, |builder, import| {
+                builder.import(ImportType::Function(
+                    import.module,
+                    import.field,
+                    FunctionType::new(import.params.to_vec(), import.result),
+                ))
+            })
+            .allow_extra(false)
+            .build())
+    }
 }
 
 impl<'a> VerifyImports<'a> {
-    pub fn with_preset(preset: &str) -> Result<Self, ()> {
-        match preset {
-            "ewasm" => Ok(VerifyImports {
-                //FIXME: It is messy to inline all the function signatures in the constructor.
-                entries: vec![
-                    ImportType::Function(
-                        "ethereum",
-                        "useGas",
-                        FunctionType::new(vec![ValueType::I64], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getGasLeft",
-                        FunctionType::new(vec![], Some(ValueType::I64)),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getAddress",
-                        FunctionType::new(vec![ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getExternalBalance",
-                        FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getBlockHash",
-                        FunctionType::new(
-                            vec![ValueType::I64, ValueType::I32],
-                            Some(ValueType::I32),
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "call",
-                        FunctionType::new(
-                            vec![
-                                ValueType::I64,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                            ],
-                            Some(ValueType::I32),
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "callCode",
-                        FunctionType::new(
-                            vec![
-                                ValueType::I64,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                            ],
-                            Some(ValueType::I32),
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "callDelegate",
-                        FunctionType::new(
-                            vec![
-                                ValueType::I64,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                            ],
-                            Some(ValueType::I32),
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "callStatic",
-                        FunctionType::new(
-                            vec![
-                                ValueType::I64,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                            ],
-                            Some(ValueType::I32),
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "create",
-                        FunctionType::new(
-                            vec![
-                                ValueType::I64,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                            ],
-                            Some(ValueType::I32),
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "callDataCopy",
-                        FunctionType::new(
-                            vec![ValueType::I32, ValueType::I32, ValueType::I32],
-                            None,
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getCallDataSize",
-                        FunctionType::new(vec![], Some(ValueType::I32)),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getCodeSize",
-                        FunctionType::new(vec![], Some(ValueType::I32)),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "externalCodeCopy",
-                        FunctionType::new(
-                            vec![
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                            ],
-                            None,
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getCaller",
-                        FunctionType::new(vec![ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getCallValue",
-                        FunctionType::new(vec![ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getBlockDifficulty",
-                        FunctionType::new(vec![ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getBlockCoinbase",
-                        FunctionType::new(vec![ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getBlockNumber",
-                        FunctionType::new(vec![], Some(ValueType::I64)),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getBlockGasLimit",
-                        FunctionType::new(vec![], Some(ValueType::I64)),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getBlockTimestamp",
-                        FunctionType::new(vec![], Some(ValueType::I64)),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getTxGasPrice",
-                        FunctionType::new(vec![ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getTxOrigin",
-                        FunctionType::new(vec![ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "storageStore",
-                        FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "storageLoad",
-                        FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "log",
-                        FunctionType::new(
-                            vec![
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                                ValueType::I32,
-                            ],
-                            None,
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "getReturnDataSize",
-                        FunctionType::new(vec![], Some(ValueType::I32)),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "returnDataCopy",
-                        FunctionType::new(
-                            vec![ValueType::I32, ValueType::I32, ValueType::I32],
-                            None,
-                        ),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "finish",
-                        FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "revert",
-                        FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-                    ),
-                    ImportType::Function(
-                        "ethereum",
-                        "selfDestruct",
-                        FunctionType::new(vec![ValueType::I32], None),
-                    ),
-                ].iter()
-                .cloned()
-                .collect(),
-                require_all: false,
-                allow_unlisted: false,
-            }),
-            _ => Err(()),
+    /// Returns a builder with no rules.
+    pub fn builder() -> VerifyImportsBuilder<'a> {
+        VerifyImportsBuilder {
+            entries: Vec::new(),
+            allow_extra: true,
+            namespaces: None,
+            forbidden: Vec::new(),
         }
     }
 
+    /// Sets whether the module may have imports which are not listed.
+    pub fn set_allow_unlisted(&mut self, arg: bool) {
+        self.allow_unlisted = arg;
+    }
+
+    /// Sets the namespaces which every import must come from, or None to allow any namespace.
+    pub fn set_namespaces(&mut self, namespaces: Option<Vec<&'a str>>) {
+        self.namespaces = namespaces;
+    }
+
+    /// Sets the namespaces and names of the imports the module must never have.
+    pub fn set_forbidden(&mut self, forbidden: Vec<(&'a str, &'a str)>) {
+        self.forbidden = forbidden;
+    }
+
     // Utility functions used in tests to get more coverage
     #[cfg(test)]
     fn set_require_all(&mut self, arg: bool) {
-        self.require_all = arg;
+        for entry in self.entries.iter_mut() {
+            entry.1 = arg;
+        }
     }
+}
 
-    #[cfg(test)]
-    fn set_allow_unlisted(&mut self, arg: bool) {
-        self.allow_unlisted = arg;
+impl<'a> VerifyImportsBuilder<'a> {
+    /// Allows imports from the namespace. Once a namespace is given, imports from any other one
+    /// are rejected.
+    pub fn namespace(mut self, namespace: &'a str) -> Self {
+        self.namespaces.get_or_insert_with(Vec::new).push(namespace);
+        self
     }
-}
 
-impl<'a> ModuleValidator for VerifyImports<'a> {
-    fn validate(self, module: &Module) -> Result<bool, String> {
-        let import_section_len = if let Some(section) = module.import_section() {
-            section.entries().len()
-        } else {
-            0
-        };
+    /// Lists an import, which must have the given kind and signature if the module imports it.
+    pub fn import(mut self, import: ImportType<'a>) -> Self {
+        self.entries.push((import, false));
+        self
+    }
 
-        Ok(match (self.require_all, self.allow_unlisted) {
-            // Check that all listed imports exist and are correct.
-            (true, true) => self
-                .entries
-                .iter()
-                .map(|e| e.is_imported(module))
-                .find(|e| *e == false)
-                .is_none(),
-            // Check that all listed imports exist, are correct, and are the only imports in the
-            // module.
-            (true, false) => {
-                self.entries
-                    .iter()
-                    .map(|e| e.is_imported(module))
-                    .find(|e| *e == false)
-                    .is_none()
-                    && (self.entries.len() == import_section_len)
-            }
-            // Check that the imports which are both listed and imported are of correct type.
-            (false, true) => self
-                .entries
-                .iter()
-                .map(|e| e.check(module))
-                .find(|e| *e == ImportStatus::Malformed)
-                .is_none(),
-            (false, false) => {
-                // Check that all existent imports are listed and correct.
-                let mut checklist: Vec<ImportStatus> =
-                    self.entries.iter().map(|e| e.check(module)).collect();
-                let valid_entries_count = checklist
-                    .iter()
-                    .filter(|e| **e == ImportStatus::Good)
-                    .count();
-
-                // Proof: If the number of valid entries is equal to the number of existing entries, all
-                // entries are valid.
-                //
-                // If an import entry is valid, it exists; If the number of existent imports is
-                // equal to the number of valid imports, all existent imports are valid; If all
-                // existent imports are valid, there are no existent invalid imports; qed
-                valid_entries_count == import_section_len
-            }
-        })
+    /// Lists an import which the module must import, with the given kind and signature.
+    pub fn require(mut self, import: ImportType<'a>) -> Self {
+        self.entries.push((import, true));
+        self
+    }
+
+    /// Forbids the import, which fails the module even if its namespace is allowed or it is
+    /// listed.
+    pub fn forbid(mut self, namespace: &'a str, field: &'a str) -> Self {
+        self.forbidden.push((namespace, field));
+        self
+    }
+
+    /// Sets whether the module may have imports which are not listed. Allowed by default.
+    pub fn allow_extra(mut self, allow: bool) -> Self {
+        self.allow_extra = allow;
+        self
+    }
+
+    pub fn build(self) -> VerifyImports<'a> {
+        VerifyImports {
+            entries: self.entries,
+            allow_unlisted: self.allow_extra,
+            namespaces: self.namespaces,
+            forbidden: self.forbidden,
+        }
     }
 }
 
-impl<'a> IsImported for ImportType<'a> {
-    fn is_imported(&self, module: &Module) -> bool {
-        if let Some(section) = module.import_section() {
-            match self {
-                ImportType::Function(namespace, field, sig) => {
-                    has_func_import(module, namespace, field, sig)
+impl<'a> ModuleValidator for VerifyImports<'a> {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let mut reasons = Vec::new();
+
+        // Listed imports which exist must always be of correct type, and must exist as well if
+        // they are required.
+        for (entry, required) in self.entries.iter() {
+            let (namespace, field) = entry.name();
+            match entry.check(module) {
+                ImportStatus::Good => (),
+                ImportStatus::Malformed(reason) => reasons.push(reason),
+                ImportStatus::NotFound if *required => {
+                    reasons.push(format!("missing import `{}::{}`", namespace, field))
                 }
-                ImportType::Global(namespace, field) => {
-                    has_global_import(section, namespace, field)
+                ImportStatus::NotFound => (),
+            }
+        }
+
+        if let Some(section) = module.import_section() {
+            for import in section.entries().iter() {
+                let name = (import.module(), import.field());
+                if self.forbidden.contains(&name) {
+                    reasons.push(format!("import `{}::{}` is forbidden", name.0, name.1));
+                    continue;
                 }
-                ImportType::Memory(namespace, field) => {
-                    has_memory_import(section, namespace, field)
+                let allowed_namespace = self
+                    .namespaces
+                    .as_ref()
+                    .is_none_or(|namespaces| namespaces.contains(&import.module()));
+                if !allowed_namespace {
+                    reasons.push(format!(
+                        "import `{}::{}` is not from an allowed namespace",
+                        name.0, name.1
+                    ));
+                } else if !self.allow_unlisted
+                    && !self.entries.iter().any(|(e, _)| e.name() == name)
+                {
+                    reasons.push(format!("unexpected import `{}::{}`", name.0, name.1));
                 }
-                ImportType::Table(namespace, field) => has_table_import(section, namespace, field),
             }
-        } else {
-            false
         }
+
+        Ok(ValidationResult::from_reasons(reasons))
     }
-}
 
-impl<'a> ImportCheck for ImportType<'a> {
-    fn check(&self, module: &Module) -> ImportStatus {
-        // Destructure self here so that it is easier to manipulate individual fields later.
-        let (module_str, field_str, func_sig) = match self {
-            ImportType::Function(namespace, field, sig) => (namespace, field, Some(sig)),
-            ImportType::Global(namespace, field) => (namespace, field, None),
-            ImportType::Memory(namespace, field) => (namespace, field, None),
-            ImportType::Table(namespace, field) => (namespace, field, None),
-        };
+    /// Only the sections declaring the interface of the module are deserialized.
+    #[cfg(feature = "std")]
+    fn validate_binary(&self, binary: &[u8]) -> Option<Result<ValidationResult, String>> {
+        Some(interface_module(binary).and_then(|module| self.validate(&module)))
+    }
 
-        if let Some(section) = module.import_section() {
-            // Find an entry that matches self. If the name matches, check the namespace and/or
-            // signature.
-            if let Some(entry) = section
-                .entries()
+    fn interface_only(&self) -> bool {
+        true
+    }
+
+    fn rules(&self) -> Vec<String> {
+        let mut rules: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(entry, required)| {
+                let verb = if *required { "requires" } else { "allows" };
+                format!("{} import {}", verb, entry.describe())
+            })
+            .collect();
+        if let Some(ref namespaces) = self.namespaces {
+            let namespaces: Vec<String> = namespaces
                 .iter()
-                .find(|e| e.field() == *field_str && *module_str == e.module())
-            {
-                match entry.external() {
-                    // TODO: Wrap this in a helper.
-                    External::Function(idx) => if let Some(sig) = func_sig {
-                        if *sig == imported_func_sig_by_index(module, *idx as usize) {
-                            ImportStatus::Good
-                        } else {
-                            ImportStatus::Malformed
-                        }
-                    } else {
-                        ImportStatus::Malformed
-                    },
-                    // NOTE: There may be a better way to do mappings between enum variants.
-                    // Just check import variant here.
-                    External::Global(_idx) => if let ImportType::Global(_n, _f) = self {
-                        ImportStatus::Good
-                    } else {
-                        ImportStatus::Malformed
-                    },
-                    External::Memory(_idx) => if let ImportType::Memory(_n, _f) = self {
-                        ImportStatus::Good
-                    } else {
-                        ImportStatus::Malformed
-                    },
-                    External::Table(_idx) => if let ImportType::Table(_n, _f) = self {
-                        ImportStatus::Good
-                    } else {
-                        ImportStatus::Malformed
-                    },
-                }
-            } else {
-                ImportStatus::NotFound
-            }
-        } else {
-            ImportStatus::NotFound
+                .map(|namespace| format!("`{}`", namespace))
+                .collect();
+            rules.push(format!(
+                "requires every import to come from {}",
+                namespaces.join(", ")
+            ));
         }
+        rules.extend(
+            self.forbidden
+                .iter()
+                .map(|(namespace, field)| format!("forbids import `{}::{}`", namespace, field)),
+        );
+        rules.push(if self.allow_unlisted {
+            "allows unlisted imports".to_string()
+        } else {
+            "rejects unlisted imports".to_string()
+        });
+        rules
     }
 }
 
-fn has_global_import(section: &ImportSection, namespace: &str, field: &str) -> bool {
-    if let Some(import) = section
-        .entries()
-        .iter()
-        .find(|e| e.module() == namespace && e.field() == field)
-    {
-        match import.external() {
-            External::Global(_globaltype) => true,
-            _ => false,
+impl<'a> ImportType<'a> {
+    /// Describes the kind, name and signature of the import, as e.g.
+    /// ``function `ethereum::useGas` (i64) -> ()``.
+    fn describe(&self) -> String {
+        let (namespace, field) = self.name();
+        match self {
+            ImportType::Function(_, _, sig) => format!(
+                "function `{}::{}` {}",
+                namespace,
+                field,
+                format_signature(sig)
+            ),
+            ImportType::Global(..) => format!("global `{}::{}`", namespace, field),
+            ImportType::Memory(..) => format!("memory `{}::{}`", namespace, field),
+            ImportType::Table(..) => format!("table `{}::{}`", namespace, field),
         }
-    } else {
-        false
     }
-}
 
-fn has_memory_import(section: &ImportSection, namespace: &str, field: &str) -> bool {
-    if let Some(import) = section
-        .entries()
-        .iter()
-        .find(|e| e.module() == namespace && e.field() == field)
-    {
-        match import.external() {
-            External::Memory(_memorytype) => true,
-            _ => false,
+    /// Returns the namespace and name under which the import is expected.
+    fn name(&self) -> (&'a str, &'a str) {
+        match self {
+            ImportType::Function(namespace, field, _) => (namespace, field),
+            ImportType::Global(namespace, field) => (namespace, field),
+            ImportType::Memory(namespace, field) => (namespace, field),
+            ImportType::Table(namespace, field) => (namespace, field),
         }
-    } else {
-        false
     }
 }
 
-fn has_table_import(section: &ImportSection, namespace: &str, field: &str) -> bool {
-    if let Some(import) = section
-        .entries()
-        .iter()
-        .find(|e| e.module() == namespace && e.field() == field)
-    {
-        match import.external() {
-            External::Table(_tabletype) => true,
-            _ => false,
+impl<'a> ImportType<'a> {
+    /// Returns the kind of import as it would be named in the text format.
+    fn kind(&self) -> &'static str {
+        match self {
+            ImportType::Function(_, _, _) => "function",
+            ImportType::Global(_, _) => "global",
+            ImportType::Memory(_, _) => "memory",
+            ImportType::Table(_, _) => "table",
         }
-    } else {
-        false
     }
 }
 
-fn has_func_import(module: &Module, namespace: &str, field: &str, sig: &FunctionType) -> bool {
-    if let Some(section) = module.import_section() {
-        if let Some(import) = section
-            .entries()
-            .iter()
-            .find(|e| e.module() == namespace && e.field() == field)
-        {
-            match import.external() {
-                External::Function(index) => {
-                    imported_func_sig_by_index(module, *index as usize) == *sig
+impl<'a> ImportCheck for ImportType<'a> {
+    fn check(&self, module: &Module) -> ImportStatus {
+        let (namespace, field) = self.name();
+        let entry = match module.import_section().and_then(|section| {
+            section
+                .entries()
+                .iter()
+                .find(|e| e.module() == namespace && e.field() == field)
+        }) {
+            Some(entry) => entry,
+            None => return ImportStatus::NotFound,
+        };
+
+        match (self, entry.external()) {
+            (ImportType::Function(_, _, expected), External::Function(type_ref)) => {
+                match func_type_by_ref(module, *type_ref) {
+                    Some(actual) if actual == expected => ImportStatus::Good,
+                    Some(actual) => ImportStatus::Malformed(format!(
+                        "import `{}::{}` has signature {}, expected {}",
+                        namespace,
+                        field,
+                        format_signature(actual),
+                        format_signature(expected)
+                    )),
+                    None => ImportStatus::Malformed(format!(
+                        "import `{}::{}` refers to a nonexistent type",
+                        namespace, field
+                    )),
                 }
-                _ => false,
             }
-        } else {
-            false
+            (ImportType::Global(_, _), External::Global(_))
+            | (ImportType::Memory(_, _), External::Memory(_))
+            | (ImportType::Table(_, _), External::Table(_)) => ImportStatus::Good,
+            (_, external) => ImportStatus::Malformed(format!(
+                "import `{}::{}` is a {}, expected a {}",
+                namespace,
+                field,
+                external_kind(external),
+                self.kind()
+            )),
         }
-    } else {
-        false
+    }
+}
+
+/// Returns the kind of an import in the module as it would be named in the text format.
+fn external_kind(external: &External) -> &'static str {
+    match external {
+        External::Function(_) => "function",
+        External::Global(_) => "global",
+        External::Memory(_) => "memory",
+        External::Table(_) => "table",
+    }
+}
+
+/// Resolves a function type from its index in the type section.
+fn func_type_by_ref(module: &Module, type_ref: u32) -> Option<&FunctionType> {
+    match module.type_section()?.types().get(type_ref as usize)? {
+        Type::Function(ref func_type) => Some(func_type),
     }
 }
 
 /// Resolves an imported function's signature from its callable index.
 pub fn imported_func_sig_by_index(module: &Module, index: usize) -> FunctionType {
     let import_section = module.import_section().expect("No function section found");
-    let type_section = module.type_section().expect("No type section found");
 
-    let func_type_ref: usize = match import_section.entries()[index].external() {
-        &External::Function(idx) => idx as usize,
-        _ => usize::max_value(),
-    };
+    // Only function imports take up a callable index.
+    let func_type_ref = import_section
+        .entries()
+        .iter()
+        .filter_map(|e| match e.external() {
+            External::Function(type_ref) => Some(*type_ref),
+            _ => None,
+        })
+        .nth(index)
+        .expect("No imported function at the given index");
 
-    match type_section.types()[func_type_ref] {
-        Type::Function(ref func_type) => func_type.clone(),
-    }
+    func_type_by_ref(module, func_type_ref)
+        .expect("No type section found")
+        .clone()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use parity_wasm::elements::deserialize_buffer;
+    use fromwat::FromWat;
+    use parity_wasm::builder;
+    use parity_wasm::elements::{deserialize_buffer, ValueType};
+    use ModuleCreator;
 
     #[test]
     fn no_imports_ok_ewasm() {
@@ -528,9 +391,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -553,9 +416,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -578,9 +441,32 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports::with_preset("ewasm").unwrap();
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap();
+        assert!(!result.passed());
+        assert_eq!(
+            result.reasons(),
+            ["import `ethereum::storageStore` has signature (i32) -> (), expected (i32, i32) -> ()"]
+        );
+    }
+
+    #[test]
+    fn one_import_wrong_kind_ewasm() {
+        let module = builder::module()
+            .import()
+            .module("ethereum")
+            .field("getGasLeft")
+            .external()
+            .memory(1, None)
+            .build()
+            .build();
+
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
         let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        assert_eq!(
+            result.reasons(),
+            ["import `ethereum::getGasLeft` is a memory, expected a function"]
+        );
     }
 
     #[test]
@@ -603,9 +489,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -628,9 +514,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -664,9 +550,9 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports::with_preset("ewasm").unwrap();
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -691,9 +577,10 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports::with_preset("ewasm").unwrap();
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
         let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        assert!(!result.passed());
+        assert_eq!(result.reasons(), ["unexpected import `ethereum::foobar`"]);
     }
 
     #[test]
@@ -718,11 +605,11 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let mut checker = VerifyImports::with_preset("ewasm").unwrap();
+        let mut checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
         // Allow unlisted, just for this test case
         checker.set_allow_unlisted(true);
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -745,11 +632,11 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let mut checker = VerifyImports::with_preset("ewasm").unwrap();
+        let mut checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
         // Require all, just for this test case
         checker.set_require_all(true);
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -772,19 +659,16 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports {
-            entries: vec![ImportType::Function(
+        let checker = VerifyImports::builder()
+            .require(ImportType::Function(
                 "ethereum",
                 "storageStore",
                 FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-            )].iter()
-            .cloned()
-            .collect(),
-            allow_unlisted: false,
-            require_all: true,
-        };
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+            ))
+            .allow_extra(false)
+            .build();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -809,19 +693,16 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports {
-            entries: vec![ImportType::Function(
+        let checker = VerifyImports::builder()
+            .require(ImportType::Function(
                 "ethereum",
                 "storageStore",
                 FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-            )].iter()
-            .cloned()
-            .collect(),
-            allow_unlisted: true,
-            require_all: true,
-        };
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(true, result);
+            ))
+            .allow_extra(true)
+            .build();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(result);
     }
 
     #[test]
@@ -846,19 +727,16 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports {
-            entries: vec![ImportType::Function(
+        let checker = VerifyImports::builder()
+            .require(ImportType::Function(
                 "ethereum",
                 "storageStore",
                 FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-            )].iter()
-            .cloned()
-            .collect(),
-            allow_unlisted: false,
-            require_all: true,
-        };
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+            ))
+            .allow_extra(false)
+            .build();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
     }
 
     #[test]
@@ -883,19 +761,146 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyImports {
-            entries: vec![ImportType::Function(
+        let checker = VerifyImports::builder()
+            .require(ImportType::Function(
                 "ethereum",
                 "storageStore",
                 FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
-            )].iter()
-            .cloned()
-            .collect(),
-            allow_unlisted: false,
-            require_all: true,
-        };
-        let result = checker.validate(&module).unwrap();
-        assert_eq!(false, result);
+            ))
+            .allow_extra(false)
+            .build();
+        let result = checker.validate(&module).unwrap().passed();
+        assert!(!result);
+    }
+
+    #[test]
+    fn namespace_not_allowed() {
+        // Same module as above, importing `env::foo` besides `ethereum::storageStore`.
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x09, 0x02, 0x60, 0x02, 0x7f,
+            0x7f, 0x00, 0x60, 0x00, 0x00, 0x02, 0x23, 0x02, 0x08, 0x65, 0x74, 0x68, 0x65, 0x72,
+            0x65, 0x75, 0x6d, 0x0c, 0x73, 0x74, 0x6f, 0x72, 0x61, 0x67, 0x65, 0x53, 0x74, 0x6f,
+            0x72, 0x65, 0x00, 0x00, 0x03, 0x65, 0x6e, 0x76, 0x03, 0x66, 0x6f, 0x6f, 0x00, 0x01,
+            0x03, 0x02, 0x01, 0x01, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x11, 0x02, 0x04, 0x6d,
+            0x61, 0x69, 0x6e, 0x00, 0x02, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00,
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let mut checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["import `env::foo` is not from an allowed namespace"]
+        );
+
+        checker.set_allow_unlisted(true);
+        assert!(!checker.validate(&module).unwrap().passed());
+        checker.set_namespaces(Some(vec!["ethereum", "env"]));
+        assert!(checker.validate(&module).unwrap().passed());
+        checker.set_namespaces(None);
+        assert!(checker.validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn builder_rules_reported_independently() {
+        let module = FromWat::new(
+            r#"(module
+                (import "ethereum" "useGas" (func (param i32)))
+                (import "ethereum" "foo" (func))
+                (import "env" "abort" (func)))"#,
+        )
+        .create()
+        .unwrap();
+        let use_gas = ImportType::Function(
+            "ethereum",
+            "useGas",
+            FunctionType::new(vec![ValueType::I64], None),
+        );
+
+        assert!(VerifyImports::builder()
+            .build()
+            .validate(&module)
+            .unwrap()
+            .passed());
+        let checker = VerifyImports::builder()
+            .namespace("ethereum")
+            .import(use_gas.clone())
+            .require(ImportType::Memory("ethereum", "memory"))
+            .allow_extra(false)
+            .build();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            [
+                "import `ethereum::useGas` has signature (i32) -> (), expected (i64) -> ()",
+                "missing import `ethereum::memory`",
+                "unexpected import `ethereum::foo`",
+                "import `env::abort` is not from an allowed namespace",
+            ]
+        );
+
+        // Each rule left out drops its own diagnostics only.
+        let checker = VerifyImports::builder()
+            .namespace("ethereum")
+            .namespace("env")
+            .import(use_gas)
+            .build();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["import `ethereum::useGas` has signature (i32) -> (), expected (i64) -> ()"]
+        );
+    }
+
+    #[test]
+    fn forbidden_import_reported() {
+        let module = FromWat::new(
+            r#"(module
+                (import "ethereum" "useGas" (func (param i64)))
+                (import "ethereum" "selfDestruct" (func (param i32))))"#,
+        )
+        .create()
+        .unwrap();
+        let mut checker = VerifyImports::builder()
+            .namespace("ethereum")
+            .forbid("ethereum", "selfDestruct")
+            .forbid("ethereum", "call")
+            .build();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["import `ethereum::selfDestruct` is forbidden"]
+        );
+
+        // Forbidding takes precedence over the imports listed by the preset.
+        checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        assert!(checker.validate(&module).unwrap().passed());
+        checker.set_forbidden(vec![("ethereum", "useGas")]);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["import `ethereum::useGas` is forbidden"]
+        );
     }
 
+    #[test]
+    fn binary_agrees_with_module() {
+        let sources = [
+            r#"(module
+                (import "ethereum" "storageStore" (func (param i32 i32)))
+                (memory 1)
+                (func (export "main")))"#,
+            r#"(module
+                (import "ethereum" "storageStore" (func (param i32)))
+                (import "ethereum" "foo" (func))
+                (import "env" "abort" (func))
+                (func (export "main")))"#,
+        ];
+        let checker = VerifyImports::with_preset(Preset::Ewasm).unwrap();
+        for source in sources.iter() {
+            let module = FromWat::new(source).create().unwrap();
+            let binary = parity_wasm::serialize(module.clone()).unwrap();
+            assert_eq!(
+                checker.validate_binary(&binary),
+                Some(checker.validate(&module))
+            );
+        }
+        assert!(checker.validate_binary(b"not wasm").unwrap().is_err());
+    }
 }
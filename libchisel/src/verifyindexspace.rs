@@ -0,0 +1,233 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::{ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{ImportCountType, Instruction, Internal, Module};
+
+/// Struct on which ModuleValidator is implemented.
+/// Verifies that every reference to a function, and every export, resolves within the index
+/// spaces of the module, in which the imports come before the entries the module defines. A
+/// module edited by hand, merged from others or transformed by a buggy tool can otherwise refer
+/// past the end of them. Only the first such reference is reported.
+#[derive(Default)]
+pub struct VerifyIndexSpace;
+
+impl VerifyIndexSpace {
+    pub fn new() -> Self {
+        VerifyIndexSpace
+    }
+}
+
+impl ModuleValidator for VerifyIndexSpace {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let reasons = match first_invalid_reference(module) {
+            Some(reason) => vec![reason],
+            None => vec![],
+        };
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        vec![
+            "requires calls, element segments, exports and the start function to refer to entries which exist"
+                .to_string(),
+        ]
+    }
+}
+
+/// Returns the number of entries in the index space of the kind, imported ones first.
+fn index_space_len(module: &Module, kind: ImportCountType) -> usize {
+    let defined = match kind {
+        ImportCountType::Function => module
+            .function_section()
+            .map_or(0, |section| section.entries().len()),
+        ImportCountType::Global => module
+            .global_section()
+            .map_or(0, |section| section.entries().len()),
+        ImportCountType::Memory => module
+            .memory_section()
+            .map_or(0, |section| section.entries().len()),
+        ImportCountType::Table => module
+            .table_section()
+            .map_or(0, |section| section.entries().len()),
+    };
+    module.import_count(kind) + defined
+}
+
+/// Describes the number of entries in an index space, e.g. "1 function".
+fn count(len: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", len, if len == 1 { singular } else { plural })
+}
+
+/// Returns the first reference which is out of range, in the order of the sections of the
+/// module: exports, the start function, element segments, then calls within function bodies.
+fn first_invalid_reference(module: &Module) -> Option<String> {
+    let functions = index_space_len(module, ImportCountType::Function);
+
+    let exports = module
+        .export_section()
+        .map_or(&[][..], |section| section.entries());
+    for export in exports {
+        let (index, kind, plural, len) = match *export.internal() {
+            Internal::Function(index) => (index, "function", "functions", functions),
+            Internal::Global(index) => (
+                index,
+                "global",
+                "globals",
+                index_space_len(module, ImportCountType::Global),
+            ),
+            Internal::Memory(index) => (
+                index,
+                "memory",
+                "memories",
+                index_space_len(module, ImportCountType::Memory),
+            ),
+            Internal::Table(index) => (
+                index,
+                "table",
+                "tables",
+                index_space_len(module, ImportCountType::Table),
+            ),
+        };
+        if index as usize >= len {
+            return Some(format!(
+                "export `{}` refers to {} {}, but the module has {}",
+                export.field(),
+                kind,
+                index,
+                count(len, kind, plural)
+            ));
+        }
+    }
+
+    if let Some(index) = module.start_section() {
+        if index as usize >= functions {
+            return Some(format!(
+                "start function {} does not exist, the module has {}",
+                index,
+                count(functions, "function", "functions")
+            ));
+        }
+    }
+
+    let segments = module
+        .elements_section()
+        .map_or(&[][..], |section| section.entries());
+    for (segment_index, segment) in segments.iter().enumerate() {
+        if let Some(index) = segment
+            .members()
+            .iter()
+            .find(|index| **index as usize >= functions)
+        {
+            return Some(format!(
+                "element segment {} refers to function {}, but the module has {}",
+                segment_index,
+                index,
+                count(functions, "function", "functions")
+            ));
+        }
+    }
+
+    let imported = module.import_count(ImportCountType::Function);
+    let bodies = module
+        .code_section()
+        .map_or(&[][..], |section| section.bodies());
+    for (body_index, body) in bodies.iter().enumerate() {
+        for instruction in body.code().elements() {
+            if let Instruction::Call(index) = *instruction {
+                if index as usize >= functions {
+                    return Some(format!(
+                        "function {} calls function {}, but the module has {}",
+                        imported + body_index,
+                        index,
+                        count(functions, "function", "functions")
+                    ));
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use parity_wasm::elements::{ElementSection, ElementSegment, ExportEntry, InitExpr, Section};
+
+    /// A module importing one function and defining another, which calls the function of the
+    /// given index.
+    fn module_calling(index: u32) -> Module {
+        builder::module()
+            .import()
+            .module("env")
+            .field("f")
+            .external()
+            .func(0)
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .with_instructions(parity_wasm::elements::Instructions::new(vec![
+                Instruction::Call(index),
+                Instruction::End,
+            ]))
+            .build()
+            .build()
+            .build()
+    }
+
+    fn validate(module: &Module) -> ValidationResult {
+        VerifyIndexSpace::new().validate(module).unwrap()
+    }
+
+    #[test]
+    fn references_in_range_good() {
+        let mut module = module_calling(0);
+        module
+            .sections_mut()
+            .push(Section::Element(ElementSection::with_entries(vec![
+                ElementSegment::new(0, Some(InitExpr::empty()), vec![0, 1], false),
+            ])));
+        module.set_start_section(1);
+        assert!(validate(&module).passed());
+        assert!(validate(&Module::default()).passed());
+    }
+
+    #[test]
+    fn call_out_of_range() {
+        assert_eq!(
+            validate(&module_calling(2)).reasons(),
+            ["function 1 calls function 2, but the module has 2 functions"]
+        );
+    }
+
+    #[test]
+    fn first_reference_reported() {
+        let mut module = module_calling(5);
+        module
+            .sections_mut()
+            .push(Section::Element(ElementSection::with_entries(vec![
+                ElementSegment::new(0, Some(InitExpr::empty()), vec![0, 3], false),
+            ])));
+        assert_eq!(
+            validate(&module).reasons(),
+            ["element segment 0 refers to function 3, but the module has 2 functions"]
+        );
+
+        module.set_start_section(4);
+        assert_eq!(
+            validate(&module).reasons(),
+            ["start function 4 does not exist, the module has 2 functions"]
+        );
+
+        let export = ExportEntry::new("memory".to_string(), Internal::Memory(0));
+        module = builder::module().with_export(export).build();
+        assert_eq!(
+            validate(&module).reasons(),
+            ["export `memory` refers to memory 0, but the module has 0 memories"]
+        );
+    }
+}
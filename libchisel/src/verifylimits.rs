@@ -0,0 +1,231 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::presets::{Limits, Preset};
+use super::{ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, Module, Type};
+
+/// Struct on which ModuleValidator is implemented.
+/// Guards against pathological modules by capping the number of functions, and the parameters
+/// and locals of each of them. Only the first cap exceeded is reported.
+pub struct VerifyLimits {
+    limits: Limits,
+}
+
+impl ModulePreset for VerifyLimits {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(VerifyLimits::new(preset.limits()))
+    }
+}
+
+impl VerifyLimits {
+    pub fn new(limits: Limits) -> Self {
+        VerifyLimits { limits }
+    }
+
+    pub fn set_max_functions(&mut self, max_functions: Option<u32>) {
+        self.limits.functions = max_functions;
+    }
+
+    pub fn set_max_imported_functions(&mut self, max_imported_functions: Option<u32>) {
+        self.limits.imported_functions = max_imported_functions;
+    }
+
+    pub fn set_max_locals(&mut self, max_locals: Option<u32>) {
+        self.limits.locals = max_locals;
+    }
+
+    pub fn set_max_params(&mut self, max_params: Option<u32>) {
+        self.limits.params = max_params;
+    }
+
+    /// Returns the first cap the module exceeds.
+    fn exceeded(&self, module: &Module) -> Result<Option<String>, String> {
+        let imported = imported_function_types(module);
+        let defined = module
+            .function_section()
+            .map_or(&[][..], |section| section.entries());
+
+        if let Some(max) = self.limits.functions {
+            if defined.len() > max as usize {
+                return Ok(Some(format!(
+                    "module defines {} functions, expected at most {}",
+                    defined.len(),
+                    max
+                )));
+            }
+        }
+        if let Some(max) = self.limits.imported_functions {
+            if imported.len() > max as usize {
+                return Ok(Some(format!(
+                    "module imports {} functions, expected at most {}",
+                    imported.len(),
+                    max
+                )));
+            }
+        }
+
+        if let Some(max) = self.limits.params {
+            let types = module
+                .type_section()
+                .map_or(&[][..], |section| section.types());
+            let type_refs = imported
+                .iter()
+                .cloned()
+                .chain(defined.iter().map(|func| func.type_ref()));
+            for (index, type_ref) in type_refs.enumerate() {
+                let params = match types.get(type_ref as usize) {
+                    Some(Type::Function(sig)) => sig.params().len(),
+                    None => return Err(format!("type {} does not exist", type_ref)),
+                };
+                if params > max as usize {
+                    return Ok(Some(format!(
+                        "function {} has {} parameters, expected at most {}",
+                        index, params, max
+                    )));
+                }
+            }
+        }
+
+        if let Some(max) = self.limits.locals {
+            let bodies = module
+                .code_section()
+                .map_or(&[][..], |section| section.bodies());
+            for (index, body) in bodies.iter().enumerate() {
+                let locals: u64 = body.locals().iter().map(|local| local.count() as u64).sum();
+                if locals > max as u64 {
+                    return Ok(Some(format!(
+                        "function {} declares {} locals, expected at most {}",
+                        imported.len() + index,
+                        locals,
+                        max
+                    )));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl ModuleValidator for VerifyLimits {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        Ok(ValidationResult::from_reasons(
+            self.exceeded(module)?.into_iter().collect(),
+        ))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        let caps = [
+            (self.limits.functions, "defined functions"),
+            (self.limits.imported_functions, "imported functions"),
+            (self.limits.params, "parameters per function"),
+            (self.limits.locals, "locals per function"),
+        ];
+        let rules: Vec<String> = caps
+            .iter()
+            .filter_map(|(max, what)| max.map(|max| format!("allows at most {} {}", max, what)))
+            .collect();
+        if rules.is_empty() {
+            return vec!["allows any number of functions, parameters and locals".to_string()];
+        }
+        rules
+    }
+}
+
+/// Returns the type of every imported function, which come first in the function index space.
+fn imported_function_types(module: &Module) -> Vec<u32> {
+    module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Function(type_ref) => Some(*type_ref),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    /// Imports a function of two parameters, and defines one declaring three locals.
+    static SAMPLE: &str = r#"(module
+        (import "env" "add" (func (param i32 i32) (result i32)))
+        (func (param i64) (local i32 i32) (local i64)))"#;
+
+    fn reasons(limits: Limits) -> Vec<String> {
+        let module = FromWat::new(SAMPLE).create().unwrap();
+        VerifyLimits::new(limits)
+            .validate(&module)
+            .unwrap()
+            .reasons()
+            .to_vec()
+    }
+
+    #[test]
+    fn within_limits_good() {
+        assert!(reasons(Limits::default()).is_empty());
+        let module = FromWat::new(SAMPLE).create().unwrap();
+        let checker = VerifyLimits::with_preset(Preset::Ewasm).unwrap();
+        assert!(checker.validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn each_cap_checked() {
+        let cap = |limits: Limits| reasons(limits);
+        assert_eq!(
+            cap(Limits {
+                functions: Some(0),
+                ..Limits::default()
+            }),
+            ["module defines 1 functions, expected at most 0"]
+        );
+        assert_eq!(
+            cap(Limits {
+                imported_functions: Some(0),
+                ..Limits::default()
+            }),
+            ["module imports 1 functions, expected at most 0"]
+        );
+        assert_eq!(
+            cap(Limits {
+                params: Some(1),
+                ..Limits::default()
+            }),
+            ["function 0 has 2 parameters, expected at most 1"]
+        );
+        assert_eq!(
+            cap(Limits {
+                locals: Some(2),
+                ..Limits::default()
+            }),
+            ["function 1 declares 3 locals, expected at most 2"]
+        );
+    }
+
+    #[test]
+    fn first_exceeded_reported() {
+        let module = FromWat::new(SAMPLE).create().unwrap();
+        let mut checker = VerifyLimits::new(Limits {
+            functions: Some(0),
+            imported_functions: Some(0),
+            locals: Some(0),
+            params: Some(0),
+        });
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["module defines 1 functions, expected at most 0"]
+        );
+        checker.set_max_functions(None);
+        checker.set_max_imported_functions(None);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["function 0 has 2 parameters, expected at most 0"]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::presets::Preset;
+use super::{ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, Module, ResizableLimits};
+
+/// Struct on which ModuleValidator is implemented.
+pub struct VerifyMemory {
+    /// Largest number of pages the memory may initially allocate.
+    max_initial_pages: u32,
+    /// Largest number of pages the memory may declare as its maximum.
+    max_pages: u32,
+}
+
+impl ModulePreset for VerifyMemory {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        let (max_initial_pages, max_pages) = preset
+            .memory_limits()
+            .ok_or_else(|| format!("Preset {} is not supported", preset))?;
+        Ok(VerifyMemory::new(max_initial_pages, max_pages))
+    }
+}
+
+impl VerifyMemory {
+    /// Constructs a validator allowing memories which initially allocate at most
+    /// `max_initial_pages`, and declare a maximum of at most `max_pages`.
+    pub fn new(max_initial_pages: u32, max_pages: u32) -> Self {
+        VerifyMemory {
+            max_initial_pages,
+            max_pages,
+        }
+    }
+
+    pub fn set_max_initial_pages(&mut self, max_initial_pages: u32) {
+        self.max_initial_pages = max_initial_pages;
+    }
+
+    pub fn set_max_pages(&mut self, max_pages: u32) {
+        self.max_pages = max_pages;
+    }
+}
+
+impl ModuleValidator for VerifyMemory {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let memories = memory_limits(module);
+        let mut reasons = Vec::new();
+
+        if memories.len() > 1 {
+            reasons.push(format!(
+                "module defines {} memories, expected at most 1",
+                memories.len()
+            ));
+        }
+
+        for (index, limits) in memories.iter().enumerate() {
+            let exceeded = limits.initial() > self.max_initial_pages
+                || limits
+                    .maximum()
+                    .is_some_and(|maximum| maximum > self.max_pages);
+            if exceeded {
+                reasons.push(format!(
+                    "memory {} declares {}, expected at most {} initial and {} maximum pages",
+                    index,
+                    format_limits(limits),
+                    self.max_initial_pages,
+                    self.max_pages
+                ));
+            }
+        }
+
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn interface_only(&self) -> bool {
+        true
+    }
+
+    fn rules(&self) -> Vec<String> {
+        vec![
+            "allows at most 1 memory".to_string(),
+            format!(
+                "allows memories of at most {} initial and {} maximum pages",
+                self.max_initial_pages, self.max_pages
+            ),
+        ]
+    }
+}
+
+/// Returns the limits of every memory in the index space, imported ones first.
+fn memory_limits(module: &Module) -> Vec<&ResizableLimits> {
+    let imported = module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .filter_map(|entry| match entry.external() {
+            External::Memory(memory_type) => Some(memory_type.limits()),
+            _ => None,
+        });
+    let defined = module
+        .memory_section()
+        .map_or(&[][..], |section| section.entries())
+        .iter()
+        .map(|memory_type| memory_type.limits());
+    imported.chain(defined).collect()
+}
+
+/// Formats limits as e.g. `17 initial and 256 maximum pages`.
+fn format_limits(limits: &ResizableLimits) -> String {
+    match limits.maximum() {
+        Some(maximum) => format!("{} initial and {} maximum pages", limits.initial(), maximum),
+        None => format!("{} initial pages and no maximum", limits.initial()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+
+    fn module_with_memory(initial: u32, maximum: Option<u32>) -> Module {
+        builder::module()
+            .memory()
+            .with_min(initial)
+            .with_max(maximum)
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn within_limits_good() {
+        let checker = VerifyMemory::with_preset(Preset::Ewasm).unwrap();
+        assert!(checker
+            .validate(&module_with_memory(17, None))
+            .unwrap()
+            .passed());
+        assert!(checker
+            .validate(&module_with_memory(1, Some(1024)))
+            .unwrap()
+            .passed());
+        assert!(checker.validate(&Module::default()).unwrap().passed());
+    }
+
+    #[test]
+    fn exceeded_limits_bad() {
+        let checker = VerifyMemory::new(16, 256);
+        let result = checker
+            .validate(&module_with_memory(17, Some(300)))
+            .unwrap();
+        assert_eq!(
+            result.reasons(),
+            ["memory 0 declares 17 initial and 300 maximum pages, expected at most 16 initial and 256 maximum pages"]
+        );
+
+        let result = checker.validate(&module_with_memory(20, None)).unwrap();
+        assert_eq!(
+            result.reasons(),
+            ["memory 0 declares 20 initial pages and no maximum, expected at most 16 initial and 256 maximum pages"]
+        );
+    }
+
+    #[test]
+    fn multiple_memories_bad() {
+        let module = builder::module()
+            .import()
+            .module("env")
+            .field("memory")
+            .external()
+            .memory(1, None)
+            .build()
+            .memory()
+            .with_min(1)
+            .build()
+            .build();
+
+        let checker = VerifyMemory::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap();
+        assert_eq!(
+            result.reasons(),
+            ["module defines 2 memories, expected at most 1"]
+        );
+    }
+
+    #[test]
+    fn pwasm_not_supported() {
+        assert!(VerifyMemory::with_preset(Preset::PWasm).is_err());
+    }
+}
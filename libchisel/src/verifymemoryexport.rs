@@ -0,0 +1,167 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, Internal, Module};
+
+/// Name under which ewasm expects the memory to be exported.
+static MEMORY_EXPORT: &str = "memory";
+
+/// Struct on which ModuleValidator is implemented.
+/// Verifies that the module has exactly one memory, exported as `memory`, as ewasm expects.
+#[derive(Default)]
+pub struct VerifyMemoryExport;
+
+impl VerifyMemoryExport {
+    pub fn new() -> Self {
+        VerifyMemoryExport
+    }
+}
+
+impl ModuleValidator for VerifyMemoryExport {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let mut reasons = Vec::new();
+
+        let memories = memory_count(module);
+        if memories != 1 {
+            reasons.push(format!(
+                "module has {} memories, expected exactly 1",
+                memories
+            ));
+        }
+
+        let exports = module
+            .export_section()
+            .map_or(&[][..], |section| section.entries());
+        match exports
+            .iter()
+            .find(|export| export.field() == MEMORY_EXPORT)
+        {
+            Some(export) => match export.internal() {
+                Internal::Memory(0) => (),
+                Internal::Memory(index) => reasons.push(format!(
+                    "export `{}` refers to memory {}, expected memory 0",
+                    MEMORY_EXPORT, index
+                )),
+                internal => reasons.push(format!(
+                    "export `{}` is a {}, expected a memory",
+                    MEMORY_EXPORT,
+                    internal_kind(internal)
+                )),
+            },
+            None => {
+                // Point out a memory exported under another name, which is likely a mistake.
+                let misnamed = exports
+                    .iter()
+                    .find(|export| matches!(export.internal(), Internal::Memory(_)));
+                reasons.push(match misnamed {
+                    Some(export) => format!(
+                        "missing export `{}`, memory is exported as `{}`",
+                        MEMORY_EXPORT,
+                        export.field()
+                    ),
+                    None => format!("missing export `{}`", MEMORY_EXPORT),
+                });
+            }
+        }
+
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn interface_only(&self) -> bool {
+        true
+    }
+
+    fn rules(&self) -> Vec<String> {
+        vec![format!(
+            "requires exactly 1 memory, exported as `{}`",
+            MEMORY_EXPORT
+        )]
+    }
+}
+
+/// Returns the number of memories in the index space, imported ones included.
+fn memory_count(module: &Module) -> usize {
+    let imported = module.import_section().map_or(0, |section| {
+        section
+            .entries()
+            .iter()
+            .filter(|entry| matches!(entry.external(), External::Memory(_)))
+            .count()
+    });
+    imported
+        + module
+            .memory_section()
+            .map_or(0, |section| section.entries().len())
+}
+
+/// Returns the kind of an export as it would be named in the text format.
+fn internal_kind(internal: &Internal) -> &'static str {
+    match internal {
+        Internal::Function(_) => "function",
+        Internal::Global(_) => "global",
+        Internal::Memory(_) => "memory",
+        Internal::Table(_) => "table",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    fn reasons(source: &str) -> Vec<String> {
+        let module = FromWat::new(source).create().unwrap();
+        VerifyMemoryExport::new()
+            .validate(&module)
+            .unwrap()
+            .reasons()
+            .to_vec()
+    }
+
+    #[test]
+    fn exported_memory_good() {
+        assert!(reasons(r#"(module (memory (export "memory") 1))"#).is_empty());
+    }
+
+    #[test]
+    fn memory_count_checked() {
+        assert_eq!(
+            reasons("(module)"),
+            [
+                "module has 0 memories, expected exactly 1",
+                "missing export `memory`",
+            ]
+        );
+        assert_eq!(
+            reasons(
+                r#"(module
+                    (import "env" "memory" (memory 1))
+                    (memory 1)
+                    (export "memory" (memory 1)))"#
+            ),
+            [
+                "module has 2 memories, expected exactly 1",
+                "export `memory` refers to memory 1, expected memory 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_export_checked() {
+        assert_eq!(
+            reasons(r#"(module (memory (export "mem") 1))"#),
+            ["missing export `memory`, memory is exported as `mem`"]
+        );
+        assert_eq!(
+            reasons(
+                r#"(module
+                    (memory 1)
+                    (func (export "memory")))"#
+            ),
+            ["export `memory` is a function, expected a memory"]
+        );
+    }
+}
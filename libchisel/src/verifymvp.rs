@@ -0,0 +1,215 @@
+use std::io;
+
+use super::presets::{Feature, Preset};
+use super::{ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{opcodes, External, Instruction, Module, Serialize};
+
+/// Struct on which ModuleValidator is implemented.
+/// Checks that the module only uses instructions of the WebAssembly MVP, and of the post-MVP
+/// features which are allowed. The non-trapping float-to-int conversions are not checked, as
+/// parity-wasm already fails to deserialize them.
+pub struct VerifyMvp {
+    /// Post-MVP features which the module may use.
+    features: Vec<Feature>,
+}
+
+impl ModulePreset for VerifyMvp {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(VerifyMvp::with_features(preset.features().to_vec()))
+    }
+}
+
+impl VerifyMvp {
+    /// Constructs a validator allowing the given post-MVP features.
+    pub fn with_features(features: Vec<Feature>) -> Self {
+        VerifyMvp { features }
+    }
+}
+
+impl ModuleValidator for VerifyMvp {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let mut reasons = Vec::new();
+        for (index, instructions) in function_bodies(module) {
+            let mut found: Vec<(String, Feature)> = Vec::new();
+            for instruction in instructions {
+                let feature = match instruction_feature(instruction)? {
+                    Some(feature) if !self.features.contains(&feature) => feature,
+                    _ => continue,
+                };
+                let name = mnemonic(instruction);
+                if !found.iter().any(|(found, _)| *found == name) {
+                    found.push((name, feature));
+                }
+            }
+            reasons.extend(found.into_iter().map(|(name, feature)| {
+                format!(
+                    "function {} uses `{}` of the {} proposal",
+                    index, name, feature
+                )
+            }));
+        }
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        let mut rules = vec!["allows the instructions of the MVP".to_string()];
+        rules.extend(Feature::ALL.iter().map(|feature| {
+            let verb = if self.features.contains(feature) {
+                "allows"
+            } else {
+                "rejects"
+            };
+            format!("{} the instructions of the {} proposal", verb, feature)
+        }));
+        rules
+    }
+}
+
+/// Returns the instructions of every defined function alongside its index in the function
+/// index space, which counts the imported functions first.
+pub(crate) fn function_bodies(module: &Module) -> Vec<(usize, &[Instruction])> {
+    let imported = module.import_section().map_or(0, |section| {
+        section
+            .entries()
+            .iter()
+            .filter(|entry| matches!(entry.external(), External::Function(_)))
+            .count()
+    });
+    module
+        .code_section()
+        .map_or(&[][..], |section| section.bodies())
+        .iter()
+        .enumerate()
+        .map(|(index, body)| (imported + index, body.code().elements()))
+        .collect()
+}
+
+/// Returns the post-MVP feature the instruction belongs to, or None if it is part of the MVP.
+/// Instructions are classified by their encoding, as each proposal has an opcode range or
+/// prefix of its own.
+pub(crate) fn instruction_feature(instruction: &Instruction) -> Result<Option<Feature>, String> {
+    let mut opcode = FirstByte(None);
+    instruction
+        .clone()
+        .serialize(&mut opcode)
+        .map_err(|err| format!("Failed to serialize instruction: {}", err))?;
+    Ok(match opcode.0.as_ref() {
+        Some(&opcodes::BULK_PREFIX) => Some(Feature::BulkMemory),
+        Some(&opcodes::SIMD_PREFIX) => Some(Feature::Simd),
+        Some(&opcodes::ATOMIC_PREFIX) => Some(Feature::Threads),
+        Some(opcode) if (opcodes::I32_EXTEND8_S..=opcodes::I64_EXTEND32_S).contains(opcode) => {
+            Some(Feature::SignExtension)
+        }
+        _ => None,
+    })
+}
+
+/// Writer keeping only the first byte written to it, which is enough to classify an instruction
+/// without allocating a buffer for each one.
+struct FirstByte(Option<u8>);
+
+impl io::Write for FirstByte {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.0.is_none() {
+            self.0 = buf.first().cloned();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the name of the instruction without its immediates, e.g. `memory.copy`.
+pub(crate) fn mnemonic(instruction: &Instruction) -> String {
+    instruction
+        .to_string()
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use parity_wasm::elements::Instructions;
+
+    fn module_with_code(code: Vec<Instruction>) -> Module {
+        builder::module()
+            .import()
+            .module("env")
+            .field("abort")
+            .external()
+            .func(0)
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .with_instructions(Instructions::new(code))
+            .build()
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn mvp_good() {
+        let module = module_with_code(vec![
+            Instruction::I32Const(0),
+            Instruction::I32Load(2, 0),
+            Instruction::Drop,
+            Instruction::End,
+        ]);
+        let checker = VerifyMvp::with_preset(Preset::Ewasm).unwrap();
+        assert!(checker.validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn post_mvp_bad() {
+        let module = module_with_code(vec![
+            Instruction::I32Const(0),
+            Instruction::I32Const(0),
+            Instruction::I32Const(0),
+            Instruction::MemoryCopy,
+            Instruction::I32Const(0),
+            Instruction::I32Extend8S,
+            Instruction::I32Extend8S,
+            Instruction::Drop,
+            Instruction::End,
+        ]);
+        let checker = VerifyMvp::with_preset(Preset::Ewasm).unwrap();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            [
+                "function 1 uses `memory.copy` of the bulk-memory proposal",
+                "function 1 uses `i32.extend8_s` of the sign-extension proposal",
+            ]
+        );
+
+        let checker = VerifyMvp::with_features(vec![Feature::BulkMemory]);
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["function 1 uses `i32.extend8_s` of the sign-extension proposal"]
+        );
+    }
+
+    #[test]
+    fn features_classified() {
+        assert_eq!(
+            instruction_feature(&Instruction::I8x16Add),
+            Ok(Some(Feature::Simd))
+        );
+        assert_eq!(
+            instruction_feature(&Instruction::AtomicWake(parity_wasm::elements::MemArg {
+                align: 2,
+                offset: 0
+            })),
+            Ok(Some(Feature::Threads))
+        );
+        assert_eq!(instruction_feature(&Instruction::Nop), Ok(None));
+    }
+}
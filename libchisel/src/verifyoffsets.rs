@@ -0,0 +1,161 @@
+use super::presets::Preset;
+use super::{ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, InitExpr, Instruction, Module};
+
+/// Struct on which ModuleValidator is implemented.
+/// Rejects data and element segments whose offset is not a constant, which the host would have
+/// to compute when instantiating the module.
+pub struct VerifyOffsets {
+    /// Whether an offset may also be read from an imported global.
+    allow_imported_globals: bool,
+}
+
+impl ModulePreset for VerifyOffsets {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(VerifyOffsets::new(preset.imported_global_offsets()))
+    }
+}
+
+impl VerifyOffsets {
+    /// Constructs a validator which only allows `i32.const` offsets, and also offsets read from
+    /// an imported global if `allow_imported_globals` is set.
+    pub fn new(allow_imported_globals: bool) -> Self {
+        VerifyOffsets {
+            allow_imported_globals,
+        }
+    }
+
+    pub fn set_allow_imported_globals(&mut self, allow_imported_globals: bool) {
+        self.allow_imported_globals = allow_imported_globals;
+    }
+
+    /// Returns true if the offset is known before the module is instantiated. Passive segments,
+    /// which have no offset, always pass.
+    fn is_constant(&self, offset: &Option<InitExpr>, imported_globals: u32) -> bool {
+        let code = match offset {
+            Some(offset) => offset.code(),
+            None => return true,
+        };
+        match code {
+            [Instruction::I32Const(_), Instruction::End] => true,
+            [Instruction::GetGlobal(index), Instruction::End] => {
+                self.allow_imported_globals && *index < imported_globals
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ModuleValidator for VerifyOffsets {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let imported_globals = imported_global_count(module);
+        let mut reasons = Vec::new();
+
+        if let Some(section) = module.data_section() {
+            reasons.extend(
+                section
+                    .entries()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, segment)| !self.is_constant(segment.offset(), imported_globals))
+                    .map(|(index, _)| format!("data segment {} has a non-constant offset", index)),
+            );
+        }
+        if let Some(section) = module.elements_section() {
+            reasons.extend(
+                section
+                    .entries()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, segment)| !self.is_constant(segment.offset(), imported_globals))
+                    .map(|(index, _)| {
+                        format!("element segment {} has a non-constant offset", index)
+                    }),
+            );
+        }
+
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        let mut rules =
+            vec!["requires data and element segments at `i32.const` offsets".to_string()];
+        if self.allow_imported_globals {
+            rules.push("allows offsets read from an imported global".to_string());
+        }
+        rules
+    }
+}
+
+/// Returns the number of imported globals, which come first in the index space.
+fn imported_global_count(module: &Module) -> u32 {
+    module.import_section().map_or(0, |section| {
+        section
+            .entries()
+            .iter()
+            .filter(|entry| matches!(entry.external(), External::Global(_)))
+            .count() as u32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    /// Places its data at an imported base, and its elements at the value of a defined global.
+    static GLOBAL_OFFSETS: &str = r#"(module
+        (import "env" "memoryBase" (global i32))
+        (global i32 (i32.const 8))
+        (memory 1)
+        (table 2 funcref)
+        (func)
+        (data (i32.const 0) "const")
+        (data (global.get 0) "imported")
+        (elem (i32.const 0) 0)
+        (elem (global.get 1) 0))"#;
+
+    #[test]
+    fn constant_offsets_good() {
+        let module = FromWat::new(
+            r#"(module
+                (memory 1)
+                (table 1 funcref)
+                (func)
+                (data (i32.const 16) "data")
+                (elem (i32.const 0) 0))"#,
+        )
+        .create()
+        .unwrap();
+        let checker = VerifyOffsets::with_preset(Preset::Ewasm).unwrap();
+        assert!(checker.validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn global_offsets_rejected() {
+        let module = FromWat::new(GLOBAL_OFFSETS).create().unwrap();
+        let checker = VerifyOffsets::with_preset(Preset::Ewasm).unwrap();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            [
+                "data segment 1 has a non-constant offset",
+                "element segment 1 has a non-constant offset",
+            ]
+        );
+    }
+
+    #[test]
+    fn imported_global_offset_allowed() {
+        let module = FromWat::new(GLOBAL_OFFSETS).create().unwrap();
+        let mut checker = VerifyOffsets::with_preset(Preset::PWasm).unwrap();
+        // The defined global is still rejected, as only imports are given by the host.
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["element segment 1 has a non-constant offset"]
+        );
+        checker.set_allow_imported_globals(false);
+        assert_eq!(checker.validate(&module).unwrap().reasons().len(), 2);
+    }
+}
@@ -0,0 +1,168 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::presets::Preset;
+use super::{
+    read_var_u32, section_id, ModulePreset, ModuleValidator, ValidationResult, SECTION_NAMES,
+};
+
+use parity_wasm::elements::Module;
+
+/// Struct on which ModuleValidator is implemented.
+/// Verifies that the module is a core wasm module of version 1 with every section the target
+/// needs, before other validators look into those sections.
+pub struct VerifyStructure {
+    /// Names of the sections the module must have.
+    required: Vec<&'static str>,
+}
+
+impl ModulePreset for VerifyStructure {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(VerifyStructure::new(preset.required_sections().to_vec()))
+    }
+}
+
+impl VerifyStructure {
+    /// Constructs a validator requiring the sections of the given names, such as `code`.
+    pub fn new(required: Vec<&'static str>) -> Self {
+        VerifyStructure { required }
+    }
+
+    fn check(&self, version: u32, section_ids: &[u8]) -> ValidationResult {
+        if version != 1 {
+            return ValidationResult::from_reasons(vec![format!(
+                "unsupported wasm version {}, expected 1",
+                version
+            )]);
+        }
+        let reasons = self
+            .required
+            .iter()
+            .filter(|name| {
+                !section_ids
+                    .iter()
+                    .any(|id| SECTION_NAMES.get(*id as usize) == Some(name))
+            })
+            .map(|name| format!("missing {} section", name))
+            .collect();
+        ValidationResult::from_reasons(reasons)
+    }
+}
+
+impl ModuleValidator for VerifyStructure {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let section_ids: Vec<u8> = module.sections().iter().map(section_id).collect();
+        Ok(self.check(module.version(), &section_ids))
+    }
+
+    /// Only the header of the binary and of each section is read.
+    fn validate_binary(&self, binary: &[u8]) -> Option<Result<ValidationResult, String>> {
+        if binary.len() < 8 || binary[..4] != *b"\0asm" {
+            return Some(Err("not a wasm binary".to_string()));
+        }
+        // Components share the magic number, but have a layer of 1 in place of the upper half of
+        // the version.
+        if binary[6..8] == [1, 0] {
+            return Some(Ok(ValidationResult::from_reasons(vec![
+                "the binary is a component, expected a core module".to_string(),
+            ])));
+        }
+        let version = u32::from_le_bytes([binary[4], binary[5], binary[6], binary[7]]);
+
+        let mut section_ids = Vec::new();
+        let mut offset = 8;
+        while offset < binary.len() {
+            let mut position = offset + 1;
+            let end = read_var_u32(binary, &mut position).map(|size| position + size as usize);
+            match end {
+                Some(end) if end <= binary.len() => {
+                    section_ids.push(binary[offset]);
+                    offset = end;
+                }
+                _ => {
+                    return Some(Err(format!(
+                        "Failed to parse the binary: truncated section at offset {}",
+                        offset
+                    )))
+                }
+            }
+        }
+        Some(Ok(self.check(version, &section_ids)))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        let mut rules = vec!["requires a core module of wasm version 1".to_string()];
+        rules.extend(
+            self.required
+                .iter()
+                .map(|name| format!("requires a {} section", name)),
+        );
+        rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    fn binary(source: &str) -> (Module, Vec<u8>) {
+        let module = FromWat::new(source).create().unwrap();
+        let binary = parity_wasm::serialize(module.clone()).unwrap();
+        (module, binary)
+    }
+
+    #[test]
+    fn complete_module_good() {
+        let (module, binary) = binary(r#"(module (memory 1) (func (export "main")))"#);
+        let checker = VerifyStructure::with_preset(Preset::Ewasm).unwrap();
+        assert!(checker.validate(&module).unwrap().passed());
+        assert_eq!(
+            checker.validate_binary(&binary),
+            Some(checker.validate(&module))
+        );
+    }
+
+    #[test]
+    fn missing_sections_reported() {
+        let (module, binary) = binary(r#"(module (func))"#);
+        let checker = VerifyStructure::with_preset(Preset::Ewasm).unwrap();
+        let result = checker.validate(&module).unwrap();
+        assert_eq!(
+            result.reasons(),
+            ["missing export section", "missing memory section"]
+        );
+        assert_eq!(checker.validate_binary(&binary), Some(Ok(result)));
+
+        let checker = VerifyStructure::with_preset(Preset::PWasm).unwrap();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["missing export section"]
+        );
+    }
+
+    #[test]
+    fn other_binaries_rejected() {
+        let checker = VerifyStructure::new(vec![]);
+        assert_eq!(
+            checker.validate_binary(b"\0asm\x0d\0\x01\0"),
+            Some(Ok(ValidationResult::from_reasons(vec![
+                "the binary is a component, expected a core module".to_string()
+            ])))
+        );
+        assert_eq!(
+            checker
+                .validate_binary(b"\0asm\x02\0\0\0")
+                .unwrap()
+                .unwrap()
+                .reasons(),
+            ["unsupported wasm version 2, expected 1"]
+        );
+        assert!(checker.validate_binary(b"not wasm").unwrap().is_err());
+        assert!(checker
+            .validate_binary(b"\0asm\x01\0\0\0\x01\x05")
+            .unwrap()
+            .is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::presets::Preset;
+use super::{ModulePreset, ModuleValidator, ValidationResult};
+
+use parity_wasm::elements::{External, ImportCountType, Module};
+
+/// Struct on which ModuleValidator is implemented.
+/// Verifies that the element segments fill existing tables with existing functions, so that the
+/// module does not trap at instantiation. Tables can only hold `funcref` once deserialized, as
+/// parity-wasm rejects any other element type.
+pub struct VerifyTable {
+    /// Whether the module may have at most one table, imported ones included.
+    single_table: bool,
+}
+
+impl ModulePreset for VerifyTable {
+    fn with_preset(preset: Preset) -> Result<Self, String> {
+        Ok(VerifyTable::new(preset.single_table()))
+    }
+}
+
+impl VerifyTable {
+    pub fn new(single_table: bool) -> Self {
+        VerifyTable { single_table }
+    }
+
+    pub fn set_single_table(&mut self, single_table: bool) {
+        self.single_table = single_table;
+    }
+}
+
+impl ModuleValidator for VerifyTable {
+    fn validate(&self, module: &Module) -> Result<ValidationResult, String> {
+        let mut reasons = Vec::new();
+        let imported_tables = module.import_section().map_or(0, |section| {
+            section
+                .entries()
+                .iter()
+                .filter(|entry| matches!(entry.external(), External::Table(_)))
+                .count()
+        });
+        let tables = imported_tables
+            + module
+                .table_section()
+                .map_or(0, |section| section.entries().len());
+        if self.single_table && tables > 1 {
+            reasons.push(format!("module has {} tables, expected at most 1", tables));
+        }
+
+        let functions = module.import_count(ImportCountType::Function)
+            + module
+                .function_section()
+                .map_or(0, |section| section.entries().len());
+        let segments = module
+            .elements_section()
+            .map_or(&[][..], |section| section.entries());
+        for (index, segment) in segments.iter().enumerate() {
+            if !segment.passive() && segment.index() as usize >= tables {
+                reasons.push(format!(
+                    "element segment {} refers to nonexistent table {}",
+                    index,
+                    segment.index()
+                ));
+            }
+            for member in segment.members() {
+                if *member as usize >= functions {
+                    reasons.push(format!(
+                        "element segment {} refers to nonexistent function {}",
+                        index, member
+                    ));
+                }
+            }
+        }
+
+        Ok(ValidationResult::from_reasons(reasons))
+    }
+
+    fn rules(&self) -> Vec<String> {
+        let mut rules = vec![
+            "requires every table to hold funcref".to_string(),
+            "requires element segments to refer to existing tables and functions".to_string(),
+        ];
+        if self.single_table {
+            rules.push("allows at most one table".to_string());
+        }
+        rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fromwat::FromWat;
+    use ModuleCreator;
+
+    #[test]
+    fn functions_in_range_good() {
+        let module = FromWat::new(
+            r#"(module
+                (import "env" "f" (func))
+                (table 2 funcref)
+                (func)
+                (elem (i32.const 0) 0 1))"#,
+        )
+        .create()
+        .unwrap();
+        let checker = VerifyTable::with_preset(Preset::Ewasm).unwrap();
+        assert!(checker.validate(&module).unwrap().passed());
+    }
+
+    #[test]
+    fn out_of_range_reported() {
+        let mut module = FromWat::new(
+            r#"(module
+                (table 2 funcref)
+                (func)
+                (elem (i32.const 0) 0))"#,
+        )
+        .create()
+        .unwrap();
+        // Corrupt the segment as a hand-edited binary would.
+        let segment = &mut module.elements_section_mut().unwrap().entries_mut()[0];
+        segment.members_mut().push(3);
+        let checker = VerifyTable::with_preset(Preset::Ewasm).unwrap();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["element segment 0 refers to nonexistent function 3"]
+        );
+
+        let module = FromWat::new(r#"(module (func) (elem (i32.const 0) func 0))"#)
+            .create()
+            .unwrap();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["element segment 0 refers to nonexistent table 0"]
+        );
+    }
+
+    #[test]
+    fn single_table_per_preset() {
+        let module = FromWat::new(
+            r#"(module
+                (import "env" "table" (table 1 funcref))
+                (table 1 funcref))"#,
+        )
+        .create()
+        .unwrap();
+        let mut checker = VerifyTable::with_preset(Preset::Ewasm).unwrap();
+        assert_eq!(
+            checker.validate(&module).unwrap().reasons(),
+            ["module has 2 tables, expected at most 1"]
+        );
+        assert!(VerifyTable::with_preset(Preset::PWasm)
+            .unwrap()
+            .validate(&module)
+            .unwrap()
+            .passed());
+        checker.set_single_table(false);
+        assert!(checker.validate(&module).unwrap().passed());
+    }
+}